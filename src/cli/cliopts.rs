@@ -12,7 +12,7 @@ pub struct CliOpts {
     #[arg(short, long, global = true, action = clap::ArgAction::SetTrue)]
     pub verbose: bool,
 
-    /// Change the rendering format (can be one of: json, yaml, json_pretty)
+    /// Change the rendering format (can be one of: json, yaml, json_pretty, csv, tsv, markdown, template=FILE, jsonpath=EXPRESSION)
     #[arg(short, long, global = true, value_name = "FORMAT")]
     pub ouput: Option<String>,
 
@@ -20,6 +20,10 @@ pub struct CliOpts {
     #[arg(short, long, value_name = "FILE or URL", global = true)]
     pub config_file: Option<String>,
 
+    /// The file containing the passphrase used to encrypt and decrypt the secret fields of entity files
+    #[arg(long, value_name = "FILE", global = true, env = "OTOROSHICTL_KEY_FILE")]
+    pub key_file: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 
@@ -258,9 +262,12 @@ pub enum ResourcesSubCommand {
         resource: Option<String>,
         /// Optional resource id to operate on
         id: Option<String>,
-        /// Optional comma separated list of columns to display
+        /// Optional comma separated list of columns to display, like `frontend.domains[0]` or `backend.targets[*].hostname`
         #[arg(long, global = true)]
         columns: Vec<String>,
+        /// A JSONPath expression selecting what is displayed from the entity list (or the entity), like `$[?(@.enabled == false)]`
+        #[arg(long, value_name = "EXPRESSION")]
+        query: Option<String>,
         /// Add kube armor to resources
         #[arg(short, long, action = clap::ArgAction::SetTrue)]
         kube: Option<bool>,
@@ -273,6 +280,45 @@ pub enum ResourcesSubCommand {
         /// Filter the returned elements
         #[arg(short, long)]
         filters: Vec<String>,
+        /// Only the entities whose metadata match the selector, like `env=prod,team!=a,tier in (front,back)`
+        #[arg(short = 'l', long = "selector", value_name = "SELECTOR")]
+        selector: Vec<String>,
+        /// Only the entities having this tag, can be repeated
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Keep polling the entities and print the ADDED, MODIFIED and DELETED events
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        watch: Option<bool>,
+        /// The polling interval of --watch in seconds. Defaults to 2
+        #[arg(long, value_name = "SECONDS")]
+        interval: Option<u64>,
+    },
+    /// Display an entity with its resolved relationships: references, plugins, authorized apikeys and certificates
+    Describe {
+        /// Resource name to operate on
+        resource: String,
+        /// The id of the entity to describe
+        id: String,
+    },
+    /// List the versions of an entity recorded locally each time the cli changed it, with their diffs
+    History {
+        /// Resource name to operate on
+        resource: String,
+        /// The id of the entity
+        id: String,
+    },
+    /// Restore a version of an entity recorded in the local history
+    Rollback {
+        /// Resource name to operate on
+        resource: String,
+        /// The id of the entity
+        id: String,
+        /// The revision to restore, as listed by `resources history`
+        #[arg(long, value_name = "REV")]
+        to: u32,
+        /// Dry run, only display the changes the rollback would make
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        dry_run: Option<bool>,
     },
     /// Delete otoroshi resources
     Delete {
@@ -280,6 +326,12 @@ pub enum ResourcesSubCommand {
         resource: Option<String>,
         /// the ids to delete
         ids: Vec<String>,
+        /// Only the entities whose metadata match the selector, like `env=prod,team!=a,tier in (front,back)`
+        #[arg(short = 'l', long = "selector", value_name = "SELECTOR")]
+        selector: Vec<String>,
+        /// Only the entities having this tag, can be repeated
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
         /// The file to delete
         #[arg(short, long, value_name = "FILE or URL")]
         file: Option<String>,
@@ -289,16 +341,34 @@ pub enum ResourcesSubCommand {
         /// Walk through sub directories
         #[arg(short, long, action = clap::ArgAction::SetTrue)]
         recursive: Option<bool>,
+        /// Dry run, do not apply the changes
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        dry_run: Option<bool>,
+        /// Values files used to resolve the `${vars.xxx}` placeholders of the entity files
+        #[arg(long, value_name = "FILE or URL")]
+        values: Vec<String>,
+        /// Write a machine readable report of the result of each entity (can be one of: json, junit)
+        #[arg(long, value_name = "FORMAT")]
+        report: Option<String>,
+        /// The file the report is written to
+        #[arg(long, value_name = "FILE")]
+        report_file: Option<PathBuf>,
     },
     /// Update otoroshi resources through json merge or json patch
     Patch {
         /// The resource name to operate on
         resource: String,
-        /// The resource id to operate on
-        id: String,
-        /// The json object to merge
+        /// The resource id to operate on. With selectors, the json object to merge or the json patch operations to apply
+        id: Option<String>,
+        /// The json object to merge or the json patch operations to apply
         merge: Option<String>,
-        /// The file cxontaining the json object to merge
+        /// Only the entities whose metadata match the selector, like `env=prod,team!=a,tier in (front,back)`
+        #[arg(short = 'l', long = "selector", value_name = "SELECTOR")]
+        selector: Vec<String>,
+        /// Only the entities having this tag, can be repeated
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// The file containing the json object to merge or the json patch operations to apply
         #[arg(short, long, value_name = "FILE or URL")]
         file: Option<String>,
         /// Use inline PATH=VALUE tuples as entity input
@@ -307,6 +377,9 @@ pub enum ResourcesSubCommand {
         /// Use stdin as entity input
         #[clap(long, action, default_value = "false")]
         stdin: bool,
+        /// The patch type (can be one of: json, merge). Guessed from the input when not specified
+        #[arg(long = "type", value_name = "TYPE")]
+        patch_type: Option<String>,
     },
     /// Update otoroshi resources
     Edit {
@@ -325,6 +398,9 @@ pub enum ResourcesSubCommand {
         /// Use stdin as entity input
         #[clap(long, action, default_value = "false")]
         stdin: bool,
+        /// Dry run, do not apply the changes
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        dry_run: Option<bool>,
     },
     /// Create otoroshi resources
     Create {
@@ -341,6 +417,9 @@ pub enum ResourcesSubCommand {
         /// Use stdin as entity input
         #[clap(long, action, default_value = "false")]
         stdin: bool,
+        /// Dry run, do not apply the changes
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        dry_run: Option<bool>,
     },
     /// Synchronise otoroshi resources from files or directories
     Apply {
@@ -356,6 +435,117 @@ pub enum ResourcesSubCommand {
         /// Keep watching file changes
         #[arg(short, long, action = clap::ArgAction::SetTrue)]
         watch: Option<bool>,
+        /// Dry run, do not apply the changes
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        dry_run: Option<bool>,
+        /// Delete the entities managed under --managed-by of the synced kinds that are not declared in the directory anymore
+        #[arg(long, requires = "managed_by", conflicts_with = "file", action = clap::ArgAction::SetTrue)]
+        prune: Option<bool>,
+        /// The name stamped in the `otoroshictl/managed-by` metadata of the synced entities, required when pruning
+        #[arg(long, value_name = "NAME")]
+        managed_by: Option<String>,
+        /// Values files used to resolve the `${vars.xxx}` placeholders of the entity files
+        #[arg(long, value_name = "FILE or URL")]
+        values: Vec<String>,
+        /// The overlay directory to build and sync
+        #[arg(long, value_name = "DIR")]
+        overlay: Option<PathBuf>,
+        /// The number of entities pushed at the same time. Entities referencing each other are still pushed in order
+        #[arg(long, value_name = "N")]
+        concurrency: Option<u32>,
+        /// Write a machine readable report of the result of each entity (can be one of: json, junit)
+        #[arg(long, value_name = "FORMAT")]
+        report: Option<String>,
+        /// The file the report is written to
+        #[arg(long, value_name = "FILE")]
+        report_file: Option<PathBuf>,
+        /// Do not validate the entity files against the schemas of their kinds before applying them
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        skip_validation: Option<bool>,
+    },
+    /// Validate entity files against the schemas of their kinds, without applying them. Exits with status 1 when a document is invalid
+    Validate {
+        /// The file to validate
+        #[arg(short, long, value_name = "FILE or URL")]
+        file: Option<String>,
+        /// The directory to validate
+        #[arg(short, long, value_name = "DIR")]
+        directory: Option<PathBuf>,
+        /// Walk through sub directories
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        recursive: Option<bool>,
+        /// Values files used to resolve the `${vars.xxx}` placeholders of the entity files
+        #[arg(long, value_name = "FILE or URL")]
+        values: Vec<String>,
+        /// Never call the cluster, only use the cached schemas or the embedded ones
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        offline: Option<bool>,
+    },
+    /// Run best practice rules over entity files, or over the entities of the current cluster when no file is given. Exits with status 1 when a finding has the error severity
+    Lint {
+        /// The file to lint
+        #[arg(short, long, value_name = "FILE or URL")]
+        file: Option<String>,
+        /// The directory to lint
+        #[arg(short, long, value_name = "DIR")]
+        directory: Option<PathBuf>,
+        /// Walk through sub directories
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        recursive: Option<bool>,
+        /// Values files used to resolve the `${vars.xxx}` placeholders of the entity files
+        #[arg(long, value_name = "FILE or URL")]
+        values: Vec<String>,
+        /// The lint configuration enabling the rules and setting their severity (`.otoroshictl-lint.yaml` of the current directory by default)
+        #[arg(long, value_name = "FILE")]
+        rules: Option<PathBuf>,
+        /// List the rules with their severity instead of linting
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        list_rules: Option<bool>,
+    },
+    /// Compare local entity files with the entities of the current cluster. Exits with status 1 when there is a drift
+    Diff {
+        /// The file to compare
+        #[arg(short, long, value_name = "FILE or URL")]
+        file: Option<String>,
+        /// The directory to compare
+        #[arg(short, long, value_name = "DIR")]
+        directory: Option<PathBuf>,
+        /// Walk through sub directories
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        recursive: Option<bool>,
+        /// Do not report cluster entities of the compared kinds that are not declared locally
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        ignore_removed: Option<bool>,
+        /// Values files used to resolve the `${vars.xxx}` placeholders of the entity files
+        #[arg(long, value_name = "FILE or URL")]
+        values: Vec<String>,
+    },
+    /// Build an overlay directory, its bases and its patches, and print the resulting entities
+    Build {
+        /// The overlay directory to build
+        #[arg(value_name = "DIR")]
+        directory: PathBuf,
+        /// Values files used to resolve the `${vars.xxx}` placeholders of the entity files
+        #[arg(long, value_name = "FILE or URL")]
+        values: Vec<String>,
+        /// Add kube armor to resources
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        kube: Option<bool>,
+    },
+    /// Print entity files with their `${env.xxx}` and `${vars.xxx}` placeholders resolved, without applying them
+    Render {
+        /// The file to render
+        #[arg(short, long, value_name = "FILE or URL")]
+        file: Option<String>,
+        /// The directory to render
+        #[arg(short, long, value_name = "DIR")]
+        directory: Option<PathBuf>,
+        /// Walk through sub directories
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        recursive: Option<bool>,
+        /// Values files used to resolve the `${vars.xxx}` placeholders of the entity files
+        #[arg(long, value_name = "FILE or URL")]
+        values: Vec<String>,
     },
     /// Export otoroshi resources to files or directories
     Export {
@@ -373,6 +563,78 @@ pub enum ResourcesSubCommand {
         /// Export in ndjson format
         #[arg(long, action = clap::ArgAction::SetTrue)]
         nd_json: Option<bool>,
+        /// Encrypt the well-known secret fields of the exported entities with the key file
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        encrypt: Option<bool>,
+        /// Write deterministic files suitable for git: sorted entities, no server managed fields, no values equal to the defaults of the kind, and split files named after the entities
+        #[arg(long, requires = "directory", action = clap::ArgAction::SetTrue)]
+        normalize: Option<bool>,
+        /// The number of kinds fetched at the same time when exporting to a directory
+        #[arg(long, value_name = "N")]
+        concurrency: Option<u32>,
+        /// Only the entities whose metadata match the selector, like `env=prod,team!=a,tier in (front,back)`
+        #[arg(short = 'l', long = "selector", value_name = "SELECTOR")]
+        selector: Vec<String>,
+        /// Only the entities having this tag, can be repeated
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+    },
+    /// Convert entity files between json, yaml, kube and ndjson, without any connection to the cluster
+    Convert {
+        /// The file to convert
+        #[arg(short, long, value_name = "FILE or URL")]
+        file: Option<String>,
+        /// The directory to convert
+        #[arg(short, long, value_name = "DIR")]
+        directory: Option<PathBuf>,
+        /// Walk through sub directories
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        recursive: Option<bool>,
+        /// The format of the files (can be one of: json, yaml, kube, ndjson). Guessed from the extension of each file by default
+        #[arg(long, value_name = "FORMAT")]
+        from: Option<String>,
+        /// The format to convert to (can be one of: json, yaml, kube, ndjson)
+        #[arg(long, value_name = "FORMAT")]
+        to: String,
+        /// Write the converted entities to this file instead of the standard output
+        #[arg(long, value_name = "FILE", conflicts_with = "out_dir")]
+        out_file: Option<PathBuf>,
+        /// Write one file per entity in this directory, under a folder per kind (one file per kind for ndjson)
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+    /// Encrypt the secret fields of entity files with the key file
+    Encrypt {
+        /// The file to encrypt
+        #[arg(short, long, value_name = "FILE")]
+        file: Option<String>,
+        /// The directory to encrypt
+        #[arg(short, long, value_name = "DIR")]
+        directory: Option<PathBuf>,
+        /// Walk through sub directories
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        recursive: Option<bool>,
+        /// The names or dotted paths of the fields to encrypt. Defaults to the well-known secret fields
+        #[arg(long = "field", value_name = "FIELD")]
+        fields: Vec<String>,
+        /// Rewrite the files instead of printing them
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        in_place: Option<bool>,
+    },
+    /// Decrypt the encrypted fields of entity files with the key file
+    Decrypt {
+        /// The file to decrypt
+        #[arg(short, long, value_name = "FILE")]
+        file: Option<String>,
+        /// The directory to decrypt
+        #[arg(short, long, value_name = "DIR")]
+        directory: Option<PathBuf>,
+        /// Walk through sub directories
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        recursive: Option<bool>,
+        /// Rewrite the files instead of printing them
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        in_place: Option<bool>,
     },
     /// Import data from an export file
     Import {
@@ -382,6 +644,111 @@ pub enum ResourcesSubCommand {
         /// import from ndjson format
         #[arg(long, action = clap::ArgAction::SetTrue)]
         nd_json: Option<bool>,
+        /// Write a machine readable report of the result of each entity (can be one of: json, junit)
+        #[arg(long, value_name = "FORMAT")]
+        report: Option<String>,
+        /// The file the report is written to
+        #[arg(long, value_name = "FILE")]
+        report_file: Option<PathBuf>,
+    },
+    /// Generate routes from an OpenAPI 3 document, as entity files or applied to the current cluster
+    ImportOpenapi {
+        /// The OpenAPI document, as json or yaml
+        #[arg(value_name = "FILE or URL")]
+        spec: String,
+        /// The domain exposing the api
+        #[arg(long, value_name = "DOMAIN")]
+        domain: String,
+        /// The url of the backend serving the api, like http://svc:8080
+        #[arg(long, value_name = "URL")]
+        backend: String,
+        /// Generate one route per tag or per first path segment instead of a single route (can be one of: tag, path)
+        #[arg(long, value_name = "GROUP")]
+        group_by: Option<String>,
+        /// Add apikey or jwt verification plugins according to the security schemes of the document
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        security: Option<bool>,
+        /// The id of the jwt verifier used by the jwt verification plugins added by --security
+        #[arg(long, value_name = "ID", requires = "security")]
+        jwt_verifier: Option<String>,
+        /// Write one entity file per route in this directory instead of printing them
+        #[arg(long, value_name = "DIR", conflicts_with = "apply")]
+        out_dir: Option<PathBuf>,
+        /// Create or update the routes on the current cluster
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        apply: Option<bool>,
+        /// Print what --apply would change without changing anything
+        #[arg(long, requires = "apply", action = clap::ArgAction::SetTrue)]
+        dry_run: Option<bool>,
+    },
+    /// Translate kubernetes Ingress and Gateway API HTTPRoute manifests into routes, without any connection to the cluster
+    ImportK8s {
+        /// The manifest file
+        #[arg(short, long, value_name = "FILE or URL")]
+        file: Option<String>,
+        /// The directory of the manifests
+        #[arg(short, long, value_name = "DIR")]
+        directory: Option<PathBuf>,
+        /// Walk through sub directories
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        recursive: Option<bool>,
+        /// Write one entity file per route in this directory instead of printing them
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+    /// Convert legacy entities of the current cluster into their new engine equivalents
+    Migrate {
+        #[command(subcommand)]
+        command: MigrateSubCommand,
+    },
+    /// Copy entities from one context to another, printing the changes before writing them
+    Copy {
+        /// The resource name to operate on
+        resource: String,
+        /// The ids of the entities to copy
+        ids: Vec<String>,
+        /// Only the entities whose metadata match the selector, like `env=prod,team!=a,tier in (front,back)`
+        #[arg(short = 'l', long = "selector", value_name = "SELECTOR")]
+        selector: Vec<String>,
+        /// Only the entities having this tag, can be repeated
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// The context to read the entities from, the current one by default
+        #[arg(long, value_name = "CONTEXT")]
+        from_context: Option<String>,
+        /// The context to write the entities to
+        #[arg(long, value_name = "CONTEXT")]
+        to_context: String,
+        /// Write the entity OLD_ID under NEW_ID in the target context, can be repeated
+        #[arg(long = "map-id", value_name = "OLD_ID=NEW_ID")]
+        map_ids: Vec<String>,
+        /// Override a field of the copied entities, like `frontend.domains=["api.prod.oto.tools"]`, can be repeated
+        #[arg(long = "set", value_name = "PATH=VALUE")]
+        set: Vec<String>,
+        /// Dry run, only display the changes the copy would make
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        dry_run: Option<bool>,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum MigrateSubCommand {
+    /// Convert service descriptors into routes, written for review or applied while disabling the service descriptors
+    ServiceDescriptors {
+        /// The ids of the service descriptors to migrate, all of them by default
+        ids: Vec<String>,
+        /// Write one entity file per route in this directory instead of printing them
+        #[arg(long, value_name = "DIR", conflicts_with = "apply")]
+        out_dir: Option<PathBuf>,
+        /// Create or update the routes on the current cluster, then disable the migrated service descriptors
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        apply: Option<bool>,
+        /// Print what --apply would change without changing anything
+        #[arg(long, requires = "apply", action = clap::ArgAction::SetTrue)]
+        dry_run: Option<bool>,
+        /// Write the settings without equivalent of each service descriptor to this file, as json
+        #[arg(long, value_name = "FILE")]
+        report_file: Option<PathBuf>,
     },
 }
 
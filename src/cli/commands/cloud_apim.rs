@@ -16,6 +16,7 @@ use crate::cli::cliopts::CliOpts;
 use crate::cli::config::OtoroshiCtlConfigCloudApim;
 use crate::cli_stderr_printline;
 use crate::cli_stdout_printline;
+use crate::utils::render::RenderHelper;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CloudApimDeployments {
@@ -405,24 +406,9 @@ impl CloudApimCommands {
             }
             Some(config) => {
                 let deployments = Self::get_deployements_list(config).await;
-                match cli_opts.ouput {
-                    Some(str) => match str.as_str() {
-                        "json" => cli_stdout_printline!(
-                            "{}",
-                            serde_json::to_string(&deployments).unwrap()
-                        ),
-                        "json_pretty" => cli_stdout_printline!(
-                            "{}",
-                            serde_json::to_string_pretty(&deployments).unwrap()
-                        ),
-                        "yaml" => cli_stdout_printline!(
-                            "{}",
-                            serde_yaml::to_string(&deployments).unwrap()
-                        ),
-                        _ => Self::default_display(deployments),
-                    },
-                    _ => Self::default_display(deployments),
-                };
+                if !RenderHelper::display(&cli_opts, &deployments, &[]) {
+                    Self::default_display(deployments)
+                }
             }
         };
     }
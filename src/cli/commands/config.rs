@@ -11,6 +11,7 @@ use crate::cli::config::OtoroshiCtlConfigSpecContext;
 use crate::cli::config::OtoroshiCtlConfigSpecUser;
 use crate::cli_stderr_printline;
 use crate::cli_stdout_printline;
+use crate::utils::render::RenderHelper;
 use crate::utils::table::TableHelper;
 use crate::utils::table::TableResource;
 use json_value_merge::Merge;
@@ -371,72 +372,42 @@ impl ConfigCommand {
     pub async fn display_context_list(cli_opts: CliOpts) {
         let config: OtoroshiCtlConfig =
             OtoroshiCtlConfig::get_current_config(cli_opts.clone()).await;
-        match cli_opts.ouput {
-            Some(str) if str == "json" => cli_stdout_printline!(
-                "{}",
-                serde_json::to_string(
-                    &config
-                        .contexts
-                        .into_iter()
-                        .map(|c| c.name)
-                        .collect::<Vec<_>>()
-                )
-                .unwrap()
-            ),
-            Some(str) if str == "json_pretty" => cli_stdout_printline!(
-                "{}",
-                serde_json::to_string_pretty(
-                    &config
-                        .contexts
-                        .into_iter()
-                        .map(|c| c.name)
-                        .collect::<Vec<_>>()
-                )
-                .unwrap()
-            ),
-            Some(str) if str == "yaml" => cli_stdout_printline!(
-                "{}",
-                serde_yaml::to_string(
-                    &config
-                        .contexts
-                        .into_iter()
-                        .map(|c| c.name)
-                        .collect::<Vec<_>>()
-                )
-                .unwrap()
-            ),
-            _ => {
-                let columns = vec![
-                    "name".to_string(),
-                    "current".to_string(),
-                    "cloud_apim".to_string(),
-                ];
-                let vec = config
-                    .contexts
-                    .into_iter()
-                    .map(|u| {
-                        if u.name == config.current_context {
-                            let current = serde_json::json!({"current":"yes"});
-                            let cloud_apim_value = if u.cloud_apim { "yes" } else { "" };
-                            let cloud_apim = serde_json::json!({"cloud_apim":cloud_apim_value});
-                            let mut res = serde_json::to_value(u).unwrap();
-                            res.merge(&current);
-                            res.merge(&cloud_apim);
-                            TableResource { raw: res }
-                        } else {
-                            let current = serde_json::json!({"current":""});
-                            let cloud_apim_value = if u.cloud_apim { "yes" } else { "" };
-                            let cloud_apim = serde_json::json!({"cloud_apim":cloud_apim_value});
-                            let mut res = serde_json::to_value(u).unwrap();
-                            res.merge(&current);
-                            res.merge(&cloud_apim);
-                            TableResource { raw: res }
-                        }
-                    })
-                    .collect();
-                TableHelper::display_table_of_resources_with_custom_columns(vec, columns)
-            }
+        let columns = vec![
+            "name".to_string(),
+            "current".to_string(),
+            "cloud_apim".to_string(),
+        ];
+        let rows: Vec<serde_json::Value> = config
+            .contexts
+            .iter()
+            .map(|u| {
+                let current = if u.name == config.current_context {
+                    "yes"
+                } else {
+                    ""
+                };
+                let cloud_apim = if u.cloud_apim { "yes" } else { "" };
+                let mut res = serde_json::to_value(u).unwrap();
+                res.merge(&serde_json::json!({"current": current, "cloud_apim": cloud_apim}));
+                res
+            })
+            .collect();
+        // the structured formats only list the names of the contexts
+        let displayed = if matches!(
+            cli_opts.ouput.as_deref(),
+            Some("json" | "json_pretty" | "yaml")
+        ) {
+            let names: Vec<String> = config.contexts.into_iter().map(|c| c.name).collect();
+            RenderHelper::display(&cli_opts, &names, &columns)
+        } else {
+            RenderHelper::display(&cli_opts, &rows, &columns)
         };
+        if !displayed {
+            TableHelper::display_table_of_resources_with_custom_columns(
+                rows.into_iter().map(|raw| TableResource { raw }).collect(),
+                columns,
+            )
+        }
     }
 
     async fn edit_current_config(cli_opts: CliOpts) {
@@ -453,17 +424,8 @@ impl ConfigCommand {
     async fn display_current_config_location(cli_opts: CliOpts) {
         let path = Self::get_config_file_path(cli_opts.clone());
         let current = DisplayPath { path: path.clone() };
-        match cli_opts.ouput {
-            Some(output) if output == "json" => {
-                cli_stdout_printline!("{}", serde_json::to_string(&current).unwrap())
-            }
-            Some(output) if output == "json_pretty" => {
-                cli_stdout_printline!("{}", serde_json::to_string_pretty(&current).unwrap())
-            }
-            Some(output) if output == "yaml" => {
-                cli_stdout_printline!("{}", serde_yaml::to_string(&current).unwrap())
-            }
-            _ => cli_stdout_printline!("{}", path.clone()),
+        if !RenderHelper::display(&cli_opts, &current, &[]) {
+            cli_stdout_printline!("{}", path.clone())
         }
     }
 
@@ -472,18 +434,9 @@ impl ConfigCommand {
         let current = ContextHolder {
             context: config.current_context,
         };
-        match cli_opts.ouput {
-            Some(str) if str == "json" => {
-                cli_stdout_printline!("{}", serde_json::to_string(&current).unwrap())
-            }
-            Some(str) if str == "json_pretty" => {
-                cli_stdout_printline!("{}", serde_json::to_string_pretty(&current).unwrap())
-            }
-            Some(str) if str == "yaml" => {
-                cli_stdout_printline!("{}", serde_yaml::to_string(&current).unwrap())
-            }
-            _ => cli_stdout_printline!("{}", current.context),
-        };
+        if !RenderHelper::display(&cli_opts, &current, &[]) {
+            cli_stdout_printline!("{}", current.context)
+        }
     }
 
     pub async fn use_context(name: &String, cli_opts: CliOpts) {
@@ -533,98 +486,63 @@ impl ConfigCommand {
 
     pub async fn list_clusters(cli_opts: CliOpts) {
         let config = OtoroshiCtlConfig::get_current_config(cli_opts.clone()).await;
-        match cli_opts.ouput {
-            Some(str) if str == "json" => {
-                cli_stdout_printline!("{}", serde_json::to_string(&config.clusters).unwrap())
-            }
-            Some(str) if str == "json_pretty" => cli_stdout_printline!(
-                "{}",
-                serde_json::to_string_pretty(&config.clusters).unwrap()
-            ),
-            Some(str) if str == "yaml" => {
-                cli_stdout_printline!("{}", serde_yaml::to_string(&config.clusters).unwrap())
-            }
-            _ => {
-                let columns = vec![
-                    "name".to_string(),
-                    "hostname".to_string(),
-                    "port".to_string(),
-                    "tls".to_string(),
-                    "client_cert".to_string(),
-                ];
-                let vec = config
-                    .clusters
-                    .into_iter()
-                    .map(|u| TableResource {
-                        raw: serde_json::to_value(u).unwrap(),
-                    })
-                    .collect();
-                TableHelper::display_table_of_resources_with_custom_columns(vec, columns)
-            }
-        };
+        let columns = vec![
+            "name".to_string(),
+            "hostname".to_string(),
+            "port".to_string(),
+            "tls".to_string(),
+            "client_cert".to_string(),
+        ];
+        if !RenderHelper::display(&cli_opts, &config.clusters, &columns) {
+            let vec = config
+                .clusters
+                .into_iter()
+                .map(|u| TableResource {
+                    raw: serde_json::to_value(u).unwrap(),
+                })
+                .collect();
+            TableHelper::display_table_of_resources_with_custom_columns(vec, columns)
+        }
     }
 
     pub async fn list_contexts(cli_opts: CliOpts) {
         let config = OtoroshiCtlConfig::get_current_config(cli_opts.clone()).await;
-        match cli_opts.ouput {
-            Some(str) if str == "json" => {
-                cli_stdout_printline!("{}", serde_json::to_string(&config.contexts).unwrap())
-            }
-            Some(str) if str == "json_pretty" => cli_stdout_printline!(
-                "{}",
-                serde_json::to_string_pretty(&config.contexts).unwrap()
-            ),
-            Some(str) if str == "yaml" => {
-                cli_stdout_printline!("{}", serde_yaml::to_string(&config.contexts).unwrap())
-            }
-            _ => {
-                let columns = vec![
-                    "name".to_string(),
-                    "cluster".to_string(),
-                    "user".to_string(),
-                    "cloud_apim".to_string(),
-                ];
-                let vec = config
-                    .contexts
-                    .into_iter()
-                    .map(|u| TableResource {
-                        raw: serde_json::to_value(u).unwrap(),
-                    })
-                    .collect();
-                TableHelper::display_table_of_resources_with_custom_columns(vec, columns)
-            }
-        };
+        let columns = vec![
+            "name".to_string(),
+            "cluster".to_string(),
+            "user".to_string(),
+            "cloud_apim".to_string(),
+        ];
+        if !RenderHelper::display(&cli_opts, &config.contexts, &columns) {
+            let vec = config
+                .contexts
+                .into_iter()
+                .map(|u| TableResource {
+                    raw: serde_json::to_value(u).unwrap(),
+                })
+                .collect();
+            TableHelper::display_table_of_resources_with_custom_columns(vec, columns)
+        }
     }
 
     pub async fn list_users(cli_opts: CliOpts) {
         let config: OtoroshiCtlConfig =
             OtoroshiCtlConfig::get_current_config(cli_opts.clone()).await;
-        match cli_opts.ouput {
-            Some(str) if str == "json" => {
-                cli_stdout_printline!("{}", serde_json::to_string(&config.users).unwrap())
-            }
-            Some(str) if str == "json_pretty" => {
-                cli_stdout_printline!("{}", serde_json::to_string_pretty(&config.users).unwrap())
-            }
-            Some(str) if str == "yaml" => {
-                cli_stdout_printline!("{}", serde_yaml::to_string(&config.users).unwrap())
-            }
-            _ => {
-                let columns = vec![
-                    "name".to_string(),
-                    "client_id".to_string(),
-                    "client_secret".to_string(),
-                ];
-                let vec = config
-                    .users
-                    .into_iter()
-                    .map(|u| TableResource {
-                        raw: serde_json::to_value(u).unwrap(),
-                    })
-                    .collect();
-                TableHelper::display_table_of_resources_with_custom_columns(vec, columns)
-            }
-        };
+        let columns = vec![
+            "name".to_string(),
+            "client_id".to_string(),
+            "client_secret".to_string(),
+        ];
+        if !RenderHelper::display(&cli_opts, &config.users, &columns) {
+            let vec = config
+                .users
+                .into_iter()
+                .map(|u| TableResource {
+                    raw: serde_json::to_value(u).unwrap(),
+                })
+                .collect();
+            TableHelper::display_table_of_resources_with_custom_columns(vec, columns)
+        }
     }
 
     pub async fn delete_cluster(name: &String, cli_opts: CliOpts) {
@@ -2,8 +2,9 @@ use cli_table::{Cell, Style, Table, print_stdout};
 use serde::{Deserialize, Serialize};
 
 use crate::cli::cliopts::{CliOpts, Commands};
+use crate::cli_stderr_printline;
 use crate::utils::otoroshi::Otoroshi;
-use crate::{cli_stderr_printline, cli_stdout_printline};
+use crate::utils::render::RenderHelper;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct OtoroshExposedResourceVersion {
@@ -28,9 +29,38 @@ pub struct OtoroshExposedResources {
     pub resources: Vec<OtoroshExposedResource>,
 }
 
+impl OtoroshExposedResources {
+    /// Find the exposed resource matching a kind as written in entity files
+    /// (`Route`, `route`, `routes` or `proxy.otoroshi.io/Route`)
+    pub fn find_by_kind(&self, kind: &str) -> Option<OtoroshExposedResource> {
+        self.resources
+            .iter()
+            .find(|i| {
+                i.kind == kind
+                    || i.singular_name == kind.to_lowercase()
+                    || i.plural_name == kind.to_lowercase()
+                    || format!("{}/{}", i.group, i.kind) == kind
+            })
+            .cloned()
+    }
+}
+
 pub struct EntitiesCommand {}
 
 impl EntitiesCommand {
+    fn columns() -> Vec<String> {
+        vec![
+            "kind".to_string(),
+            "singular_name".to_string(),
+            "plural_name".to_string(),
+            "group".to_string(),
+            "version.name".to_string(),
+            "version.served".to_string(),
+            "version.deprecated".to_string(),
+            "version.storage".to_string(),
+        ]
+    }
+
     fn default_display(resources: OtoroshExposedResources) {
         let table = resources
             .resources
@@ -68,22 +98,18 @@ impl EntitiesCommand {
                 std::process::exit(-1)
             }
             Some(resources) => {
-                match cli_opts.ouput {
-                    Some(str) => match str.as_str() {
-                        "json" => {
-                            cli_stdout_printline!("{}", serde_json::to_string(&resources).unwrap())
-                        }
-                        "json_pretty" => cli_stdout_printline!(
-                            "{}",
-                            serde_json::to_string_pretty(&resources).unwrap()
-                        ),
-                        "yaml" => {
-                            cli_stdout_printline!("{}", serde_yaml::to_string(&resources).unwrap())
-                        }
-                        _ => Self::default_display(resources),
-                    },
-                    _ => Self::default_display(resources),
+                // the structured formats keep the whole document, the others one row per resource
+                let displayed = if matches!(
+                    cli_opts.ouput.as_deref(),
+                    Some("json" | "json_pretty" | "yaml")
+                ) {
+                    RenderHelper::display(&cli_opts, &resources, &[])
+                } else {
+                    RenderHelper::display(&cli_opts, &resources.resources, &Self::columns())
                 };
+                if !displayed {
+                    Self::default_display(resources)
+                }
             }
         };
     }
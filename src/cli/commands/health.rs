@@ -2,8 +2,9 @@ use cli_table::{Cell, Style, Table, print_stdout};
 use serde::{Deserialize, Serialize};
 
 use crate::cli::cliopts::{CliOpts, Commands};
+use crate::cli_stderr_printline;
 use crate::utils::otoroshi::Otoroshi;
-use crate::{cli_stderr_printline, cli_stdout_printline};
+use crate::utils::render::RenderHelper;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ComponentHealth {
@@ -31,6 +32,18 @@ pub struct OtoroshiHealth {
 pub struct HealthCommand {}
 
 impl HealthCommand {
+    fn columns() -> Vec<String> {
+        vec![
+            "otoroshi".to_string(),
+            "datastore".to_string(),
+            "storage.status".to_string(),
+            "eventstore.status".to_string(),
+            "certificates.status".to_string(),
+            "scripts.status".to_string(),
+            "cluster.health".to_string(),
+        ]
+    }
+
     fn healthy_cell(value: String) -> cli_table::CellStruct {
         match value.as_str() {
             "healthy" => value.cell().foreground_color(Some(cli_table::Color::Green)),
@@ -83,22 +96,9 @@ impl HealthCommand {
                 std::process::exit(-1)
             }
             Some(health) => {
-                match cli_opts.ouput {
-                    Some(str) => match str.as_str() {
-                        "json" => {
-                            cli_stdout_printline!("{}", serde_json::to_string(&health).unwrap())
-                        }
-                        "json_pretty" => cli_stdout_printline!(
-                            "{}",
-                            serde_json::to_string_pretty(&health).unwrap()
-                        ),
-                        "yaml" => {
-                            cli_stdout_printline!("{}", serde_yaml::to_string(&health).unwrap())
-                        }
-                        _ => Self::default_display(health),
-                    },
-                    _ => Self::default_display(health),
-                };
+                if !RenderHelper::display(&cli_opts, &health, &Self::columns()) {
+                    Self::default_display(health)
+                }
             }
         };
     }
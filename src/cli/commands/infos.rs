@@ -2,8 +2,9 @@ use cli_table::{Cell, Style, Table, print_stdout};
 use serde::{Deserialize, Serialize};
 
 use crate::cli::cliopts::{CliOpts, Commands};
+use crate::cli_stderr_printline;
 use crate::utils::otoroshi::Otoroshi;
-use crate::{cli_stderr_printline, cli_stdout_printline};
+use crate::utils::render::RenderHelper;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct JavaVersion {
@@ -69,22 +70,9 @@ impl InfosCommand {
                 std::process::exit(-1)
             }
             Some(infos) => {
-                match cli_opts.ouput {
-                    Some(str) => match str.as_str() {
-                        "json" => {
-                            cli_stdout_printline!("{}", serde_json::to_string(&infos).unwrap())
-                        }
-                        "json_pretty" => cli_stdout_printline!(
-                            "{}",
-                            serde_json::to_string_pretty(&infos).unwrap()
-                        ),
-                        "yaml" => {
-                            cli_stdout_printline!("{}", serde_yaml::to_string(&infos).unwrap())
-                        }
-                        _ => Self::default_display(infos),
-                    },
-                    _ => Self::default_display(infos),
-                };
+                if !RenderHelper::display(&cli_opts, &infos, &[]) {
+                    Self::default_display(infos)
+                }
             }
         };
     }
@@ -1,18 +1,46 @@
 extern crate json_value_merge;
 
-use crate::cli::cliopts::{CliOpts, ResourcesSubCommand};
+use crate::cli::cliopts::{CliOpts, MigrateSubCommand, ResourcesSubCommand};
 use crate::cli::commands::entities::OtoroshExposedResource;
-use crate::utils::entity::EntityHelper;
-use crate::utils::otoroshi::Otoroshi;
+use crate::cli::config::OtoroshiCtlConfig;
+use crate::utils::convert::{ConvertHelper, EntityFormat};
+use crate::utils::dependencies::{DependencyHelper, EntityReference};
+use crate::utils::describe::{DescribeHelper, Description, RelatedEntity};
+use crate::utils::diff::{DiffHelper, FieldChange};
+use crate::utils::entity::{EntityDocument, EntityHelper};
+use crate::utils::history::{HistoryEntry, HistoryHelper};
+use crate::utils::json_patch::JsonPatchHelper;
+use crate::utils::json_path::JsonPathHelper;
+use crate::utils::k8s::{K8sHelper, K8sTranslation};
+use crate::utils::lint::{LintConfig, LintFinding, LintHelper};
+use crate::utils::migrate::{INTERNAL_DESCRIPTORS, MigrateHelper, MigrationReport};
+use crate::utils::normalize::NormalizeHelper;
+use crate::utils::openapi::{OpenApiBackend, OpenApiGroupBy, OpenApiHelper};
+use crate::utils::otoroshi::{Otoroshi, OtoroshiResponse, ResourcePager};
+use crate::utils::overlay::{
+    OVERLAY_FILE, OverlayHelper, OverlaySpec, PatchOperation, ResolvedPatch,
+};
+use crate::utils::progress::ProgressBar;
+use crate::utils::render::{RenderHelper, RenderStream};
+use crate::utils::report::ReportHelper;
+use crate::utils::schema::{SchemaHelper, SchemaViolation};
+use crate::utils::secret::{SecretHelper, SecretKey};
+use crate::utils::selector::Selector;
 use crate::utils::table::{TableHelper, TableResource};
-use crate::{cli_stderr_printline, cli_stdout_printline};
+use crate::utils::template::TemplateHelper;
+use crate::utils::watch::{WatchEvent, WatchHelper};
+use crate::{cli_stderr_printline, cli_stdout_print, cli_stdout_printline};
 
-use std::collections::HashMap;
-use std::{fs, path::PathBuf};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 use std::vec::Vec;
 
 use hyper::Method;
+use hyper::body::HttpBody;
 use json_value_merge::Merge;
 
 use notify::{RecursiveMode, Watcher};
@@ -30,7 +58,7 @@ pub struct KubeEntity {
     pub spec: serde_json::Value,
 }
 impl KubeEntity {
-    fn new(kind: String, name: String, spec: serde_json::Value) -> KubeEntity {
+    pub(crate) fn new(kind: String, name: String, spec: serde_json::Value) -> KubeEntity {
         let mut metadata = HashMap::new();
         metadata.insert("name".to_string(), name);
         KubeEntity {
@@ -42,170 +70,430 @@ impl KubeEntity {
     }
 }
 
+/// How `apply` pushes the entities to the cluster
+#[derive(Clone, Debug, Default)]
+pub struct SyncOptions {
+    pub dry_run: bool,
+    /// delete the managed entities of the synced kinds that are not declared anymore
+    pub prune: bool,
+    /// stamped in the metadata of the synced entities when defined
+    pub managed_by: Option<String>,
+    /// used to resolve the `${vars.xxx}` placeholders of the entity files
+    pub vars: serde_json::Value,
+    /// the number of entities pushed at the same time
+    pub concurrency: usize,
+    /// validate the entities against the schemas of their kinds before pushing them
+    pub validate: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlannedAction {
+    pub kind: String,
+    pub id: String,
+    pub name: String,
+    /// planned: create, update, unchanged or delete. Performed: created, updated, unchanged,
+    /// deleted, pruned or imported. error when it failed
+    pub action: String,
+    /// the status of the admin api call, once performed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EntityDiff {
+    pub kind: String,
+    pub id: String,
+    pub name: String,
+    /// one of added, changed, removed, unchanged or error
+    pub status: String,
+    pub changes: Vec<FieldChange>,
+    pub error: Option<String>,
+}
+
+/// How a directory export writes the entities of each kind
+#[derive(Clone, Copy, Debug)]
+enum ExportLayout {
+    /// one file per entity, as yaml when `yaml`, wrapped in the kubernetes armor when `kube`
+    Split { yaml: bool, kube: bool },
+    /// one pretty json array per kind
+    Array,
+    /// one ndjson line per entity
+    NdJson,
+}
+
 pub struct ResourcesCommand {}
 
 impl ResourcesCommand {
-    async fn handle_json_entity(
-        json: serde_json::Value,
-        exposed_resources: &OtoroshExposedResources,
+    /// Compute what pushing or deleting an entity would do on the cluster, without any write
+    async fn plan_entity(
+        resource: &OtoroshExposedResource,
+        id: String,
+        name: String,
+        body: &serde_json::Value,
+        deleting: bool,
         cli_opts: CliOpts,
-    ) -> () {
-        let id = EntityHelper::extract_json_entity_id(&json).unwrap();
-        let name = EntityHelper::extract_json_entity_name(&json).unwrap();
-        let kind: String = json.get("kind").unwrap().as_str().unwrap().to_string();
-        let final_resource: OtoroshExposedResource = exposed_resources
-            .resources
-            .clone()
-            .into_iter()
-            .find(|i| {
-                i.kind == kind
-                    || i.singular_name == kind.to_lowercase()
-                    || format!("{}/{}", i.group, i.kind) == kind
-            })
-            .unwrap();
-        let content = serde_json::to_string(&json).unwrap();
-        let config = Otoroshi::get_connection_config(cli_opts.clone()).await;
-        let res = Otoroshi::otoroshi_call(
-            hyper::Method::POST,
-            format!(
-                "/apis/{}/{}/{}/{}",
-                final_resource.group, final_resource.version.name, final_resource.plural_name, id
-            )
-            .as_str(),
-            None,
-            Some(hyper::Body::from(content)),
-            Some("application/json".to_string()),
-            config,
-        )
-        .await;
-        if res.status == 201 {
-            cli_stdout_printline!("  - {}: created", name);
-        } else if res.status == 200 {
-            match res.headers.get("otoroshi-entity-updated") {
-                Some(v) if v.as_str() == "true" => cli_stdout_printline!("  - {}: updated", name),
-                Some(v) if v.as_str() == "false" => {
-                    cli_stdout_printline!("  - {}: unchanged", name)
+    ) -> PlannedAction {
+        let current = Otoroshi::get_one_resource(resource.clone(), id.clone(), cli_opts).await;
+        let action = match (current, deleting) {
+            (None, false) => "create",
+            (None, true) => "unchanged",
+            (Some(_), true) => "delete",
+            (Some(current), false) => {
+                if DiffHelper::diff_declared(&current.body, &EntityHelper::without_kind(body))
+                    .is_empty()
+                {
+                    "unchanged"
+                } else {
+                    "update"
                 }
-                _ => cli_stdout_printline!("  - {}: updated", name),
-            };
-        } else {
-            cli_stdout_printline!("  - {}: error", name);
+            }
+        };
+        PlannedAction {
+            kind: format!("{}/{}", resource.group, resource.kind),
+            id,
+            name,
+            action: action.to_string(),
+            status: None,
+            error: None,
         }
     }
 
-    async fn delete_json_entity(
-        json: serde_json::Value,
+    async fn plan_document(
+        doc: &EntityDocument,
         exposed_resources: &OtoroshExposedResources,
+        deleting: bool,
         cli_opts: CliOpts,
-    ) -> () {
-        let id = EntityHelper::extract_json_entity_id(&json).unwrap();
-        let name = EntityHelper::extract_json_entity_name(&json).unwrap();
-        let kind: String = json.get("kind").unwrap().as_str().unwrap().to_string();
-        let final_resource: OtoroshExposedResource = exposed_resources
-            .resources
-            .clone()
-            .into_iter()
-            .find(|i| {
-                i.kind == kind
-                    || i.singular_name == kind.to_lowercase()
-                    || format!("{}/{}", i.group, i.kind) == kind
+    ) -> PlannedAction {
+        let resource = doc
+            .kind
+            .as_ref()
+            .and_then(|kind| exposed_resources.find_by_kind(kind));
+        match (resource, doc.id.clone()) {
+            (None, _) => PlannedAction {
+                kind: doc.kind.clone().unwrap_or("unknown".to_string()),
+                id: doc.id.clone().unwrap_or_default(),
+                name: doc.display_name(),
+                action: "error".to_string(),
+                status: None,
+                error: Some(format!("unknown kind in {}", doc.source)),
+            },
+            (Some(resource), None) => PlannedAction {
+                kind: format!("{}/{}", resource.group, resource.kind),
+                id: "".to_string(),
+                name: doc.display_name(),
+                action: "error".to_string(),
+                status: None,
+                error: Some(format!("missing entity id in {}", doc.source)),
+            },
+            (Some(resource), Some(id)) => {
+                Self::plan_entity(
+                    &resource,
+                    id,
+                    doc.display_name(),
+                    &doc.body,
+                    deleting,
+                    cli_opts,
+                )
+                .await
+            }
+        }
+    }
+
+    fn display_plan(plan: Vec<PlannedAction>, cli_opts: CliOpts) {
+        if !RenderHelper::display(&cli_opts, &plan, &[]) {
+            cli_stdout_printline!("dry run, the following actions would be performed:");
+            for planned in plan.iter() {
+                match &planned.error {
+                    Some(error) => {
+                        cli_stdout_printline!("  - {}: error - {}", planned.name, error)
+                    }
+                    None => cli_stdout_printline!(
+                        "  - {} ({} {}): {}",
+                        planned.name,
+                        planned.kind,
+                        planned.id,
+                        planned.action
+                    ),
+                }
+            }
+        }
+    }
+
+    fn exit_on_failure(results: &[PlannedAction]) {
+        if results.iter().any(ReportHelper::is_failure) {
+            std::process::exit(-1)
+        }
+    }
+
+    /// Check the report options before doing anything
+    fn check_report_options(report: &Option<String>, report_file: &Option<PathBuf>) {
+        match (report.as_deref(), report_file) {
+            (None, None) => (),
+            (Some("json" | "junit"), Some(_)) => (),
+            (Some("json" | "junit"), None) | (None, Some(_)) => {
+                cli_stderr_printline!("--report and --report-file must be used together");
+                std::process::exit(-1)
+            }
+            (Some(other), _) => {
+                cli_stderr_printline!(
+                    "unknown report format '{}' (can be one of: json, junit)",
+                    other
+                );
+                std::process::exit(-1)
+            }
+        }
+    }
+
+    /// Write the machine readable report of the results when asked
+    fn write_report(
+        command: &str,
+        results: &[PlannedAction],
+        report: &Option<String>,
+        report_file: &Option<PathBuf>,
+    ) {
+        if let (Some(format), Some(path)) = (report, report_file) {
+            let written = ReportHelper::render(format, command, results)
+                .and_then(|content| std::fs::write(path, content).map_err(|e| e.to_string()));
+            if let Err(e) = written {
+                cli_stderr_printline!("error while writing report {:?}: {}", path, e);
+                std::process::exit(-1)
+            }
+        }
+    }
+
+    /// Transform `PATH=VALUE` command line inputs into an otoroshi patch document. The value is
+    /// everything after the first `=`, without its surrounding quotes
+    fn data_as_oto_patch(data: &[String]) -> Result<serde_json::Value, String> {
+        data.iter()
+            .map(|entry| match entry.split_once('=') {
+                Some((path, value)) if !path.is_empty() => {
+                    let value = ['\'', '"']
+                        .into_iter()
+                        .find_map(|quote| {
+                            value
+                                .strip_prefix(quote)
+                                .and_then(|v| v.strip_suffix(quote))
+                        })
+                        .unwrap_or(value);
+                    Ok(serde_json::json!({ "path": path, "value": value }))
+                }
+                _ => Err(format!("invalid input '{}', expected PATH=VALUE", entry)),
             })
+            .collect::<Result<Vec<serde_json::Value>, String>>()
+            .map(serde_json::Value::Array)
+    }
+
+    /// Parse the `PATH=VALUE` inputs before calling the cluster
+    fn oto_patch(data: &[String]) -> serde_json::Value {
+        match Self::data_as_oto_patch(data) {
+            Ok(patch) => patch,
+            Err(e) => {
+                cli_stderr_printline!("{}", e);
+                std::process::exit(-1)
+            }
+        }
+    }
+
+    /// Keep the version of an entity replaced by a command in the local history
+    async fn record_history(
+        resource: &OtoroshExposedResource,
+        id: &str,
+        previous: serde_json::Value,
+        command: &str,
+        cli_opts: &CliOpts,
+    ) {
+        let config = OtoroshiCtlConfig::get_current_config(cli_opts.clone()).await;
+        let file = HistoryHelper::entity_file(
+            &HistoryHelper::dir(cli_opts.config_file.as_deref()),
+            &HistoryHelper::context(&config),
+            &format!("{}/{}", resource.group, resource.kind),
+            id,
+        );
+        if let Err(e) = HistoryHelper::record(&file, command, previous, chrono::Utc::now()) {
+            cli_stderr_printline!("{}", e);
+        }
+    }
+
+    /// The kind of an entity and its versions recorded in the local history for the current context
+    async fn history(
+        resource_name: &str,
+        id: &str,
+        cli_opts: &CliOpts,
+    ) -> (OtoroshExposedResource, Vec<HistoryEntry>) {
+        let exposed_resources = Otoroshi::get_exposed_resources(cli_opts.clone())
+            .await
             .unwrap();
-        let res = Otoroshi::delete_one_resource(final_resource, id, cli_opts.clone()).await;
-        if res {
-            cli_stdout_printline!("  - {}: deleted", name);
-        } else {
-            cli_stdout_printline!("  - {}: error", name);
+        let Some(resource) = exposed_resources.find_by_kind(resource_name) else {
+            cli_stderr_printline!("resource {} not found !", resource_name);
+            std::process::exit(-1)
+        };
+        let config = OtoroshiCtlConfig::get_current_config(cli_opts.clone()).await;
+        let file = HistoryHelper::entity_file(
+            &HistoryHelper::dir(cli_opts.config_file.as_deref()),
+            &HistoryHelper::context(&config),
+            &format!("{}/{}", resource.group, resource.kind),
+            id,
+        );
+        match HistoryHelper::read(&file) {
+            Ok(entries) => (resource, entries),
+            Err(e) => {
+                cli_stderr_printline!("{}", e);
+                std::process::exit(-1)
+            }
         }
     }
 
-    async fn handle_yaml_direct_entity(
-        json: serde_json::Value,
-        exposed_resources: &OtoroshExposedResources,
+    /// Upsert an entity, keeping the `previous` version in the local history when it succeeds.
+    /// The body is a plain entity unless a `content_type` like an oto-patch is given
+    async fn upsert_recorded(
+        resource: &OtoroshExposedResource,
+        id: &str,
+        body: String,
+        content_type: Option<&str>,
+        previous: Option<serde_json::Value>,
+        command: &str,
         cli_opts: CliOpts,
-    ) -> () {
-        let id = EntityHelper::extract_json_entity_id(&json).unwrap();
-        let name = EntityHelper::extract_json_entity_name(&json).unwrap();
-        let kind = json.get("kind").unwrap().as_str().unwrap().to_string();
-        let final_resource: OtoroshExposedResource = exposed_resources
-            .resources
-            .clone()
-            .into_iter()
-            .find(|i| i.kind == kind || i.singular_name == kind.to_lowercase())
-            .unwrap();
-        let content = serde_json::to_string(&json).unwrap();
-        let config = Otoroshi::get_connection_config(cli_opts.clone()).await;
-        let res = Otoroshi::otoroshi_call(
-            hyper::Method::POST,
-            format!(
-                "/apis/{}/{}/{}/{}",
-                final_resource.group, final_resource.version.name, final_resource.plural_name, id
-            )
-            .as_str(),
-            None,
-            Some(hyper::Body::from(content)),
-            Some("application/yaml".to_string()),
-            config,
-        )
-        .await;
-        if res.status == 201 {
-            cli_stdout_printline!("  - {}: created", name);
-        } else if res.status == 200 {
-            match res.headers.get("otoroshi-entity-updated") {
-                Some(v) if v.as_str() == "true" => cli_stdout_printline!("  - {}: updated", name),
-                Some(v) if v.as_str() == "false" => {
-                    cli_stdout_printline!("  - {}: unchanged", name)
-                }
-                _ => cli_stdout_printline!("  - {}: updated", name),
-            };
-        } else {
-            cli_stdout_printline!("  - {}: error", name);
+    ) -> bool {
+        let done = match content_type {
+            None => {
+                Otoroshi::upsert_one_resource(
+                    resource.clone(),
+                    id.to_string(),
+                    body,
+                    cli_opts.clone(),
+                )
+                .await
+            }
+            Some(content_type) => {
+                Otoroshi::upsert_one_resource_with_content_type(
+                    resource.clone(),
+                    id.to_string(),
+                    body,
+                    content_type.to_string(),
+                    cli_opts.clone(),
+                )
+                .await
+            }
+        };
+        if done && let Some(previous) = previous {
+            Self::record_history(resource, id, previous, command, &cli_opts).await;
         }
+        done
     }
 
-    async fn delete_yaml_direct_entity(
-        json: serde_json::Value,
-        exposed_resources: &OtoroshExposedResources,
+    /// Delete an entity, keeping its last version in the local history
+    async fn delete_recorded(
+        resource: OtoroshExposedResource,
+        id: String,
         cli_opts: CliOpts,
-    ) -> () {
-        let id = EntityHelper::extract_json_entity_id(&json).unwrap();
-        let name = EntityHelper::extract_json_entity_name(&json).unwrap();
-        let kind = json.get("kind").unwrap().as_str().unwrap().to_string();
-        let final_resource: OtoroshExposedResource = exposed_resources
-            .resources
-            .clone()
-            .into_iter()
-            .find(|i| i.kind == kind || i.singular_name == kind)
-            .unwrap();
-        let res = Otoroshi::delete_one_resource(final_resource, id, cli_opts.clone()).await;
-        if res {
-            cli_stdout_printline!("  - {}: deleted", name);
+    ) -> OtoroshiResponse {
+        let previous = Otoroshi::get_one_resource(resource.clone(), id.clone(), cli_opts.clone())
+            .await
+            .map(|res| res.body);
+        let res =
+            Otoroshi::delete_one_resource(resource.clone(), id.clone(), cli_opts.clone()).await;
+        if res.status == 200
+            && let Some(previous) = previous
+        {
+            Self::record_history(&resource, &id, previous, "delete", &cli_opts).await;
+        }
+        res
+    }
+
+    /// Upsert an entity read from a file, stdin, the editor or the command line.
+    /// The kube armor is removed if any and the id is read from the entity when not provided
+    async fn upsert_entity_input(
+        resource: &OtoroshExposedResource,
+        id: Option<String>,
+        content: String,
+        dry_run: bool,
+        command: &str,
+        cli_opts: CliOpts,
+    ) {
+        let is_yaml = !content.trim().starts_with("{");
+        let json = if is_yaml {
+            let json = serde_yaml::from_str::<serde_json::Value>(&content).unwrap();
+            let is_kube = json.get("apiVersion").is_some() && json.get("spec").is_some();
+            if is_kube {
+                json.get("spec").unwrap().clone()
+            } else {
+                json
+            }
+        } else {
+            serde_json::from_str::<serde_json::Value>(&content).unwrap()
+        };
+        let mut json = json;
+        if SecretHelper::contains_encrypted(&json)
+            && let Err(e) = SecretHelper::decrypt_all(&mut json, &mut Self::secret_key(&cli_opts))
+        {
+            cli_stderr_printline!("error while decrypting entity: {}", e);
+            std::process::exit(-1)
+        }
+        let id = id.unwrap_or_else(|| EntityHelper::extract_json_entity_id(&json).unwrap());
+        if dry_run {
+            let name = EntityHelper::extract_json_entity_name(&json).unwrap_or(id.clone());
+            let planned =
+                Self::plan_entity(resource, id, name, &json, false, cli_opts.clone()).await;
+            let plan = vec![planned];
+            Self::display_plan(plan.clone(), cli_opts);
+            Self::exit_on_failure(&plan);
         } else {
-            cli_stdout_printline!("  - {}: error", name);
+            let previous =
+                Otoroshi::get_one_resource(resource.clone(), id.clone(), cli_opts.clone())
+                    .await
+                    .map(|res| res.body);
+            Self::upsert_recorded(
+                resource,
+                &id,
+                serde_json::to_string(&json).unwrap(),
+                None,
+                previous,
+                command,
+                cli_opts,
+            )
+            .await;
         }
     }
 
-    async fn handle_yaml_kube_entity(
-        json: serde_yaml::Value,
+    /// Push a document to the cluster and report what has been done
+    async fn apply_document(
+        doc: &EntityDocument,
         exposed_resources: &OtoroshExposedResources,
         cli_opts: CliOpts,
-    ) -> () {
-        let spec = json.get("spec").unwrap();
-        let id = EntityHelper::extract_yaml_entity_id(spec).unwrap();
-        let metadata = json.get("metadata").unwrap();
-        let name = metadata
-            .get("name")
-            .map(|i| i.as_str().unwrap().to_string())
-            .or_else(|| EntityHelper::extract_yaml_entity_name(spec))
-            .unwrap();
-        let kind = json.get("kind").unwrap().as_str().unwrap().to_string();
-        let final_resource: OtoroshExposedResource = exposed_resources
-            .resources
-            .clone()
-            .into_iter()
-            .find(|i| i.kind == kind || i.singular_name == kind.to_lowercase())
-            .unwrap();
-        let content = serde_yaml::to_string(spec).unwrap();
+    ) -> PlannedAction {
+        let mut outcome = PlannedAction {
+            kind: doc.kind.clone().unwrap_or("unknown".to_string()),
+            id: doc.id.clone().unwrap_or_default(),
+            name: doc.display_name(),
+            action: "error".to_string(),
+            status: None,
+            error: None,
+        };
+        let final_resource = match doc
+            .kind
+            .as_ref()
+            .and_then(|kind| exposed_resources.find_by_kind(kind))
+        {
+            None => {
+                outcome.error = Some("unknown kind".to_string());
+                return outcome;
+            }
+            Some(resource) => resource,
+        };
+        outcome.kind = format!("{}/{}", final_resource.group, final_resource.kind);
+        let id = match doc.id.clone() {
+            None => {
+                outcome.error = Some("missing entity id".to_string());
+                return outcome;
+            }
+            Some(id) => id,
+        };
+        let previous =
+            Otoroshi::get_one_resource(final_resource.clone(), id.clone(), cli_opts.clone())
+                .await
+                .map(|res| res.body);
+        let content = serde_json::to_string(&doc.body).unwrap();
         let config = Otoroshi::get_connection_config(cli_opts.clone()).await;
         let res = Otoroshi::otoroshi_call(
             hyper::Method::POST,
@@ -216,242 +504,601 @@ impl ResourcesCommand {
             .as_str(),
             None,
             Some(hyper::Body::from(content)),
-            Some("application/yaml".to_string()),
+            Some("application/json".to_string()),
             config,
         )
         .await;
         if res.status == 201 {
-            cli_stdout_printline!("  - {}: created", name);
+            outcome.action = "created".to_string();
         } else if res.status == 200 {
-            cli_stdout_printline!("  - {}: updated", name);
+            outcome.action = match res.headers.get("otoroshi-entity-updated") {
+                Some(v) if v.as_str() == "false" => "unchanged".to_string(),
+                _ => "updated".to_string(),
+            };
+            if outcome.action == "updated"
+                && let Some(previous) = previous
+            {
+                Self::record_history(&final_resource, &id, previous, "apply", &cli_opts).await;
+            }
         } else {
-            cli_stdout_printline!(
-                "  - {}: error - {} - {:?}",
-                name,
-                res.status,
-                res.body_bytes
-            );
+            outcome.error = Some(String::from_utf8_lossy(&res.body_bytes).to_string());
         }
+        outcome.status = Some(res.status);
+        outcome
     }
 
-    async fn delete_yaml_kube_entity(
-        json: serde_yaml::Value,
-        exposed_resources: &OtoroshExposedResources,
-        cli_opts: CliOpts,
-    ) -> () {
-        let spec = json.get("spec").unwrap();
-        let id = EntityHelper::extract_yaml_entity_id(spec).unwrap();
-        let metadata = json.get("metadata").unwrap();
-        let name = metadata
-            .get("name")
-            .map(|i| i.as_str().unwrap().to_string())
-            .or_else(|| EntityHelper::extract_yaml_entity_name(spec))
-            .unwrap();
-        let kind = json.get("kind").unwrap().as_str().unwrap().to_string();
-        let final_resource: OtoroshExposedResource = exposed_resources
-            .resources
-            .clone()
-            .into_iter()
-            .find(|i| i.kind == kind || i.singular_name == kind.to_lowercase())
-            .unwrap();
-        let res = Otoroshi::delete_one_resource(final_resource, id, cli_opts.clone()).await;
-        if res {
-            cli_stdout_printline!("  - {}: deleted", name);
+    /// Report the result of a deletion, `action` being the name of a successful one
+    fn delete_outcome(
+        mut outcome: PlannedAction,
+        action: &str,
+        res: OtoroshiResponse,
+    ) -> PlannedAction {
+        if res.status == 200 {
+            outcome.action = action.to_string();
         } else {
-            cli_stdout_printline!("  - {}: error", name);
+            outcome.action = "error".to_string();
+            outcome.error = Some(String::from_utf8_lossy(&res.body_bytes).to_string());
         }
+        outcome.status = Some(res.status);
+        outcome
     }
 
-    async fn handle_yaml_entity(
-        yaml: serde_yaml::Value,
+    /// Delete an entity that is not declared anymore
+    async fn prune_entity(
+        planned: PlannedAction,
         exposed_resources: &OtoroshExposedResources,
         cli_opts: CliOpts,
-    ) -> () {
-        if yaml.get("spec").is_some() && yaml.get("kind").is_some() {
-            Self::handle_yaml_kube_entity(yaml, exposed_resources, cli_opts).await;
-        } else {
-            let json: serde_json::Value =
-                serde_yaml::from_value::<serde_json::Value>(yaml).unwrap();
-            Self::handle_yaml_direct_entity(json, exposed_resources, cli_opts).await;
-        }
+    ) -> PlannedAction {
+        let resource = exposed_resources.find_by_kind(&planned.kind).unwrap();
+        let res = Self::delete_recorded(resource, planned.id.clone(), cli_opts).await;
+        Self::delete_outcome(planned, "pruned", res)
     }
 
-    async fn delete_yaml_entity(
-        yaml: serde_yaml::Value,
-        exposed_resources: &OtoroshExposedResources,
-        cli_opts: CliOpts,
-    ) -> () {
-        if yaml.get("spec").is_some() && yaml.get("kind").is_some() {
-            Self::delete_yaml_kube_entity(yaml, exposed_resources, cli_opts).await;
-        } else {
-            let json: serde_json::Value =
-                serde_yaml::from_value::<serde_json::Value>(yaml).unwrap();
-            Self::delete_yaml_direct_entity(json, exposed_resources, cli_opts).await;
+    fn outcome_line(outcome: &PlannedAction) -> String {
+        match (&outcome.error, outcome.status) {
+            (Some(error), Some(status)) => {
+                format!("  - {}: error - {} - {}", outcome.name, status, error)
+            }
+            (Some(error), None) => format!("  - {}: error - {}", outcome.name, error),
+            (None, _) => format!("  - {}: {}", outcome.name, outcome.action),
         }
     }
 
-    async fn handle_json_file(
-        file: &PathBuf,
-        exposed_resources: &OtoroshExposedResources,
-        cli_opts: CliOpts,
-    ) -> () {
-        let content = fs::read_to_string(file).unwrap();
-        let json = serde_json::from_str::<serde_json::Value>(&content).unwrap();
-        if json.is_array() {
-            for doc in json.as_array().unwrap() {
-                Self::handle_json_entity(doc.to_owned(), exposed_resources, cli_opts.clone()).await;
-            }
-        } else {
-            Self::handle_json_entity(json, exposed_resources, cli_opts.clone()).await;
-        }
+    /// Run the futures, at most `concurrency` at the same time, and move the progress bar forward
+    /// each time one of them completes. Results are returned in the order of the futures
+    async fn run_concurrently<F: Future>(
+        futures: Vec<F>,
+        concurrency: usize,
+        progress: &Mutex<ProgressBar>,
+    ) -> Vec<F::Output> {
+        let semaphore = tokio::sync::Semaphore::new(concurrency.max(1));
+        futures::future::join_all(futures.into_iter().map(|future| async {
+            let _permit = semaphore.acquire().await.unwrap();
+            let output = future.await;
+            progress.lock().unwrap().inc();
+            output
+        }))
+        .await
     }
 
-    async fn delete_json_file(
-        file: &PathBuf,
-        exposed_resources: &OtoroshExposedResources,
-        cli_opts: CliOpts,
-    ) -> () {
-        let content = fs::read_to_string(file).unwrap();
-        let json = serde_json::from_str::<serde_json::Value>(&content).unwrap();
-        if json.is_array() {
-            for doc in json.as_array().unwrap() {
-                Self::delete_json_entity(doc.to_owned(), exposed_resources, cli_opts.clone()).await;
+    /// Display the number of entities created, updated, unchanged, pruned or failed for each kind
+    fn display_summary(outcomes: &[PlannedAction]) {
+        let actions = ["created", "updated", "unchanged", "pruned", "error"];
+        let mut counts: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for outcome in outcomes.iter() {
+            let row = counts
+                .entry(outcome.kind.clone())
+                .or_insert(vec![0; actions.len()]);
+            if let Some(idx) = actions.iter().position(|a| *a == outcome.action) {
+                row[idx] += 1;
             }
-        } else {
-            Self::delete_json_entity(json, exposed_resources, cli_opts.clone()).await;
         }
+        TableHelper::display_summary(
+            vec![
+                "kind",
+                "created",
+                "updated",
+                "unchanged",
+                "pruned",
+                "failed",
+            ],
+            counts.into_iter().collect(),
+        );
     }
 
-    async fn handle_json_body(
-        content: hyper::body::Bytes,
+    async fn delete_document(
+        doc: &EntityDocument,
         exposed_resources: &OtoroshExposedResources,
         cli_opts: CliOpts,
-    ) -> () {
-        let json = serde_json::from_slice::<serde_json::Value>(&content).unwrap();
-        if json.is_array() {
-            for doc in json.as_array().unwrap() {
-                Self::handle_json_entity(doc.to_owned(), exposed_resources, cli_opts.clone()).await;
+    ) -> PlannedAction {
+        let mut outcome = PlannedAction {
+            kind: doc.kind.clone().unwrap_or("unknown".to_string()),
+            id: doc.id.clone().unwrap_or_default(),
+            name: doc.display_name(),
+            action: "error".to_string(),
+            status: None,
+            error: None,
+        };
+        let final_resource = match doc
+            .kind
+            .as_ref()
+            .and_then(|kind| exposed_resources.find_by_kind(kind))
+        {
+            None => {
+                outcome.error = Some("unknown kind".to_string());
+                return outcome;
             }
-        } else {
-            Self::handle_json_entity(json, exposed_resources, cli_opts.clone()).await;
-        }
+            Some(resource) => resource,
+        };
+        outcome.kind = format!("{}/{}", final_resource.group, final_resource.kind);
+        let id = match doc.id.clone() {
+            None => {
+                outcome.error = Some("missing entity id".to_string());
+                return outcome;
+            }
+            Some(id) => id,
+        };
+        let res = Self::delete_recorded(final_resource, id, cli_opts).await;
+        Self::delete_outcome(outcome, "deleted", res)
     }
 
-    async fn delete_json_body(
-        content: hyper::body::Bytes,
+    /// Plan the deletion of the entities managed by `managed_by` whose kind is synced but whose id
+    /// is not declared in the documents anymore
+    async fn plan_prune(
+        documents: impl Iterator<Item = &EntityDocument>,
         exposed_resources: &OtoroshExposedResources,
+        managed_by: &str,
         cli_opts: CliOpts,
-    ) -> () {
-        let json = serde_json::from_slice::<serde_json::Value>(&content).unwrap();
-        if json.is_array() {
-            for doc in json.as_array().unwrap() {
-                Self::delete_json_entity(doc.to_owned(), exposed_resources, cli_opts.clone()).await;
+    ) -> Vec<PlannedAction> {
+        let mut local_ids: BTreeMap<String, (OtoroshExposedResource, HashSet<String>)> =
+            BTreeMap::new();
+        for doc in documents {
+            if let Some(resource) = doc
+                .kind
+                .as_ref()
+                .and_then(|kind| exposed_resources.find_by_kind(kind))
+            {
+                let ids = &mut local_ids
+                    .entry(format!("{}/{}", resource.group, resource.kind))
+                    .or_insert((resource, HashSet::new()))
+                    .1;
+                if let Some(id) = doc.id.clone() {
+                    ids.insert(id);
+                }
             }
-        } else {
-            Self::delete_json_entity(json, exposed_resources, cli_opts.clone()).await;
         }
-    }
-
-    async fn handle_yaml_file(
-        file: PathBuf,
-        exposed_resources: &OtoroshExposedResources,
-        cli_opts: CliOpts,
-    ) -> () {
-        let content = fs::read_to_string(file).unwrap();
-        if content.contains("---\n") {
-            for doc in content.split("---\n").filter(|s| !s.trim().is_empty()) {
-                let yaml = serde_yaml::from_str::<serde_yaml::Value>(doc).unwrap();
-                Self::handle_yaml_entity(yaml.to_owned(), exposed_resources, cli_opts.clone())
-                    .await;
+        let mut plan: Vec<PlannedAction> = Vec::new();
+        for (kind, (resource, ids)) in local_ids.into_iter() {
+            let remote_entities =
+                Otoroshi::get_all_resources(resource.clone(), Vec::new(), cli_opts.clone())
+                    .await
+                    .map(|res| res.body)
+                    .unwrap_or_default();
+            for entity in remote_entities.iter() {
+                if EntityHelper::managed_by(entity).as_deref() != Some(managed_by) {
+                    continue;
+                }
+                if let Some(id) = EntityHelper::extract_json_entity_id(entity)
+                    && !ids.contains(&id)
+                {
+                    plan.push(PlannedAction {
+                        kind: kind.clone(),
+                        name: EntityHelper::extract_json_entity_name(entity).unwrap_or(id.clone()),
+                        id,
+                        action: "delete".to_string(),
+                        status: None,
+                        error: None,
+                    });
+                }
             }
-        } else {
-            let yaml = serde_yaml::from_str::<serde_yaml::Value>(&content).unwrap();
-            Self::handle_yaml_entity(yaml, exposed_resources, cli_opts.clone()).await;
         }
+        plan
     }
 
-    async fn delete_yaml_file(
-        file: PathBuf,
+    /// Sort the documents in batches so that referenced entities are pushed first. Every reference
+    /// must exist either in the documents or on the cluster, otherwise nothing is written
+    async fn order_documents(
+        documents: Vec<EntityDocument>,
         exposed_resources: &OtoroshExposedResources,
         cli_opts: CliOpts,
-    ) -> () {
-        let content = fs::read_to_string(file).unwrap();
-        if content.contains("---\n") {
-            for doc in content.split("---\n").filter(|s| !s.trim().is_empty()) {
-                let yaml = serde_yaml::from_str::<serde_yaml::Value>(doc).unwrap();
-                Self::delete_yaml_entity(yaml.to_owned(), exposed_resources, cli_opts.clone())
-                    .await;
+    ) -> Vec<Vec<EntityDocument>> {
+        let resources: Vec<Option<OtoroshExposedResource>> = documents
+            .iter()
+            .map(|doc| {
+                doc.kind
+                    .as_ref()
+                    .and_then(|kind| exposed_resources.find_by_kind(kind))
+            })
+            .collect();
+        let keys: Vec<String> = documents
+            .iter()
+            .zip(resources.iter())
+            .map(|(doc, resource)| match (resource, &doc.id) {
+                (Some(resource), Some(id)) => {
+                    format!("{}/{}:{}", resource.group, resource.kind, id)
+                }
+                _ => "".to_string(),
+            })
+            .collect();
+        let batch: HashSet<&String> = keys.iter().collect();
+        let mut on_cluster: HashMap<String, bool> = HashMap::new();
+        let mut unresolved: Vec<String> = Vec::new();
+        let mut dependencies: Vec<Vec<String>> = Vec::new();
+        for (doc, resource) in documents.iter().zip(resources.iter()) {
+            let mut deps: Vec<String> = Vec::new();
+            let references = resource
+                .as_ref()
+                .map(|r| DependencyHelper::references(&r.kind, &doc.body))
+                .unwrap_or_default();
+            for reference in references.into_iter() {
+                let Some(ref_resource) = exposed_resources.find_by_kind(&reference.kind) else {
+                    continue;
+                };
+                let key = format!(
+                    "{}/{}:{}",
+                    ref_resource.group, ref_resource.kind, reference.id
+                );
+                if !batch.contains(&key) {
+                    let exists = match on_cluster.get(&key) {
+                        Some(exists) => *exists,
+                        None => {
+                            let exists = Otoroshi::get_one_resource(
+                                ref_resource,
+                                reference.id.clone(),
+                                cli_opts.clone(),
+                            )
+                            .await
+                            .is_some();
+                            on_cluster.insert(key.clone(), exists);
+                            exists
+                        }
+                    };
+                    if !exists {
+                        unresolved.push(format!(
+                            "  - {}: {} references the {} '{}' which exists neither in the synced entities nor on the cluster",
+                            doc.display_name(),
+                            reference.path,
+                            reference.kind,
+                            reference.id
+                        ));
+                    }
+                }
+                deps.push(key);
             }
-        } else {
-            let yaml = serde_yaml::from_str::<serde_yaml::Value>(&content).unwrap();
-            Self::delete_yaml_entity(yaml, exposed_resources, cli_opts.clone()).await;
+            dependencies.push(deps);
         }
-    }
-
-    async fn handle_yaml_body(
-        body: hyper::body::Bytes,
-        exposed_resources: &OtoroshExposedResources,
-        cli_opts: CliOpts,
-    ) -> () {
-        let content: String = String::from_utf8(body.to_vec()).unwrap();
-        if content.contains("---\n") {
-            for doc in content.split("---\n").filter(|s| !s.trim().is_empty()) {
-                let yaml = serde_yaml::from_str::<serde_yaml::Value>(doc).unwrap();
-                Self::handle_yaml_entity(yaml.to_owned(), exposed_resources, cli_opts.clone())
-                    .await;
+        if !unresolved.is_empty() {
+            cli_stderr_printline!("unresolved references, nothing has been applied:");
+            for line in unresolved.iter() {
+                cli_stderr_printline!("{}", line);
             }
-        } else {
-            let yaml = serde_yaml::from_str::<serde_yaml::Value>(&content).unwrap();
-            Self::handle_yaml_entity(yaml, exposed_resources, cli_opts.clone()).await;
+            std::process::exit(-1)
+        }
+        let (_, cyclic) = DependencyHelper::order(&keys, &dependencies);
+        if !cyclic.is_empty() {
+            let names: Vec<String> = cyclic
+                .iter()
+                .map(|idx| documents[*idx].display_name())
+                .collect();
+            cli_stderr_printline!(
+                "warning: circular references between {}, they will be applied in file order",
+                names.join(", ")
+            );
         }
+        let mut slots: Vec<Option<EntityDocument>> = documents.into_iter().map(Some).collect();
+        DependencyHelper::batches(&keys, &dependencies)
+            .into_iter()
+            .map(|batch| {
+                batch
+                    .into_iter()
+                    .filter_map(|idx| slots[idx].take())
+                    .collect()
+            })
+            .collect()
     }
 
-    async fn delete_yaml_body(
-        body: hyper::body::Bytes,
-        exposed_resources: &OtoroshExposedResources,
+    async fn sync_documents(
+        mut documents: Vec<EntityDocument>,
+        options: &SyncOptions,
         cli_opts: CliOpts,
-    ) -> () {
-        let content: String = String::from_utf8(body.to_vec()).unwrap();
-        if content.contains("---\n") {
-            for doc in content.split("---\n").filter(|s| !s.trim().is_empty()) {
-                let yaml = serde_yaml::from_str::<serde_yaml::Value>(doc).unwrap();
-                Self::delete_yaml_entity(yaml.to_owned(), exposed_resources, cli_opts.clone())
-                    .await;
+    ) -> Vec<PlannedAction> {
+        Self::decrypt_documents(&mut documents, &cli_opts);
+        let exposed_resources = Otoroshi::get_exposed_resources(cli_opts.clone())
+            .await
+            .unwrap();
+        if options.validate {
+            let violations = Self::validate_documents(
+                &documents,
+                Some(exposed_resources.clone()),
+                false,
+                cli_opts.clone(),
+            )
+            .await;
+            if !violations.is_empty() {
+                return Self::invalid_outcomes(&documents, &violations);
+            }
+        }
+        if let Some(managed_by) = &options.managed_by {
+            for doc in documents.iter_mut() {
+                EntityHelper::stamp_managed_by(&mut doc.body, managed_by);
+            }
+        }
+        let batches = Self::order_documents(documents, &exposed_resources, cli_opts.clone()).await;
+        let prune_plan = match (&options.managed_by, options.prune) {
+            (Some(managed_by), true) => {
+                Self::plan_prune(
+                    batches.iter().flatten(),
+                    &exposed_resources,
+                    managed_by,
+                    cli_opts.clone(),
+                )
+                .await
+            }
+            _ => Vec::new(),
+        };
+        if options.dry_run {
+            let mut plan: Vec<PlannedAction> = Vec::new();
+            for doc in batches.iter().flatten() {
+                plan.push(
+                    Self::plan_document(doc, &exposed_resources, false, cli_opts.clone()).await,
+                );
             }
+            plan.extend(prune_plan);
+            Self::display_plan(plan.clone(), cli_opts);
+            plan
         } else {
-            let yaml = serde_yaml::from_str::<serde_yaml::Value>(&content).unwrap();
-            Self::delete_yaml_entity(yaml, exposed_resources, cli_opts.clone()).await;
+            let total = batches.iter().map(Vec::len).sum::<usize>() + prune_plan.len();
+            let progress = Mutex::new(ProgressBar::new("apply", total));
+            let report = |outcome: PlannedAction| {
+                progress
+                    .lock()
+                    .unwrap()
+                    .println(&Self::outcome_line(&outcome));
+                outcome
+            };
+            let mut outcomes: Vec<PlannedAction> = Vec::new();
+            for batch in batches.iter() {
+                let futures = batch
+                    .iter()
+                    .map(|doc| async {
+                        report(
+                            Self::apply_document(doc, &exposed_resources, cli_opts.clone()).await,
+                        )
+                    })
+                    .collect();
+                outcomes
+                    .extend(Self::run_concurrently(futures, options.concurrency, &progress).await);
+            }
+            if !prune_plan.is_empty() {
+                progress.lock().unwrap().println(&format!(
+                    "will try to prune {} entities ...",
+                    prune_plan.len()
+                ));
+            }
+            let futures = prune_plan
+                .into_iter()
+                .map(|planned| async {
+                    report(Self::prune_entity(planned, &exposed_resources, cli_opts.clone()).await)
+                })
+                .collect();
+            outcomes.extend(Self::run_concurrently(futures, options.concurrency, &progress).await);
+            progress.lock().unwrap().finish();
+            Self::display_summary(&outcomes);
+            outcomes
         }
     }
 
-    async fn sync_files(files: Vec<PathBuf>, cli_opts: CliOpts) -> () {
-        cli_stdout_printline!("will try to sync {} files ...", files.len());
-        let exposed_resources = Otoroshi::get_exposed_resources(cli_opts.clone())
-            .await
-            .unwrap();
-        for file in files.iter() {
-            let fname = file.file_name().unwrap().to_str().unwrap();
-            if fname.ends_with(".json") {
-                Self::handle_json_file(file, &exposed_resources, cli_opts.clone()).await;
-            } else if fname.ends_with(".yaml") || fname.ends_with(".yml") {
-                Self::handle_yaml_file(file.to_owned(), &exposed_resources, cli_opts.clone()).await;
+    /// The schema of a kind: the one derived from the template of the cluster unless `offline`,
+    /// cached for the offline runs, else the cached one, else the embedded one
+    async fn kind_schema(
+        resource: &OtoroshExposedResource,
+        cache: &Path,
+        offline: bool,
+        cli_opts: CliOpts,
+    ) -> Option<serde_json::Value> {
+        if !offline
+            && let Some(template) =
+                Otoroshi::get_resource_template(resource.clone(), cli_opts).await
+        {
+            let schema = SchemaHelper::from_template(&template);
+            if let Err(e) = SchemaHelper::store(cache, resource, &schema) {
+                cli_stderr_printline!("{}", e);
             }
+            return Some(schema);
         }
+        SchemaHelper::cached(cache, resource).or_else(|| SchemaHelper::embedded(&resource.kind))
     }
 
-    async fn delete_files(files: Vec<PathBuf>, cli_opts: CliOpts) -> () {
-        cli_stdout_printline!("will try to delete {} files ...", files.len());
+    /// Check the kind, the id and the fields of each document against the schema of its kind.
+    /// The kinds of the cluster are the given ones, the fetched ones, or the cached ones when
+    /// `offline` or when the cluster can't be reached
+    async fn validate_documents(
+        documents: &[EntityDocument],
+        exposed_resources: Option<OtoroshExposedResources>,
+        offline: bool,
+        cli_opts: CliOpts,
+    ) -> Vec<SchemaViolation> {
+        let cache = SchemaHelper::cache_dir(cli_opts.config_file.as_deref());
+        let exposed_resources = match exposed_resources {
+            Some(exposed_resources) => Some(exposed_resources),
+            None if offline => SchemaHelper::cached_resources(&cache),
+            None => Otoroshi::get_exposed_resources(cli_opts.clone())
+                .await
+                .or_else(|| SchemaHelper::cached_resources(&cache)),
+        };
+        if let Some(exposed_resources) = &exposed_resources
+            && let Err(e) = SchemaHelper::store_resources(&cache, exposed_resources)
+        {
+            cli_stderr_printline!("{}", e);
+        }
+        let mut schemas: HashMap<String, Option<serde_json::Value>> = HashMap::new();
+        let mut contents: HashMap<String, String> = HashMap::new();
+        let mut violations: Vec<SchemaViolation> = Vec::new();
+        for doc in documents.iter() {
+            let content = contents
+                .entry(doc.source.clone())
+                .or_insert_with(|| std::fs::read_to_string(&doc.source).unwrap_or_default());
+            let mut violation = |path: &str, message: String| {
+                let mut keys: Vec<&str> = if doc.kube { vec!["spec"] } else { Vec::new() };
+                keys.extend(path.split('.').filter(|k| !k.is_empty()));
+                violations.push(SchemaViolation {
+                    source: doc.source.clone(),
+                    line: SchemaHelper::line_of(content, doc.line, &keys),
+                    path: path.to_string(),
+                    message,
+                })
+            };
+            let Some(kind) = &doc.kind else {
+                violation("", "missing kind".to_string());
+                continue;
+            };
+            if doc.id.is_none() {
+                violation("", "missing id".to_string());
+            }
+            let resource = exposed_resources.as_ref().map(|e| e.find_by_kind(kind));
+            let schema = match resource {
+                Some(None) => {
+                    violation("", format!("unknown kind {}", kind));
+                    continue;
+                }
+                Some(Some(resource)) => {
+                    let key = format!("{}/{}", resource.group, resource.kind);
+                    if !schemas.contains_key(&key) {
+                        let schema =
+                            Self::kind_schema(&resource, &cache, offline, cli_opts.clone()).await;
+                        schemas.insert(key.clone(), schema);
+                    }
+                    schemas[&key].clone()
+                }
+                // without the kinds of the cluster, only the embedded schemas can be used
+                None => SchemaHelper::embedded(kind.rsplit('/').next().unwrap_or(kind)),
+            };
+            match schema {
+                Some(schema) => {
+                    for (path, message) in SchemaHelper::validate(&schema, &doc.body) {
+                        violation(&path, message);
+                    }
+                }
+                None => cli_stderr_printline!(
+                    "no schema for kind {}, {} not validated",
+                    kind,
+                    doc.display_name()
+                ),
+            }
+        }
+        violations
+    }
+
+    fn display_violations(violations: &[SchemaViolation]) {
+        for violation in violations.iter() {
+            cli_stderr_printline!(
+                "{}:{}: {}",
+                violation.source,
+                violation.line,
+                violation.message
+            );
+        }
+    }
+
+    /// One failed outcome per invalid document, nothing is applied when a document is invalid
+    fn invalid_outcomes(
+        documents: &[EntityDocument],
+        violations: &[SchemaViolation],
+    ) -> Vec<PlannedAction> {
+        Self::display_violations(violations);
+        cli_stderr_printline!(
+            "{} validation errors, nothing applied. Use --skip-validation to apply anyway",
+            violations.len()
+        );
+        // a violation belongs to the last document of its source starting before it
+        let owner = |violation: &SchemaViolation| {
+            documents
+                .iter()
+                .enumerate()
+                .filter(|(_, doc)| doc.source == violation.source && doc.line <= violation.line)
+                .max_by_key(|(_, doc)| doc.line)
+                .map(|(idx, _)| idx)
+        };
+        documents
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, doc)| {
+                let errors: Vec<String> = violations
+                    .iter()
+                    .filter(|v| owner(v) == Some(idx))
+                    .map(|v| format!("{}:{}: {}", v.source, v.line, v.message))
+                    .collect();
+                (!errors.is_empty()).then(|| PlannedAction {
+                    kind: doc.kind.clone().unwrap_or("unknown".to_string()),
+                    id: doc.id.clone().unwrap_or_default(),
+                    name: doc.display_name(),
+                    action: "error".to_string(),
+                    status: None,
+                    error: Some(errors.join("\n")),
+                })
+            })
+            .collect()
+    }
+
+    async fn delete_documents(
+        documents: Vec<EntityDocument>,
+        dry_run: bool,
+        cli_opts: CliOpts,
+    ) -> Vec<PlannedAction> {
         let exposed_resources = Otoroshi::get_exposed_resources(cli_opts.clone())
             .await
             .unwrap();
-        for file in files.iter() {
-            let fname = file.file_name().unwrap().to_str().unwrap();
-            if fname.ends_with(".json") {
-                Self::delete_json_file(file, &exposed_resources, cli_opts.clone()).await;
-            } else if fname.ends_with(".yaml") || fname.ends_with(".yml") {
-                Self::delete_yaml_file(file.to_owned(), &exposed_resources, cli_opts.clone()).await;
+        if dry_run {
+            let mut plan: Vec<PlannedAction> = Vec::new();
+            for doc in documents.iter() {
+                plan.push(
+                    Self::plan_document(doc, &exposed_resources, true, cli_opts.clone()).await,
+                );
             }
+            Self::display_plan(plan.clone(), cli_opts);
+            plan
+        } else {
+            let mut outcomes: Vec<PlannedAction> = Vec::new();
+            for doc in documents.iter() {
+                let outcome =
+                    Self::delete_document(doc, &exposed_resources, cli_opts.clone()).await;
+                cli_stdout_printline!("{}", Self::outcome_line(&outcome));
+                outcomes.push(outcome);
+            }
+            outcomes
+        }
+    }
+
+    fn files_as_sources(files: Vec<PathBuf>) -> Vec<String> {
+        files
+            .into_iter()
+            .map(|f| f.to_string_lossy().to_string())
+            .collect()
+    }
+
+    async fn sync_files(
+        files: Vec<PathBuf>,
+        options: &SyncOptions,
+        cli_opts: CliOpts,
+    ) -> Vec<PlannedAction> {
+        if !options.dry_run {
+            cli_stdout_printline!("will try to sync {} files ...", files.len());
+        }
+        let documents = Self::load_documents(Self::files_as_sources(files), &options.vars).await;
+        Self::sync_documents(documents, options, cli_opts).await
+    }
+
+    async fn delete_files(
+        files: Vec<PathBuf>,
+        dry_run: bool,
+        vars: &serde_json::Value,
+        cli_opts: CliOpts,
+    ) -> Vec<PlannedAction> {
+        if !dry_run {
+            cli_stdout_printline!("will try to delete {} files ...", files.len());
         }
+        let documents = Self::load_documents(Self::files_as_sources(files), vars).await;
+        Self::delete_documents(documents, dry_run, cli_opts).await
     }
 
     async fn fetch_url_http(url: String) -> (hyper::body::Bytes, String) {
@@ -528,68 +1175,56 @@ impl ResourcesCommand {
         }
     }
 
-    async fn sync_url_http(url: String, cli_opts: CliOpts) -> () {
-        cli_stdout_printline!("will try to sync one url ...");
-        let (body, content_type) = Self::fetch_url_http(url).await;
-        let exposed_resources = Otoroshi::get_exposed_resources(cli_opts.clone())
-            .await
-            .unwrap();
-        if content_type.starts_with("application/json") {
-            Self::handle_json_body(body, &exposed_resources, cli_opts.clone()).await;
-        } else if content_type.starts_with("application/yaml") {
-            Self::handle_yaml_body(body, &exposed_resources, cli_opts.clone()).await;
+    async fn fetch_url_documents(url: String, vars: &serde_json::Value) -> Vec<EntityDocument> {
+        let (body, content_type) = if url.starts_with("https://") {
+            Self::fetch_url_https(url.clone()).await
         } else {
-            cli_stderr_printline!("bad response content-type {}", content_type);
-            std::process::exit(-1)
-        }
-    }
-
-    async fn sync_url_https(url: String, cli_opts: CliOpts) -> () {
-        cli_stdout_printline!("will try to sync one url ...");
-        let (body, content_type) = Self::fetch_url_https(url).await;
-        let exposed_resources = Otoroshi::get_exposed_resources(cli_opts.clone())
-            .await
-            .unwrap();
-        if content_type.starts_with("application/json") {
-            Self::handle_json_body(body, &exposed_resources, cli_opts.clone()).await;
+            Self::fetch_url_http(url.clone()).await
+        };
+        let is_json = if content_type.starts_with("application/json") {
+            true
         } else if content_type.starts_with("application/yaml") {
-            Self::handle_yaml_body(body, &exposed_resources, cli_opts.clone()).await;
+            false
         } else {
             cli_stderr_printline!("bad response content-type {}", content_type);
             std::process::exit(-1)
+        };
+        let raw_content: String = String::from_utf8(body.to_vec()).unwrap();
+        let content = match TemplateHelper::render(&raw_content, vars) {
+            Ok(content) => content,
+            Err(e) => {
+                cli_stderr_printline!("error while rendering {}:\n{}", url, e);
+                std::process::exit(-1)
+            }
+        };
+        match EntityHelper::parse_documents(&content, is_json, &url) {
+            Ok(documents) => documents,
+            Err(e) => {
+                cli_stderr_printline!("{}", e);
+                std::process::exit(-1)
+            }
         }
     }
 
-    async fn delete_url_http(url: String, cli_opts: CliOpts) -> () {
-        cli_stdout_printline!("will try to delete one url ...");
-        let (body, content_type) = Self::fetch_url_http(url).await;
-        let exposed_resources = Otoroshi::get_exposed_resources(cli_opts.clone())
-            .await
-            .unwrap();
-        if content_type.starts_with("application/json") {
-            Self::delete_json_body(body, &exposed_resources, cli_opts.clone()).await;
-        } else if content_type.starts_with("application/yaml") {
-            Self::delete_yaml_body(body, &exposed_resources, cli_opts.clone()).await;
-        } else {
-            cli_stderr_printline!("bad response content-type {}", content_type);
-            std::process::exit(-1)
+    async fn sync_url(url: String, options: &SyncOptions, cli_opts: CliOpts) -> Vec<PlannedAction> {
+        if !options.dry_run {
+            cli_stdout_printline!("will try to sync one url ...");
         }
+        let documents = Self::fetch_url_documents(url, &options.vars).await;
+        Self::sync_documents(documents, options, cli_opts).await
     }
 
-    async fn delete_url_https(url: String, cli_opts: CliOpts) -> () {
-        cli_stdout_printline!("will try to delete one url ...");
-        let (body, content_type) = Self::fetch_url_https(url).await;
-        let exposed_resources = Otoroshi::get_exposed_resources(cli_opts.clone())
-            .await
-            .unwrap();
-        if content_type.starts_with("application/json") {
-            Self::delete_json_body(body, &exposed_resources, cli_opts.clone()).await;
-        } else if content_type.starts_with("application/yaml") {
-            Self::delete_yaml_body(body, &exposed_resources, cli_opts.clone()).await;
-        } else {
-            cli_stderr_printline!("bad response content-type {}", content_type);
-            std::process::exit(-1)
+    async fn delete_url(
+        url: String,
+        dry_run: bool,
+        vars: &serde_json::Value,
+        cli_opts: CliOpts,
+    ) -> Vec<PlannedAction> {
+        if !dry_run {
+            cli_stdout_printline!("will try to delete one url ...");
         }
+        let documents = Self::fetch_url_documents(url, vars).await;
+        Self::delete_documents(documents, dry_run, cli_opts).await
     }
 
     fn find_files(directory: &PathBuf, recursive: bool) -> Vec<PathBuf> {
@@ -608,49 +1243,479 @@ impl ResourcesCommand {
         res.to_vec()
     }
 
-    fn display_table_of_routes(vec: Vec<TableResource>) {
-        TableHelper::display_table_of_resources_with_custom_columns(
-            vec,
-            vec![
-                "id".to_string(),
-                "name".to_string(),
-                "enabled".to_string(),
-                "debug_flow".to_string(),
-                "capture".to_string(),
-                "frontend.domains".to_string(),
-                "backend.targets".to_string(),
-                "plugins".to_string(),
-            ],
-        )
+    /// The columns displayed for a kind, unless custom comma separated columns are provided
+    fn table_columns(kind: &str, columns_raw: &[String]) -> Vec<String> {
+        if columns_raw.is_empty() {
+            match kind {
+                "route" => vec![
+                    "id".to_string(),
+                    "name".to_string(),
+                    "enabled".to_string(),
+                    "debug_flow".to_string(),
+                    "capture".to_string(),
+                    "frontend.domains".to_string(),
+                    "backend.targets".to_string(),
+                    "plugins".to_string(),
+                ],
+                _ => TableHelper::default_columns(),
+            }
+        } else {
+            columns_raw
+                .iter()
+                .flat_map(|col| col.split(",").map(|s| s.to_string()))
+                .collect()
+        }
     }
 
-    fn display_table_of_resources(kind: String, vec: Vec<TableResource>, columns_raw: Vec<String>) {
-        if columns_raw.is_empty() {
-            match kind.as_str() {
-                "route" => Self::display_table_of_routes(vec),
-                _ => TableHelper::display_table_of_resources_default(vec),
+    /// Check the custom columns, `--query` and `-o jsonpath=` expressions before calling the cluster
+    fn check_expressions(columns_raw: &[String], query: &Option<String>, cli_opts: &CliOpts) {
+        let columns = columns_raw
+            .iter()
+            .flat_map(|col| col.split(","))
+            .map(|col| ("column", col));
+        let jsonpath = cli_opts
+            .ouput
+            .as_deref()
+            .and_then(|o| o.strip_prefix("jsonpath="))
+            .map(|e| ("jsonpath", e));
+        let query = query.as_deref().map(|e| ("query", e));
+        for (name, expression) in columns.chain(jsonpath).chain(query) {
+            if let Err(e) = JsonPathHelper::select(expression, &serde_json::Value::Null) {
+                cli_stderr_printline!("invalid {} {}: {}", name, expression, e);
+                std::process::exit(-1)
+            }
+        }
+    }
+
+    /// The body and content type patching an entity, with its current version. `--data`
+    /// inputs are sent as an otoroshi patch, patch documents are applied here
+    async fn patch_entity(
+        exposed_resource: &OtoroshExposedResource,
+        resource_name: &str,
+        id: &str,
+        patch: &Option<(serde_json::Value, bool)>,
+        oto_patch: &serde_json::Value,
+        cli_opts: CliOpts,
+    ) -> Result<(String, Option<&'static str>, serde_json::Value), String> {
+        let res =
+            Otoroshi::get_one_resource(exposed_resource.clone(), id.to_string(), cli_opts.clone())
+                .await
+                .ok_or(format!(
+                    "error while fetching resource {}/{}",
+                    resource_name, id
+                ))?;
+        let Some((input, json_patch)) = patch else {
+            return Ok((
+                oto_patch.to_string(),
+                Some("application/json+oto-patch"),
+                res.body,
+            ));
+        };
+        let doc = if *json_patch {
+            JsonPatchHelper::apply(&res.body, input)
+                .map_err(|e| format!("error while patching {}/{}: {}", resource_name, id, e))?
+        } else {
+            let mut doc = res.body.clone();
+            doc.merge(input);
+            doc
+        };
+        Ok((serde_json::to_string(&doc).unwrap(), None, res.body))
+    }
+
+    /// Parse the `-l` and `--tag` selectors before calling the cluster
+    fn selector(selector: &[String], tags: &[String]) -> Option<Selector> {
+        match Selector::parse(selector, tags) {
+            Ok(selector) => selector,
+            Err(e) => {
+                cli_stderr_printline!("{}", e);
+                std::process::exit(-1)
+            }
+        }
+    }
+
+    /// The ids of the entities of a kind matching the selector
+    async fn selected_ids(
+        resource: &OtoroshExposedResource,
+        selector: &Selector,
+        cli_opts: CliOpts,
+    ) -> Vec<String> {
+        match Otoroshi::get_all_resources(resource.clone(), Vec::new(), cli_opts).await {
+            Some(raw_resources) => selector
+                .select(raw_resources.body)
+                .iter()
+                .filter_map(EntityHelper::extract_json_entity_id)
+                .collect(),
+            None => {
+                cli_stderr_printline!("error while fetching {}", resource.plural_name);
+                std::process::exit(-1)
             }
+        }
+    }
+
+    /// Display entities in the format of `-o`, or as a table. Values that are not entities, like
+    /// the ones selected by `--query`, are displayed one per line
+    fn display_entities(document: serde_json::Value, columns: Vec<String>, cli_opts: &CliOpts) {
+        if RenderHelper::display(cli_opts, &document, &columns) {
+            return;
+        }
+        let rows = RenderHelper::rows(&document);
+        if rows.iter().all(|v| v.is_object()) {
+            TableHelper::display_table_of_resources_with_custom_columns(
+                rows.into_iter().map(|raw| TableResource { raw }).collect(),
+                columns,
+            )
         } else {
-            let columns: Vec<String> = columns_raw
+            for value in rows.iter() {
+                cli_stdout_printline!("{}", JsonPathHelper::text(value));
+            }
+        }
+    }
+
+    /// Display the values selected by `--query` in the format of `-o`. A `-o jsonpath=` is applied
+    /// to the list of the selected values
+    fn display_query(
+        document: serde_json::Value,
+        query: &str,
+        columns: Vec<String>,
+        cli_opts: &CliOpts,
+    ) {
+        match JsonPathHelper::select(query, &document) {
+            Ok(values) => {
+                Self::display_entities(serde_json::Value::Array(values), columns, cli_opts)
+            }
+            Err(e) => {
+                cli_stderr_printline!("invalid query {}: {}", query, e);
+                std::process::exit(-1)
+            }
+        }
+    }
+
+    fn kube_entity(kind: &str, res_body: serde_json::Value) -> KubeEntity {
+        let res_name = EntityHelper::extract_json_entity_name(&res_body).unwrap();
+        KubeEntity::new(kind.to_string(), res_name, res_body)
+    }
+
+    /// Display entities wrapped in the kubernetes armor, as yaml
+    fn display_kube_entities(document: serde_json::Value, kind: String) {
+        let armor = |res_body: serde_json::Value| Self::kube_entity(&kind, res_body);
+        match document {
+            serde_json::Value::Array(items) => {
+                let kube_entities: Vec<KubeEntity> = items.into_iter().map(armor).collect();
+                cli_stdout_printline!("{}", serde_yaml::to_string(&kube_entities).unwrap())
+            }
+            entity => cli_stdout_printline!("{}", serde_yaml::to_string(&armor(entity)).unwrap()),
+        }
+    }
+
+    /// Print the entities of a kind page by page, as they are fetched, so the whole list is
+    /// never held in memory
+    async fn stream_entities(
+        resource: OtoroshExposedResource,
+        filters: Vec<String>,
+        selector: Option<Selector>,
+        mut stream: RenderStream,
+        kube: bool,
+        cli_opts: CliOpts,
+    ) {
+        let kind = format!("{}/{}", resource.group, resource.kind);
+        let mut pager = ResourcePager::new(resource.clone(), filters, cli_opts).await;
+        let mut fetched = false;
+        loop {
+            let page = match pager.next_page().await {
+                Some(Some(page)) => match &selector {
+                    Some(selector) => selector.select(page),
+                    None => page,
+                },
+                Some(None) => break,
+                None if !fetched => {
+                    cli_stdout_printline!("resources {} not found", resource.plural_name);
+                    return;
+                }
+                None => {
+                    cli_stderr_printline!("error while fetching {}", resource.plural_name);
+                    std::process::exit(-1)
+                }
+            };
+            fetched = true;
+            let chunk = if kube {
+                stream.push(
+                    &page
+                        .into_iter()
+                        .map(|entity| Self::kube_entity(&kind, entity))
+                        .collect::<Vec<KubeEntity>>(),
+                )
+            } else {
+                stream.push(
+                    &page
+                        .iter()
+                        .map(|entity| EntityHelper::with_kind(entity, kind.to_string()))
+                        .collect::<Vec<serde_json::Value>>(),
+                )
+            };
+            match chunk {
+                Ok(chunk) => cli_stdout_print!("{}", chunk),
+                Err(e) => {
+                    cli_stderr_printline!("{}", e);
+                    std::process::exit(-1)
+                }
+            }
+        }
+        cli_stdout_print!("{}", stream.finish());
+    }
+
+    fn display_watch_event(event: &WatchEvent, columns: &[String], cli_opts: &CliOpts) {
+        match cli_opts.ouput.as_deref() {
+            Some("json") => cli_stdout_printline!("{}", serde_json::to_string(event).unwrap()),
+            Some("json_pretty") => {
+                cli_stdout_printline!("{}", serde_json::to_string_pretty(event).unwrap())
+            }
+            Some("yaml") => {
+                cli_stdout_printline!("---\n{}", serde_yaml::to_string(event).unwrap().trim_end())
+            }
+            _ => {
+                let mut cells = vec![event.event_type.clone()];
+                cells.extend(
+                    columns
+                        .iter()
+                        .map(|column| RenderHelper::cell_text(column, &event.entity)),
+                );
+                cli_stdout_printline!("{}", TableHelper::format_row(&cells, 20));
+            }
+        }
+    }
+
+    /// Lint local documents, locating the findings in their sources
+    fn lint_documents(documents: &[EntityDocument], config: &LintConfig) -> Vec<LintFinding> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut contents: HashMap<String, String> = HashMap::new();
+        let mut findings: Vec<LintFinding> = Vec::new();
+        for doc in documents.iter() {
+            let Some(kind) = &doc.kind else {
+                continue;
+            };
+            let content = contents
+                .entry(doc.source.clone())
+                .or_insert_with(|| std::fs::read_to_string(&doc.source).unwrap_or_default());
+            for mut finding in LintHelper::lint(kind, &doc.body, config, now) {
+                let mut keys: Vec<&str> = if doc.kube { vec!["spec"] } else { Vec::new() };
+                keys.extend(finding.path.split('.').filter(|k| !k.is_empty()));
+                finding.line = Some(SchemaHelper::line_of(content, doc.line, &keys));
+                finding.source = Some(doc.source.clone());
+                findings.push(finding);
+            }
+        }
+        findings
+    }
+
+    /// Lint the entities of the current cluster having a kind checked by the rules
+    async fn lint_cluster(config: &LintConfig, cli_opts: CliOpts) -> Vec<LintFinding> {
+        let exposed_resources = Otoroshi::get_exposed_resources(cli_opts.clone())
+            .await
+            .unwrap();
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut findings: Vec<LintFinding> = Vec::new();
+        for kind in ["Route", "Backend", "ApiKey", "Certificate"] {
+            let Some(resource) = exposed_resources.find_by_kind(kind) else {
+                continue;
+            };
+            let entities = Otoroshi::get_all_resources(resource, Vec::new(), cli_opts.clone())
+                .await
+                .map(|res| res.body)
+                .unwrap_or_default();
+            for entity in entities.iter() {
+                findings.extend(LintHelper::lint(kind, entity, config, now));
+            }
+        }
+        findings
+    }
+
+    /// Fetch an entity and resolve the entities it references, the plugins it uses, the apikeys
+    /// authorized on it and its certificates
+    async fn describe(resource_name: &str, id: &str, cli_opts: CliOpts) -> Description {
+        let exposed_resources = Otoroshi::get_exposed_resources(cli_opts.clone())
+            .await
+            .unwrap();
+        let Some(resource) = exposed_resources.find_by_kind(resource_name) else {
+            cli_stderr_printline!("resource {} not found !", resource_name);
+            std::process::exit(-1)
+        };
+        let Some(entity) =
+            Otoroshi::get_one_resource(resource.clone(), id.to_string(), cli_opts.clone())
+                .await
+                .map(|res| res.body)
+        else {
+            cli_stderr_printline!("resource {} with id {} not found", resource_name, id);
+            std::process::exit(-1)
+        };
+        let fetch = |kind: &str, id: String| {
+            let resource = exposed_resources.find_by_kind(kind);
+            let cli_opts = cli_opts.clone();
+            async move {
+                match resource {
+                    Some(resource) => Otoroshi::get_one_resource(resource, id, cli_opts)
+                        .await
+                        .map(|res| res.body),
+                    None => None,
+                }
+            }
+        };
+        let (certificate_refs, references): (Vec<EntityReference>, Vec<EntityReference>) =
+            DependencyHelper::references(&resource.kind, &entity)
                 .into_iter()
-                .flat_map(|col| {
-                    let sub_cols: Vec<String> = col.split(",").map(|s| s.to_string()).collect();
-                    sub_cols
-                })
-                .collect();
-            TableHelper::display_table_of_resources_with_custom_columns(vec, columns);
+                .partition(|r| r.kind == "Certificate");
+        let referenced =
+            futures::future::join_all(references.iter().map(|r| fetch(&r.kind, r.id.clone())))
+                .await;
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut certificates: Vec<RelatedEntity> = Vec::new();
+        for reference in certificate_refs.iter() {
+            certificates.push(match fetch("Certificate", reference.id.clone()).await {
+                Some(certificate) => {
+                    DescribeHelper::certificate(&reference.path, &certificate, now)
+                }
+                None => DescribeHelper::related(reference, None),
+            });
+        }
+        let domains: Vec<String> = entity
+            .get("frontend")
+            .and_then(|f| f.get("domains"))
+            .and_then(|d| d.as_array())
+            .map(|d| {
+                d.iter()
+                    .filter_map(|d| d.as_str().map(|d| d.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        if let (false, Some(certificates_resource)) = (
+            domains.is_empty(),
+            exposed_resources.find_by_kind("Certificate"),
+        ) {
+            let all =
+                Otoroshi::get_all_resources(certificates_resource, Vec::new(), cli_opts.clone())
+                    .await
+                    .map(|res| res.body)
+                    .unwrap_or_default();
+            certificates.extend(DescribeHelper::certificates_for_domains(
+                &domains, &all, now,
+            ));
+        }
+        let plugins = if entity.get("plugins").is_some_and(|p| p.is_array()) {
+            let catalog = Otoroshi::get_plugins(cli_opts.clone())
+                .await
+                .unwrap_or_default();
+            DescribeHelper::plugins(&entity, &catalog)
+        } else {
+            Vec::new()
+        };
+        let authorizable = [
+            "Route",
+            "RouteComposition",
+            "ServiceDescriptor",
+            "ServiceGroup",
+        ];
+        let apikeys = match exposed_resources.find_by_kind("ApiKey") {
+            Some(apikeys_resource) if authorizable.contains(&resource.kind.as_str()) => {
+                let groups: Vec<String> = entity
+                    .get("groups")
+                    .and_then(|g| g.as_array())
+                    .map(|g| {
+                        g.iter()
+                            .filter_map(|g| g.as_str().map(|g| g.to_string()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let apikeys: Vec<(serde_json::Value, Vec<EntityReference>)> =
+                    Otoroshi::get_all_resources(apikeys_resource, Vec::new(), cli_opts.clone())
+                        .await
+                        .map(|res| res.body)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|apikey| {
+                            let refs = DependencyHelper::references("ApiKey", &apikey);
+                            (apikey, refs)
+                        })
+                        .collect();
+                DescribeHelper::authorized_apikeys(&resource.kind, id, &groups, &apikeys)
+            }
+            _ => Vec::new(),
+        };
+        Description {
+            kind: format!("{}/{}", resource.group, resource.kind),
+            id: id.to_string(),
+            name: EntityHelper::extract_json_entity_name(&entity),
+            enabled: entity.get("enabled").and_then(|e| e.as_bool()),
+            references: references
+                .iter()
+                .zip(referenced.iter())
+                .map(|(r, entity)| DescribeHelper::related(r, entity.as_ref()))
+                .collect(),
+            plugins,
+            apikeys,
+            certificates,
+            entity,
         }
     }
 
-    pub fn with_kind(value: &serde_json::Value, kd: String) -> serde_json::Value {
-        let mut kind: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
-        kind.insert("kind".to_string(), serde_json::Value::String(kd));
-        let mut entity_with_kind = value.clone();
-        entity_with_kind.merge(&serde_json::Value::Object(kind));
-        entity_with_kind
+    /// Poll the entities of a kind, or one entity, and print what changed at each poll until the
+    /// command is interrupted. Existing entities are first reported as added
+    async fn watch_resources(
+        resource: OtoroshExposedResource,
+        id: Option<String>,
+        filters: Vec<String>,
+        selector: Option<Selector>,
+        columns: Vec<String>,
+        interval: u64,
+        cli_opts: CliOpts,
+    ) {
+        let kind = format!("{}/{}", resource.group, resource.kind);
+        if !matches!(
+            cli_opts.ouput.as_deref(),
+            Some("json" | "json_pretty" | "yaml")
+        ) {
+            let mut titles = vec!["EVENT".to_string()];
+            titles.extend(columns.iter().map(|c| c.to_uppercase()));
+            cli_stdout_printline!("{}", TableHelper::format_row(&titles, 20));
+        }
+        let mut state: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+        loop {
+            let current = match &id {
+                // a missing entity is deleted, a failed request is retried on the next poll
+                Some(id) => {
+                    Otoroshi::find_one_resource(resource.clone(), id.to_string(), cli_opts.clone())
+                        .await
+                        .map(|entity| entity.into_iter().collect())
+                }
+                None => {
+                    Otoroshi::get_all_resources(resource.clone(), filters.clone(), cli_opts.clone())
+                        .await
+                        .map(|res| res.body)
+                }
+            };
+            match current {
+                None => cli_stderr_printline!("error while fetching {}", resource.plural_name),
+                Some(current) => {
+                    let current = match &selector {
+                        Some(selector) => selector.select(current),
+                        None => current,
+                    };
+                    let (events, next) = WatchHelper::events(&kind, &state, current);
+                    for event in events.iter() {
+                        Self::display_watch_event(event, &columns, &cli_opts);
+                    }
+                    state = next;
+                }
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(interval.max(1))).await;
+        }
     }
 
-    fn run_watch(path: String, dir: bool, recursive: bool, cli_opts: CliOpts) {
+    fn run_watch(
+        path: String,
+        dir: bool,
+        recursive: bool,
+        options: SyncOptions,
+        cli_opts: CliOpts,
+    ) {
         let path_err = path.clone();
         let mut watcher = notify::recommended_watcher(move |res| match res {
             Ok(_) => {
@@ -658,10 +1723,11 @@ impl ResourcesCommand {
                     if dir {
                         let files =
                             Self::find_files(&PathBuf::from(path_err.to_owned()), recursive);
-                        Self::sync_files(files, cli_opts.clone()).await;
+                        Self::sync_files(files, &options, cli_opts.clone()).await;
                     } else {
                         Self::sync_files(
                             vec![PathBuf::from(path_err.to_owned())],
+                            &options,
                             cli_opts.clone(),
                         )
                         .await;
@@ -685,63 +1751,1241 @@ impl ResourcesCommand {
             .unwrap();
     }
 
-    pub async fn display(cli_opts: CliOpts, command: &ResourcesSubCommand) -> () {
-        match command {
-            ResourcesSubCommand::Rbac {
-                file,
-                username,
-                namespace,
-            } => {
-                let exposed_resources: OtoroshExposedResources =
-                    Otoroshi::get_exposed_resources(cli_opts.clone())
-                        .await
-                        .unwrap();
-                let resources = exposed_resources
-                    .resources
+    fn find_sources(
+        file: &Option<String>,
+        directory: &Option<PathBuf>,
+        recursive: &Option<bool>,
+    ) -> Vec<String> {
+        match file {
+            Some(file) => vec![file.to_string()],
+            None => match directory {
+                None => {
+                    cli_stderr_printline!("you need to provide a file or directory path");
+                    std::process::exit(-1)
+                }
+                Some(directory) => Self::find_files(directory, recursive.unwrap_or(false))
                     .into_iter()
-                    .map(|resource| format!("      - {}", resource.plural_name))
-                    .collect::<Vec<String>>()
-                    .join("\n");
-                let final_namespace: String = namespace.to_owned().unwrap_or("default".to_string());
-                let final_username: String = username
-                    .to_owned()
-                    .unwrap_or("otoroshi-admin-user".to_string());
-                let output: String = format!(
-                    "---
-kind: ServiceAccount
-apiVersion: v1
-metadata:
-  name: {}
----
-apiVersion: rbac.authorization.k8s.io/v1
-kind: ClusterRoleBinding
-metadata:
-  name: {}
-roleRef:
-  apiGroup: rbac.authorization.k8s.io
-  kind: ClusterRole
-  name: {}
-subjects:
-- kind: ServiceAccount
-  name: {}
-  namespace: {}
----
-kind: ClusterRole
-apiVersion: rbac.authorization.k8s.io/v1
-metadata:
-  name: {}
-rules:
-  - apiGroups:
-      - \"\"
-    resources:
-      - services
-      - endpoints
-      - secrets
-      - configmaps
-      - deployments
-      - pods
-      - namespaces
-    verbs:
+                    .map(|f| f.to_string_lossy().to_string())
+                    .collect(),
+            },
+        }
+    }
+
+    /// The entity files of `resources convert`, ndjson files included, in a stable order
+    fn convert_sources(
+        file: &Option<String>,
+        directory: &Option<PathBuf>,
+        recursive: bool,
+    ) -> Vec<String> {
+        let Some(directory) = directory else {
+            return Self::find_sources(file, directory, &Some(recursive));
+        };
+        let mut sources: Vec<String> = WalkDir::new(directory)
+            .max_depth(if recursive { usize::MAX } else { 1 })
+            .follow_links(true)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.path().to_string_lossy().to_string())
+            .filter(|f| {
+                [".json", ".yaml", ".yml", ".ndjson"]
+                    .iter()
+                    .any(|ext| f.ends_with(ext))
+            })
+            .collect();
+        sources.sort();
+        sources
+    }
+
+    /// Write converted entities to a directory: `<plural>/<id>.<ext>` for each entity, or
+    /// `<plural>.ndjson` for each kind
+    fn write_converted(
+        converted: Vec<(String, String, serde_json::Value)>,
+        format: EntityFormat,
+        directory: &Path,
+    ) -> Result<usize, String> {
+        let write = |path: PathBuf, content: String| {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("error while creating {:?}: {}", parent, e))?;
+            }
+            std::fs::write(&path, content)
+                .map_err(|e| format!("error while writing {:?}: {}", path, e))
+        };
+        if format == EntityFormat::NdJson {
+            let mut kinds: BTreeMap<String, Vec<serde_json::Value>> = BTreeMap::new();
+            for (plural, _, entity) in converted.into_iter() {
+                kinds.entry(plural).or_default().push(entity);
+            }
+            for (plural, entities) in kinds.iter() {
+                write(
+                    directory.join(format!("{}.ndjson", plural)),
+                    ConvertHelper::render(entities, format),
+                )?;
+            }
+            return Ok(kinds.len());
+        }
+        let count = converted.len();
+        for (plural, id, entity) in converted.into_iter() {
+            write(
+                directory
+                    .join(plural)
+                    .join(format!("{}.{}", id, format.extension())),
+                ConvertHelper::render(&[entity], format),
+            )?;
+        }
+        Ok(count)
+    }
+
+    /// Convert the service descriptors into routes and print them, write them, or apply them and
+    /// disable the migrated service descriptors
+    async fn migrate_service_descriptors(
+        ids: &[String],
+        out_dir: &Option<PathBuf>,
+        apply: bool,
+        dry_run: bool,
+        report_file: &Option<PathBuf>,
+        cli_opts: CliOpts,
+    ) {
+        let exposed_resources = Otoroshi::get_exposed_resources(cli_opts.clone())
+            .await
+            .unwrap();
+        let Some(resource) = exposed_resources.find_by_kind("ServiceDescriptor") else {
+            cli_stderr_printline!("resource ServiceDescriptor not found !");
+            std::process::exit(-1)
+        };
+        let descriptors: Vec<serde_json::Value> =
+            Otoroshi::get_all_resources(resource.clone(), Vec::new(), cli_opts.clone())
+                .await
+                .map(|res| res.body)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|descriptor| {
+                    let id = EntityHelper::extract_json_entity_id(descriptor).unwrap_or_default();
+                    if ids.is_empty() {
+                        !INTERNAL_DESCRIPTORS.contains(&id.as_str())
+                    } else {
+                        ids.contains(&id)
+                    }
+                })
+                .collect();
+        for id in ids.iter() {
+            if !descriptors
+                .iter()
+                .any(|d| EntityHelper::extract_json_entity_id(d).as_ref() == Some(id))
+            {
+                cli_stderr_printline!("service descriptor {} not found !", id);
+                std::process::exit(-1)
+            }
+        }
+        let mut routes: Vec<serde_json::Value> = Vec::new();
+        let mut reports: Vec<MigrationReport> = Vec::new();
+        for descriptor in descriptors.iter() {
+            let (route, unmigrated) = MigrateHelper::route(descriptor);
+            let report = MigrationReport {
+                descriptor: EntityHelper::extract_json_entity_id(descriptor).unwrap_or_default(),
+                name: EntityHelper::extract_json_entity_name(descriptor).unwrap_or_default(),
+                route: EntityHelper::extract_json_entity_id(&route).unwrap_or_default(),
+                unmigrated,
+            };
+            for setting in report.unmigrated.iter() {
+                cli_stderr_printline!(
+                    "service descriptor {} ({}): {} has no equivalent",
+                    report.descriptor,
+                    report.name,
+                    setting
+                );
+            }
+            routes.push(route);
+            reports.push(report);
+        }
+        cli_stderr_printline!(
+            "{} service descriptors migrated, {} settings without equivalent",
+            reports.len(),
+            reports.iter().map(|r| r.unmigrated.len()).sum::<usize>()
+        );
+        if let Some(report_file) = report_file
+            && let Err(e) =
+                std::fs::write(report_file, serde_json::to_string_pretty(&reports).unwrap())
+        {
+            cli_stderr_printline!("error while writing {:?}: {}", report_file, e);
+            std::process::exit(-1)
+        }
+        if !apply {
+            Self::output_routes(routes, out_dir);
+            return;
+        }
+        let routes: Vec<serde_json::Value> = routes
+            .iter()
+            .map(|route| EntityHelper::with_kind(route, "proxy.otoroshi.io/Route".to_string()))
+            .collect();
+        let documents = EntityHelper::parse_documents(
+            &serde_json::to_string(&routes).unwrap(),
+            true,
+            "migrate",
+        )
+        .unwrap();
+        let options = SyncOptions {
+            dry_run,
+            prune: false,
+            managed_by: None,
+            vars: serde_json::json!({}),
+            concurrency: 1,
+            validate: true,
+        };
+        let results = Self::sync_documents(documents, &options, cli_opts.clone()).await;
+        for (descriptor, report) in descriptors.iter().zip(reports.iter()) {
+            let applied = results
+                .iter()
+                .any(|r| r.id == report.route && r.action != "error");
+            if !applied || descriptor.get("enabled") == Some(&serde_json::json!(false)) {
+                continue;
+            }
+            if dry_run {
+                cli_stdout_printline!("service descriptor {} would be disabled", report.descriptor);
+                continue;
+            }
+            let mut disabled = descriptor.clone();
+            disabled["enabled"] = serde_json::json!(false);
+            if Self::upsert_recorded(
+                &resource,
+                &report.descriptor,
+                serde_json::to_string(&disabled).unwrap(),
+                None,
+                Some(descriptor.clone()),
+                "migrate",
+                cli_opts.clone(),
+            )
+            .await
+            {
+                cli_stdout_printline!("service descriptor {} disabled", report.descriptor);
+            } else {
+                cli_stderr_printline!(
+                    "error while disabling service descriptor {}",
+                    report.descriptor
+                );
+            }
+        }
+        Self::exit_on_failure(&results);
+    }
+
+    /// The connection config of a named context of the config file
+    fn context_config(
+        config: &OtoroshiCtlConfig,
+        name: &str,
+    ) -> crate::utils::otoroshi::OtoroshiConnectionConfig {
+        let known = config.contexts.iter().find(|c| c.name == name);
+        match known {
+            Some(context)
+                if config.clusters.iter().any(|c| c.name == context.cluster)
+                    && config.users.iter().any(|u| u.name == context.user) =>
+            {
+                config.get_context(name.to_string())
+            }
+            Some(_) => {
+                cli_stderr_printline!("context {} has no cluster or user !", name);
+                std::process::exit(-1)
+            }
+            None => {
+                cli_stderr_printline!("context {} not found !", name);
+                std::process::exit(-1)
+            }
+        }
+    }
+
+    /// Copy entities of a kind from a context to another. The entities are fetched by id or with
+    /// a selector, their ids remapped and their fields overridden, then the changes are printed
+    /// and written to the target context, keeping the previous versions in its local history
+    #[allow(clippy::too_many_arguments)]
+    async fn copy_between_contexts(
+        resource_name: &str,
+        ids: &[String],
+        selector: Option<Selector>,
+        from_context: &Option<String>,
+        to_context: &str,
+        map_ids: &[String],
+        set: &[String],
+        dry_run: bool,
+        cli_opts: CliOpts,
+    ) {
+        if ids.is_empty() && selector.is_none() {
+            cli_stderr_printline!("you need to provide ids or a selector");
+            std::process::exit(-1)
+        }
+        let mut renames: HashMap<String, String> = HashMap::new();
+        for map_id in map_ids.iter() {
+            match map_id.split_once('=') {
+                Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+                    renames.insert(old.to_string(), new.to_string());
+                }
+                _ => {
+                    cli_stderr_printline!(
+                        "invalid id mapping '{}', expected OLD_ID=NEW_ID",
+                        map_id
+                    );
+                    std::process::exit(-1)
+                }
+            }
+        }
+        let patch = Self::oto_patch(set);
+        let config = OtoroshiCtlConfig::get_current_config(cli_opts.clone()).await;
+        let source = match from_context {
+            Some(name) => Self::context_config(&config, name),
+            None => Otoroshi::get_connection_config(cli_opts.clone()).await,
+        };
+        let target = Self::context_config(&config, to_context);
+        let find_resource = |exposed: Option<OtoroshExposedResources>, context: &str| {
+            let Some(exposed) = exposed else {
+                cli_stderr_printline!("error while fetching the resources of {}", context);
+                std::process::exit(-1)
+            };
+            match exposed.find_by_kind(resource_name) {
+                Some(resource) => resource,
+                None => {
+                    cli_stderr_printline!("resource {} not found in {} !", resource_name, context);
+                    std::process::exit(-1)
+                }
+            }
+        };
+        let from_name = from_context
+            .clone()
+            .unwrap_or(config.current_context.clone());
+        let source_resource = find_resource(
+            Otoroshi::get_exposed_resources_with_config(source.clone()).await,
+            &from_name,
+        );
+        let target_resource = find_resource(
+            Otoroshi::get_exposed_resources_with_config(target.clone()).await,
+            to_context,
+        );
+        let mut entities: Vec<serde_json::Value> = Vec::new();
+        if ids.is_empty() {
+            match Otoroshi::get_all_resources_with_config(
+                source_resource.clone(),
+                Vec::new(),
+                source.clone(),
+            )
+            .await
+            {
+                Some(res) => entities.extend(res.body),
+                None => {
+                    cli_stderr_printline!("error while fetching {}", source_resource.plural_name);
+                    std::process::exit(-1)
+                }
+            }
+        } else {
+            for id in ids.iter() {
+                match Otoroshi::get_one_resource_with_config(
+                    source_resource.clone(),
+                    id.clone(),
+                    source.clone(),
+                )
+                .await
+                {
+                    Some(res) => entities.push(res.body),
+                    None => {
+                        cli_stderr_printline!(
+                            "{} {} not found in {} !",
+                            resource_name,
+                            id,
+                            from_name
+                        );
+                        std::process::exit(-1)
+                    }
+                }
+            }
+        }
+        if let Some(selector) = &selector {
+            entities = selector.select(entities);
+        }
+        let kind = format!("{}/{}", target_resource.group, target_resource.kind);
+        let mut copies: Vec<(serde_json::Value, Option<serde_json::Value>)> = Vec::new();
+        let mut results: Vec<EntityDiff> = Vec::new();
+        for entity in entities.into_iter() {
+            let old_id = EntityHelper::extract_json_entity_id(&entity).unwrap_or_default();
+            let id = renames.get(&old_id).cloned().unwrap_or(old_id);
+            let mut copy = entity;
+            NormalizeHelper::strip_server_fields(&mut copy);
+            EntityHelper::unstamp_managed_by(&mut copy);
+            EntityHelper::set_json_entity_id(&mut copy, &id);
+            let copy = match JsonPatchHelper::apply_oto_patch(&copy, &patch) {
+                Ok(copy) => copy,
+                Err(e) => {
+                    cli_stderr_printline!("error while overriding the fields of {}: {}", id, e);
+                    std::process::exit(-1)
+                }
+            };
+            let name = EntityHelper::extract_json_entity_name(&copy).unwrap_or(id.clone());
+            let current = Otoroshi::get_one_resource_with_config(
+                target_resource.clone(),
+                id.clone(),
+                target.clone(),
+            )
+            .await
+            .map(|res| res.body);
+            let (status, changes) = match &current {
+                None => ("added", Vec::new()),
+                Some(current) => match DiffHelper::diff_declared(current, &copy) {
+                    changes if changes.is_empty() => ("unchanged", changes),
+                    changes => ("changed", changes),
+                },
+            };
+            results.push(EntityDiff {
+                kind: kind.clone(),
+                id,
+                name,
+                status: status.to_string(),
+                changes,
+                error: None,
+            });
+            copies.push((copy, current));
+        }
+        Self::display_diff(&results);
+        if dry_run {
+            return;
+        }
+        let history = HistoryHelper::dir(cli_opts.config_file.as_deref());
+        let mut failures = 0;
+        for ((copy, current), result) in copies.into_iter().zip(results.iter()) {
+            if result.status == "unchanged" {
+                continue;
+            }
+            if !Otoroshi::upsert_one_resource_with_config(
+                target_resource.clone(),
+                result.id.clone(),
+                serde_json::to_string(&copy).unwrap(),
+                target.clone(),
+            )
+            .await
+            {
+                cli_stderr_printline!("error while copying {} to {}", result.id, to_context);
+                failures += 1;
+                continue;
+            }
+            if let Some(current) = current {
+                let file = HistoryHelper::entity_file(&history, to_context, &kind, &result.id);
+                if let Err(e) = HistoryHelper::record(&file, "copy", current, chrono::Utc::now()) {
+                    cli_stderr_printline!("{}", e);
+                }
+            }
+            cli_stdout_printline!("{} copied to {}", result.id, to_context);
+        }
+        if failures > 0 {
+            std::process::exit(-1)
+        }
+    }
+
+    /// Print generated routes as yaml entity files, or write one file per route in `out_dir`
+    fn output_routes(routes: Vec<serde_json::Value>, out_dir: &Option<PathBuf>) {
+        let routes: Vec<serde_json::Value> = routes
+            .iter()
+            .map(|route| EntityHelper::with_kind(route, "proxy.otoroshi.io/Route".to_string()))
+            .collect();
+        let Some(out_dir) = out_dir else {
+            cli_stdout_printline!(
+                "{}",
+                ConvertHelper::render(&routes, EntityFormat::Yaml).trim_end()
+            );
+            return;
+        };
+        let converted = routes
+            .into_iter()
+            .map(|route| {
+                let id = EntityHelper::extract_json_entity_id(&route).unwrap_or_default();
+                ("routes".to_string(), id, route)
+            })
+            .collect();
+        match Self::write_converted(converted, EntityFormat::Yaml, out_dir) {
+            Ok(count) => cli_stderr_printline!("{} files written", count),
+            Err(e) => {
+                cli_stderr_printline!("{}", e);
+                std::process::exit(-1)
+            }
+        }
+    }
+
+    /// Print local entities as a yaml stream, or a json array, with their kind or their kube armor
+    fn display_documents(documents: Vec<EntityDocument>, kube: bool, cli_opts: CliOpts) {
+        let entities: Vec<serde_json::Value> = documents
+            .into_iter()
+            .map(|doc| match doc.kind.clone() {
+                Some(kind) if kube => {
+                    let short_kind = kind.rsplit('/').next().unwrap_or_default().to_string();
+                    serde_json::to_value(KubeEntity::new(
+                        short_kind,
+                        doc.display_name(),
+                        EntityHelper::without_kind(&doc.body),
+                    ))
+                    .unwrap()
+                }
+                Some(kind) => EntityHelper::with_kind(&doc.body, kind),
+                None => doc.body,
+            })
+            .collect();
+        match cli_opts.ouput.as_deref() {
+            Some("json") => cli_stdout_printline!("{}", serde_json::to_string(&entities).unwrap()),
+            Some("json_pretty") => {
+                cli_stdout_printline!("{}", serde_json::to_string_pretty(&entities).unwrap())
+            }
+            _ => {
+                for entity in entities.iter() {
+                    cli_stdout_printline!(
+                        "---\n{}",
+                        serde_yaml::to_string(entity).unwrap().trim_end()
+                    );
+                }
+            }
+        };
+    }
+
+    async fn read_overlay_file(path: &Path, vars: &serde_json::Value) -> serde_json::Value {
+        let source = path.to_string_lossy().to_string();
+        let content = crate::utils::file::FileHelper::get_content_string_result(&source)
+            .await
+            .map_err(|e| format!("error while reading {}: {}", source, e))
+            .and_then(|content| {
+                TemplateHelper::render(&content, vars)
+                    .map_err(|e| format!("error while rendering {}:\n{}", source, e))
+            })
+            .and_then(|content| {
+                serde_yaml::from_str::<serde_json::Value>(&content)
+                    .map_err(|e| format!("error while parsing {}: {}", source, e))
+            });
+        match content {
+            Ok(content) => content,
+            Err(e) => {
+                cli_stderr_printline!("{}", e);
+                std::process::exit(-1)
+            }
+        }
+    }
+
+    /// Build the entities of an overlay directory: the entities of its bases, its own resources,
+    /// then its patches. A directory without `overlay.yaml` is a plain base
+    async fn build_overlay(
+        directory: &Path,
+        vars: &serde_json::Value,
+        visited: &mut Vec<PathBuf>,
+    ) -> Vec<EntityDocument> {
+        let canonical = match directory.canonicalize() {
+            Ok(canonical) => canonical,
+            Err(e) => {
+                cli_stderr_printline!("error while reading {:?}: {}", directory, e);
+                std::process::exit(-1)
+            }
+        };
+        if visited.contains(&canonical) {
+            cli_stderr_printline!("circular overlay bases on {:?}", directory);
+            std::process::exit(-1)
+        }
+        let overlay_file = directory.join(OVERLAY_FILE);
+        if !overlay_file.exists() {
+            let mut files = Self::find_files(&directory.to_path_buf(), true);
+            files.sort();
+            return Self::load_documents(Self::files_as_sources(files), vars).await;
+        }
+        visited.push(canonical);
+        let spec: OverlaySpec =
+            match serde_json::from_value(Self::read_overlay_file(&overlay_file, vars).await) {
+                Ok(spec) => spec,
+                Err(e) => {
+                    cli_stderr_printline!("error while parsing {:?}: {}", overlay_file, e);
+                    std::process::exit(-1)
+                }
+            };
+        let mut documents: Vec<EntityDocument> = Vec::new();
+        for base in spec.bases.iter() {
+            documents
+                .extend(Box::pin(Self::build_overlay(&directory.join(base), vars, visited)).await);
+        }
+        let resources: Vec<String> = spec
+            .resources
+            .iter()
+            .map(|r| directory.join(r).to_string_lossy().to_string())
+            .collect();
+        documents.extend(Self::load_documents(resources, vars).await);
+        let mut patches: Vec<ResolvedPatch> = Vec::new();
+        for (idx, patch) in spec.patches.into_iter().enumerate() {
+            let source = format!("{}#patches.{}", overlay_file.to_string_lossy(), idx);
+            let resolved = match (patch.target, patch.path, patch.merge, patch.json_patch) {
+                (None, Some(path), None, None) => {
+                    let patch_docs = Self::load_documents(
+                        vec![directory.join(path).to_string_lossy().to_string()],
+                        vars,
+                    )
+                    .await;
+                    OverlayHelper::patches_from_documents(patch_docs)
+                }
+                (Some(target), Some(path), None, None) => {
+                    let content = Self::read_overlay_file(&directory.join(path), vars).await;
+                    let operation = if JsonPatchHelper::is_json_patch(&content) {
+                        PatchOperation::JsonPatch(content)
+                    } else {
+                        PatchOperation::Merge(content)
+                    };
+                    Ok(vec![ResolvedPatch {
+                        target,
+                        operation,
+                        source,
+                    }])
+                }
+                (Some(target), None, Some(merge), None) => Ok(vec![ResolvedPatch {
+                    target,
+                    operation: PatchOperation::Merge(merge),
+                    source,
+                }]),
+                (Some(target), None, None, Some(json_patch)) => Ok(vec![ResolvedPatch {
+                    target,
+                    operation: PatchOperation::JsonPatch(json_patch),
+                    source,
+                }]),
+                _ => Err(format!(
+                    "invalid patch {}, expected a path, or a target with one of path, merge or json_patch",
+                    source
+                )),
+            };
+            match resolved {
+                Ok(resolved) => patches.extend(resolved),
+                Err(e) => {
+                    cli_stderr_printline!("{}", e);
+                    std::process::exit(-1)
+                }
+            }
+        }
+        visited.pop();
+        match OverlayHelper::apply(documents, &patches) {
+            Ok(documents) => documents,
+            Err(e) => {
+                cli_stderr_printline!("{}", e);
+                std::process::exit(-1)
+            }
+        }
+    }
+
+    /// Read the values files, later files override the values of the previous ones
+    async fn load_values(values: &[String]) -> serde_json::Value {
+        let mut vars = serde_json::json!({});
+        for file in values.iter() {
+            let content =
+                match crate::utils::file::FileHelper::get_content_string_result(file).await {
+                    Ok(content) => content,
+                    Err(e) => {
+                        cli_stderr_printline!("error while reading {}: {}", file, e);
+                        std::process::exit(-1)
+                    }
+                };
+            match serde_yaml::from_str::<serde_json::Value>(&content) {
+                Ok(serde_json::Value::Object(values)) => {
+                    vars.merge(&serde_json::Value::Object(values))
+                }
+                Ok(serde_json::Value::Null) => (),
+                Ok(_) => {
+                    cli_stderr_printline!("error while reading {}: values must be an object", file);
+                    std::process::exit(-1)
+                }
+                Err(e) => {
+                    cli_stderr_printline!("error while parsing {}: {}", file, e);
+                    std::process::exit(-1)
+                }
+            }
+        }
+        vars
+    }
+
+    async fn load_documents(sources: Vec<String>, vars: &serde_json::Value) -> Vec<EntityDocument> {
+        let mut documents: Vec<EntityDocument> = Vec::new();
+        for source in sources.iter() {
+            match EntityHelper::load_documents(source, vars).await {
+                Ok(docs) => documents.extend(docs),
+                Err(e) => {
+                    cli_stderr_printline!("{}", e);
+                    std::process::exit(-1)
+                }
+            }
+        }
+        documents
+    }
+
+    /// The key protecting the secret fields, read from `--key-file` or `OTOROSHICTL_KEY_FILE`
+    fn secret_key(cli_opts: &CliOpts) -> SecretKey {
+        match &cli_opts.key_file {
+            None => {
+                cli_stderr_printline!(
+                    "encrypted values need a key file, use --key-file or OTOROSHICTL_KEY_FILE"
+                );
+                std::process::exit(-1)
+            }
+            Some(path) => match SecretKey::from_file(path) {
+                Ok(key) => key,
+                Err(e) => {
+                    cli_stderr_printline!("{}", e);
+                    std::process::exit(-1)
+                }
+            },
+        }
+    }
+
+    /// Decrypt the encrypted fields of the documents. The key is only needed if there are some
+    fn decrypt_documents(documents: &mut [EntityDocument], cli_opts: &CliOpts) {
+        if !documents
+            .iter()
+            .any(|doc| SecretHelper::contains_encrypted(&doc.body))
+        {
+            return;
+        }
+        let mut key = Self::secret_key(cli_opts);
+        for doc in documents.iter_mut() {
+            if let Err(e) = SecretHelper::decrypt_all(&mut doc.body, &mut key) {
+                cli_stderr_printline!("error while decrypting {}: {}", doc.display_name(), e);
+                std::process::exit(-1)
+            }
+        }
+    }
+
+    /// Run `transform` on a full export, or on each line of an ndjson export, after keeping only
+    /// the entities matching the selector
+    fn transform_export(
+        content: &[u8],
+        nd_json: bool,
+        mut transform: impl FnMut(&mut serde_json::Value) -> Result<usize, String>,
+        selector: Option<&Selector>,
+    ) -> Result<Vec<u8>, String> {
+        let mut run = |raw: &[u8]| -> Result<Option<Vec<u8>>, String> {
+            let mut value = serde_json::from_slice::<serde_json::Value>(raw)
+                .map_err(|e| format!("error while parsing export: {}", e))?;
+            if let Some(selector) = selector {
+                if !nd_json {
+                    Self::select_export(&mut value, selector)
+                } else if value
+                    .get("value")
+                    .is_some_and(|entity| entity.is_object() && !selector.matches(entity))
+                {
+                    // an ndjson line is a `{"type": ..., "value": ...}` entity
+                    return Ok(None);
+                }
+            }
+            transform(&mut value)?;
+            Ok(Some(serde_json::to_vec(&value).unwrap()))
+        };
+        if !nd_json {
+            return run(content).map(|value| value.unwrap_or_default());
+        }
+        let mut output: Vec<u8> = Vec::new();
+        for line in content.split(|b| *b == b'\n') {
+            if line.iter().all(|b| b.is_ascii_whitespace()) {
+                continue;
+            }
+            if let Some(value) = run(line)? {
+                output.extend(value);
+                output.push(b'\n');
+            }
+        }
+        Ok(output)
+    }
+
+    /// Write the full export to the file as its chunks arrive. An ndjson export is selected and
+    /// encrypted line by line, a json export has to be complete to be selected or encrypted
+    async fn export_file(
+        file: &Path,
+        mut body: hyper::Body,
+        nd_json: bool,
+        selector: Option<&Selector>,
+        mut secret_key: Option<&mut SecretKey>,
+    ) -> Result<(), String> {
+        let secret_fields = SecretHelper::default_fields();
+        let transformed = selector.is_some() || secret_key.is_some();
+        let mut transform = |content: &[u8]| match secret_key.as_deref_mut() {
+            None => Self::transform_export(content, nd_json, |_| Ok(0), selector),
+            Some(key) => Self::transform_export(
+                content,
+                nd_json,
+                |value| SecretHelper::encrypt_fields(value, &secret_fields, key),
+                selector,
+            )
+            .map_err(|e| format!("error while encrypting export: {}", e)),
+        };
+        let write_error = |e: std::io::Error| format!("error while writing {:?}: {}", file, e);
+        let mut out = std::fs::File::create(file).map_err(write_error)?;
+        let mut pending: Vec<u8> = Vec::new();
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.map_err(|e| format!("error while fetching export: {}", e))?;
+            if !transformed {
+                out.write_all(&chunk).map_err(write_error)?;
+                continue;
+            }
+            pending.extend_from_slice(&chunk);
+            if nd_json && let Some(end) = pending.iter().rposition(|b| *b == b'\n') {
+                let lines: Vec<u8> = pending.drain(..=end).collect();
+                out.write_all(&transform(&lines)?).map_err(write_error)?;
+            }
+        }
+        if transformed && (!nd_json || !pending.is_empty()) {
+            out.write_all(&transform(&pending)?).map_err(write_error)?;
+        }
+        Ok(())
+    }
+
+    /// Write one entity of a split directory export, in `<file_name>.json` or `<file_name>.yaml`
+    fn export_entity_file(
+        entity: serde_json::Value,
+        resource: &OtoroshExposedResource,
+        folder: &Path,
+        file_name: &str,
+        yaml: bool,
+        kube: bool,
+    ) -> Result<(), String> {
+        let kind = format!("{}/{}", resource.group, resource.kind);
+        let (path, content) = if yaml && kube {
+            let entity_name = EntityHelper::extract_json_entity_name(&entity).unwrap();
+            let kube_entity = KubeEntity::new(resource.kind.clone(), entity_name, entity);
+            (
+                folder.join(format!("{}.yaml", file_name)),
+                serde_yaml::to_string(&kube_entity).unwrap(),
+            )
+        } else if yaml {
+            (
+                folder.join(format!("{}.yaml", file_name)),
+                serde_yaml::to_string(&EntityHelper::with_kind(&entity, kind)).unwrap(),
+            )
+        } else {
+            (
+                folder.join(format!("{}.json", file_name)),
+                serde_json::to_string_pretty(&EntityHelper::with_kind(&entity, kind)).unwrap(),
+            )
+        };
+        std::fs::write(&path, content).map_err(|e| format!("error while writing {:?}: {}", path, e))
+    }
+
+    /// The entities of a previous export of a kind, by id, so a normalized export keeps the
+    /// encrypted values of the secrets that did not change
+    fn previous_export(
+        folder: &Path,
+        path: &Option<PathBuf>,
+    ) -> HashMap<String, serde_json::Value> {
+        let sources: Vec<PathBuf> = match path {
+            Some(path) => vec![path.clone()],
+            None => std::fs::read_dir(folder)
+                .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+                .unwrap_or_default(),
+        };
+        sources
+            .iter()
+            .filter_map(|source| {
+                let content = std::fs::read_to_string(source).ok()?;
+                let source = source.to_string_lossy().to_string();
+                ConvertHelper::read(&content, EntityFormat::guess(&source), &source).ok()
+            })
+            .flatten()
+            .filter_map(|doc| doc.id.clone().map(|id| (id, doc.body)))
+            .collect()
+    }
+
+    /// Export the entities of a kind to the directory, writing them page by page as they are
+    /// fetched so the whole collection is never held in memory. A normalized export needs the
+    /// whole collection to sort it, it is written once every page is fetched
+    async fn export_kind(
+        resource: OtoroshExposedResource,
+        directory: &Path,
+        layout: ExportLayout,
+        normalize: bool,
+        selector: Option<&Selector>,
+        secret_key: &Mutex<Option<SecretKey>>,
+        cli_opts: CliOpts,
+    ) -> Result<(), String> {
+        let kind = format!("{}/{}", resource.group, resource.kind);
+        let folder = directory.join(&resource.plural_name);
+        let path = match layout {
+            ExportLayout::Split { .. } => None,
+            ExportLayout::Array => Some(directory.join(format!("{}.json", resource.plural_name))),
+            ExportLayout::NdJson => {
+                Some(directory.join(format!("{}.ndjson", resource.plural_name)))
+            }
+        };
+        let previous = if normalize && secret_key.lock().unwrap().is_some() {
+            Self::previous_export(&folder, &path)
+        } else {
+            HashMap::new()
+        };
+        let write_error = |e: std::io::Error| format!("error while writing {:?}: {}", path, e);
+        let mut out = match &path {
+            Some(path) => Some(std::fs::File::create(path).map_err(write_error)?),
+            None => None,
+        };
+        let mut stream = RenderStream::new(Some("json_pretty"), &[])?.unwrap();
+        let secret_fields = SecretHelper::default_fields();
+        let template = if normalize {
+            Otoroshi::get_resource_template(resource.clone(), cli_opts.clone()).await
+        } else {
+            None
+        };
+        let mut collected: Option<Vec<serde_json::Value>> = normalize.then(Vec::new);
+        let mut pager = ResourcePager::new(resource.clone(), Vec::new(), cli_opts).await;
+        loop {
+            let next = pager
+                .next_page()
+                .await
+                .ok_or_else(|| format!("error while fetching {}", resource.plural_name))?;
+            let mut page = match (next, collected.as_mut()) {
+                (Some(page), Some(collected)) => {
+                    collected.extend(page);
+                    continue;
+                }
+                (Some(page), None) => page,
+                (None, Some(_)) => {
+                    NormalizeHelper::entities(collected.take().unwrap(), template.as_ref())
+                }
+                (None, None) => break,
+            };
+            if let Some(selector) = selector {
+                page = selector.select(page);
+            }
+            if let Some(key) = secret_key.lock().unwrap().as_mut() {
+                for entity in page.iter_mut() {
+                    match EntityHelper::extract_json_entity_id(entity)
+                        .and_then(|id| previous.get(&id))
+                    {
+                        Some(previous) => SecretHelper::encrypt_fields_since(
+                            entity,
+                            previous,
+                            &secret_fields,
+                            key,
+                        ),
+                        None => SecretHelper::encrypt_fields(entity, &secret_fields, key),
+                    }
+                    .map_err(|e| format!("error while encrypting: {}", e))?;
+                }
+            }
+            let page: Vec<serde_json::Value> = match layout {
+                ExportLayout::Split { yaml, kube } => {
+                    if !page.is_empty() {
+                        std::fs::create_dir_all(&folder)
+                            .map_err(|e| format!("error while creating {:?}: {}", folder, e))?;
+                    }
+                    let file_names: Vec<String> = if normalize {
+                        NormalizeHelper::file_names(&page)
+                    } else {
+                        page.iter()
+                            .map(|entity| EntityHelper::extract_json_entity_id(entity).unwrap())
+                            .collect()
+                    };
+                    for (entity, file_name) in page.into_iter().zip(file_names.iter()) {
+                        Self::export_entity_file(
+                            entity, &resource, &folder, file_name, yaml, kube,
+                        )?;
+                    }
+                    continue;
+                }
+                _ => page
+                    .iter()
+                    .map(|entity| EntityHelper::with_kind(entity, kind.to_string()))
+                    .collect(),
+            };
+            let chunk = match layout {
+                ExportLayout::NdJson => page
+                    .iter()
+                    .map(|entity| format!("{}\n", serde_json::to_string(entity).unwrap()))
+                    .collect(),
+                _ => stream.push(&page)?,
+            };
+            if let Some(out) = out.as_mut() {
+                out.write_all(chunk.as_bytes()).map_err(write_error)?;
+            }
+        }
+        if let (ExportLayout::Array, Some(out)) = (layout, out.as_mut()) {
+            out.write_all(stream.finish().as_bytes())
+                .map_err(write_error)?;
+        }
+        Ok(())
+    }
+
+    /// Keep the matching entities of every list of a full export, the other values are untouched
+    fn select_export(export: &mut serde_json::Value, selector: &Selector) {
+        if let Some(obj) = export.as_object_mut() {
+            for value in obj.values_mut() {
+                if let serde_json::Value::Array(entities) = value {
+                    let selected = selector.select(std::mem::take(entities));
+                    *entities = selected;
+                }
+            }
+        }
+    }
+
+    /// Encrypt or decrypt entity files, keeping their json or yaml format. The result is printed,
+    /// or written back to the files with `in_place`
+    fn transform_secret_files(
+        sources: Vec<String>,
+        in_place: bool,
+        verb: &str,
+        mut transform: impl FnMut(&mut serde_json::Value) -> Result<usize, String>,
+    ) {
+        if sources.len() > 1 && !in_place {
+            cli_stderr_printline!("use --in-place to transform a directory");
+            std::process::exit(-1)
+        }
+        for source in sources.iter() {
+            let content = match std::fs::read_to_string(source) {
+                Ok(content) => content,
+                Err(e) => {
+                    cli_stderr_printline!("error while reading {}: {}", source, e);
+                    std::process::exit(-1)
+                }
+            };
+            let is_json = source.ends_with(".json")
+                || content.trim_start().starts_with('{')
+                || content.trim_start().starts_with('[');
+            let result: Result<(String, usize), String> = if is_json {
+                serde_json::from_str::<serde_json::Value>(&content)
+                    .map_err(|e| e.to_string())
+                    .and_then(|mut value| {
+                        let count = transform(&mut value)?;
+                        Ok((serde_json::to_string_pretty(&value).unwrap(), count))
+                    })
+            } else {
+                let mut outputs: Vec<String> = Vec::new();
+                let mut count = 0;
+                serde_yaml::Deserializer::from_str(&content)
+                    .try_for_each(|doc| {
+                        let mut value =
+                            serde_json::Value::deserialize(doc).map_err(|e| e.to_string())?;
+                        if !value.is_null() {
+                            count += transform(&mut value)?;
+                            outputs.push(serde_yaml::to_string(&value).unwrap());
+                        }
+                        Ok::<(), String>(())
+                    })
+                    .map(|_| (outputs.join("---\n"), count))
+            };
+            match result {
+                Err(e) => {
+                    cli_stderr_printline!("error while processing {}: {}", source, e);
+                    std::process::exit(-1)
+                }
+                Ok((output, count)) if in_place => {
+                    std::fs::write(source, output).unwrap();
+                    cli_stdout_printline!("{}: {} fields {}", source, count, verb);
+                }
+                Ok((output, _)) => cli_stdout_printline!("{}", output.trim_end()),
+            }
+        }
+    }
+
+    /// Compare local documents with the cluster. Remote entities are fetched once per kind so
+    /// entities that only exist on the cluster can be reported as removed.
+    async fn diff_documents(
+        documents: Vec<EntityDocument>,
+        ignore_removed: bool,
+        cli_opts: CliOpts,
+    ) -> Vec<EntityDiff> {
+        let exposed_resources = Otoroshi::get_exposed_resources(cli_opts.clone())
+            .await
+            .unwrap();
+        let mut results: Vec<EntityDiff> = Vec::new();
+        let mut by_kind: BTreeMap<String, (OtoroshExposedResource, Vec<EntityDocument>)> =
+            BTreeMap::new();
+        for doc in documents.into_iter() {
+            match doc
+                .kind
+                .as_ref()
+                .and_then(|kind| exposed_resources.find_by_kind(kind))
+            {
+                None => results.push(EntityDiff {
+                    kind: doc.kind.clone().unwrap_or("unknown".to_string()),
+                    id: doc.id.clone().unwrap_or_default(),
+                    name: doc.display_name(),
+                    status: "error".to_string(),
+                    changes: Vec::new(),
+                    error: Some(format!("unknown kind in {}", doc.source)),
+                }),
+                Some(resource) => by_kind
+                    .entry(format!("{}/{}", resource.group, resource.kind))
+                    .or_insert((resource, Vec::new()))
+                    .1
+                    .push(doc),
+            }
+        }
+        for (kind, (resource, docs)) in by_kind.into_iter() {
+            // an unreachable cluster must not look like every entity was added
+            let Some(remote) =
+                Otoroshi::get_all_resources(resource.clone(), Vec::new(), cli_opts.clone()).await
+            else {
+                results.extend(docs.iter().map(|doc| EntityDiff {
+                    kind: kind.clone(),
+                    id: doc.id.clone().unwrap_or_default(),
+                    name: doc.display_name(),
+                    status: "error".to_string(),
+                    changes: Vec::new(),
+                    error: Some(format!("error while fetching {}", resource.plural_name)),
+                }));
+                continue;
+            };
+            let remote_entities: HashMap<String, serde_json::Value> = remote
+                .body
+                .into_iter()
+                .filter_map(|entity| {
+                    EntityHelper::extract_json_entity_id(&entity).map(|id| (id, entity))
+                })
+                .collect();
+            let mut local_ids: HashSet<String> = HashSet::new();
+            let mut kind_results: Vec<EntityDiff> = Vec::new();
+            for doc in docs.into_iter() {
+                let name = doc.display_name();
+                match doc.id.clone() {
+                    None => kind_results.push(EntityDiff {
+                        kind: kind.clone(),
+                        id: "".to_string(),
+                        name,
+                        status: "error".to_string(),
+                        changes: Vec::new(),
+                        error: Some(format!("missing entity id in {}", doc.source)),
+                    }),
+                    Some(id) => {
+                        local_ids.insert(id.clone());
+                        let local = EntityHelper::without_kind(&doc.body);
+                        match remote_entities.get(&id) {
+                            None => kind_results.push(EntityDiff {
+                                kind: kind.clone(),
+                                id,
+                                name,
+                                status: "added".to_string(),
+                                changes: Vec::new(),
+                                error: None,
+                            }),
+                            Some(remote) => {
+                                let changes = DiffHelper::diff_declared(remote, &local);
+                                kind_results.push(EntityDiff {
+                                    kind: kind.clone(),
+                                    id,
+                                    name,
+                                    status: if changes.is_empty() {
+                                        "unchanged".to_string()
+                                    } else {
+                                        "changed".to_string()
+                                    },
+                                    changes,
+                                    error: None,
+                                })
+                            }
+                        }
+                    }
+                }
+            }
+            if !ignore_removed {
+                for (id, remote) in remote_entities.iter() {
+                    if !local_ids.contains(id) {
+                        kind_results.push(EntityDiff {
+                            kind: kind.clone(),
+                            id: id.to_string(),
+                            name: EntityHelper::extract_json_entity_name(remote)
+                                .unwrap_or(id.to_string()),
+                            status: "removed".to_string(),
+                            changes: Vec::new(),
+                            error: None,
+                        });
+                    }
+                }
+            }
+            kind_results.sort_by(|a, b| a.id.cmp(&b.id));
+            results.extend(kind_results);
+        }
+        results
+    }
+
+    fn display_diff(results: &[EntityDiff]) {
+        let mut logger = paris::Logger::new();
+        let mut current_kind = "".to_string();
+        for result in results.iter().filter(|r| r.status != "unchanged") {
+            if result.kind != current_kind {
+                current_kind = result.kind.clone();
+                logger.log(format!("<bold>{}</>", current_kind));
+            }
+            match result.status.as_str() {
+                "added" => {
+                    logger.log(format!("<green>  + {} ({})</>", result.id, result.name));
+                }
+                "removed" => {
+                    logger.log(format!("<red>  - {} ({})</>", result.id, result.name));
+                }
+                "changed" => {
+                    logger.log(format!("<yellow>  ~ {} ({})</>", result.id, result.name));
+                    DiffHelper::print_changes(&result.changes, "      ");
+                }
+                _ => {
+                    logger.log(format!(
+                        "<red>  ! {}: {}</>",
+                        result.name,
+                        result.error.clone().unwrap_or_default()
+                    ));
+                }
+            }
+        }
+        let count = |status: &str| results.iter().filter(|r| r.status == status).count();
+        cli_stdout_printline!(
+            "\n{} added, {} changed, {} removed, {} unchanged, {} errors",
+            count("added"),
+            count("changed"),
+            count("removed"),
+            count("unchanged"),
+            count("error")
+        );
+    }
+
+    pub async fn display(cli_opts: CliOpts, command: &ResourcesSubCommand) -> () {
+        match command {
+            ResourcesSubCommand::Rbac {
+                file,
+                username,
+                namespace,
+            } => {
+                let exposed_resources: OtoroshExposedResources =
+                    Otoroshi::get_exposed_resources(cli_opts.clone())
+                        .await
+                        .unwrap();
+                let resources = exposed_resources
+                    .resources
+                    .into_iter()
+                    .map(|resource| format!("      - {}", resource.plural_name))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                let final_namespace: String = namespace.to_owned().unwrap_or("default".to_string());
+                let final_username: String = username
+                    .to_owned()
+                    .unwrap_or("otoroshi-admin-user".to_string());
+                let output: String = format!(
+                    "---
+kind: ServiceAccount
+apiVersion: v1
+metadata:
+  name: {}
+---
+apiVersion: rbac.authorization.k8s.io/v1
+kind: ClusterRoleBinding
+metadata:
+  name: {}
+roleRef:
+  apiGroup: rbac.authorization.k8s.io
+  kind: ClusterRole
+  name: {}
+subjects:
+- kind: ServiceAccount
+  name: {}
+  namespace: {}
+---
+kind: ClusterRole
+apiVersion: rbac.authorization.k8s.io/v1
+metadata:
+  name: {}
+rules:
+  - apiGroups:
+      - \"\"
+    resources:
+      - services
+      - endpoints
+      - secrets
+      - configmaps
+      - deployments
+      - pods
+      - namespaces
+    verbs:
       - get
       - list
       - watch
@@ -1020,6 +3264,90 @@ spec:
                     }
                 }
             }
+            ResourcesSubCommand::Describe { resource, id } => {
+                let description = Self::describe(resource, id, cli_opts.clone()).await;
+                if !RenderHelper::display(&cli_opts, &description, &[]) {
+                    DescribeHelper::display(&description);
+                }
+            }
+            ResourcesSubCommand::History { resource, id } => {
+                let (exposed_resource, entries) = Self::history(resource, id, &cli_opts).await;
+                let current =
+                    Otoroshi::get_one_resource(exposed_resource, id.to_string(), cli_opts.clone())
+                        .await
+                        .map(|res| res.body);
+                let versions = HistoryHelper::versions(&entries, current.as_ref());
+                if !RenderHelper::display(&cli_opts, &versions, &[]) {
+                    if versions.is_empty() {
+                        cli_stdout_printline!("no recorded history for {} {}", resource, id);
+                    }
+                    HistoryHelper::display(&versions);
+                }
+            }
+            ResourcesSubCommand::Rollback {
+                resource,
+                id,
+                to,
+                dry_run,
+            } => {
+                let (exposed_resource, entries) = Self::history(resource, id, &cli_opts).await;
+                let Some(entry) = entries.into_iter().find(|e| e.rev == *to) else {
+                    cli_stderr_printline!(
+                        "revision {} not found in the history of {} {}",
+                        to,
+                        resource,
+                        id
+                    );
+                    std::process::exit(-1)
+                };
+                let current = Otoroshi::get_one_resource(
+                    exposed_resource.clone(),
+                    id.to_string(),
+                    cli_opts.clone(),
+                )
+                .await
+                .map(|res| res.body);
+                if dry_run.unwrap_or(false) {
+                    let changes = current
+                        .as_ref()
+                        .map(|current| DiffHelper::diff(current, &entry.entity))
+                        .unwrap_or_default();
+                    if !RenderHelper::display(&cli_opts, &changes, &[]) {
+                        cli_stdout_printline!(
+                            "dry run, {} {} would be restored to rev {}{}",
+                            resource,
+                            id,
+                            to,
+                            if current.is_none() {
+                                " (created again)"
+                            } else {
+                                ""
+                            }
+                        );
+                        DiffHelper::print_changes(&changes, "    ");
+                    }
+                } else if Self::upsert_recorded(
+                    &exposed_resource,
+                    id,
+                    serde_json::to_string(&entry.entity).unwrap(),
+                    None,
+                    current,
+                    "rollback",
+                    cli_opts.clone(),
+                )
+                .await
+                {
+                    cli_stdout_printline!("{} {} restored to rev {}", resource, id, to);
+                } else {
+                    cli_stderr_printline!(
+                        "error while restoring {} {} to rev {}",
+                        resource,
+                        id,
+                        to
+                    );
+                    std::process::exit(-1)
+                }
+            }
             ResourcesSubCommand::Get {
                 resource,
                 id,
@@ -1028,7 +3356,14 @@ spec:
                 page,
                 page_size,
                 filters,
+                watch,
+                interval,
+                query,
+                selector,
+                tags,
             } => {
+                Self::check_expressions(columns, query, &cli_opts);
+                let selector = Self::selector(selector, tags);
                 match resource {
                     Some(resource_name) => {
                         let exposed_resources: OtoroshExposedResources =
@@ -1052,206 +3387,118 @@ spec:
                         );
                         //let final_resource_name: String = exposed_resource.plural_name;
                         let kind: String = exposed_resource.clone().singular_name;
-                        match id {
-                            Some(resource_id) => {
-                                match Otoroshi::get_one_resource(
-                                    exposed_resource.clone(),
-                                    resource_id.to_string(),
-                                    cli_opts.clone(),
-                                )
-                                .await
-                                {
-                                    Some(raw_resource) => {
-                                        match cli_opts.ouput {
-                                            Some(str) => {
-                                                match str.as_str() {
-                                                    "json" => cli_stdout_printline!(
-                                                        "{}",
-                                                        serde_json::to_string(&Self::with_kind(
-                                                            &raw_resource.body,
-                                                            res_kind
-                                                        ))
-                                                        .unwrap()
-                                                    ),
-                                                    "json_pretty" => cli_stdout_printline!(
-                                                        "{}",
-                                                        serde_json::to_string_pretty(
-                                                            &Self::with_kind(
-                                                                &raw_resource.body,
-                                                                res_kind
-                                                            )
-                                                        )
-                                                        .unwrap()
-                                                    ),
-                                                    "yaml" => {
-                                                        if kube.unwrap_or(true) {
-                                                            let res_body = raw_resource.body;
-                                                            let res_name = EntityHelper::extract_json_entity_name(&res_body).unwrap();
-                                                            let kube_res = KubeEntity::new(
-                                                                res_kind, res_name, res_body,
-                                                            );
-                                                            cli_stdout_printline!(
-                                                                "{}",
-                                                                serde_yaml::to_string(&kube_res)
-                                                                    .unwrap()
-                                                            )
-                                                        } else {
-                                                            cli_stdout_printline!(
-                                                                "{}",
-                                                                serde_yaml::to_string(
-                                                                    &Self::with_kind(
-                                                                        &raw_resource.body,
-                                                                        res_kind
-                                                                    )
-                                                                )
-                                                                .unwrap()
-                                                            )
-                                                        }
-                                                    }
-                                                    _ => {
-                                                        let vec = vec![TableResource {
-                                                            raw: raw_resource.body,
-                                                        }];
-                                                        Self::display_table_of_resources(
-                                                            kind.to_string(),
-                                                            vec,
-                                                            columns.to_vec(),
-                                                        )
-                                                    }
-                                                }
-                                            }
-                                            _ => {
-                                                let vec = vec![TableResource {
-                                                    raw: raw_resource.body,
-                                                }];
-                                                Self::display_table_of_resources(
-                                                    kind.to_string(),
-                                                    vec,
-                                                    columns.to_vec(),
-                                                )
-                                            }
-                                        };
-                                    }
-                                    None => {
-                                        cli_stdout_printline!(
-                                            "resource {} with id {} not found",
-                                            resource_name,
-                                            resource_id
-                                        )
-                                    }
+                        if watch.unwrap_or(false) {
+                            Self::watch_resources(
+                                exposed_resource,
+                                id.clone(),
+                                filters.to_vec(),
+                                selector,
+                                Self::table_columns(&kind, columns),
+                                interval.unwrap_or(2),
+                                cli_opts.clone(),
+                            )
+                            .await;
+                            return;
+                        }
+                        let columns = Self::table_columns(&kind, columns);
+                        if id.is_none() && page.is_none() && page_size.is_none() && query.is_none()
+                        {
+                            let kube =
+                                cli_opts.ouput.as_deref() == Some("yaml") && kube.unwrap_or(true);
+                            match RenderStream::new(cli_opts.ouput.as_deref(), &columns) {
+                                Ok(Some(stream)) => {
+                                    Self::stream_entities(
+                                        exposed_resource,
+                                        filters.to_vec(),
+                                        selector,
+                                        stream,
+                                        kube,
+                                        cli_opts.clone(),
+                                    )
+                                    .await;
+                                    return;
+                                }
+                                Ok(None) => (),
+                                Err(e) => {
+                                    cli_stderr_printline!("{}", e);
+                                    std::process::exit(-1)
                                 }
                             }
-                            None => {
-                                match Otoroshi::get_resources(
-                                    exposed_resource.clone(),
-                                    page.unwrap_or(1),
-                                    page_size.unwrap_or(99999),
-                                    filters.to_vec(),
-                                    cli_opts.clone(),
-                                )
-                                .await
+                        }
+                        let document = match id {
+                            Some(resource_id) => match Otoroshi::get_one_resource(
+                                exposed_resource.clone(),
+                                resource_id.to_string(),
+                                cli_opts.clone(),
+                            )
+                            .await
+                            {
+                                Some(raw_resource)
+                                    if selector
+                                        .as_ref()
+                                        .is_none_or(|s| s.matches(&raw_resource.body)) =>
                                 {
-                                    Some(raw_resources) => {
-                                        match cli_opts.ouput {
-                                            Some(str) => match str.as_str() {
-                                                "json" => {
-                                                    let items: Vec<serde_json::Value> =
-                                                        raw_resources
-                                                            .body
-                                                            .into_iter()
-                                                            .map(|v| {
-                                                                Self::with_kind(
-                                                                    &v,
-                                                                    res_kind.to_string(),
-                                                                )
-                                                            })
-                                                            .collect();
-                                                    cli_stdout_printline!(
-                                                        "{}",
-                                                        serde_json::to_string(&items).unwrap()
-                                                    )
-                                                }
-                                                "json_pretty" => {
-                                                    let items: Vec<serde_json::Value> =
-                                                        raw_resources
-                                                            .body
-                                                            .into_iter()
-                                                            .map(|v| {
-                                                                Self::with_kind(
-                                                                    &v,
-                                                                    res_kind.to_string(),
-                                                                )
-                                                            })
-                                                            .collect();
-                                                    cli_stdout_printline!(
-                                                        "{}",
-                                                        serde_json::to_string_pretty(&items)
-                                                            .unwrap()
-                                                    )
-                                                }
-                                                "yaml" => {
-                                                    if kube.unwrap_or(true) {
-                                                        let kube_entities: Vec<KubeEntity> = raw_resources.body.into_iter().map(|res_body| {
-                                                                let res_name = EntityHelper::extract_json_entity_name(&res_body).unwrap();
-                                                                KubeEntity::new(res_kind.clone(), res_name, res_body)
-                                                            }).collect();
-                                                        cli_stdout_printline!(
-                                                            "{}",
-                                                            serde_yaml::to_string(&kube_entities)
-                                                                .unwrap()
-                                                        )
-                                                    } else {
-                                                        let items: Vec<serde_json::Value> =
-                                                            raw_resources
-                                                                .body
-                                                                .into_iter()
-                                                                .map(|v| {
-                                                                    Self::with_kind(
-                                                                        &v,
-                                                                        res_kind.to_string(),
-                                                                    )
-                                                                })
-                                                                .collect();
-                                                        cli_stdout_printline!(
-                                                            "{}",
-                                                            serde_yaml::to_string(&items).unwrap()
-                                                        )
-                                                    }
-                                                }
-                                                _ => {
-                                                    let vec = raw_resources
-                                                        .body
-                                                        .into_iter()
-                                                        .map(|item| TableResource { raw: item })
-                                                        .collect();
-                                                    Self::display_table_of_resources(
-                                                        kind,
-                                                        vec,
-                                                        columns.to_vec(),
-                                                    );
-                                                }
-                                            },
-                                            _ => {
-                                                let vec = raw_resources
-                                                    .body
-                                                    .into_iter()
-                                                    .map(|item| TableResource { raw: item })
-                                                    .collect();
-                                                Self::display_table_of_resources(
-                                                    kind,
-                                                    vec,
-                                                    columns.to_vec(),
-                                                );
-                                            }
-                                        };
-                                    }
-                                    None => {
-                                        cli_stdout_printline!(
-                                            "resources {} not found",
-                                            resource_name
-                                        )
-                                    }
+                                    raw_resource.body
+                                }
+                                _ => {
+                                    cli_stdout_printline!(
+                                        "resource {} with id {} not found",
+                                        resource_name,
+                                        resource_id
+                                    );
+                                    return;
+                                }
+                            },
+                            None => match match page.or(*page_size) {
+                                Some(_) => {
+                                    Otoroshi::get_resources(
+                                        exposed_resource.clone(),
+                                        page.unwrap_or(1),
+                                        page_size.unwrap_or(ResourcePager::PAGE_SIZE),
+                                        filters.to_vec(),
+                                        cli_opts.clone(),
+                                    )
+                                    .await
+                                }
+                                None => {
+                                    Otoroshi::get_all_resources(
+                                        exposed_resource.clone(),
+                                        filters.to_vec(),
+                                        cli_opts.clone(),
+                                    )
+                                    .await
+                                }
+                            } {
+                                Some(raw_resources) => serde_json::Value::Array(match &selector {
+                                    Some(selector) => selector.select(raw_resources.body),
+                                    None => raw_resources.body,
+                                }),
+                                None => {
+                                    cli_stdout_printline!("resources {} not found", resource_name);
+                                    return;
                                 }
+                            },
+                        };
+                        match query {
+                            Some(query) => Self::display_query(document, query, columns, &cli_opts),
+                            None if cli_opts.ouput.as_deref() == Some("yaml")
+                                && kube.unwrap_or(true) =>
+                            {
+                                Self::display_kube_entities(document, res_kind)
+                            }
+                            None => {
+                                let document = match document {
+                                    serde_json::Value::Array(items) => serde_json::Value::Array(
+                                        items
+                                            .iter()
+                                            .map(|v| {
+                                                EntityHelper::with_kind(v, res_kind.to_string())
+                                            })
+                                            .collect(),
+                                    ),
+                                    entity => EntityHelper::with_kind(&entity, res_kind),
+                                };
+                                Self::display_entities(document, columns, &cli_opts)
                             }
                         }
                     }
@@ -1266,85 +3513,254 @@ spec:
                 file,
                 directory,
                 recursive,
-            } => match resource {
-                Some(resource) => {
-                    let final_resource_name: String = if resource.ends_with("s") {
-                        resource.to_string()
-                    } else {
-                        format!("{}s", resource)
-                    };
-                    let exposed_resources: OtoroshExposedResources =
-                        Otoroshi::get_exposed_resources(cli_opts.clone())
-                            .await
+                dry_run,
+                values,
+                report,
+                report_file,
+                selector,
+                tags,
+            } => {
+                Self::check_report_options(report, report_file);
+                let selector = Self::selector(selector, tags);
+                if selector.is_some() && !ids.is_empty() {
+                    cli_stderr_printline!("ids and selectors can't be used together");
+                    std::process::exit(-1)
+                }
+                if selector.is_some() && resource.is_none() {
+                    cli_stderr_printline!("selectors need a resource name");
+                    std::process::exit(-1)
+                }
+                let results = match resource {
+                    Some(resource) => {
+                        let final_resource_name: String = if resource.ends_with("s") {
+                            resource.to_string()
+                        } else {
+                            format!("{}s", resource)
+                        };
+                        let exposed_resources: OtoroshExposedResources =
+                            Otoroshi::get_exposed_resources(cli_opts.clone())
+                                .await
+                                .unwrap();
+                        let exposed_resource = exposed_resources
+                            .resources
+                            .into_iter()
+                            .find(|r| {
+                                r.plural_name == final_resource_name
+                                    || r.singular_name == final_resource_name
+                                    || r.kind == final_resource_name
+                                    || format!("{}/{}", r.group, r.kind) == final_resource_name
+                            })
                             .unwrap();
-                    let exposed_resource = exposed_resources
-                        .resources
-                        .into_iter()
-                        .find(|r| {
-                            r.plural_name == final_resource_name
-                                || r.singular_name == final_resource_name
-                                || r.kind == final_resource_name
-                                || format!("{}/{}", r.group, r.kind) == final_resource_name
-                        })
+                        let ids = match &selector {
+                            Some(selector) => {
+                                Self::selected_ids(&exposed_resource, selector, cli_opts.clone())
+                                    .await
+                            }
+                            None => ids.to_vec(),
+                        };
+                        if dry_run.unwrap_or(false) {
+                            let mut plan: Vec<PlannedAction> = Vec::new();
+                            for id in ids.iter() {
+                                plan.push(
+                                    Self::plan_entity(
+                                        &exposed_resource,
+                                        id.to_string(),
+                                        id.to_string(),
+                                        &serde_json::Value::Null,
+                                        true,
+                                        cli_opts.clone(),
+                                    )
+                                    .await,
+                                );
+                            }
+                            Self::display_plan(plan.clone(), cli_opts.clone());
+                            plan
+                        } else {
+                            let mut results: Vec<PlannedAction> = Vec::new();
+                            for id in ids.iter() {
+                                let res = Self::delete_recorded(
+                                    exposed_resource.clone(),
+                                    id.to_string(),
+                                    cli_opts.clone(),
+                                )
+                                .await;
+                                let outcome = PlannedAction {
+                                    kind: format!(
+                                        "{}/{}",
+                                        exposed_resource.group, exposed_resource.kind
+                                    ),
+                                    id: id.to_string(),
+                                    name: id.to_string(),
+                                    action: "error".to_string(),
+                                    status: None,
+                                    error: None,
+                                };
+                                results.push(Self::delete_outcome(outcome, "deleted", res));
+                            }
+                            let print_ids: Vec<String> = results
+                                .iter()
+                                .filter(|r| ReportHelper::is_failure(r))
+                                .map(|r| format!("  - {}", r.id))
+                                .collect();
+                            if !print_ids.is_empty() {
+                                cli_stdout_printline!(
+                                    "failed to delete the following {}\n",
+                                    final_resource_name
+                                );
+                                cli_stdout_printline!("{}\n", print_ids.join("\n"))
+                            }
+                            results
+                        }
+                    }
+                    None => match file {
+                        None => match directory {
+                            None => {
+                                cli_stdout_printline!(
+                                    "you need to provide a file or directory path"
+                                );
+                                Vec::new()
+                            }
+                            Some(directory) => {
+                                let files = Self::find_files(directory, recursive.unwrap_or(false));
+                                Self::delete_files(
+                                    files,
+                                    dry_run.unwrap_or(false),
+                                    &Self::load_values(values).await,
+                                    cli_opts.clone(),
+                                )
+                                .await
+                            }
+                        },
+                        Some(file) => {
+                            if file.starts_with("http://") || file.starts_with("https://") {
+                                Self::delete_url(
+                                    file.to_owned(),
+                                    dry_run.unwrap_or(false),
+                                    &Self::load_values(values).await,
+                                    cli_opts.clone(),
+                                )
+                                .await
+                            } else {
+                                Self::delete_files(
+                                    vec![PathBuf::from(file.to_owned())],
+                                    dry_run.unwrap_or(false),
+                                    &Self::load_values(values).await,
+                                    cli_opts.clone(),
+                                )
+                                .await
+                            }
+                        }
+                    },
+                };
+                Self::write_report("delete", &results, report, report_file);
+                Self::exit_on_failure(&results);
+            }
+            ResourcesSubCommand::Create {
+                resource,
+                file,
+                data,
+                input,
+                stdin,
+                dry_run,
+            } => {
+                let oto_patch = Self::oto_patch(data);
+                let final_resource_name: String = if resource.ends_with("s") {
+                    resource.to_string()
+                } else {
+                    format!("{}s", resource)
+                };
+                let exposed_resources: OtoroshExposedResources =
+                    Otoroshi::get_exposed_resources(cli_opts.clone())
+                        .await
                         .unwrap();
-                    let mut failed_results: Vec<String> = Vec::new();
-                    for id in ids.iter() {
-                        let res = Otoroshi::delete_one_resource(
+                let exposed_resource = exposed_resources
+                    .resources
+                    .into_iter()
+                    .find(|r| {
+                        r.plural_name == final_resource_name
+                            || r.singular_name == final_resource_name
+                            || r.kind == final_resource_name
+                            || format!("{}/{}", r.group, r.kind) == final_resource_name
+                    })
+                    .unwrap();
+                let dry_run = dry_run.unwrap_or(false);
+                if file.is_none() && !data.is_empty() {
+                    if dry_run {
+                        let name = oto_patch
+                            .as_array()
+                            .and_then(|ops| ops.iter().find(|op| op["path"] == "name"))
+                            .and_then(|op| op["value"].as_str())
+                            .unwrap_or("<new entity>")
+                            .to_string();
+                        Self::display_plan(
+                            vec![PlannedAction {
+                                kind: format!(
+                                    "{}/{}",
+                                    exposed_resource.group, exposed_resource.kind
+                                ),
+                                id: "".to_string(),
+                                name,
+                                action: "create".to_string(),
+                                status: None,
+                                error: None,
+                            }],
+                            cli_opts.clone(),
+                        );
+                    } else {
+                        let _ = Otoroshi::create_one_resource_with_content_type(
                             exposed_resource.clone(),
-                            id.to_string(),
+                            oto_patch.to_string(),
+                            "application/json+oto-patch".to_string(),
                             cli_opts.clone(),
                         )
                         .await;
-                        if !res {
-                            failed_results.push(id.to_string());
-                        }
-                    }
-                    if !failed_results.is_empty() {
-                        let print_ids: Vec<String> = failed_results
-                            .into_iter()
-                            .map(|s| format!("  - {}", s))
-                            .collect();
-                        cli_stdout_printline!(
-                            "failed to delete the following {}\n",
-                            final_resource_name
-                        );
-                        cli_stdout_printline!("{}\n", print_ids.join("\n"))
                     }
-                }
-                None => match file {
-                    None => match directory {
-                        None => {
-                            cli_stdout_printline!("you need to provide a file or directory path")
-                        }
-                        Some(directory) => {
-                            let files = Self::find_files(directory, recursive.unwrap_or(false));
-                            Self::delete_files(files, cli_opts.clone()).await
+                } else {
+                    let content = match file {
+                        Some(file) => {
+                            match crate::utils::file::FileHelper::get_content_string_result(file)
+                                .await
+                            {
+                                Err(e) => {
+                                    cli_stderr_printline!("error while reading {:?}: {}", file, e);
+                                    std::process::exit(-1)
+                                }
+                                Ok(content) => content,
+                            }
                         }
-                    },
-                    Some(file) => {
-                        if file.starts_with("http://") || file.starts_with("https://") {
-                            if file.starts_with("http://") {
-                                Self::delete_url_http(file.to_owned(), cli_opts.clone()).await;
-                            } else if file.starts_with("https://") {
-                                Self::delete_url_https(file.to_owned(), cli_opts.clone()).await;
+                        None => match input {
+                            Some(input) => input.clone(),
+                            None => {
+                                let to_edit = "".to_string();
+                                if *stdin {
+                                    std::io::read_to_string(std::io::stdin()).unwrap()
+                                } else {
+                                    edit::edit(&to_edit).unwrap_or(to_edit.clone())
+                                }
                             }
-                        } else {
-                            Self::delete_files(
-                                vec![PathBuf::from(file.to_owned())],
-                                cli_opts.clone(),
-                            )
-                            .await
-                        }
-                    }
-                },
-            },
-            ResourcesSubCommand::Create {
+                        },
+                    };
+                    Self::upsert_entity_input(
+                        &exposed_resource,
+                        None,
+                        content,
+                        dry_run,
+                        "create",
+                        cli_opts.clone(),
+                    )
+                    .await;
+                }
+            }
+            ResourcesSubCommand::Edit {
                 resource,
+                id,
                 file,
                 data,
                 input,
                 stdin,
+                dry_run,
             } => {
+                let oto_patch = Self::oto_patch(data);
                 let final_resource_name: String = if resource.ends_with("s") {
                     resource.to_string()
                 } else {
@@ -1364,6 +3780,7 @@ spec:
                             || format!("{}/{}", r.group, r.kind) == final_resource_name
                     })
                     .unwrap();
+                let dry_run = dry_run.unwrap_or(false);
                 match file {
                     Some(file) => {
                         match crate::utils::file::FileHelper::get_content_string_result(file).await
@@ -1373,162 +3790,128 @@ spec:
                                 std::process::exit(-1)
                             }
                             Ok(content) => {
-                                let is_yaml = !content.trim().starts_with("{");
-                                if is_yaml {
-                                    let mut json =
-                                        serde_yaml::from_str::<serde_json::Value>(&content)
-                                            .unwrap();
-                                    let is_kube = json.get("apiVersion").is_some()
-                                        && json.get("spec").is_some();
-                                    if is_kube {
-                                        json = json.get("spec").unwrap().clone();
-                                    }
-                                    let id: String =
-                                        EntityHelper::extract_json_entity_id(&json).unwrap();
-                                    let _ = Otoroshi::upsert_one_resource(
-                                        exposed_resource.clone(),
-                                        id,
-                                        serde_json::to_string(&json).unwrap(),
-                                        cli_opts.clone(),
-                                    )
-                                    .await;
-                                } else {
-                                    let json = serde_json::from_str::<serde_json::Value>(&content)
-                                        .unwrap();
-                                    let id = EntityHelper::extract_json_entity_id(&json).unwrap();
-                                    let _ = Otoroshi::upsert_one_resource(
-                                        exposed_resource.clone(),
-                                        id,
-                                        content,
-                                        cli_opts.clone(),
-                                    )
-                                    .await;
-                                }
+                                Self::upsert_entity_input(
+                                    &exposed_resource,
+                                    Some(id.to_string()),
+                                    content,
+                                    dry_run,
+                                    "edit",
+                                    cli_opts.clone(),
+                                )
+                                .await;
                             }
                         }
                     }
                     _ => {
-                        if data.is_empty() {
-                            if input.is_none() {
-                                let to_edit = "".to_string();
-                                let edited = if *stdin {
-                                    std::io::read_to_string(std::io::stdin()).unwrap()
-                                } else {
-                                    edit::edit(&to_edit).unwrap_or(to_edit.clone())
-                                };
-                                let is_yaml = !edited.trim().starts_with("{");
-                                if is_yaml {
-                                    let mut json =
-                                        serde_yaml::from_str::<serde_json::Value>(&edited).unwrap();
-                                    let is_kube = json.get("apiVersion").is_some()
-                                        && json.get("spec").is_some();
-                                    if is_kube {
-                                        json = json.get("spec").unwrap().clone();
-                                    }
-                                    let id: String =
-                                        EntityHelper::extract_json_entity_id(&json).unwrap();
-                                    let _ = Otoroshi::upsert_one_resource(
-                                        exposed_resource.clone(),
-                                        id.to_string(),
-                                        serde_json::to_string(&json).unwrap(),
-                                        cli_opts.clone(),
-                                    )
-                                    .await;
-                                } else {
-                                    let json =
-                                        serde_json::from_str::<serde_json::Value>(&edited).unwrap();
-                                    let id: String =
-                                        EntityHelper::extract_json_entity_id(&json).unwrap();
-                                    let _ = Otoroshi::upsert_one_resource(
-                                        exposed_resource.clone(),
-                                        id.to_string(),
-                                        edited,
+                        match Otoroshi::get_one_resource(
+                            exposed_resource.clone(),
+                            id.to_string(),
+                            cli_opts.clone(),
+                        )
+                        .await
+                        {
+                            None => {
+                                cli_stderr_printline!(
+                                    "error while fetching entity {}/{}",
+                                    final_resource_name,
+                                    id
+                                );
+                                std::process::exit(-1)
+                            }
+                            Some(res) => {
+                                if data.is_empty() {
+                                    let content = match input {
+                                        Some(input) => input.clone(),
+                                        None => {
+                                            let to_edit =
+                                                serde_json::to_string_pretty(&res.body).unwrap();
+                                            if *stdin {
+                                                std::io::read_to_string(std::io::stdin()).unwrap()
+                                            } else {
+                                                edit::edit(&to_edit).unwrap_or(to_edit.clone())
+                                            }
+                                        }
+                                    };
+                                    Self::upsert_entity_input(
+                                        &exposed_resource,
+                                        Some(id.to_string()),
+                                        content,
+                                        dry_run,
+                                        "edit",
                                         cli_opts.clone(),
                                     )
                                     .await;
-                                }
-                            } else {
-                                let edited = input.clone().unwrap();
-                                let is_yaml = !edited.trim().starts_with("{");
-                                if is_yaml {
-                                    let mut json =
-                                        serde_yaml::from_str::<serde_json::Value>(&edited).unwrap();
-                                    let is_kube = json.get("apiVersion").is_some()
-                                        && json.get("spec").is_some();
-                                    if is_kube {
-                                        json = json.get("spec").unwrap().clone();
-                                    }
-                                    let id = EntityHelper::extract_json_entity_id(&json).unwrap();
-                                    let _ = Otoroshi::upsert_one_resource(
-                                        exposed_resource.clone(),
-                                        id.to_string(),
-                                        serde_json::to_string(&json).unwrap(),
+                                } else if dry_run {
+                                    let (action, error) = match JsonPatchHelper::apply_oto_patch(
+                                        &res.body, &oto_patch,
+                                    ) {
+                                        Ok(patched)
+                                            if DiffHelper::diff_declared(&res.body, &patched)
+                                                .is_empty() =>
+                                        {
+                                            ("unchanged", None)
+                                        }
+                                        Ok(_) => ("update", None),
+                                        Err(e) => ("error", Some(e)),
+                                    };
+                                    Self::display_plan(
+                                        vec![PlannedAction {
+                                            kind: format!(
+                                                "{}/{}",
+                                                exposed_resource.group, exposed_resource.kind
+                                            ),
+                                            id: id.to_string(),
+                                            name: EntityHelper::extract_json_entity_name(&res.body)
+                                                .unwrap_or(id.to_string()),
+                                            action: action.to_string(),
+                                            status: None,
+                                            error,
+                                        }],
                                         cli_opts.clone(),
-                                    )
-                                    .await;
+                                    );
                                 } else {
-                                    let json = serde_json::from_str(&edited).unwrap();
-                                    let id = EntityHelper::extract_json_entity_id(&json).unwrap();
-                                    let _ = Otoroshi::upsert_one_resource(
-                                        exposed_resource.clone(),
-                                        id.to_string(),
-                                        edited,
+                                    Self::upsert_recorded(
+                                        &exposed_resource,
+                                        id,
+                                        oto_patch.to_string(),
+                                        Some("application/json+oto-patch"),
+                                        Some(res.body),
+                                        "edit",
                                         cli_opts.clone(),
                                     )
                                     .await;
                                 }
                             }
-                        } else {
-                            let serie: String = data
-                                .iter()
-                                .filter(|str| str.contains('='))
-                                .map(|str| {
-                                    let parts: Vec<String> =
-                                        str.split('=').map(|s| s.to_string()).collect();
-                                    let path = parts.first().unwrap();
-                                    let mut value = parts.get(1).unwrap().to_string();
-                                    if value.starts_with('\'') && value.ends_with('\'') {
-                                        value = value
-                                            .strip_suffix('\'')
-                                            .unwrap()
-                                            .strip_prefix('\'')
-                                            .unwrap()
-                                            .to_string();
-                                    };
-                                    if value.starts_with('"') && value.ends_with('"') {
-                                        value = value
-                                            .strip_suffix('"')
-                                            .unwrap()
-                                            .strip_prefix('"')
-                                            .unwrap()
-                                            .to_string();
-                                    };
-                                    serde_json::to_string(
-                                        &serde_json::json!({ "path": path, "value": value }),
-                                    )
-                                    .unwrap()
-                                })
-                                .collect::<Vec<String>>()
-                                .join(",");
-                            let _ = Otoroshi::create_one_resource_with_content_type(
-                                exposed_resource.clone(),
-                                format!("[{}]", serie),
-                                "application/json+oto-patch".to_string(),
-                                cli_opts.clone(),
-                            )
-                            .await;
                         }
                     }
                 }
             }
-            ResourcesSubCommand::Edit {
+            ResourcesSubCommand::Patch {
                 resource,
                 id,
+                merge,
                 file,
                 data,
-                input,
                 stdin,
+                patch_type,
+                selector,
+                tags,
             } => {
+                let selector = Self::selector(selector, tags);
+                let oto_patch = Self::oto_patch(data);
+                // with selectors, the only positional argument after the resource is the patch
+                let (id, merge) = match (&selector, id, merge) {
+                    (Some(_), _, Some(_)) => {
+                        cli_stderr_printline!("an id and selectors can't be used together");
+                        std::process::exit(-1)
+                    }
+                    (Some(_), merge, None) => (None, merge.clone()),
+                    (None, Some(id), merge) => (Some(id.to_string()), merge.clone()),
+                    (None, None, _) => {
+                        cli_stderr_printline!("you need to provide an id or selectors");
+                        std::process::exit(-1)
+                    }
+                };
                 let final_resource_name: String = if resource.ends_with("s") {
                     resource.to_string()
                 } else {
@@ -1548,335 +3931,509 @@ spec:
                             || format!("{}/{}", r.group, r.kind) == final_resource_name
                     })
                     .unwrap();
-                match file {
+                let ids = match (&selector, id) {
+                    (Some(selector), _) => {
+                        Self::selected_ids(&exposed_resource, selector, cli_opts.clone()).await
+                    }
+                    (None, id) => id.into_iter().collect(),
+                };
+                // the patch is read once, and applied to every entity
+                let patch = if data.is_empty() {
+                    let content: String = match file {
+                        None => {
+                            if *stdin {
+                                std::io::read_to_string(std::io::stdin()).unwrap()
+                            } else {
+                                match merge {
+                                    None => edit::edit("").unwrap_or_default(),
+                                    Some(merge) => merge.to_string(),
+                                }
+                            }
+                        }
+                        Some(file) => {
+                            crate::utils::file::FileHelper::get_content_string(file).await
+                        }
+                    };
+                    let is_json =
+                        content.trim().starts_with("{") || content.trim().starts_with("[");
+                    let parsed = if is_json {
+                        serde_json::from_str::<serde_json::Value>(&content)
+                            .map_err(|e| e.to_string())
+                    } else {
+                        serde_yaml::from_str::<serde_json::Value>(&content)
+                            .map_err(|e| e.to_string())
+                    };
+                    let input = match parsed {
+                        Err(e) => {
+                            cli_stderr_printline!("error while parsing the patch: {}", e);
+                            std::process::exit(-1)
+                        }
+                        Ok(input) => input,
+                    };
+                    let json_patch = match patch_type.as_deref() {
+                        None => JsonPatchHelper::is_json_patch(&input),
+                        Some("json") => true,
+                        Some("merge") => false,
+                        Some(other) => {
+                            cli_stderr_printline!(
+                                "unknown patch type '{}', can be one of: json, merge",
+                                other
+                            );
+                            std::process::exit(-1)
+                        }
+                    };
+                    Some((input, json_patch))
+                } else {
+                    None
+                };
+                // every entity is patched before writing any of them
+                let mut patched = Vec::new();
+                for id in ids.iter() {
+                    match Self::patch_entity(
+                        &exposed_resource,
+                        &final_resource_name,
+                        id,
+                        &patch,
+                        &oto_patch,
+                        cli_opts.clone(),
+                    )
+                    .await
+                    {
+                        Ok(entity) => patched.push((id, entity)),
+                        Err(e) => {
+                            cli_stderr_printline!("{}", e);
+                            std::process::exit(-1)
+                        }
+                    }
+                }
+                let mut results: Vec<PlannedAction> = Vec::new();
+                for (id, (body, content_type, previous)) in patched.into_iter() {
+                    let done = Self::upsert_recorded(
+                        &exposed_resource,
+                        id,
+                        body,
+                        content_type,
+                        Some(previous),
+                        "patch",
+                        cli_opts.clone(),
+                    )
+                    .await;
+                    if !done {
+                        cli_stderr_printline!(
+                            "error while patching {} {}",
+                            final_resource_name,
+                            id
+                        );
+                    } else if selector.is_some() {
+                        cli_stdout_printline!("{} {} patched", final_resource_name, id);
+                    }
+                    results.push(PlannedAction {
+                        kind: format!("{}/{}", exposed_resource.group, exposed_resource.kind),
+                        id: id.to_string(),
+                        name: id.to_string(),
+                        action: if done { "patched" } else { "error" }.to_string(),
+                        status: None,
+                        error: None,
+                    });
+                }
+                Self::exit_on_failure(&results);
+            }
+            ResourcesSubCommand::Apply {
+                file,
+                directory,
+                recursive,
+                watch,
+                dry_run,
+                prune,
+                managed_by,
+                values,
+                overlay,
+                concurrency,
+                report,
+                report_file,
+                skip_validation,
+            } => {
+                Self::check_report_options(report, report_file);
+                let options = SyncOptions {
+                    dry_run: dry_run.unwrap_or(false),
+                    prune: prune.unwrap_or(false),
+                    managed_by: managed_by.clone(),
+                    vars: Self::load_values(values).await,
+                    concurrency: concurrency.unwrap_or(1) as usize,
+                    validate: !skip_validation.unwrap_or(false),
+                };
+                let watch = watch.unwrap_or(false) && !options.dry_run;
+                if let Some(overlay) = overlay {
+                    if !options.dry_run {
+                        cli_stdout_printline!("will try to sync overlay {:?} ...", overlay);
+                    }
+                    let documents =
+                        Self::build_overlay(overlay, &options.vars, &mut Vec::new()).await;
+                    let results = Self::sync_documents(documents, &options, cli_opts.clone()).await;
+                    Self::write_report("apply", &results, report, report_file);
+                    Self::exit_on_failure(&results);
+                    return;
+                }
+                // the watched path, and whether it is a directory
+                let (results, watched) = match file {
+                    None => match directory {
+                        None => {
+                            cli_stdout_printline!("you need to provide a file or directory path");
+                            (Vec::new(), None)
+                        }
+                        Some(directory) => {
+                            let files = Self::find_files(directory, recursive.unwrap_or(false));
+                            (
+                                Self::sync_files(files, &options, cli_opts.clone()).await,
+                                Some((directory.as_os_str().to_string_lossy().to_string(), true)),
+                            )
+                        }
+                    },
                     Some(file) => {
-                        match crate::utils::file::FileHelper::get_content_string_result(file).await
-                        {
-                            Err(e) => {
-                                cli_stderr_printline!("error while reading {:?}: {}", file, e);
-                                std::process::exit(-1)
+                        if file.starts_with("http://") || file.starts_with("https://") {
+                            (
+                                Self::sync_url(file.to_owned(), &options, cli_opts.clone()).await,
+                                None,
+                            )
+                        } else {
+                            (
+                                Self::sync_files(
+                                    vec![PathBuf::from(file.to_owned())],
+                                    &options,
+                                    cli_opts.clone(),
+                                )
+                                .await,
+                                Some((file.to_string(), false)),
+                            )
+                        }
+                    }
+                };
+                Self::write_report("apply", &results, report, report_file);
+                match watched {
+                    Some((path, is_directory)) if watch => Self::run_watch(
+                        path,
+                        is_directory,
+                        recursive.unwrap_or(false),
+                        options.clone(),
+                        cli_opts.clone(),
+                    ),
+                    _ => Self::exit_on_failure(&results),
+                }
+            }
+            ResourcesSubCommand::Validate {
+                file,
+                directory,
+                recursive,
+                values,
+                offline,
+            } => {
+                let mut documents = Self::load_documents(
+                    Self::find_sources(file, directory, recursive),
+                    &Self::load_values(values).await,
+                )
+                .await;
+                Self::decrypt_documents(&mut documents, &cli_opts);
+                let violations = Self::validate_documents(
+                    &documents,
+                    None,
+                    offline.unwrap_or(false),
+                    cli_opts.clone(),
+                )
+                .await;
+                if !RenderHelper::display(&cli_opts, &violations, &[]) {
+                    for violation in violations.iter() {
+                        cli_stdout_printline!(
+                            "{}:{}: {}",
+                            violation.source,
+                            violation.line,
+                            violation.message
+                        );
+                    }
+                    if violations.is_empty() {
+                        cli_stdout_printline!("{} documents are valid", documents.len());
+                    }
+                }
+                if !violations.is_empty() {
+                    std::process::exit(1)
+                }
+            }
+            ResourcesSubCommand::Lint {
+                file,
+                directory,
+                recursive,
+                values,
+                rules,
+                list_rules,
+            } => {
+                let config = match LintConfig::load(rules.as_deref()) {
+                    Ok(config) => config,
+                    Err(e) => {
+                        cli_stderr_printline!("{}", e);
+                        std::process::exit(-1)
+                    }
+                };
+                if list_rules.unwrap_or(false) {
+                    let rules: Vec<serde_json::Value> = LintHelper::rules()
+                        .iter()
+                        .map(|rule| {
+                            serde_json::json!({
+                                "rule": rule.rule,
+                                "kinds": rule.kinds,
+                                "severity": config.severity(rule).unwrap_or("off".to_string()),
+                                "description": rule.description,
+                            })
+                        })
+                        .collect();
+                    if !RenderHelper::display(&cli_opts, &rules, &[]) {
+                        for rule in rules.iter() {
+                            cli_stdout_printline!(
+                                "{:<30} {:<8} {}",
+                                rule["rule"].as_str().unwrap(),
+                                rule["severity"].as_str().unwrap(),
+                                rule["description"].as_str().unwrap()
+                            );
+                        }
+                    }
+                    return;
+                }
+                let findings = if file.is_none() && directory.is_none() {
+                    Self::lint_cluster(&config, cli_opts.clone()).await
+                } else {
+                    let documents = Self::load_documents(
+                        Self::find_sources(file, directory, recursive),
+                        &Self::load_values(values).await,
+                    )
+                    .await;
+                    Self::lint_documents(&documents, &config)
+                };
+                if cli_opts.ouput.as_deref() == Some("sarif") {
+                    cli_stdout_printline!("{}", LintHelper::sarif(&findings, &config));
+                } else if !RenderHelper::display(&cli_opts, &findings, &[]) {
+                    LintHelper::display(&findings);
+                }
+                if findings.iter().any(|f| f.severity == "error") {
+                    std::process::exit(1)
+                }
+            }
+            ResourcesSubCommand::Diff {
+                file,
+                directory,
+                recursive,
+                ignore_removed,
+                values,
+            } => {
+                let mut documents = Self::load_documents(
+                    Self::find_sources(file, directory, recursive),
+                    &Self::load_values(values).await,
+                )
+                .await;
+                Self::decrypt_documents(&mut documents, &cli_opts);
+                let results = Self::diff_documents(
+                    documents,
+                    ignore_removed.unwrap_or(false),
+                    cli_opts.clone(),
+                )
+                .await;
+                if !RenderHelper::display(&cli_opts, &results, &[]) {
+                    Self::display_diff(&results)
+                }
+                if results.iter().any(|r| r.status == "error") {
+                    std::process::exit(-1)
+                } else if results.iter().any(|r| r.status != "unchanged") {
+                    std::process::exit(1)
+                }
+            }
+            ResourcesSubCommand::Render {
+                file,
+                directory,
+                recursive,
+                values,
+            } => {
+                let documents = Self::load_documents(
+                    Self::find_sources(file, directory, recursive),
+                    &Self::load_values(values).await,
+                )
+                .await;
+                Self::display_documents(documents, false, cli_opts.clone());
+            }
+            ResourcesSubCommand::Build {
+                directory,
+                values,
+                kube,
+            } => {
+                let documents = Self::build_overlay(
+                    directory,
+                    &Self::load_values(values).await,
+                    &mut Vec::new(),
+                )
+                .await;
+                Self::display_documents(documents, kube.unwrap_or(false), cli_opts.clone());
+            }
+            ResourcesSubCommand::Convert {
+                file,
+                directory,
+                recursive,
+                from,
+                to,
+                out_file,
+                out_dir,
+            } => {
+                let parse = |format: &str| match EntityFormat::parse(format) {
+                    Ok(format) => format,
+                    Err(e) => {
+                        cli_stderr_printline!("{}", e);
+                        std::process::exit(-1)
+                    }
+                };
+                let to = parse(to);
+                let from = from.as_deref().map(parse);
+                let resources = SchemaHelper::cached_resources(&SchemaHelper::cache_dir(
+                    cli_opts.config_file.as_deref(),
+                ));
+                let mut converted: Vec<(String, String, serde_json::Value)> = Vec::new();
+                for source in Self::convert_sources(file, directory, recursive.unwrap_or(false)) {
+                    let documents =
+                        crate::utils::file::FileHelper::get_content_string_result(&source)
+                            .await
+                            .map_err(|e| format!("error while reading {}: {}", source, e))
+                            .and_then(|content| {
+                                let format = from.unwrap_or_else(|| EntityFormat::guess(&source));
+                                ConvertHelper::read(&content, format, &source)
+                            });
+                    let documents = match documents {
+                        Ok(documents) => documents,
+                        Err(e) => {
+                            cli_stderr_printline!("{}", e);
+                            std::process::exit(-1)
+                        }
+                    };
+                    for doc in documents.iter() {
+                        match ConvertHelper::entity(doc, to, resources.as_ref()) {
+                            Ok(entity) => {
+                                let (_, plural) = ConvertHelper::kind(
+                                    doc.kind.as_deref().unwrap_or_default(),
+                                    resources.as_ref(),
+                                );
+                                converted.push((plural, doc.id.clone().unwrap_or_default(), entity))
                             }
-                            Ok(content) => {
-                                let is_yaml = !content.trim().starts_with("{");
-                                if is_yaml {
-                                    let mut json =
-                                        serde_yaml::from_str::<serde_json::Value>(&content)
-                                            .unwrap();
-                                    let is_kube = json.get("apiVersion").is_some()
-                                        && json.get("spec").is_some();
-                                    if is_kube {
-                                        json = json.get("spec").unwrap().clone();
-                                    }
-                                    let _ = Otoroshi::upsert_one_resource(
-                                        exposed_resource.clone(),
-                                        id.to_string(),
-                                        serde_json::to_string(&json).unwrap(),
-                                        cli_opts.clone(),
-                                    )
-                                    .await;
-                                } else {
-                                    let _ = Otoroshi::upsert_one_resource(
-                                        exposed_resource.clone(),
-                                        id.to_string(),
-                                        content,
-                                        cli_opts.clone(),
-                                    )
-                                    .await;
-                                }
+                            Err(e) => {
+                                cli_stderr_printline!("{}", e);
+                                std::process::exit(-1)
                             }
                         }
                     }
-                    _ => {
-                        match Otoroshi::get_one_resource(
-                            exposed_resource.clone(),
-                            id.to_string(),
-                            cli_opts.clone(),
-                        )
-                        .await
+                }
+                let written = match (out_file, out_dir) {
+                    (_, Some(out_dir)) => {
+                        if let Some((_, _, entity)) =
+                            converted.iter().find(|(_, id, _)| id.is_empty())
                         {
-                            None => {
-                                cli_stderr_printline!(
-                                    "error while fetching entity {}/{}",
-                                    final_resource_name,
-                                    id
-                                );
-                                std::process::exit(-1)
-                            }
-                            Some(res) => {
-                                if data.is_empty() {
-                                    if input.is_none() {
-                                        let to_edit =
-                                            serde_json::to_string_pretty(&res.body).unwrap();
-                                        let edited = if *stdin {
-                                            std::io::read_to_string(std::io::stdin()).unwrap()
-                                        } else {
-                                            edit::edit(&to_edit).unwrap_or(to_edit.clone())
-                                        };
-                                        let is_yaml = !edited.trim().starts_with("{");
-                                        if is_yaml {
-                                            let mut json =
-                                                serde_yaml::from_str::<serde_json::Value>(&edited)
-                                                    .unwrap();
-                                            let is_kube = json.get("apiVersion").is_some()
-                                                && json.get("spec").is_some();
-                                            if is_kube {
-                                                json = json.get("spec").unwrap().clone();
-                                            }
-                                            let _ = Otoroshi::upsert_one_resource(
-                                                exposed_resource.clone(),
-                                                id.to_string(),
-                                                serde_json::to_string(&json).unwrap(),
-                                                cli_opts.clone(),
-                                            )
-                                            .await;
-                                        } else {
-                                            let _ = Otoroshi::upsert_one_resource(
-                                                exposed_resource.clone(),
-                                                id.to_string(),
-                                                edited,
-                                                cli_opts.clone(),
-                                            )
-                                            .await;
-                                        }
-                                    } else {
-                                        let edited = input.clone().unwrap();
-                                        let is_yaml = !edited.trim().starts_with("{");
-                                        if is_yaml {
-                                            let mut json =
-                                                serde_yaml::from_str::<serde_json::Value>(&edited)
-                                                    .unwrap();
-                                            let is_kube = json.get("apiVersion").is_some()
-                                                && json.get("spec").is_some();
-                                            if is_kube {
-                                                json = json.get("spec").unwrap().clone();
-                                            }
-                                            let _ = Otoroshi::upsert_one_resource(
-                                                exposed_resource.clone(),
-                                                id.to_string(),
-                                                serde_json::to_string(&json).unwrap(),
-                                                cli_opts.clone(),
-                                            )
-                                            .await;
-                                        } else {
-                                            let _ = Otoroshi::upsert_one_resource(
-                                                exposed_resource.clone(),
-                                                id.to_string(),
-                                                edited,
-                                                cli_opts.clone(),
-                                            )
-                                            .await;
-                                        }
-                                    }
-                                } else {
-                                    let serie: String = data.iter()
-                                        .filter(|str| str.contains('='))
-                                        .map(|str| {
-                                            let parts: Vec<String> = str.split('=').map(|s| s.to_string()).collect();
-                                            let path = parts.first().unwrap();
-                                            let mut value = parts.get(1).unwrap().to_string();
-                                            if value.starts_with('\'') && value.ends_with('\'') {
-                                                value = value.strip_suffix('\'').unwrap().strip_prefix('\'').unwrap().to_string();
-                                            };
-                                            if value.starts_with('"') && value.ends_with('"') {
-                                                value = value.strip_suffix('"').unwrap().strip_prefix('"').unwrap().to_string();
-                                            };
-                                            serde_json::to_string(&serde_json::json!({ "path": path, "value": value })).unwrap()
-                                        })
-                                        .collect::<Vec<String>>()
-                                        .join(",");
-                                    let _ = Otoroshi::upsert_one_resource_with_content_type(
-                                        exposed_resource.clone(),
-                                        id.to_string(),
-                                        format!("[{}]", serie),
-                                        "application/json+oto-patch".to_string(),
-                                        cli_opts.clone(),
-                                    )
-                                    .await;
-                                }
-                            }
+                            cli_stderr_printline!(
+                                "an entity without id can't be written to its own file: {}",
+                                entity
+                            );
+                            std::process::exit(-1)
                         }
+                        Self::write_converted(converted, to, out_dir)
+                    }
+                    (Some(out_file), None) => {
+                        let entities: Vec<serde_json::Value> =
+                            converted.into_iter().map(|(_, _, entity)| entity).collect();
+                        std::fs::write(out_file, ConvertHelper::render(&entities, to))
+                            .map(|_| 1)
+                            .map_err(|e| format!("error while writing {:?}: {}", out_file, e))
+                    }
+                    (None, None) => {
+                        let entities: Vec<serde_json::Value> =
+                            converted.into_iter().map(|(_, _, entity)| entity).collect();
+                        cli_stdout_printline!(
+                            "{}",
+                            ConvertHelper::render(&entities, to).trim_end()
+                        );
+                        Ok(0)
+                    }
+                };
+                match written {
+                    Ok(0) => (),
+                    Ok(count) => cli_stderr_printline!("{} files written", count),
+                    Err(e) => {
+                        cli_stderr_printline!("{}", e);
+                        std::process::exit(-1)
                     }
                 }
             }
-            ResourcesSubCommand::Patch {
-                resource,
-                id,
-                merge,
+            ResourcesSubCommand::Encrypt {
                 file,
-                data,
-                stdin,
+                directory,
+                recursive,
+                fields,
+                in_place,
             } => {
-                // TODO: handle json patch
-                let final_resource_name: String = if resource.ends_with("s") {
-                    resource.to_string()
+                let fields = if fields.is_empty() {
+                    SecretHelper::default_fields()
                 } else {
-                    format!("{}s", resource)
+                    fields.clone()
                 };
-                let exposed_resources: OtoroshExposedResources =
-                    Otoroshi::get_exposed_resources(cli_opts.clone())
-                        .await
-                        .unwrap();
-                let exposed_resource = exposed_resources
-                    .resources
-                    .into_iter()
-                    .find(|r| {
-                        r.plural_name == final_resource_name
-                            || r.singular_name == final_resource_name
-                            || r.kind == final_resource_name
-                            || format!("{}/{}", r.group, r.kind) == final_resource_name
-                    })
-                    .unwrap();
-                match Otoroshi::get_one_resource(
-                    exposed_resource.clone(),
-                    id.to_string(),
-                    cli_opts.clone(),
-                )
-                .await
-                {
-                    None => {
-                        cli_stderr_printline!(
-                            "error while fetching resource {}/{}",
-                            final_resource_name,
-                            id
-                        );
-                        std::process::exit(-1)
-                    }
-                    Some(res) => {
-                        if data.is_empty() {
-                            let content: String = match file {
-                                None => {
-                                    if *stdin {
-                                        std::io::read_to_string(std::io::stdin()).unwrap()
-                                    } else {
-                                        match merge {
-                                            None => edit::edit("").unwrap_or_default(),
-                                            Some(merge) => merge.to_string(),
-                                        }
-                                    }
-                                }
-                                Some(file) => {
-                                    crate::utils::file::FileHelper::get_content_string(file).await
-                                }
-                            };
-                            let is_yaml = !content.trim().starts_with("{");
-                            if is_yaml {
-                                let input =
-                                    serde_yaml::from_str::<serde_json::Value>(&content).unwrap();
-                                let mut doc = res.body;
-                                doc.merge(&input);
-                                let edited = serde_json::to_string(&doc).unwrap();
-                                let _ = Otoroshi::upsert_one_resource(
-                                    exposed_resource.clone(),
-                                    id.to_string(),
-                                    serde_json::to_string(&edited).unwrap(),
-                                    cli_opts.clone(),
-                                )
-                                .await;
-                            } else {
-                                let input =
-                                    serde_json::from_str::<serde_json::Value>(&content).unwrap();
-                                let mut doc = res.body;
-                                doc.merge(&input);
-                                let edited = serde_json::to_string(&doc).unwrap();
-                                let _ = Otoroshi::upsert_one_resource(
-                                    exposed_resource.clone(),
-                                    id.to_string(),
-                                    edited,
-                                    cli_opts.clone(),
-                                )
-                                .await;
-                            }
-                        } else {
-                            let serie: String = data
-                                .iter()
-                                .filter(|str| str.contains('='))
-                                .map(|str| {
-                                    let parts: Vec<String> =
-                                        str.split('=').map(|s| s.to_string()).collect();
-                                    let path = parts.first().unwrap();
-                                    let mut value = parts.get(1).unwrap().to_string();
-                                    if value.starts_with('\'') && value.ends_with('\'') {
-                                        value = value
-                                            .strip_suffix('\'')
-                                            .unwrap()
-                                            .strip_prefix('\'')
-                                            .unwrap()
-                                            .to_string();
-                                    };
-                                    if value.starts_with('"') && value.ends_with('"') {
-                                        value = value
-                                            .strip_suffix('"')
-                                            .unwrap()
-                                            .strip_prefix('"')
-                                            .unwrap()
-                                            .to_string();
-                                    };
-                                    serde_json::to_string(
-                                        &serde_json::json!({ "path": path, "value": value }),
-                                    )
-                                    .unwrap()
-                                })
-                                .collect::<Vec<String>>()
-                                .join(",");
-                            let _ = Otoroshi::upsert_one_resource_with_content_type(
-                                exposed_resource.clone(),
-                                id.to_string(),
-                                format!("[{}]", serie),
-                                "application/json+oto-patch".to_string(),
-                                cli_opts.clone(),
-                            )
-                            .await;
-                        }
-                    }
-                }
+                let mut key = Self::secret_key(&cli_opts);
+                Self::transform_secret_files(
+                    Self::find_sources(file, directory, recursive),
+                    in_place.unwrap_or(false),
+                    "encrypted",
+                    |value| SecretHelper::encrypt_fields(value, &fields, &mut key),
+                );
             }
-            ResourcesSubCommand::Apply {
+            ResourcesSubCommand::Decrypt {
                 file,
                 directory,
                 recursive,
-                watch,
-            } => match file {
-                None => match directory {
-                    None => cli_stdout_printline!("you need to provide a file or directory path"),
-                    Some(directory) => {
-                        let files = Self::find_files(directory, recursive.unwrap_or(false));
-                        Self::sync_files(files, cli_opts.clone()).await;
-                        if watch.unwrap_or(false) {
-                            Self::run_watch(
-                                directory.as_os_str().to_string_lossy().to_string(),
-                                true,
-                                recursive.unwrap_or(false),
-                                cli_opts.clone(),
-                            );
+                in_place,
+            } => {
+                let mut key = Self::secret_key(&cli_opts);
+                Self::transform_secret_files(
+                    Self::find_sources(file, directory, recursive),
+                    in_place.unwrap_or(false),
+                    "decrypted",
+                    |value| SecretHelper::decrypt_all(value, &mut key),
+                );
+            }
+            ResourcesSubCommand::Import {
+                file,
+                nd_json,
+                report,
+                report_file,
+            } => {
+                Self::check_report_options(report, report_file);
+                let mut content = crate::utils::file::FileHelper::get_content_string(file).await;
+                // the key is only needed when a field is encrypted
+                let mut key: Option<SecretKey> = None;
+                let mut decrypted = 0;
+                let transformed = Self::transform_export(
+                    content.as_bytes(),
+                    nd_json.unwrap_or(false),
+                    |value| {
+                        if !SecretHelper::contains_encrypted(value) {
+                            return Ok(0);
                         }
+                        let key = key.get_or_insert_with(|| Self::secret_key(&cli_opts));
+                        let count = SecretHelper::decrypt_all(value, key)?;
+                        decrypted += count;
+                        Ok(count)
+                    },
+                    None,
+                );
+                match transformed {
+                    Ok(transformed) if decrypted > 0 => {
+                        content = String::from_utf8(transformed).unwrap()
                     }
-                },
-                Some(file) => {
-                    if file.starts_with("http://") || file.starts_with("https://") {
-                        if file.starts_with("http://") {
-                            Self::sync_url_http(file.to_owned(), cli_opts.clone()).await;
-                        } else if file.starts_with("https://") {
-                            Self::sync_url_https(file.to_owned(), cli_opts.clone()).await;
-                        }
-                    } else {
-                        Self::sync_files(vec![PathBuf::from(file.to_owned())], cli_opts.clone())
-                            .await;
-                        if watch.unwrap_or(false) {
-                            Self::run_watch(
-                                file.to_string(),
-                                false,
-                                recursive.unwrap_or(false),
-                                cli_opts.clone(),
-                            );
-                        }
+                    Ok(_) => (),
+                    Err(e) => {
+                        cli_stderr_printline!("error while reading {}: {}", file, e);
+                        std::process::exit(-1)
                     }
                 }
-            },
-            ResourcesSubCommand::Import { file, nd_json } => {
                 let cconfig = crate::cli::config::OtoroshiCtlConfig::get_current_config(cli_opts)
                     .await
                     .to_connection_config();
-                let content = crate::utils::file::FileHelper::get_content_string(file).await;
                 let content_type = Some(
                     nd_json
                         .filter(|i| *i)
@@ -1892,162 +4449,298 @@ spec:
                     cconfig.clone(),
                 )
                 .await;
-                if res.status != 200 {
+                let failed = res.status != 200;
+                let result = PlannedAction {
+                    kind: "import".to_string(),
+                    id: file.to_string(),
+                    name: file.to_string(),
+                    action: if failed { "error" } else { "imported" }.to_string(),
+                    status: Some(res.status),
+                    error: failed.then(|| String::from_utf8_lossy(&res.body_bytes).to_string()),
+                };
+                Self::write_report("import", &[result], report, report_file);
+                if failed {
                     cli_stderr_printline!("import error ! {:?}", res.body_bytes);
                     std::process::exit(-1)
                 }
             }
+            ResourcesSubCommand::ImportOpenapi {
+                spec,
+                domain,
+                backend,
+                group_by,
+                security,
+                jwt_verifier,
+                out_dir,
+                apply,
+                dry_run,
+            } => {
+                let inputs = OpenApiGroupBy::parse(group_by.as_deref()).and_then(|group_by| {
+                    OpenApiBackend::parse(backend).map(|backend| (group_by, backend))
+                });
+                let (group_by, backend) = match inputs {
+                    Ok(inputs) => inputs,
+                    Err(e) => {
+                        cli_stderr_printline!("{}", e);
+                        std::process::exit(-1)
+                    }
+                };
+                let document = crate::utils::file::FileHelper::get_content_string_result(spec)
+                    .await
+                    .map_err(|e| format!("error while reading {}: {}", spec, e))
+                    .and_then(|content| OpenApiHelper::parse(&content, spec));
+                let document = match document {
+                    Ok(document) => document,
+                    Err(e) => {
+                        cli_stderr_printline!("{}", e);
+                        std::process::exit(-1)
+                    }
+                };
+                let Some(template) = Otoroshi::get_route_template(cli_opts.clone()).await else {
+                    cli_stderr_printline!("error: failed to get route template from Otoroshi");
+                    std::process::exit(-1)
+                };
+                let routes = OpenApiHelper::routes(
+                    &document,
+                    &template,
+                    domain,
+                    &backend,
+                    group_by,
+                    security.unwrap_or(false),
+                    jwt_verifier.as_deref(),
+                );
+                for issue in routes.issues.iter() {
+                    cli_stderr_printline!("warning: {}", issue);
+                }
+                let routes = routes.routes;
+                if routes.is_empty() {
+                    cli_stderr_printline!("no operation found in {}", spec);
+                    std::process::exit(-1)
+                }
+                let missing_verifier = routes.iter().any(OpenApiHelper::missing_jwt_verifier);
+                if missing_verifier && apply.unwrap_or(false) {
+                    cli_stderr_printline!(
+                        "error: the routes verify jwt tokens, use --jwt-verifier to choose the verifier"
+                    );
+                    std::process::exit(-1)
+                }
+                if missing_verifier {
+                    cli_stderr_printline!(
+                        "warning: the routes verify jwt tokens without a verifier, they reject every call until one is set"
+                    );
+                }
+                if apply.unwrap_or(false) {
+                    let routes: Vec<serde_json::Value> = routes
+                        .iter()
+                        .map(|route| {
+                            EntityHelper::with_kind(route, "proxy.otoroshi.io/Route".to_string())
+                        })
+                        .collect();
+                    let documents = EntityHelper::parse_documents(
+                        &serde_json::to_string(&routes).unwrap(),
+                        true,
+                        spec,
+                    )
+                    .unwrap();
+                    let options = SyncOptions {
+                        dry_run: dry_run.unwrap_or(false),
+                        prune: false,
+                        managed_by: None,
+                        vars: serde_json::json!({}),
+                        concurrency: 1,
+                        validate: true,
+                    };
+                    let results = Self::sync_documents(documents, &options, cli_opts).await;
+                    Self::exit_on_failure(&results);
+                } else {
+                    Self::output_routes(routes, out_dir);
+                }
+            }
+            ResourcesSubCommand::ImportK8s {
+                file,
+                directory,
+                recursive,
+                out_dir,
+            } => {
+                let mut translation = K8sTranslation::default();
+                for source in Self::convert_sources(file, directory, recursive.unwrap_or(false)) {
+                    let manifests =
+                        crate::utils::file::FileHelper::get_content_string_result(&source)
+                            .await
+                            .map_err(|e| format!("error while reading {}: {}", source, e))
+                            .and_then(|content| {
+                                K8sHelper::manifests(&content, source.ends_with(".json"), &source)
+                            });
+                    match manifests {
+                        Ok(manifests) => {
+                            K8sHelper::translate(&manifests, &source, &mut translation)
+                        }
+                        Err(e) => {
+                            cli_stderr_printline!("{}", e);
+                            std::process::exit(-1)
+                        }
+                    }
+                }
+                for issue in translation.issues.iter() {
+                    cli_stderr_printline!(
+                        "{}: {}: {}",
+                        issue.source,
+                        issue.resource,
+                        issue.message
+                    );
+                }
+                if translation.routes.is_empty() {
+                    cli_stderr_printline!("no Ingress or HTTPRoute could be translated");
+                    std::process::exit(-1)
+                }
+                cli_stderr_printline!(
+                    "{} routes translated, {} issues",
+                    translation.routes.len(),
+                    translation.issues.len()
+                );
+                Self::output_routes(translation.routes, out_dir);
+            }
+            ResourcesSubCommand::Migrate { command } => match command {
+                MigrateSubCommand::ServiceDescriptors {
+                    ids,
+                    out_dir,
+                    apply,
+                    dry_run,
+                    report_file,
+                } => {
+                    Self::migrate_service_descriptors(
+                        ids,
+                        out_dir,
+                        apply.unwrap_or(false),
+                        dry_run.unwrap_or(false),
+                        report_file,
+                        cli_opts,
+                    )
+                    .await
+                }
+            },
+            ResourcesSubCommand::Copy {
+                resource,
+                ids,
+                selector,
+                tags,
+                from_context,
+                to_context,
+                map_ids,
+                set,
+                dry_run,
+            } => {
+                Self::copy_between_contexts(
+                    resource,
+                    ids,
+                    Self::selector(selector, tags),
+                    from_context,
+                    to_context,
+                    map_ids,
+                    set,
+                    dry_run.unwrap_or(false),
+                    cli_opts,
+                )
+                .await
+            }
             ResourcesSubCommand::Export {
                 file,
                 directory,
                 split_files,
                 kube,
                 nd_json,
-            } => match file {
-                Some(file) => {
-                    if !file.exists() {
-                        std::fs::File::create(file).unwrap();
+                encrypt,
+                normalize,
+                concurrency,
+                selector,
+                tags,
+            } => {
+                let selector = Self::selector(selector, tags);
+                let mut secret_key = encrypt.filter(|e| *e).map(|_| Self::secret_key(&cli_opts));
+                match file {
+                    Some(file) => {
+                        if !file.exists() {
+                            std::fs::File::create(file).unwrap();
+                        }
+                        let accept = nd_json
+                            .filter(|i| *i)
+                            .map(|_| "application/x-ndjson".to_string());
+                        match Otoroshi::get_export_body(accept, cli_opts.clone()).await {
+                            None => {
+                                cli_stderr_printline!("error while fetching export");
+                                std::process::exit(-1)
+                            }
+                            Some(body) => {
+                                if let Err(e) = Self::export_file(
+                                    file,
+                                    body,
+                                    nd_json.unwrap_or(false),
+                                    selector.as_ref(),
+                                    secret_key.as_mut(),
+                                )
+                                .await
+                                {
+                                    cli_stderr_printline!("{}", e);
+                                    std::process::exit(-1)
+                                }
+                            }
+                        }
                     }
-                    let accept = nd_json
-                        .filter(|i| *i)
-                        .map(|_| "application/x-ndjson".to_string());
-                    match Otoroshi::get_export_json(accept, cli_opts.clone()).await {
+                    None => match directory {
                         None => {
-                            cli_stderr_printline!("error while fetching export");
+                            cli_stderr_printline!("you have to specify a file or directory");
                             std::process::exit(-1)
                         }
-                        Some(bytes) => std::fs::write(file, bytes).unwrap(),
-                    }
-                }
-                None => match directory {
-                    None => {
-                        cli_stderr_printline!("you have to specify a file or directory");
-                        std::process::exit(-1)
-                    }
-                    Some(directory) => {
-                        if !directory.exists() {
-                            std::fs::create_dir_all(directory).unwrap();
-                        }
-                        for resource in Otoroshi::get_exposed_resources(cli_opts.clone())
-                            .await
-                            .unwrap()
-                            .resources
-                            .into_iter()
-                        {
-                            let name = resource.plural_name.to_string();
-                            let name2 = resource.plural_name.to_string();
-                            let res = Otoroshi::get_resources(
-                                resource.clone(),
-                                1,
-                                99999,
-                                Vec::new(),
-                                cli_opts.clone(),
-                            )
-                            .await
-                            .unwrap();
-                            if split_files.unwrap_or(false) {
-                                if !res.body.is_empty() {
-                                    std::fs::create_dir_all(directory.join(name)).unwrap();
-                                }
-                                for entity in res.body {
-                                    let entity_id =
-                                        EntityHelper::extract_json_entity_id(&entity).unwrap();
-                                    let entity_name =
-                                        EntityHelper::extract_json_entity_name(&entity).unwrap();
-                                    if cli_opts.clone().ouput.filter(|v| *v == "yaml").is_some()
-                                        && kube.unwrap_or(true)
-                                    {
-                                        let kube_entity = KubeEntity::new(
-                                            resource.clone().kind,
-                                            entity_name,
-                                            entity,
-                                        );
-                                        std::fs::write(
-                                            directory
-                                                .join(&name2)
-                                                .join(format!("{}.yaml", entity_id)),
-                                            serde_yaml::to_string(&kube_entity).unwrap(),
-                                        )
-                                        .unwrap();
-                                    } else if cli_opts
-                                        .clone()
-                                        .ouput
-                                        .filter(|v| *v == "yaml")
-                                        .is_some()
-                                        && !kube.unwrap_or(true)
-                                    {
-                                        let mut kind: serde_json::Map<String, serde_json::Value> =
-                                            serde_json::Map::new();
-                                        kind.insert(
-                                            "kind".to_string(),
-                                            serde_json::Value::String(format!(
-                                                "{}/{}",
-                                                resource.clone().group,
-                                                resource.clone().kind
-                                            )),
-                                        );
-                                        let mut entity_with_kind = entity.clone();
-                                        entity_with_kind.merge(&serde_json::Value::Object(kind));
-                                        std::fs::write(
-                                            directory
-                                                .join(&name2)
-                                                .join(format!("{}.yaml", entity_id)),
-                                            serde_yaml::to_string(&entity_with_kind).unwrap(),
-                                        )
-                                        .unwrap();
-                                    } else {
-                                        let mut kind: serde_json::Map<String, serde_json::Value> =
-                                            serde_json::Map::new();
-                                        kind.insert(
-                                            "kind".to_string(),
-                                            serde_json::Value::String(format!(
-                                                "{}/{}",
-                                                resource.clone().group,
-                                                resource.clone().kind
-                                            )),
-                                        );
-                                        let mut entity_with_kind = entity.clone();
-                                        entity_with_kind.merge(&serde_json::Value::Object(kind));
-                                        std::fs::write(
-                                            directory
-                                                .join(&name2)
-                                                .join(format!("{}.json", entity_id)),
-                                            serde_json::to_string_pretty(&entity_with_kind)
-                                                .unwrap(),
-                                        )
-                                        .unwrap();
-                                    }
+                        Some(directory) => {
+                            if !directory.exists() {
+                                std::fs::create_dir_all(directory).unwrap();
+                            }
+                            let resources = Otoroshi::get_exposed_resources(cli_opts.clone())
+                                .await
+                                .unwrap()
+                                .resources;
+                            let yaml = cli_opts.ouput.as_deref() == Some("yaml");
+                            let layout = if split_files.unwrap_or(false) {
+                                ExportLayout::Split {
+                                    yaml,
+                                    kube: kube.unwrap_or(true),
                                 }
+                            } else if nd_json.unwrap_or(false) {
+                                ExportLayout::NdJson
                             } else {
-                                let kinded_docs: Vec<serde_json::Value> = res
-                                    .body
-                                    .into_iter()
-                                    .map(|mut i| {
-                                        let mut kind: serde_json::Map<String, serde_json::Value> =
-                                            serde_json::Map::new();
-                                        kind.insert(
-                                            "kind".to_string(),
-                                            serde_json::Value::String(format!(
-                                                "{}/{}",
-                                                resource.clone().group,
-                                                resource.clone().kind
-                                            )),
-                                        );
-                                        i.merge(&serde_json::Value::Object(kind));
-                                        i
-                                    })
-                                    .collect();
-                                let doc = serde_json::Value::Array(kinded_docs);
-                                std::fs::write(
-                                    directory.join(format!("{}.json", name)),
-                                    serde_json::to_string_pretty(&doc).unwrap(),
-                                )
-                                .unwrap();
+                                ExportLayout::Array
+                            };
+                            let secret_key = Mutex::new(secret_key);
+                            let progress = Mutex::new(ProgressBar::new("export", resources.len()));
+                            let futures = resources
+                                .iter()
+                                .map(|resource| {
+                                    Self::export_kind(
+                                        resource.clone(),
+                                        directory,
+                                        layout,
+                                        normalize.unwrap_or(false),
+                                        selector.as_ref(),
+                                        &secret_key,
+                                        cli_opts.clone(),
+                                    )
+                                })
+                                .collect();
+                            let exported = Self::run_concurrently(
+                                futures,
+                                concurrency.unwrap_or(1) as usize,
+                                &progress,
+                            )
+                            .await;
+                            progress.lock().unwrap().finish();
+                            if let Some(Err(e)) = exported.into_iter().find(|r| r.is_err()) {
+                                cli_stderr_printline!("{}", e);
+                                std::process::exit(-1)
                             }
                         }
-                    }
-                },
-            },
+                    },
+                }
+            }
         }
     }
 }
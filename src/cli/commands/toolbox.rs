@@ -3,6 +3,7 @@ use crate::cli_stderr_printline;
 use crate::cli_stdout_printline;
 use crate::utils::interactive::{resolve_bool, resolve_param, resolve_password, resolve_port};
 use crate::utils::otoroshi::Otoroshi;
+use crate::utils::render::RenderHelper;
 use secrecy::ExposeSecret;
 
 /// Mailer plugin ID constant - used for both verification and route creation
@@ -148,10 +149,7 @@ impl ToolboxCommands {
         }
 
         // Show progress only in interactive mode (not JSON/YAML output)
-        let is_structured_output = matches!(
-            cli_opts.ouput.as_deref(),
-            Some("json") | Some("json_pretty") | Some("yaml")
-        );
+        let is_structured_output = RenderHelper::is_rendered(cli_opts.ouput.as_deref());
         if !is_structured_output {
             cli_stdout_printline!("\nCreating mailer...");
         }
@@ -277,29 +275,8 @@ impl ToolboxCommands {
             url,
         };
 
-        match cli_opts.ouput {
-            Some(ref format) => match format.as_str() {
-                "json" => {
-                    cli_stdout_printline!(
-                        "{}",
-                        serde_json::to_string(&result).expect("Failed to serialize result")
-                    );
-                }
-                "json_pretty" => {
-                    cli_stdout_printline!(
-                        "{}",
-                        serde_json::to_string_pretty(&result).expect("Failed to serialize result")
-                    );
-                }
-                "yaml" => {
-                    cli_stdout_printline!(
-                        "{}",
-                        serde_yaml::to_string(&result).expect("Failed to serialize result")
-                    );
-                }
-                _ => Self::display_mailer_result(&result, &smtp_user),
-            },
-            None => Self::display_mailer_result(&result, &smtp_user),
+        if !RenderHelper::display(&cli_opts, &result, &[]) {
+            Self::display_mailer_result(&result, &smtp_user)
         }
         Ok(())
     }
@@ -368,23 +345,8 @@ impl ToolboxCommands {
                             .unwrap()
                             .to_string();
                         let doc = serde_json::json!({"mode": mode});
-                        match cli_opts.ouput {
-                            Some(str) => match str.as_str() {
-                                "json" => cli_stdout_printline!(
-                                    "{}",
-                                    serde_json::to_string(&doc).unwrap()
-                                ),
-                                "json_pretty" => cli_stdout_printline!(
-                                    "{}",
-                                    serde_json::to_string_pretty(&doc).unwrap()
-                                ),
-                                "yaml" => cli_stdout_printline!(
-                                    "{}",
-                                    serde_yaml::to_string(&doc).unwrap()
-                                ),
-                                _ => cli_stdout_printline!("mTLS mode: {}", mode),
-                            },
-                            _ => cli_stdout_printline!("mTLS mode: {}", mode),
+                        if !RenderHelper::display(&cli_opts, &doc, &[]) {
+                            cli_stdout_printline!("mTLS mode: {}", mode)
                         }
                     }
                 }
@@ -1,5 +1,6 @@
 use crate::cli::cliopts::{CliOpts, Commands};
 use crate::utils::otoroshi::Otoroshi;
+use crate::utils::render::RenderHelper;
 use crate::{cli_stderr_printline, cli_stdout_printline};
 
 use cli_table::{Cell, Style, Table, print_stdout};
@@ -65,23 +66,11 @@ impl VersionCommand {
                 std::process::exit(-1)
             }
             Some(version) => {
-                match cli_opts.ouput {
-                    Some(str) => match str.as_str() {
-                        "json" => {
-                            cli_stdout_printline!("{}", serde_json::to_string(&version).unwrap())
-                        }
-                        "json_pretty" => cli_stdout_printline!(
-                            "{}",
-                            serde_json::to_string_pretty(&version).unwrap()
-                        ),
-                        "yaml" => {
-                            cli_stdout_printline!("{}", serde_yaml::to_string(&version).unwrap())
-                        }
-                        "raw" => cli_stdout_printline!("{}", version.version),
-                        _ => Self::default_display(version),
-                    },
-                    _ => Self::default_display(version),
-                };
+                if cli_opts.ouput.as_deref() == Some("raw") {
+                    cli_stdout_printline!("{}", version.version)
+                } else if !RenderHelper::display(&cli_opts, &version, &[]) {
+                    Self::default_display(version)
+                }
             }
         };
     }
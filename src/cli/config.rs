@@ -388,6 +388,19 @@ impl OtoroshiCtlConfig {
         }
     }
 
+    /// The directory of the config file in use, where the local data of otoroshictl is kept
+    pub fn config_dir(config_file: Option<&str>) -> std::path::PathBuf {
+        let config_file = match config_file {
+            Some(file) if !file.starts_with("http://") && !file.starts_with("https://") => {
+                Some(std::path::PathBuf::from(file))
+            }
+            _ => confy::get_configuration_file_path("io.otoroshi.otoroshictl", Some("config")).ok(),
+        };
+        config_file
+            .and_then(|path| path.parent().map(|p| p.to_path_buf()))
+            .unwrap_or_else(std::env::temp_dir)
+    }
+
     pub fn write_current_config(cfg: OtoroshiCtlConfig) {
         confy::store("io.otoroshi.otoroshictl", Some("config"), cfg).unwrap();
     }
@@ -0,0 +1,229 @@
+use crate::cli::commands::entities::OtoroshExposedResources;
+use crate::cli::commands::resources::KubeEntity;
+use crate::utils::entity::{EntityDocument, EntityHelper};
+
+/// The shapes of the entity files read and written by `resources convert`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntityFormat {
+    /// a json entity, or a json array of entities, with their `kind`
+    Json,
+    /// yaml documents separated by `---`, with their `kind`
+    Yaml,
+    /// yaml documents wrapped in the kubernetes armor
+    Kube,
+    /// one json entity per line, as written by `export --nd-json`
+    NdJson,
+}
+
+impl EntityFormat {
+    pub fn parse(format: &str) -> Result<EntityFormat, String> {
+        match format {
+            "json" => Ok(EntityFormat::Json),
+            "yaml" => Ok(EntityFormat::Yaml),
+            "kube" => Ok(EntityFormat::Kube),
+            "ndjson" => Ok(EntityFormat::NdJson),
+            other => Err(format!(
+                "unknown format '{}' (can be one of: json, yaml, kube, ndjson)",
+                other
+            )),
+        }
+    }
+
+    /// The format of a file from its extension, yaml when it is not json nor ndjson
+    pub fn guess(source: &str) -> EntityFormat {
+        if source.ends_with(".ndjson") {
+            EntityFormat::NdJson
+        } else if source.ends_with(".json") {
+            EntityFormat::Json
+        } else {
+            EntityFormat::Yaml
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            EntityFormat::Json => "json",
+            EntityFormat::Yaml | EntityFormat::Kube => "yaml",
+            EntityFormat::NdJson => "ndjson",
+        }
+    }
+}
+
+/// Offline conversion of entity files between json, yaml, kube and ndjson
+pub struct ConvertHelper {}
+
+impl ConvertHelper {
+    /// Parse the content of a file. Json, yaml and kube files are all read as entity files, the
+    /// armor being detected on each document. An ndjson line is an entity with its kind, or a
+    /// `{"type": ..., "value": ...}` line of a full export
+    pub fn read(
+        content: &str,
+        format: EntityFormat,
+        source: &str,
+    ) -> Result<Vec<EntityDocument>, String> {
+        match format {
+            EntityFormat::Json => EntityHelper::parse_documents(content, true, source),
+            EntityFormat::Yaml | EntityFormat::Kube => {
+                EntityHelper::parse_documents(content, false, source)
+            }
+            EntityFormat::NdJson => {
+                let mut documents = Vec::new();
+                for (idx, line) in content.lines().enumerate() {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let mut value =
+                        serde_json::from_str::<serde_json::Value>(line).map_err(|e| {
+                            format!("error while parsing {}:{}: {}", source, idx + 1, e)
+                        })?;
+                    if let (Some(kind), Some(entity)) = (
+                        value.get("type").and_then(|t| t.as_str()).map(String::from),
+                        value.get("value").filter(|v| v.is_object()),
+                    ) {
+                        value = EntityHelper::with_kind(entity, kind);
+                    }
+                    let line = serde_json::to_string(&value).unwrap();
+                    for mut document in EntityHelper::parse_documents(&line, true, source)? {
+                        document.line = idx + 1;
+                        documents.push(document);
+                    }
+                }
+                Ok(documents)
+            }
+        }
+    }
+
+    /// The kind of a document as `group/Kind` and the plural name of the kind, from the cached
+    /// exposed resources. A kind missing from the cache is kept as written
+    pub fn kind(kind: &str, resources: Option<&OtoroshExposedResources>) -> (String, String) {
+        match resources.and_then(|r| r.find_by_kind(kind)) {
+            Some(resource) => (
+                format!("{}/{}", resource.group, resource.kind),
+                resource.plural_name,
+            ),
+            None => {
+                let short = kind.rsplit('/').next().unwrap_or(kind).to_lowercase();
+                let plural = if short.ends_with('s') {
+                    short
+                } else {
+                    format!("{}s", short)
+                };
+                (kind.to_string(), plural)
+            }
+        }
+    }
+
+    /// The entity of a document in the shape of the format
+    pub fn entity(
+        document: &EntityDocument,
+        format: EntityFormat,
+        resources: Option<&OtoroshExposedResources>,
+    ) -> Result<serde_json::Value, String> {
+        let Some(kind) = &document.kind else {
+            return Err(format!(
+                "{}:{}: missing kind",
+                document.source, document.line
+            ));
+        };
+        let (kind, _) = Self::kind(kind, resources);
+        let body = EntityHelper::without_kind(&document.body);
+        Ok(match format {
+            EntityFormat::Kube => serde_json::to_value(KubeEntity::new(
+                kind.rsplit('/').next().unwrap_or_default().to_string(),
+                document.display_name(),
+                body,
+            ))
+            .unwrap(),
+            _ => EntityHelper::with_kind(&body, kind),
+        })
+    }
+
+    /// The content of a file holding the entities. A single json entity is written as an
+    /// object, several as an array
+    pub fn render(entities: &[serde_json::Value], format: EntityFormat) -> String {
+        match format {
+            EntityFormat::Json => match entities {
+                [entity] => serde_json::to_string_pretty(entity).unwrap(),
+                entities => serde_json::to_string_pretty(entities).unwrap(),
+            },
+            EntityFormat::Yaml | EntityFormat::Kube => entities
+                .iter()
+                .map(|entity| serde_yaml::to_string(entity).unwrap())
+                .collect::<Vec<String>>()
+                .join("---\n"),
+            EntityFormat::NdJson => entities
+                .iter()
+                .map(|entity| format!("{}\n", serde_json::to_string(entity).unwrap()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resources() -> OtoroshExposedResources {
+        serde_json::from_value(serde_json::json!({
+            "version": "v1",
+            "resources": [{
+                "kind": "Route",
+                "plural_name": "routes",
+                "singular_name": "route",
+                "group": "proxy.otoroshi.io",
+                "version": {"name": "v1", "served": true, "deprecated": false, "storage": true}
+            }]
+        }))
+        .unwrap()
+    }
+
+    fn convert(content: &str, from: EntityFormat, to: EntityFormat) -> String {
+        let resources = resources();
+        let entities: Vec<serde_json::Value> = ConvertHelper::read(content, from, "test")
+            .unwrap()
+            .iter()
+            .map(|doc| ConvertHelper::entity(doc, to, Some(&resources)).unwrap())
+            .collect();
+        ConvertHelper::render(&entities, to)
+    }
+
+    #[test]
+    fn test_convert_ndjson_to_kube_and_back() {
+        let ndjson = "{\"type\":\"route\",\"value\":{\"id\":\"route_1\",\"name\":\"api\"}}\n{\"kind\":\"Route\",\"id\":\"route_2\",\"name\":\"admin\"}\n";
+        let kube = convert(ndjson, EntityFormat::NdJson, EntityFormat::Kube);
+        assert_eq!(
+            kube,
+            "apiVersion: proxy.otoroshi.io/v1\nkind: Route\nmetadata:\n  name: api\nspec:\n  id: route_1\n  name: api\n---\napiVersion: proxy.otoroshi.io/v1\nkind: Route\nmetadata:\n  name: admin\nspec:\n  id: route_2\n  name: admin\n"
+        );
+        assert_eq!(
+            convert(&kube, EntityFormat::Kube, EntityFormat::NdJson),
+            "{\"id\":\"route_1\",\"kind\":\"proxy.otoroshi.io/Route\",\"name\":\"api\"}\n{\"id\":\"route_2\",\"kind\":\"proxy.otoroshi.io/Route\",\"name\":\"admin\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_convert_yaml_to_json() {
+        let yaml = "kind: route\nid: route_1\nname: api\n";
+        let json: serde_json::Value =
+            serde_json::from_str(&convert(yaml, EntityFormat::Yaml, EntityFormat::Json)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"kind": "proxy.otoroshi.io/Route", "id": "route_1", "name": "api"})
+        );
+    }
+
+    #[test]
+    fn test_kind_without_cache() {
+        assert_eq!(
+            ConvertHelper::kind("proxy.otoroshi.io/Backend", None),
+            (
+                "proxy.otoroshi.io/Backend".to_string(),
+                "backends".to_string()
+            )
+        );
+        assert_eq!(
+            ConvertHelper::kind("route", Some(&resources())),
+            ("proxy.otoroshi.io/Route".to_string(), "routes".to_string())
+        );
+    }
+}
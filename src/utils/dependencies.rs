@@ -0,0 +1,328 @@
+use std::collections::{BTreeSet, HashMap};
+
+/// A reference from one entity to another one, found in a well-known field
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityReference {
+    /// The kind of the referenced entity as exposed by otoroshi (`Backend`, `Certificate`, ...)
+    pub kind: String,
+    pub id: String,
+    /// Where the reference has been found, using the `--data PATH=VALUE` notation
+    pub path: String,
+}
+
+pub struct DependencyHelper {}
+
+impl DependencyHelper {
+    fn reference(kind: &str, id: &str, path: String) -> EntityReference {
+        EntityReference {
+            kind: kind.to_string(),
+            id: id.to_string(),
+            path,
+        }
+    }
+
+    fn string_refs(
+        value: Option<&serde_json::Value>,
+        kind: &str,
+        path: &str,
+    ) -> Vec<EntityReference> {
+        match value {
+            Some(serde_json::Value::String(id)) if !id.is_empty() => {
+                vec![Self::reference(kind, id, path.to_string())]
+            }
+            Some(serde_json::Value::Array(ids)) => ids
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, id)| {
+                    id.as_str()
+                        .filter(|id| !id.is_empty())
+                        .map(|id| Self::reference(kind, id, format!("{}.{}", path, idx)))
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Certificates used by any `tls_config` object of the entity
+    fn certificate_refs(path: &str, value: &serde_json::Value, refs: &mut Vec<EntityReference>) {
+        let child_path = |key: &str| {
+            if path.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", path, key)
+            }
+        };
+        match value {
+            serde_json::Value::Object(obj) => {
+                for (key, value) in obj.iter() {
+                    if key == "tls_config" {
+                        for field in ["certs", "trusted_certs"] {
+                            refs.extend(Self::string_refs(
+                                value.get(field),
+                                "Certificate",
+                                &format!("{}.{}", child_path(key), field),
+                            ));
+                        }
+                    } else {
+                        Self::certificate_refs(&child_path(key), value, refs);
+                    }
+                }
+            }
+            serde_json::Value::Array(arr) => {
+                for (idx, value) in arr.iter().enumerate() {
+                    Self::certificate_refs(&child_path(&idx.to_string()), value, refs);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// The references of an entity to other entities, read from the well-known fields of its kind
+    pub fn references(kind: &str, entity: &serde_json::Value) -> Vec<EntityReference> {
+        let mut refs: Vec<EntityReference> = Vec::new();
+        match kind.to_lowercase().as_str() {
+            "route" => {
+                refs.extend(Self::string_refs(
+                    entity.get("backend_ref"),
+                    "Backend",
+                    "backend_ref",
+                ));
+                refs.extend(Self::string_refs(
+                    entity.get("groups"),
+                    "ServiceGroup",
+                    "groups",
+                ));
+                let plugins = entity
+                    .get("plugins")
+                    .and_then(|p| p.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+                for (idx, plugin) in plugins.iter().enumerate() {
+                    let config = plugin.get("config");
+                    let path = format!("plugins.{}.config", idx);
+                    for field in ["auth_module", "module"] {
+                        refs.extend(Self::string_refs(
+                            config.and_then(|c| c.get(field)),
+                            "AuthModule",
+                            &format!("{}.{}", path, field),
+                        ));
+                    }
+                    refs.extend(Self::string_refs(
+                        config.and_then(|c| c.get("verifiers")),
+                        "JwtVerifier",
+                        &format!("{}.verifiers", path),
+                    ));
+                }
+            }
+            "servicedescriptor" => {
+                refs.extend(Self::string_refs(
+                    entity.get("groups"),
+                    "ServiceGroup",
+                    "groups",
+                ));
+                refs.extend(Self::string_refs(
+                    entity.get("authConfigRef"),
+                    "AuthModule",
+                    "authConfigRef",
+                ));
+            }
+            "apikey" => {
+                let authorized = entity
+                    .get("authorizedEntities")
+                    .and_then(|a| a.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+                for (idx, value) in authorized.iter().enumerate() {
+                    let path = format!("authorizedEntities.{}", idx);
+                    let value = value.as_str().unwrap_or_default();
+                    let found = [
+                        ("group_", "ServiceGroup"),
+                        ("service_", "ServiceDescriptor"),
+                        ("routecomp_", "RouteComposition"),
+                        ("route_", "Route"),
+                    ]
+                    .into_iter()
+                    .find_map(|(prefix, kind)| value.strip_prefix(prefix).map(|id| (kind, id)));
+                    if let Some((kind, id)) = found {
+                        refs.push(Self::reference(kind, id, path));
+                    }
+                }
+            }
+            _ => (),
+        }
+        Self::certificate_refs("", entity, &mut refs);
+        refs
+    }
+
+    /// Order the nodes so each one comes after the nodes it depends on, keeping the original
+    /// order otherwise. Dependencies on keys that are not part of the nodes are ignored.
+    /// Returns the ordered indexes and, at the end in their original order, the nodes of a cycle
+    pub fn order(keys: &[String], dependencies: &[Vec<String>]) -> (Vec<usize>, Vec<usize>) {
+        let mut by_key: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (idx, key) in keys.iter().enumerate() {
+            by_key.entry(key.as_str()).or_default().push(idx);
+        }
+        let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); keys.len()];
+        let mut pending: Vec<usize> = vec![0; keys.len()];
+        for (idx, deps) in dependencies.iter().enumerate() {
+            for dep in deps.iter() {
+                for dep_idx in by_key.get(dep.as_str()).cloned().unwrap_or_default() {
+                    if dep_idx != idx {
+                        dependents[dep_idx].push(idx);
+                        pending[idx] += 1;
+                    }
+                }
+            }
+        }
+        let mut ready: BTreeSet<usize> = (0..keys.len()).filter(|i| pending[*i] == 0).collect();
+        let mut ordered: Vec<usize> = Vec::new();
+        while let Some(idx) = ready.pop_first() {
+            ordered.push(idx);
+            for dependent in dependents[idx].iter() {
+                pending[*dependent] -= 1;
+                if pending[*dependent] == 0 {
+                    ready.insert(*dependent);
+                }
+            }
+        }
+        let cyclic: Vec<usize> = (0..keys.len()).filter(|i| pending[*i] > 0).collect();
+        (ordered, cyclic)
+    }
+
+    /// Group the ordered nodes into batches whose nodes do not depend on each other, so a batch
+    /// can be processed concurrently once the previous ones are done. Each node of a cycle gets
+    /// its own batch, at the end, in its original order
+    pub fn batches(keys: &[String], dependencies: &[Vec<String>]) -> Vec<Vec<usize>> {
+        let (ordered, cyclic) = Self::order(keys, dependencies);
+        let mut level_by_key: HashMap<&str, usize> = HashMap::new();
+        let mut batches: Vec<Vec<usize>> = Vec::new();
+        for idx in ordered.into_iter() {
+            let level = dependencies[idx]
+                .iter()
+                .filter(|dep| **dep != keys[idx])
+                .filter_map(|dep| level_by_key.get(dep.as_str()))
+                .map(|level| level + 1)
+                .max()
+                .unwrap_or(0);
+            let entry = level_by_key.entry(keys[idx].as_str()).or_insert(level);
+            *entry = (*entry).max(level);
+            if batches.len() <= level {
+                batches.resize(level + 1, Vec::new());
+            }
+            batches[level].push(idx);
+        }
+        batches.extend(cyclic.into_iter().map(|idx| vec![idx]));
+        batches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(refs: &[EntityReference]) -> Vec<String> {
+        refs.iter()
+            .map(|r| format!("{}:{}", r.kind, r.id))
+            .collect()
+    }
+
+    #[test]
+    fn test_route_references() {
+        let route = serde_json::json!({
+            "id": "route_1",
+            "backend_ref": "backend_1",
+            "backend": {"targets": [{"tls_config": {"certs": ["cert_1"], "trusted_certs": []}}]},
+            "plugins": [
+                {"plugin": "cp:otoroshi.next.plugins.AuthModule", "config": {"module": "auth_1"}},
+                {"plugin": "cp:otoroshi.next.plugins.JwtVerification", "config": {"verifiers": ["verifier_1"]}}
+            ]
+        });
+        let refs = DependencyHelper::references("Route", &route);
+        assert_eq!(
+            keys(&refs),
+            vec![
+                "Backend:backend_1",
+                "AuthModule:auth_1",
+                "JwtVerifier:verifier_1",
+                "Certificate:cert_1"
+            ]
+        );
+        assert_eq!(refs[3].path, "backend.targets.0.tls_config.certs.0");
+    }
+
+    #[test]
+    fn test_apikey_references() {
+        let apikey = serde_json::json!({
+            "clientId": "key_1",
+            "authorizedEntities": ["group_default", "route_route_1", "routecomp_comp_1", "service_svc_1"]
+        });
+        assert_eq!(
+            keys(&DependencyHelper::references("ApiKey", &apikey)),
+            vec![
+                "ServiceGroup:default",
+                "Route:route_1",
+                "RouteComposition:comp_1",
+                "ServiceDescriptor:svc_1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_order_dependencies_first() {
+        let keys = vec![
+            "Route:r1".to_string(),
+            "ApiKey:k1".to_string(),
+            "Backend:b1".to_string(),
+            "Certificate:c1".to_string(),
+        ];
+        let deps = vec![
+            vec!["Backend:b1".to_string()],
+            vec!["Route:r1".to_string()],
+            vec![
+                "Certificate:c1".to_string(),
+                "Certificate:unknown".to_string(),
+            ],
+            vec![],
+        ];
+        let (ordered, cyclic) = DependencyHelper::order(&keys, &deps);
+        assert_eq!(ordered, vec![3, 2, 0, 1]);
+        assert!(cyclic.is_empty());
+    }
+
+    #[test]
+    fn test_batches_of_independent_entities() {
+        let keys = vec![
+            "Route:r1".to_string(),
+            "Route:r2".to_string(),
+            "Backend:b1".to_string(),
+            "Certificate:c1".to_string(),
+            "A:1".to_string(),
+            "B:1".to_string(),
+        ];
+        let deps = vec![
+            vec!["Backend:b1".to_string()],
+            vec![],
+            vec!["Certificate:c1".to_string()],
+            vec![],
+            vec!["B:1".to_string()],
+            vec!["A:1".to_string()],
+        ];
+        assert_eq!(
+            DependencyHelper::batches(&keys, &deps),
+            vec![vec![1, 3], vec![2], vec![0], vec![4], vec![5]]
+        );
+    }
+
+    #[test]
+    fn test_order_reports_cycles() {
+        let keys = vec!["A:1".to_string(), "B:1".to_string(), "C:1".to_string()];
+        let deps = vec![
+            vec!["B:1".to_string()],
+            vec!["A:1".to_string()],
+            vec!["A:1".to_string(), "C:1".to_string()],
+        ];
+        let (ordered, cyclic) = DependencyHelper::order(&keys, &deps);
+        assert!(ordered.is_empty());
+        assert_eq!(cyclic, vec![0, 1, 2]);
+    }
+}
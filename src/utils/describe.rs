@@ -0,0 +1,410 @@
+use serde::{Deserialize, Serialize};
+
+use crate::cli_stdout_printline;
+use crate::utils::dependencies::EntityReference;
+use crate::utils::entity::EntityHelper;
+
+/// Certificates expiring in less than this number of days are reported as `expiring`
+const EXPIRING_DAYS: i64 = 30;
+
+/// An entity related to the described one, resolved to its name and status
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RelatedEntity {
+    /// where the relation comes from, like `backend_ref`, `frontend.domains` or `group xxx`
+    pub relation: String,
+    pub kind: String,
+    pub id: String,
+    pub name: Option<String>,
+    /// enabled, disabled or missing. valid, expiring or expired for certificates
+    pub status: String,
+    /// the expiry date of a certificate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+}
+
+/// A plugin of the described entity, with its name from the plugins of the cluster
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DescribedPlugin {
+    pub plugin: String,
+    pub name: String,
+    pub enabled: bool,
+}
+
+/// The result of `resources describe`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Description {
+    pub kind: String,
+    pub id: String,
+    pub name: Option<String>,
+    pub enabled: Option<bool>,
+    pub references: Vec<RelatedEntity>,
+    pub plugins: Vec<DescribedPlugin>,
+    pub apikeys: Vec<RelatedEntity>,
+    pub certificates: Vec<RelatedEntity>,
+    pub entity: serde_json::Value,
+}
+
+pub struct DescribeHelper {}
+
+impl DescribeHelper {
+    fn status(entity: &serde_json::Value) -> String {
+        match entity.get("enabled").and_then(|e| e.as_bool()) {
+            Some(false) => "disabled".to_string(),
+            _ => "enabled".to_string(),
+        }
+    }
+
+    /// A referenced entity, `missing` when it can't be found in the cluster
+    pub fn related(
+        reference: &EntityReference,
+        entity: Option<&serde_json::Value>,
+    ) -> RelatedEntity {
+        RelatedEntity {
+            relation: reference.path.clone(),
+            kind: reference.kind.clone(),
+            id: reference.id.clone(),
+            name: entity.and_then(EntityHelper::extract_json_entity_name),
+            status: entity
+                .map(Self::status)
+                .unwrap_or_else(|| "missing".to_string()),
+            expires: None,
+        }
+    }
+
+    /// The plugins of a route, named after the plugins of the cluster or the last segment of
+    /// their id when they are not known
+    pub fn plugins(
+        entity: &serde_json::Value,
+        catalog: &serde_json::Value,
+    ) -> Vec<DescribedPlugin> {
+        let known = catalog.as_array().cloned().unwrap_or_default();
+        entity
+            .get("plugins")
+            .and_then(|p| p.as_array())
+            .map(|plugins| {
+                plugins
+                    .iter()
+                    .filter_map(|plugin| {
+                        let id = plugin.get("plugin").and_then(|p| p.as_str())?;
+                        let name = known
+                            .iter()
+                            .find(|p| p.get("id").and_then(|i| i.as_str()) == Some(id))
+                            .and_then(|p| p.get("name").and_then(|n| n.as_str()))
+                            .unwrap_or_else(|| id.rsplit('.').next().unwrap_or(id));
+                        Some(DescribedPlugin {
+                            plugin: id.to_string(),
+                            name: name.to_string(),
+                            enabled: plugin
+                                .get("enabled")
+                                .and_then(|e| e.as_bool())
+                                .unwrap_or(true),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The apikeys authorized on the entity, directly or through one of its groups. `references`
+    /// are the references of each apikey, as found by `DependencyHelper::references`
+    pub fn authorized_apikeys(
+        kind: &str,
+        id: &str,
+        groups: &[String],
+        apikeys: &[(serde_json::Value, Vec<EntityReference>)],
+    ) -> Vec<RelatedEntity> {
+        apikeys
+            .iter()
+            .filter_map(|(apikey, references)| {
+                let relation = references.iter().find_map(|r| {
+                    if r.kind == kind && r.id == id {
+                        Some("direct".to_string())
+                    } else if r.kind == "ServiceGroup" && groups.contains(&r.id) {
+                        Some(format!("group {}", r.id))
+                    } else {
+                        None
+                    }
+                })?;
+                Some(RelatedEntity {
+                    relation,
+                    kind: "ApiKey".to_string(),
+                    id: EntityHelper::extract_json_entity_id(apikey).unwrap_or_default(),
+                    name: EntityHelper::extract_json_entity_name(apikey),
+                    status: Self::status(apikey),
+                    expires: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Whether a certificate domain, possibly a wildcard like `*.oto.tools`, covers the domain of
+    /// a route. The path of the route domain is ignored
+    pub fn domain_matches(pattern: &str, domain: &str) -> bool {
+        let domain = domain.split('/').next().unwrap_or(domain).to_lowercase();
+        let pattern = pattern.to_lowercase();
+        match pattern.strip_prefix("*.") {
+            Some(suffix) => domain
+                .split_once('.')
+                .is_some_and(|(head, rest)| !head.is_empty() && rest == suffix),
+            None => pattern == domain,
+        }
+    }
+
+    /// A certificate with its expiry date and status, `now` being in milliseconds since epoch
+    pub fn certificate(relation: &str, certificate: &serde_json::Value, now: i64) -> RelatedEntity {
+        let to = certificate.get("to").and_then(|t| t.as_i64());
+        let status = match to {
+            Some(to) if to <= now => "expired",
+            Some(to) if to - now < EXPIRING_DAYS * 24 * 3600 * 1000 => "expiring",
+            Some(_) => "valid",
+            None => "unknown",
+        };
+        RelatedEntity {
+            relation: relation.to_string(),
+            kind: "Certificate".to_string(),
+            id: EntityHelper::extract_json_entity_id(certificate).unwrap_or_default(),
+            name: EntityHelper::extract_json_entity_name(certificate),
+            status: status.to_string(),
+            expires: to
+                .and_then(chrono::DateTime::from_timestamp_millis)
+                .map(|date| date.format("%Y-%m-%d").to_string()),
+        }
+    }
+
+    /// The certificates whose domain or sans cover one of the domains
+    pub fn certificates_for_domains(
+        domains: &[String],
+        certificates: &[serde_json::Value],
+        now: i64,
+    ) -> Vec<RelatedEntity> {
+        certificates
+            .iter()
+            .filter(|certificate| {
+                let mut names: Vec<&str> = certificate
+                    .get("sans")
+                    .and_then(|s| s.as_array())
+                    .map(|sans| sans.iter().filter_map(|s| s.as_str()).collect())
+                    .unwrap_or_default();
+                if let Some(domain) = certificate.get("domain").and_then(|d| d.as_str()) {
+                    names.push(domain);
+                }
+                names.iter().any(|name| {
+                    domains
+                        .iter()
+                        .any(|domain| Self::domain_matches(name, domain))
+                })
+            })
+            .map(|certificate| Self::certificate("frontend.domains", certificate, now))
+            .collect()
+    }
+
+    /// Print the rows with aligned columns, or `<none>`
+    fn section(title: &str, columns: &[&str], rows: Vec<Vec<String>>) {
+        cli_stdout_printline!();
+        cli_stdout_printline!("{}:", title);
+        if rows.is_empty() {
+            cli_stdout_printline!("  <none>");
+            return;
+        }
+        let mut widths: Vec<usize> = columns.iter().map(|c| c.len()).collect();
+        for row in rows.iter() {
+            for (idx, cell) in row.iter().enumerate() {
+                widths[idx] = widths[idx].max(cell.chars().count());
+            }
+        }
+        let line = |cells: Vec<String>| {
+            let cells: Vec<String> = cells
+                .iter()
+                .enumerate()
+                .map(|(idx, cell)| format!("{:<width$}", cell, width = widths[idx]))
+                .collect();
+            format!("  {}", cells.join("   ").trim_end())
+        };
+        cli_stdout_printline!(
+            "{}",
+            line(columns.iter().map(|c| c.to_uppercase()).collect())
+        );
+        for row in rows.into_iter() {
+            cli_stdout_printline!("{}", line(row));
+        }
+    }
+
+    fn related_rows(related: &[RelatedEntity]) -> Vec<Vec<String>> {
+        related
+            .iter()
+            .map(|r| {
+                vec![
+                    r.relation.clone(),
+                    r.kind.clone(),
+                    r.id.clone(),
+                    r.name.clone().unwrap_or_default(),
+                    r.status.clone(),
+                ]
+            })
+            .collect()
+    }
+
+    /// The sectioned human view of `resources describe`, like `kubectl describe`
+    pub fn display(description: &Description) {
+        let yes_no = |enabled: Option<bool>| match enabled {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "",
+        };
+        cli_stdout_printline!("Kind:     {}", description.kind);
+        cli_stdout_printline!("Id:       {}", description.id);
+        cli_stdout_printline!("Name:     {}", description.name.clone().unwrap_or_default());
+        cli_stdout_printline!("Enabled:  {}", yes_no(description.enabled));
+        Self::section(
+            "References",
+            &["relation", "kind", "id", "name", "status"],
+            Self::related_rows(&description.references),
+        );
+        Self::section(
+            "Plugins",
+            &["name", "plugin", "enabled"],
+            description
+                .plugins
+                .iter()
+                .map(|p| {
+                    vec![
+                        p.name.clone(),
+                        p.plugin.clone(),
+                        yes_no(Some(p.enabled)).to_string(),
+                    ]
+                })
+                .collect(),
+        );
+        Self::section(
+            "Authorized apikeys",
+            &["relation", "kind", "id", "name", "status"],
+            Self::related_rows(&description.apikeys),
+        );
+        Self::section(
+            "Certificates",
+            &["relation", "id", "name", "expires", "status"],
+            description
+                .certificates
+                .iter()
+                .map(|c| {
+                    vec![
+                        c.relation.clone(),
+                        c.id.clone(),
+                        c.name.clone().unwrap_or_default(),
+                        c.expires.clone().unwrap_or_default(),
+                        c.status.clone(),
+                    ]
+                })
+                .collect(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::dependencies::DependencyHelper;
+
+    const DAY: i64 = 24 * 3600 * 1000;
+
+    #[test]
+    fn test_authorized_apikeys() {
+        let apikeys: Vec<(serde_json::Value, Vec<EntityReference>)> = [
+            serde_json::json!({"clientId": "key_1", "clientName": "direct", "enabled": true, "authorizedEntities": ["route_route_1"]}),
+            serde_json::json!({"clientId": "key_2", "clientName": "by group", "enabled": false, "authorizedEntities": ["group_default"]}),
+            serde_json::json!({"clientId": "key_3", "clientName": "other", "authorizedEntities": ["route_route_2"]}),
+        ]
+        .into_iter()
+        .map(|apikey| {
+            let refs = DependencyHelper::references("apikey", &apikey);
+            (apikey, refs)
+        })
+        .collect();
+        let found = DescribeHelper::authorized_apikeys(
+            "Route",
+            "route_1",
+            &["default".to_string()],
+            &apikeys,
+        );
+        assert_eq!(
+            found
+                .iter()
+                .map(|r| (r.id.as_str(), r.relation.as_str(), r.status.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("key_1", "direct", "enabled"),
+                ("key_2", "group default", "disabled")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plugins_and_references() {
+        let route = serde_json::json!({
+            "plugins": [
+                {"plugin": "cp:otoroshi.next.plugins.ApikeyCalls", "enabled": true},
+                {"plugin": "cp:otoroshi.next.plugins.Custom", "enabled": false}
+            ]
+        });
+        let catalog =
+            serde_json::json!([{"id": "cp:otoroshi.next.plugins.ApikeyCalls", "name": "Apikeys"}]);
+        let plugins = DescribeHelper::plugins(&route, &catalog);
+        assert_eq!(plugins[0].name, "Apikeys");
+        assert_eq!(plugins[1].name, "Custom");
+        assert!(!plugins[1].enabled);
+
+        let reference = EntityReference {
+            kind: "Backend".to_string(),
+            id: "backend_1".to_string(),
+            path: "backend_ref".to_string(),
+        };
+        assert_eq!(DescribeHelper::related(&reference, None).status, "missing");
+        let backend =
+            serde_json::json!({"id": "backend_1", "name": "my backend", "enabled": false});
+        let related = DescribeHelper::related(&reference, Some(&backend));
+        assert_eq!(related.name.as_deref(), Some("my backend"));
+        assert_eq!(related.status, "disabled");
+    }
+
+    #[test]
+    fn test_certificates() {
+        assert!(DescribeHelper::domain_matches(
+            "*.oto.tools",
+            "api.oto.tools/v1"
+        ));
+        assert!(!DescribeHelper::domain_matches("*.oto.tools", "oto.tools"));
+        assert!(!DescribeHelper::domain_matches(
+            "*.oto.tools",
+            "a.b.oto.tools"
+        ));
+        assert!(DescribeHelper::domain_matches(
+            "API.oto.tools",
+            "api.oto.tools"
+        ));
+
+        let now = 1_700_000_000_000;
+        let certificates = vec![
+            serde_json::json!({"id": "cert_1", "name": "wildcard", "domain": "*.oto.tools", "to": now + 90 * DAY}),
+            serde_json::json!({"id": "cert_2", "name": "sans", "domain": "other.io", "sans": ["api.oto.tools"], "to": now + 2 * DAY}),
+            serde_json::json!({"id": "cert_3", "name": "old", "domain": "api.oto.tools", "to": now - DAY}),
+            serde_json::json!({"id": "cert_4", "name": "unrelated", "domain": "foo.io", "to": now}),
+        ];
+        let found = DescribeHelper::certificates_for_domains(
+            &["api.oto.tools".to_string()],
+            &certificates,
+            now,
+        );
+        assert_eq!(
+            found
+                .iter()
+                .map(|c| (c.id.as_str(), c.status.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("cert_1", "valid"),
+                ("cert_2", "expiring"),
+                ("cert_3", "expired")
+            ]
+        );
+        assert_eq!(found[0].expires.as_deref(), Some("2024-02-12"));
+    }
+}
@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+
+/// One field level difference between two versions of an entity.
+/// Paths use the same dotted notation as the `--data PATH=VALUE` inputs (`plugins.0.config`)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FieldChange {
+    Added {
+        path: String,
+        value: serde_json::Value,
+    },
+    Removed {
+        path: String,
+        value: serde_json::Value,
+    },
+    Changed {
+        path: String,
+        from: serde_json::Value,
+        to: serde_json::Value,
+    },
+}
+
+pub struct DiffHelper {}
+
+impl DiffHelper {
+    fn child_path(path: &str, key: &str) -> String {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    }
+
+    fn walk(
+        path: &str,
+        from: &serde_json::Value,
+        to: &serde_json::Value,
+        declared_only: bool,
+        changes: &mut Vec<FieldChange>,
+    ) {
+        match (from, to) {
+            (serde_json::Value::Object(from_obj), serde_json::Value::Object(to_obj)) => {
+                if !declared_only {
+                    for (key, value) in from_obj.iter() {
+                        if !to_obj.contains_key(key) {
+                            changes.push(FieldChange::Removed {
+                                path: Self::child_path(path, key),
+                                value: value.clone(),
+                            });
+                        }
+                    }
+                }
+                for (key, value) in to_obj.iter() {
+                    match from_obj.get(key) {
+                        None => changes.push(FieldChange::Added {
+                            path: Self::child_path(path, key),
+                            value: value.clone(),
+                        }),
+                        Some(old) => Self::walk(
+                            &Self::child_path(path, key),
+                            old,
+                            value,
+                            declared_only,
+                            changes,
+                        ),
+                    }
+                }
+            }
+            (serde_json::Value::Array(from_arr), serde_json::Value::Array(to_arr)) => {
+                // arrays are always declared as a whole, extra items are removals
+                for (idx, value) in to_arr.iter().enumerate() {
+                    let item_path = Self::child_path(path, &idx.to_string());
+                    match from_arr.get(idx) {
+                        None => changes.push(FieldChange::Added {
+                            path: item_path,
+                            value: value.clone(),
+                        }),
+                        Some(old) => Self::walk(&item_path, old, value, declared_only, changes),
+                    }
+                }
+                for (idx, value) in from_arr.iter().enumerate().skip(to_arr.len()) {
+                    changes.push(FieldChange::Removed {
+                        path: Self::child_path(path, &idx.to_string()),
+                        value: value.clone(),
+                    });
+                }
+            }
+            (old, new) if old != new => changes.push(FieldChange::Changed {
+                path: path.to_string(),
+                from: old.clone(),
+                to: new.clone(),
+            }),
+            _ => (),
+        }
+    }
+
+    /// Compute the differences for the fields declared in `to` only. Object fields that only exist
+    /// in `from` are ignored as otoroshi fills every missing field with its default value.
+    pub fn diff_declared(from: &serde_json::Value, to: &serde_json::Value) -> Vec<FieldChange> {
+        let mut changes = Vec::new();
+        Self::walk("", from, to, true, &mut changes);
+        changes
+    }
+
+    /// Compute every difference between two versions of an entity, removed fields included
+    pub fn diff(from: &serde_json::Value, to: &serde_json::Value) -> Vec<FieldChange> {
+        let mut changes = Vec::new();
+        Self::walk("", from, to, false, &mut changes);
+        changes
+    }
+
+    fn value_as_string(value: &serde_json::Value) -> String {
+        serde_json::to_string(value).unwrap_or_default()
+    }
+
+    /// Print a coloured list of field changes
+    pub fn print_changes(changes: &[FieldChange], indent: &str) {
+        let mut logger = paris::Logger::new();
+        for change in changes.iter() {
+            match change {
+                FieldChange::Added { path, value } => logger.log(format!(
+                    "{}<green>+ {}: {}</>",
+                    indent,
+                    path,
+                    Self::value_as_string(value)
+                )),
+                FieldChange::Removed { path, value } => logger.log(format!(
+                    "{}<red>- {}: {}</>",
+                    indent,
+                    path,
+                    Self::value_as_string(value)
+                )),
+                FieldChange::Changed { path, from, to } => logger.log(format!(
+                    "{}<yellow>~ {}: {} -> {}</>",
+                    indent,
+                    path,
+                    Self::value_as_string(from),
+                    Self::value_as_string(to)
+                )),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_declared_identical_values() {
+        let value = serde_json::json!({"id": "route_1", "frontend": {"domains": ["a.oto.tools"]}});
+        assert!(DiffHelper::diff_declared(&value, &value).is_empty());
+    }
+
+    #[test]
+    fn test_diff_declared_nested_changes() {
+        let from = serde_json::json!({"name": "a", "enabled": true, "frontend": {"domains": ["a.oto.tools"]}});
+        let to = serde_json::json!({"name": "b", "frontend": {"domains": ["a.oto.tools", "b.oto.tools"]}, "tags": []});
+        let changes = DiffHelper::diff_declared(&from, &to);
+        assert_eq!(
+            changes,
+            vec![
+                FieldChange::Added {
+                    path: "frontend.domains.1".to_string(),
+                    value: serde_json::json!("b.oto.tools")
+                },
+                FieldChange::Changed {
+                    path: "name".to_string(),
+                    from: serde_json::json!("a"),
+                    to: serde_json::json!("b")
+                },
+                FieldChange::Added {
+                    path: "tags".to_string(),
+                    value: serde_json::json!([])
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_declared_ignores_server_defaults() {
+        let remote = serde_json::json!({"id": "r", "name": "a", "capture": false, "backend": {"root": "/", "rewrite": false}});
+        let local = serde_json::json!({"id": "r", "name": "a", "backend": {"root": "/api"}});
+        let changes = DiffHelper::diff_declared(&remote, &local);
+        assert_eq!(
+            changes,
+            vec![FieldChange::Changed {
+                path: "backend.root".to_string(),
+                from: serde_json::json!("/"),
+                to: serde_json::json!("/api")
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diff_reports_removed_fields() {
+        let from = serde_json::json!({"id": "r", "name": "a", "capture": false});
+        let to = serde_json::json!({"id": "r", "name": "b"});
+        assert_eq!(
+            DiffHelper::diff(&from, &to),
+            vec![
+                FieldChange::Removed {
+                    path: "capture".to_string(),
+                    value: serde_json::json!(false)
+                },
+                FieldChange::Changed {
+                    path: "name".to_string(),
+                    from: serde_json::json!("a"),
+                    to: serde_json::json!("b")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_declared_reports_removed_array_items() {
+        let remote = serde_json::json!({"plugins": [{"plugin": "a"}, {"plugin": "b"}]});
+        let local = serde_json::json!({"plugins": [{"plugin": "a"}]});
+        let changes = DiffHelper::diff_declared(&remote, &local);
+        assert_eq!(
+            changes,
+            vec![FieldChange::Removed {
+                path: "plugins.1".to_string(),
+                value: serde_json::json!({"plugin": "b"})
+            }]
+        );
+    }
+}
@@ -1,3 +1,9 @@
+use crate::utils::template::TemplateHelper;
+use json_value_merge::Merge;
+
+/// Metadata key marking the entities synced by otoroshictl, used to prune them
+pub const MANAGED_BY_KEY: &str = "otoroshictl/managed-by";
+
 pub struct EntityHelper {}
 
 impl EntityHelper {
@@ -13,16 +19,23 @@ impl EntityHelper {
             .and_then(|id| id.as_str().map(|v| v.to_string()))
     }
 
-    pub fn extract_yaml_entity_id(entity: &serde_yaml::Value) -> Option<String> {
-        entity
-            .get("id")
-            .or_else(|| entity.get("client_id"))
-            .or_else(|| entity.get("clientId"))
-            .or_else(|| entity.get("username"))
-            .or_else(|| entity.get("otoroshiId"))
-            .or_else(|| entity.get("serviceId"))
-            .or_else(|| entity.get("randomId"))
-            .and_then(|id| id.as_str().map(|v| v.to_string()))
+    /// Replace the id of an entity, in the field holding it, `id` when it has none
+    pub fn set_json_entity_id(entity: &mut serde_json::Value, id: &str) {
+        let field = [
+            "id",
+            "client_id",
+            "clientId",
+            "username",
+            "otoroshiId",
+            "serviceId",
+            "randomId",
+        ]
+        .into_iter()
+        .find(|field| entity.get(*field).is_some_and(|v| v.is_string()))
+        .unwrap_or("id");
+        if let Some(obj) = entity.as_object_mut() {
+            obj.insert(field.to_string(), serde_json::Value::String(id.to_string()));
+        }
     }
 
     pub fn extract_json_entity_name(entity: &serde_json::Value) -> Option<String> {
@@ -35,15 +48,312 @@ impl EntityHelper {
             .or_else(|| entity.get("username"))
             .and_then(|id| id.as_str().map(|v| v.to_string()))
     }
+}
+
+/// An entity document read from a local file or an url, before being pushed to the cluster
+#[derive(Clone, Debug)]
+pub struct EntityDocument {
+    /// the kind as written in the document (`Route`, `proxy.otoroshi.io/Route`, ...)
+    pub kind: Option<String>,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    /// the entity itself, without the kube armor
+    pub body: serde_json::Value,
+    /// where the document comes from
+    pub source: String,
+    /// the line of the source where the document starts, from 1
+    pub line: usize,
+    /// whether the entity is wrapped in the kube armor, under `spec`
+    pub kube: bool,
+}
+
+impl EntityDocument {
+    fn from_json(json: serde_json::Value, source: &str, line: usize) -> EntityDocument {
+        let is_kube = json.get("spec").is_some()
+            && json.get("kind").is_some()
+            && (json.get("apiVersion").is_some() || json.get("metadata").is_some());
+        let kind = json
+            .get("kind")
+            .and_then(|k| k.as_str())
+            .map(|k| k.to_string());
+        if is_kube {
+            let spec = json.get("spec").unwrap().clone();
+            let name = json
+                .get("metadata")
+                .and_then(|m| m.get("name"))
+                .and_then(|n| n.as_str())
+                .map(|n| n.to_string())
+                .or_else(|| EntityHelper::extract_json_entity_name(&spec));
+            EntityDocument {
+                kind,
+                id: EntityHelper::extract_json_entity_id(&spec),
+                name,
+                body: spec,
+                source: source.to_string(),
+                line,
+                kube: true,
+            }
+        } else {
+            EntityDocument {
+                kind,
+                id: EntityHelper::extract_json_entity_id(&json),
+                name: EntityHelper::extract_json_entity_name(&json),
+                body: json,
+                source: source.to_string(),
+                line,
+                kube: false,
+            }
+        }
+    }
+
+    /// A displayable name for the document, falling back on the id
+    pub fn display_name(&self) -> String {
+        self.name
+            .clone()
+            .or_else(|| self.id.clone())
+            .unwrap_or("<unnamed>".to_string())
+    }
+}
+
+impl EntityHelper {
+    /// Parse the content of an entity file. JSON content can be a single entity or an array of
+    /// entities, YAML content can contain several documents separated by `---` and each document
+    /// can be a plain entity or an entity with kube armor.
+    pub fn parse_documents(
+        content: &str,
+        is_json: bool,
+        source: &str,
+    ) -> Result<Vec<EntityDocument>, String> {
+        let values: Vec<(serde_json::Value, usize)> = if is_json {
+            match serde_json::from_str::<serde_json::Value>(content) {
+                Ok(serde_json::Value::Array(arr)) => {
+                    let lines = Self::json_item_lines(content);
+                    arr.into_iter()
+                        .enumerate()
+                        .map(|(idx, json)| (json, lines.get(idx).copied().unwrap_or(1)))
+                        .collect()
+                }
+                Ok(json) => vec![(json, Self::first_line(content))],
+                Err(e) => return Err(format!("error while parsing {}: {}", source, e)),
+            }
+        } else {
+            let mut values = Vec::new();
+            let mut line = 1;
+            for doc in content.split("---\n") {
+                let start = line + Self::first_line(doc) - 1;
+                line += doc.matches('\n').count() + 1;
+                if doc.trim().is_empty() {
+                    continue;
+                }
+                match serde_yaml::from_str::<serde_json::Value>(doc) {
+                    Ok(serde_json::Value::Array(arr)) => {
+                        values.extend(arr.into_iter().map(|json| (json, start)))
+                    }
+                    Ok(serde_json::Value::Null) => (),
+                    Ok(json) => values.push((json, start)),
+                    Err(e) => return Err(format!("error while parsing {}: {}", source, e)),
+                }
+            }
+            values
+        };
+        Ok(values
+            .into_iter()
+            .map(|(json, line)| EntityDocument::from_json(json, source, line))
+            .collect())
+    }
 
-    pub fn extract_yaml_entity_name(entity: &serde_yaml::Value) -> Option<String> {
+    /// The first line that is not blank nor a comment, from 1
+    fn first_line(content: &str) -> usize {
+        content
+            .lines()
+            .position(|l| !l.trim().is_empty() && !l.trim_start().starts_with('#'))
+            .unwrap_or(0)
+            + 1
+    }
+
+    /// The line where each item of a top level json array starts, from 1
+    fn json_item_lines(content: &str) -> Vec<usize> {
+        let mut lines: Vec<usize> = Vec::new();
+        let (mut line, mut depth) = (1, 0);
+        let (mut in_string, mut escaped, mut expecting) = (false, false, false);
+        for c in content.chars() {
+            if c == '\n' {
+                line += 1;
+            }
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    _ => (),
+                }
+                continue;
+            }
+            if depth == 1 && expecting && !c.is_whitespace() && c != ']' {
+                lines.push(line);
+                expecting = false;
+            }
+            match c {
+                '"' => in_string = true,
+                '[' | '{' => {
+                    depth += 1;
+                    expecting = depth == 1;
+                }
+                ']' | '}' => depth -= 1,
+                ',' if depth == 1 => expecting = true,
+                _ => (),
+            }
+        }
+        lines
+    }
+
+    /// The entity with a `kind` field, like in exports and `resources get`
+    pub fn with_kind(value: &serde_json::Value, kd: String) -> serde_json::Value {
+        let mut kind: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
+        kind.insert("kind".to_string(), serde_json::Value::String(kd));
+        let mut entity_with_kind = value.clone();
+        entity_with_kind.merge(&serde_json::Value::Object(kind));
+        entity_with_kind
+    }
+
+    /// The entity without the `kind` field added by exports and `resources get`
+    pub fn without_kind(entity: &serde_json::Value) -> serde_json::Value {
+        let mut entity = entity.clone();
+        if let Some(obj) = entity.as_object_mut() {
+            obj.remove("kind");
+        }
         entity
-            .get("name")
-            .or_else(|| entity.get("clientName"))
-            .or_else(|| entity.get("client_name"))
-            .or_else(|| entity.get("otoroshiId"))
-            .or_else(|| entity.get("label"))
-            .or_else(|| entity.get("username"))
-            .and_then(|id| id.as_str().map(|v| v.to_string()))
+    }
+
+    /// Stamp the `otoroshictl/managed-by` marker in the entity metadata
+    pub fn stamp_managed_by(entity: &mut serde_json::Value, name: &str) {
+        if let Some(obj) = entity.as_object_mut() {
+            let metadata = obj
+                .entry("metadata")
+                .or_insert_with(|| serde_json::json!({}));
+            if let Some(metadata) = metadata.as_object_mut() {
+                metadata.insert(
+                    MANAGED_BY_KEY.to_string(),
+                    serde_json::Value::String(name.to_string()),
+                );
+            }
+        }
+    }
+
+    /// Remove the `otoroshictl/managed-by` marker from the entity metadata
+    pub fn unstamp_managed_by(entity: &mut serde_json::Value) {
+        if let Some(metadata) = entity.get_mut("metadata").and_then(|m| m.as_object_mut()) {
+            metadata.remove(MANAGED_BY_KEY);
+        }
+    }
+
+    /// The value of the `otoroshictl/managed-by` marker of the entity, if any
+    pub fn managed_by(entity: &serde_json::Value) -> Option<String> {
+        entity
+            .get("metadata")
+            .and_then(|metadata| metadata.get(MANAGED_BY_KEY))
+            .and_then(|name| name.as_str().map(|v| v.to_string()))
+    }
+
+    /// Read, render and parse an entity file or url, the format is guessed from the extension and the content
+    pub async fn load_documents(
+        source: &String,
+        vars: &serde_json::Value,
+    ) -> Result<Vec<EntityDocument>, String> {
+        let raw_content = crate::utils::file::FileHelper::get_content_string_result(source)
+            .await
+            .map_err(|e| format!("error while reading {}: {}", source, e))?;
+        let content = TemplateHelper::render(&raw_content, vars)
+            .map_err(|e| format!("error while rendering {}:\n{}", source, e))?;
+        let trimmed = content.trim_start();
+        let is_json = source.ends_with(".json")
+            || (!source.ends_with(".yaml")
+                && !source.ends_with(".yml")
+                && (trimmed.starts_with('{') || trimmed.starts_with('[')));
+        Self::parse_documents(&content, is_json, source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_documents_json_array() {
+        let content = r#"[
+            {"kind": "proxy.otoroshi.io/Route", "id": "route_1", "name": "route 1"},
+            {"kind": "Apikey", "clientId": "key_1", "clientName": "key 1"}
+        ]"#;
+        let docs = EntityHelper::parse_documents(content, true, "test.json").unwrap();
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0].kind.as_deref(), Some("proxy.otoroshi.io/Route"));
+        assert_eq!(docs[0].id.as_deref(), Some("route_1"));
+        assert_eq!(docs[1].id.as_deref(), Some("key_1"));
+        assert_eq!(docs[1].name.as_deref(), Some("key 1"));
+    }
+
+    #[test]
+    fn test_parse_documents_yaml_multi_docs_with_kube_armor() {
+        let content = r#"---
+apiVersion: proxy.otoroshi.io/v1
+kind: Route
+metadata:
+  name: kube-name
+spec:
+  id: route_1
+  name: route 1
+---
+kind: Backend
+id: backend_1
+name: backend 1
+"#;
+        let docs = EntityHelper::parse_documents(content, false, "test.yaml").unwrap();
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0].kind.as_deref(), Some("Route"));
+        assert_eq!(docs[0].id.as_deref(), Some("route_1"));
+        assert_eq!(docs[0].name.as_deref(), Some("kube-name"));
+        assert!(docs[0].body.get("spec").is_none());
+        assert_eq!(docs[1].kind.as_deref(), Some("Backend"));
+        assert_eq!(docs[1].id.as_deref(), Some("backend_1"));
+    }
+
+    #[test]
+    fn test_parse_documents_lines() {
+        let content = "[\n  {\"id\": \"a\", \"name\": \"[x]\"},\n\n  {\n    \"id\": \"b\"\n  }\n]";
+        let docs = EntityHelper::parse_documents(content, true, "t.json").unwrap();
+        assert_eq!(docs.iter().map(|d| d.line).collect::<Vec<_>>(), vec![2, 4]);
+
+        let content = "# routes\nkind: Route\nid: a\n---\n\nkind: Route\nid: b\n";
+        let docs = EntityHelper::parse_documents(content, false, "t.yaml").unwrap();
+        assert_eq!(docs.iter().map(|d| d.line).collect::<Vec<_>>(), vec![2, 6]);
+        assert!(!docs[0].kube);
+    }
+
+    #[test]
+    fn test_parse_documents_missing_id() {
+        let docs = EntityHelper::parse_documents(r#"{"kind": "Route"}"#, true, "t.json").unwrap();
+        assert!(docs[0].id.is_none());
+        assert_eq!(docs[0].display_name(), "<unnamed>");
+    }
+
+    #[test]
+    fn test_parse_documents_invalid_content() {
+        assert!(EntityHelper::parse_documents("{ nope", true, "t.json").is_err());
+    }
+
+    #[test]
+    fn test_stamp_managed_by() {
+        let mut entity = serde_json::json!({"id": "route_1", "metadata": {"foo": "bar"}});
+        assert_eq!(EntityHelper::managed_by(&entity), None);
+        EntityHelper::stamp_managed_by(&mut entity, "prod");
+        assert_eq!(EntityHelper::managed_by(&entity).as_deref(), Some("prod"));
+        assert_eq!(entity["metadata"]["foo"], "bar");
+        EntityHelper::unstamp_managed_by(&mut entity);
+        assert_eq!(EntityHelper::managed_by(&entity), None);
+        assert_eq!(entity["metadata"], serde_json::json!({"foo": "bar"}));
+
+        let mut entity = serde_json::json!({"id": "route_2"});
+        EntityHelper::stamp_managed_by(&mut entity, "prod");
+        assert_eq!(entity["metadata"][MANAGED_BY_KEY], "prod");
     }
 }
@@ -0,0 +1,210 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cli::config::OtoroshiCtlConfig;
+use crate::utils::diff::{DiffHelper, FieldChange};
+
+/// How many versions are kept for each entity
+const HISTORY_SIZE: usize = 50;
+
+/// A version of an entity as it was before the cli changed it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub rev: u32,
+    pub timestamp: String,
+    /// the command that replaced this version: edit, patch, apply, delete or rollback
+    pub command: String,
+    pub entity: serde_json::Value,
+}
+
+/// A version of the history with what the command that replaced it changed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryVersion {
+    pub rev: u32,
+    pub timestamp: String,
+    pub command: String,
+    pub changes: Vec<FieldChange>,
+}
+
+/// The local history of the entities mutated by the cli, one json file per entity under
+/// `history/<context>/<group>_<kind>/<id>.json` next to the config file
+pub struct HistoryHelper {}
+
+impl HistoryHelper {
+    /// Where the history is kept: `OTOROSHICTL_HISTORY_DIR`, or the `history` directory next to
+    /// the config file
+    pub fn dir(config_file: Option<&str>) -> PathBuf {
+        if let Ok(dir) = std::env::var("OTOROSHICTL_HISTORY_DIR")
+            && !dir.is_empty()
+        {
+            return PathBuf::from(dir);
+        }
+        OtoroshiCtlConfig::config_dir(config_file).join("history")
+    }
+
+    /// The name of the current context. The contexts built from the command line flags or the
+    /// environment are named after their cluster
+    pub fn context(config: &OtoroshiCtlConfig) -> String {
+        match config.current_context.as_str() {
+            "tmp" | "env" => config
+                .clusters
+                .first()
+                .map(|c| format!("{}:{}", c.hostname, c.port))
+                .unwrap_or(config.current_context.clone()),
+            context => context.to_string(),
+        }
+    }
+
+    fn sanitize(value: &str) -> String {
+        value
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == ':' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+
+    /// The history file of an entity, `kind` being `group/Kind`
+    pub fn entity_file(dir: &Path, context: &str, kind: &str, id: &str) -> PathBuf {
+        dir.join(Self::sanitize(context))
+            .join(Self::sanitize(kind))
+            .join(format!("{}.json", Self::sanitize(id)))
+    }
+
+    pub fn read(file: &Path) -> Result<Vec<HistoryEntry>, String> {
+        match std::fs::read_to_string(file) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(format!("error while reading {:?}: {}", file, e)),
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| format!("invalid history file {:?}: {}", file, e)),
+        }
+    }
+
+    /// Append a version to the history of an entity, dropping the oldest ones beyond
+    /// `HISTORY_SIZE`. Returns the revision of the recorded version
+    pub fn record(
+        file: &Path,
+        command: &str,
+        entity: serde_json::Value,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    ) -> Result<u32, String> {
+        let mut entries = Self::read(file)?;
+        let rev = entries.last().map(|e| e.rev + 1).unwrap_or(1);
+        entries.push(HistoryEntry {
+            rev,
+            timestamp: timestamp.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            command: command.to_string(),
+            entity,
+        });
+        if entries.len() > HISTORY_SIZE {
+            entries.drain(..entries.len() - HISTORY_SIZE);
+        }
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("error while creating {:?}: {}", parent, e))?;
+        }
+        std::fs::write(file, serde_json::to_string_pretty(&entries).unwrap())
+            .map_err(|e| format!("error while writing {:?}: {}", file, e))?;
+        Ok(rev)
+    }
+
+    /// The versions, newest first, each one with the changes made by the command that replaced
+    /// it: up to the next version, or up to the `current` entity for the last one. Nothing is
+    /// reported for a version replaced by a deletion
+    pub fn versions(
+        entries: &[HistoryEntry],
+        current: Option<&serde_json::Value>,
+    ) -> Vec<HistoryVersion> {
+        entries
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                let next = match entries.get(idx + 1) {
+                    Some(next) => Some(&next.entity),
+                    None => current,
+                };
+                let changes = match (entry.command.as_str(), next) {
+                    ("delete", _) | (_, None) => Vec::new(),
+                    (_, Some(next)) => DiffHelper::diff(&entry.entity, next),
+                };
+                HistoryVersion {
+                    rev: entry.rev,
+                    timestamp: entry.timestamp.clone(),
+                    command: entry.command.clone(),
+                    changes,
+                }
+            })
+            .rev()
+            .collect()
+    }
+
+    pub fn display(versions: &[HistoryVersion]) {
+        let mut logger = paris::Logger::new();
+        for version in versions.iter() {
+            logger.log(format!(
+                "<bold>rev {}</> {} replaced by {}",
+                version.rev, version.timestamp, version.command
+            ));
+            DiffHelper::print_changes(&version.changes, "    ");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_versions() {
+        let dir = std::env::temp_dir().join(format!("otoroshictl-history-{}", std::process::id()));
+        let file = HistoryHelper::entity_file(&dir, "dev", "proxy.otoroshi.io/Route", "route 1");
+        assert!(file.ends_with("dev/proxy.otoroshi.io_Route/route_1.json"));
+        let at = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let v1 = serde_json::json!({"id": "route 1", "name": "a"});
+        let v2 = serde_json::json!({"id": "route 1", "name": "b"});
+        let current = serde_json::json!({"id": "route 1", "name": "b", "enabled": false});
+        assert_eq!(HistoryHelper::record(&file, "edit", v1, at).unwrap(), 1);
+        assert_eq!(HistoryHelper::record(&file, "apply", v2, at).unwrap(), 2);
+
+        let entries = HistoryHelper::read(&file).unwrap();
+        assert_eq!(entries[0].timestamp, "2023-11-14T22:13:20Z");
+        let versions = HistoryHelper::versions(&entries, Some(&current));
+        assert_eq!(versions[0].rev, 2);
+        assert_eq!(
+            versions[0].changes,
+            vec![FieldChange::Added {
+                path: "enabled".to_string(),
+                value: serde_json::json!(false)
+            }]
+        );
+        assert_eq!(
+            versions[1].changes,
+            vec![FieldChange::Changed {
+                path: "name".to_string(),
+                from: serde_json::json!("a"),
+                to: serde_json::json!("b")
+            }]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_history_size() {
+        let dir =
+            std::env::temp_dir().join(format!("otoroshictl-history-size-{}", std::process::id()));
+        let file = HistoryHelper::entity_file(&dir, "dev", "proxy.otoroshi.io/Route", "route_1");
+        let at = chrono::Utc::now();
+        for idx in 0..HISTORY_SIZE + 2 {
+            HistoryHelper::record(&file, "patch", serde_json::json!({ "idx": idx }), at).unwrap();
+        }
+        let entries = HistoryHelper::read(&file).unwrap();
+        assert_eq!(entries.len(), HISTORY_SIZE);
+        assert_eq!(entries[0].rev, 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,313 @@
+/// RFC 6902 JSON Patch, applied on a copy of the document so a failing operation never leaves
+/// a partially patched entity
+pub struct JsonPatchHelper {}
+
+impl JsonPatchHelper {
+    /// Is the value a list of `{op, path, ...}` operations
+    pub fn is_json_patch(value: &serde_json::Value) -> bool {
+        match value.as_array() {
+            Some(operations) => {
+                !operations.is_empty()
+                    && operations
+                        .iter()
+                        .all(|op| op.get("op").map(|o| o.is_string()).unwrap_or(false))
+            }
+            None => false,
+        }
+    }
+
+    /// Apply all the operations, returns the patched document or the first failure
+    pub fn apply(
+        doc: &serde_json::Value,
+        operations: &serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let operations = operations
+            .as_array()
+            .ok_or("a json patch must be an array of operations".to_string())?;
+        let mut patched = doc.clone();
+        for (idx, operation) in operations.iter().enumerate() {
+            Self::apply_operation(&mut patched, operation)
+                .map_err(|e| format!("operation {} failed: {}", idx, e))?;
+        }
+        Ok(patched)
+    }
+
+    /// Apply an otoroshi patch, a list of `{path, value}` with dotted paths as sent with the
+    /// `application/json+oto-patch` content type. Missing objects along the path are created and
+    /// values holding json, like `false`, `42` or `["a"]`, are set as json
+    pub fn apply_oto_patch(
+        doc: &serde_json::Value,
+        patch: &serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let items = patch
+            .as_array()
+            .ok_or("an otoroshi patch must be an array of {path, value}".to_string())?;
+        let mut patched = doc.clone();
+        for item in items.iter() {
+            let path = Self::string_field(item, "path")?;
+            let value = match Self::value_field(item)? {
+                serde_json::Value::String(raw) => {
+                    serde_json::from_str(&raw).unwrap_or(serde_json::Value::String(raw))
+                }
+                value => value,
+            };
+            let mut current = &mut patched;
+            for token in path.split('.') {
+                current = match current {
+                    serde_json::Value::Object(obj) => obj
+                        .entry(token.to_string())
+                        .or_insert(serde_json::Value::Object(serde_json::Map::new())),
+                    serde_json::Value::Array(arr) => token
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|idx| arr.get_mut(idx))
+                        .ok_or(format!("invalid array index '{}' in '{}'", token, path))?,
+                    _ => return Err(format!("'{}' goes through a value in '{}'", token, path)),
+                };
+            }
+            *current = value;
+        }
+        Ok(patched)
+    }
+
+    fn string_field<'a>(operation: &'a serde_json::Value, name: &str) -> Result<&'a str, String> {
+        operation
+            .get(name)
+            .and_then(|v| v.as_str())
+            .ok_or(format!("missing '{}' field", name))
+    }
+
+    fn value_field(operation: &serde_json::Value) -> Result<serde_json::Value, String> {
+        operation
+            .get("value")
+            .cloned()
+            .ok_or("missing 'value' field".to_string())
+    }
+
+    fn apply_operation(
+        doc: &mut serde_json::Value,
+        operation: &serde_json::Value,
+    ) -> Result<(), String> {
+        let op = Self::string_field(operation, "op")?;
+        let path = Self::string_field(operation, "path")?;
+        match op {
+            "add" => Self::add(doc, path, Self::value_field(operation)?),
+            "remove" => Self::remove(doc, path).map(|_| ()),
+            "replace" => {
+                let value = Self::value_field(operation)?;
+                let target = doc
+                    .pointer_mut(path)
+                    .ok_or(format!("path '{}' does not exist", path))?;
+                *target = value;
+                Ok(())
+            }
+            "move" => {
+                let from = Self::string_field(operation, "from")?;
+                if path.starts_with(&format!("{}/", from)) {
+                    return Err(format!("cannot move '{}' into one of its children", from));
+                }
+                let value = Self::remove(doc, from)?;
+                Self::add(doc, path, value)
+            }
+            "copy" => {
+                let from = Self::string_field(operation, "from")?;
+                let value = doc
+                    .pointer(from)
+                    .cloned()
+                    .ok_or(format!("path '{}' does not exist", from))?;
+                Self::add(doc, path, value)
+            }
+            "test" => {
+                let expected = Self::value_field(operation)?;
+                match doc.pointer(path) {
+                    Some(actual) if *actual == expected => Ok(()),
+                    Some(actual) => Err(format!(
+                        "test failed on '{}', expected {} but was {}",
+                        path, expected, actual
+                    )),
+                    None => Err(format!("path '{}' does not exist", path)),
+                }
+            }
+            other => Err(format!("unknown operation '{}'", other)),
+        }
+    }
+
+    /// Split a pointer into its parent pointer and its unescaped last token
+    fn split_pointer(path: &str) -> Result<(&str, String), String> {
+        match path.rfind('/') {
+            Some(pos) => Ok((
+                &path[..pos],
+                path[pos + 1..].replace("~1", "/").replace("~0", "~"),
+            )),
+            None => Err(format!("invalid path '{}'", path)),
+        }
+    }
+
+    fn array_index(token: &str, len: usize, path: &str) -> Result<usize, String> {
+        let valid = !token.is_empty()
+            && token.chars().all(|c| c.is_ascii_digit())
+            && (token == "0" || !token.starts_with('0'));
+        match token.parse::<usize>() {
+            Ok(idx) if valid && idx <= len => Ok(idx),
+            _ => Err(format!("invalid array index in '{}'", path)),
+        }
+    }
+
+    fn add(
+        doc: &mut serde_json::Value,
+        path: &str,
+        value: serde_json::Value,
+    ) -> Result<(), String> {
+        if path.is_empty() {
+            *doc = value;
+            return Ok(());
+        }
+        let (parent, token) = Self::split_pointer(path)?;
+        match doc.pointer_mut(parent) {
+            Some(serde_json::Value::Object(obj)) => {
+                obj.insert(token, value);
+                Ok(())
+            }
+            Some(serde_json::Value::Array(arr)) => {
+                let idx = if token == "-" {
+                    arr.len()
+                } else {
+                    Self::array_index(&token, arr.len(), path)?
+                };
+                arr.insert(idx, value);
+                Ok(())
+            }
+            Some(_) => Err(format!("parent of '{}' is not a container", path)),
+            None => Err(format!("parent of '{}' does not exist", path)),
+        }
+    }
+
+    fn remove(doc: &mut serde_json::Value, path: &str) -> Result<serde_json::Value, String> {
+        if path.is_empty() {
+            return Err("cannot remove the whole document".to_string());
+        }
+        let (parent, token) = Self::split_pointer(path)?;
+        match doc.pointer_mut(parent) {
+            Some(serde_json::Value::Object(obj)) => obj
+                .remove(&token)
+                .ok_or(format!("path '{}' does not exist", path)),
+            Some(serde_json::Value::Array(arr)) => {
+                let idx = Self::array_index(&token, arr.len(), path)?;
+                if idx < arr.len() {
+                    Ok(arr.remove(idx))
+                } else {
+                    Err(format!("path '{}' does not exist", path))
+                }
+            }
+            _ => Err(format!("path '{}' does not exist", path)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route() -> serde_json::Value {
+        serde_json::json!({
+            "id": "route_1",
+            "enabled": true,
+            "plugins": [{"plugin": "a"}, {"plugin": "b"}, {"plugin": "c"}],
+            "metadata": {"a/b": "slash"}
+        })
+    }
+
+    #[test]
+    fn test_is_json_patch() {
+        assert!(JsonPatchHelper::is_json_patch(
+            &serde_json::json!([{"op": "remove", "path": "/enabled"}])
+        ));
+        assert!(!JsonPatchHelper::is_json_patch(
+            &serde_json::json!({"enabled": false})
+        ));
+        assert!(!JsonPatchHelper::is_json_patch(&serde_json::json!([])));
+    }
+
+    #[test]
+    fn test_add_remove_replace() {
+        let ops = serde_json::json!([
+            {"op": "remove", "path": "/plugins/1"},
+            {"op": "add", "path": "/plugins/-", "value": {"plugin": "d"}},
+            {"op": "replace", "path": "/enabled", "value": false},
+            {"op": "add", "path": "/metadata/team", "value": "core"}
+        ]);
+        let patched = JsonPatchHelper::apply(&route(), &ops).unwrap();
+        assert_eq!(
+            patched["plugins"],
+            serde_json::json!([{"plugin": "a"}, {"plugin": "c"}, {"plugin": "d"}])
+        );
+        assert_eq!(patched["enabled"], false);
+        assert_eq!(patched["metadata"]["team"], "core");
+    }
+
+    #[test]
+    fn test_move_copy_and_escaped_pointers() {
+        let ops = serde_json::json!([
+            {"op": "copy", "from": "/metadata/a~1b", "path": "/metadata/copied"},
+            {"op": "move", "from": "/plugins/0", "path": "/plugins/2"}
+        ]);
+        let patched = JsonPatchHelper::apply(&route(), &ops).unwrap();
+        assert_eq!(patched["metadata"]["copied"], "slash");
+        assert_eq!(
+            patched["plugins"],
+            serde_json::json!([{"plugin": "b"}, {"plugin": "c"}, {"plugin": "a"}])
+        );
+    }
+
+    #[test]
+    fn test_failing_test_operation_is_atomic() {
+        let doc = route();
+        let ops = serde_json::json!([
+            {"op": "remove", "path": "/plugins/0"},
+            {"op": "test", "path": "/id", "value": "route_2"}
+        ]);
+        let res = JsonPatchHelper::apply(&doc, &ops);
+        assert!(res.unwrap_err().starts_with("operation 1 failed"));
+        assert_eq!(doc, route());
+    }
+
+    #[test]
+    fn test_oto_patch() {
+        let patch = serde_json::json!([
+            {"path": "enabled", "value": "false"},
+            {"path": "plugins.1.plugin", "value": "e"},
+            {"path": "frontend.domains", "value": "[\"api.prod.oto.tools\"]"},
+            {"path": "metadata.env", "value": "prod"}
+        ]);
+        let patched = JsonPatchHelper::apply_oto_patch(&route(), &patch).unwrap();
+        assert_eq!(patched["enabled"], false);
+        assert_eq!(patched["plugins"][1]["plugin"], "e");
+        assert_eq!(
+            patched["frontend"],
+            serde_json::json!({"domains": ["api.prod.oto.tools"]})
+        );
+        assert_eq!(patched["metadata"]["env"], "prod");
+        assert!(
+            JsonPatchHelper::apply_oto_patch(
+                &route(),
+                &serde_json::json!([{"path": "plugins.5.plugin", "value": "x"}])
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_invalid_operations() {
+        let doc = route();
+        for ops in [
+            serde_json::json!([{"op": "remove", "path": "/missing"}]),
+            serde_json::json!([{"op": "replace", "path": "/plugins/5", "value": 1}]),
+            serde_json::json!([{"op": "add", "path": "/plugins/01", "value": 1}]),
+            serde_json::json!([{"op": "add", "path": "/plugins"}]),
+            serde_json::json!([{"op": "move", "from": "/metadata", "path": "/metadata/a"}]),
+            serde_json::json!([{"op": "frobnicate", "path": "/id"}]),
+        ] {
+            assert!(JsonPatchHelper::apply(&doc, &ops).is_err(), "{}", ops);
+        }
+    }
+}
@@ -0,0 +1,307 @@
+/// A subset of JSONPath used by `-o jsonpath=`, `--query` and the table columns. Supported:
+/// `$` (optional), `.key`, `['key']`, `[0]`, `[-1]`, `[*]`, `.*`, `..key` and filters like
+/// `[?(@.enabled == true)]` with `==`, `!=`, `<`, `<=`, `>`, `>=` or a simple `[?(@.key)]`.
+/// Expressions can be wrapped in `{}` like in kubectl, and the leading `.` can be omitted
+pub struct JsonPathHelper {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(i64),
+    Wildcard,
+    Descendant(String),
+    Filter(Vec<Segment>, Option<(String, serde_json::Value)>),
+}
+
+impl JsonPathHelper {
+    const OPERATORS: [&'static str; 6] = ["==", "!=", "<=", ">=", "<", ">"];
+
+    fn read_name(chars: &[char], pos: &mut usize) -> String {
+        let start = *pos;
+        while *pos < chars.len() && chars[*pos] != '.' && chars[*pos] != '[' {
+            *pos += 1;
+        }
+        chars[start..*pos].iter().collect()
+    }
+
+    fn parse_literal(raw: &str) -> Result<serde_json::Value, String> {
+        let raw = raw.trim();
+        if raw.len() >= 2
+            && ((raw.starts_with('\'') && raw.ends_with('\''))
+                || (raw.starts_with('"') && raw.ends_with('"')))
+        {
+            return Ok(serde_json::Value::String(raw[1..raw.len() - 1].to_string()));
+        }
+        serde_json::from_str(raw).map_err(|_| format!("invalid value '{}' in filter", raw))
+    }
+
+    fn parse_filter(filter: &str) -> Result<Segment, String> {
+        let inner = filter
+            .strip_prefix("?(")
+            .and_then(|f| f.strip_suffix(')'))
+            .ok_or_else(|| format!("invalid filter '[{}]'", filter))?;
+        let found = Self::OPERATORS
+            .iter()
+            .filter_map(|op| inner.find(op).map(|idx| (idx, *op)))
+            .min_by_key(|(idx, op)| (*idx, std::cmp::Reverse(op.len())));
+        let (left, comparison) = match found {
+            None => (inner.trim(), None),
+            Some((idx, op)) => (
+                inner[..idx].trim(),
+                Some((
+                    op.to_string(),
+                    Self::parse_literal(&inner[idx + op.len()..])?,
+                )),
+            ),
+        };
+        let path = left
+            .strip_prefix('@')
+            .ok_or_else(|| format!("filters must start with @ in '[{}]'", filter))?;
+        Ok(Segment::Filter(Self::parse(path)?, comparison))
+    }
+
+    fn parse(expression: &str) -> Result<Vec<Segment>, String> {
+        let expression = expression.trim();
+        let expression = expression
+            .strip_prefix('{')
+            .and_then(|e| e.strip_suffix('}'))
+            .unwrap_or(expression)
+            .trim();
+        let expression = expression.strip_prefix('$').unwrap_or(expression);
+        let chars: Vec<char> = expression.chars().collect();
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut pos = 0;
+        if !chars.is_empty() && chars[0] != '.' && chars[0] != '[' {
+            segments.push(Segment::Key(Self::read_name(&chars, &mut pos)));
+        }
+        while pos < chars.len() {
+            match chars[pos] {
+                '.' if chars.get(pos + 1) == Some(&'.') => {
+                    pos += 2;
+                    let name = Self::read_name(&chars, &mut pos);
+                    segments.push(Segment::Descendant(name));
+                }
+                '.' if chars.get(pos + 1) == Some(&'[') => pos += 1,
+                '.' => {
+                    pos += 1;
+                    let name = Self::read_name(&chars, &mut pos);
+                    segments.push(match name.as_str() {
+                        "" => return Err(format!("empty key in '{}'", expression)),
+                        "*" => Segment::Wildcard,
+                        _ => Segment::Key(name),
+                    });
+                }
+                '[' => {
+                    // filters can contain brackets in their literals, look for the closing `)]`
+                    let content: String = chars[pos + 1..].iter().collect();
+                    let end = if content.starts_with('?') {
+                        content.find(")]").map(|idx| idx + 1)
+                    } else {
+                        content.find(']')
+                    }
+                    .ok_or_else(|| format!("missing ] in '{}'", expression))?;
+                    let inner = content[..end].trim();
+                    pos += 1 + content[..=end].chars().count();
+                    segments.push(if inner == "*" {
+                        Segment::Wildcard
+                    } else if inner.starts_with('?') {
+                        Self::parse_filter(inner)?
+                    } else if inner.starts_with('\'') || inner.starts_with('"') {
+                        match Self::parse_literal(inner)? {
+                            serde_json::Value::String(key) => Segment::Key(key),
+                            _ => return Err(format!("invalid key [{}]", inner)),
+                        }
+                    } else {
+                        Segment::Index(
+                            inner
+                                .parse::<i64>()
+                                .map_err(|_| format!("invalid index [{}]", inner))?,
+                        )
+                    });
+                }
+                other => return Err(format!("unexpected '{}' in '{}'", other, expression)),
+            }
+        }
+        Ok(segments)
+    }
+
+    fn children(value: &serde_json::Value) -> Vec<&serde_json::Value> {
+        match value {
+            serde_json::Value::Object(obj) => obj.values().collect(),
+            serde_json::Value::Array(arr) => arr.iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn descendants<'a>(value: &'a serde_json::Value, found: &mut Vec<&'a serde_json::Value>) {
+        found.push(value);
+        for child in Self::children(value) {
+            Self::descendants(child, found);
+        }
+    }
+
+    fn compare(value: &serde_json::Value, op: &str, expected: &serde_json::Value) -> bool {
+        let ordering = match (value, expected) {
+            (serde_json::Value::Number(a), serde_json::Value::Number(b)) => a
+                .as_f64()
+                .zip(b.as_f64())
+                .and_then(|(a, b)| a.partial_cmp(&b)),
+            (serde_json::Value::String(a), serde_json::Value::String(b)) => Some(a.cmp(b)),
+            (a, b) if a == b => Some(std::cmp::Ordering::Equal),
+            _ => None,
+        };
+        match op {
+            "==" => ordering == Some(std::cmp::Ordering::Equal),
+            "!=" => ordering != Some(std::cmp::Ordering::Equal),
+            "<" => ordering == Some(std::cmp::Ordering::Less),
+            "<=" => matches!(
+                ordering,
+                Some(std::cmp::Ordering::Less | std::cmp::Ordering::Equal)
+            ),
+            ">" => ordering == Some(std::cmp::Ordering::Greater),
+            ">=" => matches!(
+                ordering,
+                Some(std::cmp::Ordering::Greater | std::cmp::Ordering::Equal)
+            ),
+            _ => false,
+        }
+    }
+
+    fn apply<'a>(segments: &[Segment], root: &'a serde_json::Value) -> Vec<&'a serde_json::Value> {
+        let mut current: Vec<&serde_json::Value> = vec![root];
+        for segment in segments.iter() {
+            current = current
+                .into_iter()
+                .flat_map(|value| -> Vec<&serde_json::Value> {
+                    match segment {
+                        Segment::Key(key) => value.get(key.as_str()).into_iter().collect(),
+                        Segment::Index(idx) => {
+                            let arr = value.as_array().map(|a| a.as_slice()).unwrap_or(&[]);
+                            let idx = if *idx < 0 {
+                                arr.len() as i64 + idx
+                            } else {
+                                *idx
+                            };
+                            usize::try_from(idx)
+                                .ok()
+                                .and_then(|idx| arr.get(idx))
+                                .into_iter()
+                                .collect()
+                        }
+                        Segment::Wildcard => Self::children(value),
+                        Segment::Descendant(name) => {
+                            let mut found = Vec::new();
+                            Self::descendants(value, &mut found);
+                            if name == "*" {
+                                found.into_iter().skip(1).collect()
+                            } else {
+                                found
+                                    .into_iter()
+                                    .filter_map(|v| v.as_object().and_then(|o| o.get(name)))
+                                    .collect()
+                            }
+                        }
+                        Segment::Filter(path, comparison) => Self::children(value)
+                            .into_iter()
+                            .filter(|child| {
+                                let selected = Self::apply(path, child);
+                                match comparison {
+                                    None => !selected.is_empty(),
+                                    Some((op, expected)) => {
+                                        selected.iter().any(|v| Self::compare(v, op, expected))
+                                    }
+                                }
+                            })
+                            .collect(),
+                    }
+                })
+                .collect();
+        }
+        current
+    }
+
+    /// Select the values matching the expression
+    pub fn select(
+        expression: &str,
+        value: &serde_json::Value,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let segments = Self::parse(expression)?;
+        Ok(Self::apply(&segments, value).into_iter().cloned().collect())
+    }
+
+    /// Strings are displayed as is, other values as json
+    pub fn text(value: &serde_json::Value) -> String {
+        match value {
+            serde_json::Value::String(str) => str.to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn routes() -> serde_json::Value {
+        serde_json::json!([
+            {
+                "id": "route_1",
+                "enabled": true,
+                "frontend": {"domains": ["api.oto.tools", "api2.oto.tools"]},
+                "backend": {"targets": [{"hostname": "a.svc", "port": 8080}, {"hostname": "b.svc", "port": 8443}]}
+            },
+            {
+                "id": "route_2",
+                "enabled": false,
+                "frontend": {"domains": ["admin.oto.tools"]},
+                "backend": {"targets": [{"hostname": "c.svc", "port": 80}]}
+            }
+        ])
+    }
+
+    #[test]
+    fn test_paths_and_projections() {
+        let routes = routes();
+        let select = |expr: &str| JsonPathHelper::select(expr, &routes).unwrap();
+        assert_eq!(select("$[*].id"), vec!["route_1", "route_2"]);
+        assert_eq!(
+            select("{.[-1].frontend.domains[0]}"),
+            vec!["admin.oto.tools"]
+        );
+        assert_eq!(
+            select("$[0].backend.targets[*].hostname"),
+            vec!["a.svc", "b.svc"]
+        );
+        assert_eq!(select("$..port"), vec![8080, 8443, 80]);
+        assert_eq!(select("[1]['id']"), vec!["route_2"]);
+        assert!(select("$[5].id").is_empty());
+
+        let route = &routes[0];
+        assert_eq!(
+            JsonPathHelper::select("frontend.domains[1]", route).unwrap(),
+            vec!["api2.oto.tools"]
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let routes = routes();
+        let select = |expr: &str| JsonPathHelper::select(expr, &routes).unwrap();
+        assert_eq!(select("$[?(@.enabled == false)].id"), vec!["route_2"]);
+        assert_eq!(select("$[?(@.id != 'route_2')].id"), vec!["route_1"]);
+        assert_eq!(
+            select("$[*].backend.targets[?(@.port >= 8080)].hostname"),
+            vec!["a.svc", "b.svc"]
+        );
+        assert_eq!(select("$[?(@.frontend.domains[1])].id"), vec!["route_1"]);
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        let routes = routes();
+        assert!(JsonPathHelper::select("$[0", &routes).is_err());
+        assert!(JsonPathHelper::select("$[abc]", &routes).is_err());
+        assert!(JsonPathHelper::select("$[?(enabled == true)]", &routes).is_err());
+        assert!(JsonPathHelper::select("$..", &routes).unwrap().is_empty());
+    }
+}
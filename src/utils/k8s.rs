@@ -0,0 +1,649 @@
+use crate::utils::normalize::NormalizeHelper;
+
+/// Something of a kubernetes manifest that has no equivalent in the generated routes
+#[derive(Clone, Debug, PartialEq)]
+pub struct K8sIssue {
+    pub source: String,
+    /// the manifest, as `Kind namespace/name`
+    pub resource: String,
+    pub message: String,
+}
+
+/// The routes translated from kubernetes manifests, and what could not be translated
+#[derive(Clone, Debug, Default)]
+pub struct K8sTranslation {
+    pub routes: Vec<serde_json::Value>,
+    pub issues: Vec<K8sIssue>,
+}
+
+/// The conditions of a generated route
+#[derive(Clone, Debug, Default)]
+struct K8sMatch {
+    domains: Vec<String>,
+    exact: bool,
+    headers: serde_json::Map<String, serde_json::Value>,
+    query: serde_json::Map<String, serde_json::Value>,
+    methods: Vec<String>,
+}
+
+/// A kubernetes manifest being translated, where its issues are collected
+struct K8sManifest<'a> {
+    kind: &'a str,
+    namespace: String,
+    name: String,
+    source: &'a str,
+}
+
+impl K8sManifest<'_> {
+    fn issue(&self, translation: &mut K8sTranslation, message: String) {
+        translation.issues.push(K8sIssue {
+            source: self.source.to_string(),
+            resource: format!("{} {}/{}", self.kind, self.namespace, self.name),
+            message,
+        });
+    }
+
+    /// A route of the manifest, numbered from 1 within the manifest
+    fn route(
+        &self,
+        idx: usize,
+        conditions: K8sMatch,
+        targets: Vec<serde_json::Value>,
+    ) -> serde_json::Value {
+        let slug = NormalizeHelper::slug(&format!(
+            "{} {} {} {}",
+            self.kind, self.namespace, self.name, idx
+        ));
+        serde_json::json!({
+            "id": format!("route_{}", slug),
+            "name": format!("{}/{} {}", self.namespace, self.name, idx),
+            "enabled": true,
+            "metadata": {
+                "kubernetes": format!("{}/{}/{}", self.kind, self.namespace, self.name)
+            },
+            "frontend": {
+                "domains": conditions.domains,
+                "strip_path": false,
+                "exact": conditions.exact,
+                "headers": conditions.headers,
+                "query": conditions.query,
+                "methods": conditions.methods,
+            },
+            "backend": {
+                "root": "/",
+                "rewrite": false,
+                "targets": targets,
+            },
+            "plugins": [],
+        })
+    }
+}
+
+/// Offline translation of kubernetes `Ingress` and Gateway API `HTTPRoute` manifests into
+/// otoroshi routes
+pub struct K8sHelper {}
+
+impl K8sHelper {
+    /// The manifests of a json or yaml file, the items of `List` manifests included
+    pub fn manifests(
+        content: &str,
+        is_json: bool,
+        source: &str,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let parsed: Vec<serde_json::Value> = if is_json {
+            vec![
+                serde_json::from_str(content)
+                    .map_err(|e| format!("error while parsing {}: {}", source, e))?,
+            ]
+        } else {
+            let mut parsed = Vec::new();
+            for doc in content.split("---\n") {
+                if doc.trim().is_empty() {
+                    continue;
+                }
+                match serde_yaml::from_str::<serde_json::Value>(doc) {
+                    Ok(serde_json::Value::Null) => (),
+                    Ok(json) => parsed.push(json),
+                    Err(e) => return Err(format!("error while parsing {}: {}", source, e)),
+                }
+            }
+            parsed
+        };
+        Ok(parsed
+            .into_iter()
+            .flat_map(|manifest| match manifest {
+                serde_json::Value::Array(items) => items,
+                manifest if manifest.get("kind").and_then(|k| k.as_str()) == Some("List") => {
+                    manifest
+                        .get("items")
+                        .and_then(|i| i.as_array())
+                        .cloned()
+                        .unwrap_or_default()
+                }
+                manifest => vec![manifest],
+            })
+            .collect())
+    }
+
+    /// Translate the `Ingress` and `HTTPRoute` manifests, the other kinds are ignored
+    pub fn translate(
+        manifests: &[serde_json::Value],
+        source: &str,
+        translation: &mut K8sTranslation,
+    ) {
+        for json in manifests.iter() {
+            let kind = json.get("kind").and_then(|k| k.as_str()).unwrap_or("");
+            let api_version = json
+                .get("apiVersion")
+                .and_then(|k| k.as_str())
+                .unwrap_or("");
+            let manifest = K8sManifest {
+                kind,
+                namespace: json
+                    .pointer("/metadata/namespace")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or("default")
+                    .to_string(),
+                name: json
+                    .pointer("/metadata/name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or("unnamed")
+                    .to_string(),
+                source,
+            };
+            match kind {
+                "Ingress" if api_version.starts_with("networking.k8s.io/") => {
+                    Self::ingress(json, &manifest, translation)
+                }
+                "HTTPRoute" if api_version.starts_with("gateway.networking.k8s.io/") => {
+                    Self::http_route(json, &manifest, translation)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn target(service: &str, namespace: &str, port: u64, weight: u64) -> serde_json::Value {
+        let hostname = format!("{}.{}.svc.cluster.local", service, namespace);
+        serde_json::json!({
+            "id": format!("{}:{}", hostname, port),
+            "hostname": hostname,
+            "port": port,
+            "tls": false,
+            "weight": weight,
+        })
+    }
+
+    fn domain(host: &str, path: &str) -> String {
+        match path.trim_end_matches('/') {
+            "" => host.to_string(),
+            path => format!("{}{}", host, path),
+        }
+    }
+
+    fn ingress(json: &serde_json::Value, manifest: &K8sManifest, translation: &mut K8sTranslation) {
+        let annotations: Vec<&String> = json
+            .pointer("/metadata/annotations")
+            .and_then(|a| a.as_object())
+            .map(|a| {
+                a.keys()
+                    .filter(|k| !k.starts_with("kubectl.kubernetes.io/"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        if !annotations.is_empty() {
+            let names: Vec<&str> = annotations.iter().map(|a| a.as_str()).collect();
+            manifest.issue(
+                translation,
+                format!("annotations not translated: {}", names.join(", ")),
+            );
+        }
+        if json.pointer("/spec/defaultBackend").is_some() {
+            manifest.issue(
+                translation,
+                "the default backend is not translated".to_string(),
+            );
+        }
+        if let Some(tls) = json.pointer("/spec/tls").and_then(|t| t.as_array()) {
+            let hosts: Vec<&str> = tls
+                .iter()
+                .filter_map(|t| t.get("hosts").and_then(|h| h.as_array()))
+                .flatten()
+                .filter_map(|h| h.as_str())
+                .collect();
+            manifest.issue(
+                translation,
+                format!(
+                    "tls is not translated, the certificates of {} have to be added to otoroshi",
+                    hosts.join(", ")
+                ),
+            );
+        }
+        let rules = json
+            .pointer("/spec/rules")
+            .and_then(|r| r.as_array())
+            .cloned()
+            .unwrap_or_default();
+        let mut idx = 0;
+        for (rule_idx, rule) in rules.iter().enumerate() {
+            let Some(host) = rule.get("host").and_then(|h| h.as_str()) else {
+                manifest.issue(
+                    translation,
+                    format!("rule {} has no host, it is not translated", rule_idx + 1),
+                );
+                continue;
+            };
+            let paths = rule
+                .pointer("/http/paths")
+                .and_then(|p| p.as_array())
+                .cloned()
+                .unwrap_or_default();
+            for path in paths.iter() {
+                let value = path.get("path").and_then(|p| p.as_str()).unwrap_or("/");
+                let exact = match path.get("pathType").and_then(|t| t.as_str()) {
+                    Some("Exact") => true,
+                    Some("Prefix") => false,
+                    other => {
+                        manifest.issue(
+                            translation,
+                            format!(
+                                "path type {} of {}{} translated as a prefix match",
+                                other.unwrap_or("none"),
+                                host,
+                                value
+                            ),
+                        );
+                        false
+                    }
+                };
+                let service = path.pointer("/backend/service");
+                let port = service
+                    .and_then(|s| s.pointer("/port/number"))
+                    .and_then(|p| p.as_u64());
+                let target = match (
+                    service.and_then(|s| s.get("name")).and_then(|n| n.as_str()),
+                    port,
+                ) {
+                    (Some(name), Some(port)) => Self::target(name, &manifest.namespace, port, 1),
+                    (Some(_), None) => {
+                        manifest.issue(
+                            translation,
+                            format!(
+                                "the backend of {}{} uses a named service port, it is not translated",
+                                host, value
+                            ),
+                        );
+                        continue;
+                    }
+                    (None, _) => {
+                        manifest.issue(
+                            translation,
+                            format!(
+                                "the backend of {}{} is not a service, it is not translated",
+                                host, value
+                            ),
+                        );
+                        continue;
+                    }
+                };
+                idx += 1;
+                let conditions = K8sMatch {
+                    domains: vec![Self::domain(host, value)],
+                    exact,
+                    ..Default::default()
+                };
+                translation
+                    .routes
+                    .push(manifest.route(idx, conditions, vec![target]));
+            }
+        }
+    }
+
+    /// The conditions of an `HTTPRoute` match, `None` when they can not be translated
+    fn http_match(
+        hostnames: &[&str],
+        matching: &serde_json::Value,
+        manifest: &K8sManifest,
+        translation: &mut K8sTranslation,
+    ) -> Option<K8sMatch> {
+        let path_type = matching
+            .pointer("/path/type")
+            .and_then(|t| t.as_str())
+            .unwrap_or("PathPrefix");
+        let path = matching
+            .pointer("/path/value")
+            .and_then(|v| v.as_str())
+            .unwrap_or("/");
+        let exact = match path_type {
+            "Exact" => true,
+            "PathPrefix" => false,
+            other => {
+                manifest.issue(
+                    translation,
+                    format!(
+                        "path match {} {} is not translated, the match is skipped",
+                        other, path
+                    ),
+                );
+                return None;
+            }
+        };
+        let mut conditions = K8sMatch {
+            domains: hostnames
+                .iter()
+                .map(|host| Self::domain(host, path))
+                .collect(),
+            exact,
+            ..Default::default()
+        };
+        for (field, into) in [
+            ("headers", &mut conditions.headers),
+            ("queryParams", &mut conditions.query),
+        ] {
+            for item in matching
+                .get(field)
+                .and_then(|h| h.as_array())
+                .into_iter()
+                .flatten()
+            {
+                let name = item.get("name").and_then(|n| n.as_str()).unwrap_or("");
+                let value = item.get("value").cloned().unwrap_or_default();
+                match item.get("type").and_then(|t| t.as_str()).unwrap_or("Exact") {
+                    "Exact" => {
+                        into.insert(name.to_string(), value);
+                    }
+                    other => {
+                        manifest.issue(
+                            translation,
+                            format!(
+                                "{} match {} on {} is not translated, the match is skipped",
+                                field, other, name
+                            ),
+                        );
+                        return None;
+                    }
+                }
+            }
+        }
+        if let Some(method) = matching.get("method").and_then(|m| m.as_str()) {
+            conditions.methods.push(method.to_string());
+        }
+        Some(conditions)
+    }
+
+    fn http_route(
+        json: &serde_json::Value,
+        manifest: &K8sManifest,
+        translation: &mut K8sTranslation,
+    ) {
+        let hostnames: Vec<&str> = json
+            .pointer("/spec/hostnames")
+            .and_then(|h| h.as_array())
+            .map(|h| h.iter().filter_map(|h| h.as_str()).collect())
+            .unwrap_or_default();
+        if hostnames.is_empty() {
+            manifest.issue(
+                translation,
+                "no hostnames, the hostnames of the gateway listeners are unknown offline"
+                    .to_string(),
+            );
+            return;
+        }
+        let rules = json
+            .pointer("/spec/rules")
+            .and_then(|r| r.as_array())
+            .cloned()
+            .unwrap_or_default();
+        let mut idx = 0;
+        for (rule_idx, rule) in rules.iter().enumerate() {
+            let filters: Vec<&str> = rule
+                .get("filters")
+                .and_then(|f| f.as_array())
+                .into_iter()
+                .flatten()
+                .chain(
+                    rule.get("backendRefs")
+                        .and_then(|b| b.as_array())
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|b| b.get("filters").and_then(|f| f.as_array()))
+                        .flatten(),
+                )
+                .filter_map(|f| f.get("type").and_then(|t| t.as_str()))
+                .collect();
+            if !filters.is_empty() {
+                manifest.issue(
+                    translation,
+                    format!(
+                        "filters of rule {} not translated: {}",
+                        rule_idx + 1,
+                        filters.join(", ")
+                    ),
+                );
+            }
+            let mut targets = Vec::new();
+            for backend in rule
+                .get("backendRefs")
+                .and_then(|b| b.as_array())
+                .into_iter()
+                .flatten()
+            {
+                let name = backend.get("name").and_then(|n| n.as_str()).unwrap_or("");
+                let kind = backend
+                    .get("kind")
+                    .and_then(|k| k.as_str())
+                    .unwrap_or("Service");
+                let namespace = backend
+                    .get("namespace")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or(&manifest.namespace);
+                let weight = backend.get("weight").and_then(|w| w.as_u64()).unwrap_or(1);
+                match (kind, backend.get("port").and_then(|p| p.as_u64())) {
+                    (_, _) if weight == 0 => (),
+                    ("Service", Some(port)) => {
+                        targets.push(Self::target(name, namespace, port, weight))
+                    }
+                    ("Service", None) => manifest.issue(
+                        translation,
+                        format!("backend {} has no port, it is not translated", name),
+                    ),
+                    (kind, _) => manifest.issue(
+                        translation,
+                        format!(
+                            "backend {} {} is not a service, it is not translated",
+                            kind, name
+                        ),
+                    ),
+                }
+            }
+            if targets.is_empty() {
+                manifest.issue(
+                    translation,
+                    format!(
+                        "rule {} has no service backend, it is skipped",
+                        rule_idx + 1
+                    ),
+                );
+                continue;
+            }
+            let matches = match rule.get("matches").and_then(|m| m.as_array()) {
+                Some(matches) if !matches.is_empty() => matches.clone(),
+                _ => vec![serde_json::json!({})],
+            };
+            for matching in matches.iter() {
+                if let Some(conditions) =
+                    Self::http_match(&hostnames, matching, manifest, translation)
+                {
+                    idx += 1;
+                    translation
+                        .routes
+                        .push(manifest.route(idx, conditions, targets.clone()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(content: &str) -> K8sTranslation {
+        let mut translation = K8sTranslation::default();
+        let manifests = K8sHelper::manifests(content, false, "test.yaml").unwrap();
+        K8sHelper::translate(&manifests, "test.yaml", &mut translation);
+        translation
+    }
+
+    #[test]
+    fn test_ingress() {
+        let translation = translate(
+            r#"
+apiVersion: networking.k8s.io/v1
+kind: Ingress
+metadata:
+  name: web
+  namespace: shop
+  annotations:
+    nginx.ingress.kubernetes.io/rewrite-target: /
+spec:
+  rules:
+    - host: shop.example.com
+      http:
+        paths:
+          - path: /api
+            pathType: Exact
+            backend:
+              service:
+                name: api
+                port:
+                  number: 8080
+          - path: /
+            pathType: ImplementationSpecific
+            backend:
+              service:
+                name: front
+                port:
+                  name: http
+---
+apiVersion: v1
+kind: Service
+metadata:
+  name: api
+"#,
+        );
+        assert_eq!(translation.routes.len(), 1);
+        let route = &translation.routes[0];
+        assert_eq!(route["id"], "route_ingress-shop-web-1");
+        assert_eq!(
+            route["frontend"]["domains"],
+            serde_json::json!(["shop.example.com/api"])
+        );
+        assert_eq!(route["frontend"]["exact"], true);
+        assert_eq!(
+            route["backend"]["targets"][0]["hostname"],
+            "api.shop.svc.cluster.local"
+        );
+        assert_eq!(route["backend"]["targets"][0]["port"], 8080);
+        let messages: Vec<&str> = translation
+            .issues
+            .iter()
+            .map(|i| i.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "annotations not translated: nginx.ingress.kubernetes.io/rewrite-target",
+                "path type ImplementationSpecific of shop.example.com/ translated as a prefix match",
+                "the backend of shop.example.com/ uses a named service port, it is not translated",
+            ]
+        );
+        assert_eq!(translation.issues[0].resource, "Ingress shop/web");
+    }
+
+    #[test]
+    fn test_http_route() {
+        let translation = translate(
+            r#"
+apiVersion: gateway.networking.k8s.io/v1
+kind: HTTPRoute
+metadata:
+  name: store
+spec:
+  hostnames: [store.example.com, www.store.example.com]
+  rules:
+    - matches:
+        - path:
+            type: PathPrefix
+            value: /v2
+          headers:
+            - name: x-canary
+              value: "true"
+          method: GET
+        - path:
+            type: RegularExpression
+            value: /v[0-9]+
+      backendRefs:
+        - name: store-v2
+          port: 80
+          weight: 90
+        - name: store-v1
+          namespace: legacy
+          port: 80
+          weight: 10
+        - name: bucket
+          kind: S3Bucket
+"#,
+        );
+        assert_eq!(translation.routes.len(), 1);
+        let route = &translation.routes[0];
+        assert_eq!(route["id"], "route_httproute-default-store-1");
+        assert_eq!(
+            route["frontend"]["domains"],
+            serde_json::json!(["store.example.com/v2", "www.store.example.com/v2"])
+        );
+        assert_eq!(
+            route["frontend"]["headers"],
+            serde_json::json!({"x-canary": "true"})
+        );
+        assert_eq!(route["frontend"]["methods"], serde_json::json!(["GET"]));
+        let targets: Vec<(&str, u64)> = route["backend"]["targets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| {
+                (
+                    t["hostname"].as_str().unwrap(),
+                    t["weight"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                ("store-v2.default.svc.cluster.local", 90),
+                ("store-v1.legacy.svc.cluster.local", 10)
+            ]
+        );
+        assert_eq!(translation.issues.len(), 2);
+        assert!(
+            translation.issues[0]
+                .message
+                .contains("S3Bucket bucket is not a service")
+        );
+        assert!(
+            translation.issues[1]
+                .message
+                .contains("RegularExpression /v[0-9]+ is not translated")
+        );
+    }
+
+    #[test]
+    fn test_http_route_without_hostnames() {
+        let translation = translate(
+            "apiVersion: gateway.networking.k8s.io/v1\nkind: HTTPRoute\nmetadata:\n  name: store\nspec:\n  rules: []\n",
+        );
+        assert!(translation.routes.is_empty());
+        assert_eq!(translation.issues.len(), 1);
+    }
+}
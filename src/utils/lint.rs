@@ -0,0 +1,572 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cli_stdout_printline;
+use crate::utils::entity::EntityHelper;
+
+/// The lint configuration read by default from the current directory
+pub const LINT_CONFIG_FILE: &str = ".otoroshictl-lint.yaml";
+
+/// Apikeys quotas at this value or above are the unlimited defaults of otoroshi
+const UNLIMITED_QUOTA: i64 = 10000000;
+
+/// The plugins considered as authenticating the calls of a route
+const AUTHENTICATION_PLUGINS: &[&str] = &[
+    "ApikeyCalls",
+    "ApikeyAuthModule",
+    "AuthModule",
+    "MultiAuthModule",
+    "BasicAuthWithAuthModule",
+    "SimpleBasicAuth",
+    "JwtVerification",
+    "JwtVerificationOnly",
+    "OIDCJwtVerifier",
+    "NgBiscuitValidator",
+    "NgHasClientCertValidator",
+    "NgHasClientCertMatchingValidator",
+    "NgHasClientCertMatchingApikeyValidator",
+];
+
+/// A rule of `resources lint`
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct LintRule {
+    pub rule: &'static str,
+    pub kinds: &'static str,
+    pub severity: &'static str,
+    pub description: &'static str,
+}
+
+const RULES: &[LintRule] = &[
+    LintRule {
+        rule: "route-without-authentication",
+        kinds: "Route",
+        severity: "error",
+        description: "the route has no enabled authentication plugin",
+    },
+    LintRule {
+        rule: "backend-plain-http",
+        kinds: "Route, Backend",
+        severity: "warning",
+        description: "a target of the backend is called over plain http",
+    },
+    LintRule {
+        rule: "apikey-without-quotas",
+        kinds: "ApiKey",
+        severity: "warning",
+        description: "the apikey keeps the unlimited throttling, daily and monthly quotas",
+    },
+    LintRule {
+        rule: "apikey-without-expiration",
+        kinds: "ApiKey",
+        severity: "warning",
+        description: "the apikey has no valid until date and no rotation",
+    },
+    LintRule {
+        rule: "certificate-expiring",
+        kinds: "Certificate",
+        severity: "warning",
+        description: "the certificate is expired or expires within `days` days (30 by default)",
+    },
+    LintRule {
+        rule: "wildcard-domain",
+        kinds: "Route",
+        severity: "note",
+        description: "the route is exposed on a wildcard domain",
+    },
+];
+
+const SEVERITIES: &[&str] = &["error", "warning", "note"];
+
+/// The settings of a rule in the lint configuration
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct LintRuleConfig {
+    pub enabled: Option<bool>,
+    /// error, warning or note
+    pub severity: Option<String>,
+    /// for `certificate-expiring`, the number of days before expiry
+    pub days: Option<i64>,
+    /// for `route-without-authentication`, more plugins considered as authenticating
+    #[serde(default)]
+    pub plugins: Vec<String>,
+}
+
+/// The content of `.otoroshictl-lint.yaml`
+///
+/// ```yaml
+/// rules:
+///   wildcard-domain:
+///     enabled: false
+///   certificate-expiring:
+///     severity: error
+///     days: 15
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct LintConfig {
+    #[serde(default)]
+    pub rules: BTreeMap<String, LintRuleConfig>,
+}
+
+impl LintConfig {
+    pub fn parse(content: &str) -> Result<LintConfig, String> {
+        let config: LintConfig = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+        for (rule, settings) in config.rules.iter() {
+            if !RULES.iter().any(|r| r.rule == rule) {
+                return Err(format!("unknown rule '{}'", rule));
+            }
+            if let Some(severity) = &settings.severity
+                && !SEVERITIES.contains(&severity.as_str())
+            {
+                return Err(format!(
+                    "unknown severity '{}' for rule '{}' (can be one of: error, warning, note)",
+                    severity, rule
+                ));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Read the given file, or `.otoroshictl-lint.yaml` when it exists. Every rule is enabled
+    /// with its default severity when there is no configuration
+    pub fn load(path: Option<&Path>) -> Result<LintConfig, String> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(LINT_CONFIG_FILE).exists() => Path::new(LINT_CONFIG_FILE),
+            None => return Ok(LintConfig::default()),
+        };
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("error while reading {:?}: {}", path, e))?;
+        Self::parse(&content).map_err(|e| format!("invalid lint configuration {:?}: {}", path, e))
+    }
+
+    fn settings(&self, rule: &str) -> LintRuleConfig {
+        self.rules.get(rule).cloned().unwrap_or_default()
+    }
+
+    /// The severity of the rule, `None` when it is disabled
+    pub fn severity(&self, rule: &LintRule) -> Option<String> {
+        let settings = self.settings(rule.rule);
+        if settings.enabled == Some(false) {
+            return None;
+        }
+        Some(
+            settings
+                .severity
+                .unwrap_or_else(|| rule.severity.to_string()),
+        )
+    }
+}
+
+/// A rule violated by an entity
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LintFinding {
+    pub rule: String,
+    pub severity: String,
+    pub kind: String,
+    pub id: String,
+    pub name: Option<String>,
+    /// the path of the offending field in the entity
+    pub path: String,
+    pub message: String,
+    /// the file and line of the entity, for local files
+    pub source: Option<String>,
+    pub line: Option<usize>,
+}
+
+pub struct LintHelper {}
+
+impl LintHelper {
+    pub fn rules() -> &'static [LintRule] {
+        RULES
+    }
+
+    fn kind(kind: &str) -> &str {
+        kind.rsplit('/').next().unwrap_or(kind)
+    }
+
+    fn targets(backend: Option<&serde_json::Value>, prefix: &str) -> Vec<(String, String)> {
+        backend
+            .and_then(|b| b.get("targets"))
+            .and_then(|t| t.as_array())
+            .map(|targets| {
+                targets
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, target)| target.get("tls").and_then(|t| t.as_bool()) != Some(true))
+                    .map(|(idx, target)| {
+                        let host = target
+                            .get("hostname")
+                            .and_then(|h| h.as_str())
+                            .unwrap_or_default();
+                        let port = target
+                            .get("port")
+                            .map(|p| p.to_string())
+                            .unwrap_or_default();
+                        (
+                            format!("{}.targets.{}.tls", prefix, idx),
+                            format!("target {}:{} is called over plain http", host, port),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn quota(entity: &serde_json::Value, field: &str) -> i64 {
+        entity
+            .get(field)
+            .and_then(|q| q.as_i64())
+            .unwrap_or(UNLIMITED_QUOTA)
+    }
+
+    /// The path and message of each violation of a rule by an entity, `kind` being lowercase
+    /// as otoroshi spells some kinds differently than the documents (`Apikey` and `ApiKey`), and
+    /// `now` being in milliseconds since epoch
+    fn check(
+        rule: &str,
+        kind: &str,
+        entity: &serde_json::Value,
+        settings: &LintRuleConfig,
+        now: i64,
+    ) -> Vec<(String, String)> {
+        match (rule, kind) {
+            ("route-without-authentication", "route") => {
+                let authenticated = entity
+                    .get("plugins")
+                    .and_then(|p| p.as_array())
+                    .is_some_and(|plugins| {
+                        plugins.iter().any(|plugin| {
+                            let id = plugin
+                                .get("plugin")
+                                .and_then(|p| p.as_str())
+                                .unwrap_or_default();
+                            let short = id.rsplit('.').next().unwrap_or(id);
+                            plugin.get("enabled").and_then(|e| e.as_bool()) != Some(false)
+                                && (AUTHENTICATION_PLUGINS.contains(&short)
+                                    || settings.plugins.iter().any(|p| p == id || p == short))
+                        })
+                    });
+                if authenticated {
+                    vec![]
+                } else {
+                    vec![(
+                        "plugins".to_string(),
+                        "no authentication plugin is enabled".to_string(),
+                    )]
+                }
+            }
+            ("backend-plain-http", "route" | "backend") => {
+                Self::targets(entity.get("backend"), "backend")
+            }
+            ("apikey-without-quotas", "apikey") => {
+                if ["throttlingQuota", "dailyQuota", "monthlyQuota"]
+                    .iter()
+                    .all(|field| Self::quota(entity, field) >= UNLIMITED_QUOTA)
+                {
+                    vec![(
+                        "throttlingQuota".to_string(),
+                        "throttling, daily and monthly quotas are unlimited".to_string(),
+                    )]
+                } else {
+                    vec![]
+                }
+            }
+            ("apikey-without-expiration", "apikey") => {
+                let valid_until = entity.get("validUntil").is_some_and(|v| !v.is_null());
+                let rotation = entity
+                    .get("rotation")
+                    .and_then(|r| r.get("enabled"))
+                    .and_then(|e| e.as_bool())
+                    .unwrap_or(false);
+                if valid_until || rotation {
+                    vec![]
+                } else {
+                    vec![(
+                        "validUntil".to_string(),
+                        "no valid until date and no rotation".to_string(),
+                    )]
+                }
+            }
+            ("certificate-expiring", "certificate") => {
+                let days = settings.days.unwrap_or(30);
+                let date = |to: i64| {
+                    chrono::DateTime::from_timestamp_millis(to)
+                        .map(|date| date.format("%Y-%m-%d").to_string())
+                        .unwrap_or_default()
+                };
+                match entity.get("to").and_then(|t| t.as_i64()) {
+                    Some(to) if to <= now => {
+                        vec![("to".to_string(), format!("expired on {}", date(to)))]
+                    }
+                    Some(to) if to - now < days * 24 * 3600 * 1000 => vec![(
+                        "to".to_string(),
+                        format!("expires on {}, in less than {} days", date(to), days),
+                    )],
+                    _ => vec![],
+                }
+            }
+            ("wildcard-domain", "route") => entity
+                .get("frontend")
+                .and_then(|f| f.get("domains"))
+                .and_then(|d| d.as_array())
+                .map(|domains| {
+                    domains
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(idx, domain)| domain.as_str().map(|d| (idx, d)))
+                        .filter(|(_, domain)| domain.contains('*'))
+                        .map(|(idx, domain)| {
+                            (
+                                format!("frontend.domains.{}", idx),
+                                format!("exposed on the wildcard domain {}", domain),
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            _ => vec![],
+        }
+    }
+
+    /// Run the enabled rules over an entity. The source and line of the findings are left empty
+    pub fn lint(
+        kind: &str,
+        entity: &serde_json::Value,
+        config: &LintConfig,
+        now: i64,
+    ) -> Vec<LintFinding> {
+        let kind = Self::kind(kind);
+        RULES
+            .iter()
+            .filter_map(|rule| config.severity(rule).map(|severity| (rule, severity)))
+            .flat_map(|(rule, severity)| {
+                Self::check(
+                    rule.rule,
+                    &kind.to_lowercase(),
+                    entity,
+                    &config.settings(rule.rule),
+                    now,
+                )
+                .into_iter()
+                .map(move |(path, message)| LintFinding {
+                    rule: rule.rule.to_string(),
+                    severity: severity.clone(),
+                    kind: kind.to_string(),
+                    id: EntityHelper::extract_json_entity_id(entity).unwrap_or_default(),
+                    name: EntityHelper::extract_json_entity_name(entity),
+                    path,
+                    message,
+                    source: None,
+                    line: None,
+                })
+            })
+            .collect()
+    }
+
+    /// The findings as a SARIF 2.1.0 log, so they can be uploaded to code review tools
+    pub fn sarif(findings: &[LintFinding], config: &LintConfig) -> String {
+        let rules: Vec<serde_json::Value> = RULES
+            .iter()
+            .map(|rule| {
+                serde_json::json!({
+                    "id": rule.rule,
+                    "shortDescription": { "text": rule.description },
+                    "defaultConfiguration": {
+                        "enabled": config.severity(rule).is_some(),
+                        "level": config.severity(rule).unwrap_or_else(|| rule.severity.to_string()),
+                    },
+                })
+            })
+            .collect();
+        let results: Vec<serde_json::Value> = findings
+            .iter()
+            .map(|finding| {
+                let location = match &finding.source {
+                    Some(source) => serde_json::json!({
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": source.trim_start_matches("./") },
+                            "region": { "startLine": finding.line.unwrap_or(1) },
+                        },
+                        "logicalLocations": [{ "name": finding.id, "kind": finding.kind }],
+                    }),
+                    None => serde_json::json!({
+                        "logicalLocations": [{ "name": finding.id, "kind": finding.kind }],
+                    }),
+                };
+                serde_json::json!({
+                    "ruleId": finding.rule,
+                    "ruleIndex": RULES.iter().position(|r| r.rule == finding.rule),
+                    "level": finding.severity,
+                    "message": { "text": format!("{} {}: {}", finding.kind, finding.id, finding.message) },
+                    "locations": [location],
+                })
+            })
+            .collect();
+        let log = serde_json::json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "otoroshictl",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": "https://github.com/cloud-apim/otoroshictl",
+                        "rules": rules,
+                    }
+                },
+                "results": results,
+            }],
+        });
+        serde_json::to_string_pretty(&log).unwrap()
+    }
+
+    pub fn display(findings: &[LintFinding]) {
+        for finding in findings.iter() {
+            let location = match (&finding.source, finding.line) {
+                (Some(source), Some(line)) => format!("{}:{}", source, line),
+                (Some(source), None) => source.to_string(),
+                _ => format!("{} {}", finding.kind, finding.id),
+            };
+            cli_stdout_printline!(
+                "{}: {} [{}] {}",
+                location,
+                finding.severity,
+                finding.rule,
+                finding.message
+            );
+        }
+        let count = |severity: &str| findings.iter().filter(|f| f.severity == severity).count();
+        cli_stdout_printline!(
+            "{} errors, {} warnings, {} notes",
+            count("error"),
+            count("warning"),
+            count("note")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000_000;
+    const DAY: i64 = 24 * 3600 * 1000;
+
+    fn rules(findings: &[LintFinding]) -> Vec<&str> {
+        findings.iter().map(|f| f.rule.as_str()).collect()
+    }
+
+    #[test]
+    fn test_route_rules() {
+        let config = LintConfig::default();
+        let route = serde_json::json!({
+            "id": "route_1",
+            "frontend": {"domains": ["api.oto.tools", "*.oto.tools"]},
+            "backend": {"targets": [{"hostname": "a.svc", "port": 443, "tls": true}, {"hostname": "b.svc", "port": 80, "tls": false}]},
+            "plugins": [{"plugin": "cp:otoroshi.next.plugins.ApikeyCalls", "enabled": false}],
+        });
+        let findings = LintHelper::lint("proxy.otoroshi.io/Route", &route, &config, NOW);
+        assert_eq!(
+            rules(&findings),
+            vec![
+                "route-without-authentication",
+                "backend-plain-http",
+                "wildcard-domain"
+            ]
+        );
+        assert_eq!(findings[1].path, "backend.targets.1.tls");
+        assert_eq!(findings[2].path, "frontend.domains.1");
+        assert_eq!(findings[2].severity, "note");
+
+        let secured = serde_json::json!({
+            "id": "route_2",
+            "plugins": [{"plugin": "cp:otoroshi.next.plugins.ApikeyCalls", "enabled": true}],
+        });
+        assert!(LintHelper::lint("Route", &secured, &config, NOW).is_empty());
+    }
+
+    #[test]
+    fn test_apikey_and_certificate_rules() {
+        let config = LintConfig::default();
+        let apikey = serde_json::json!({"clientId": "key_1", "throttlingQuota": 10000000, "validUntil": null});
+        assert_eq!(
+            rules(&LintHelper::lint("ApiKey", &apikey, &config, NOW)),
+            vec!["apikey-without-quotas", "apikey-without-expiration"]
+        );
+        assert_eq!(
+            LintHelper::lint("apim.otoroshi.io/Apikey", &apikey, &config, NOW).len(),
+            2
+        );
+        let limited = serde_json::json!({"clientId": "key_2", "dailyQuota": 1000, "rotation": {"enabled": true}});
+        assert!(LintHelper::lint("ApiKey", &limited, &config, NOW).is_empty());
+
+        let expiring = serde_json::json!({"id": "cert_1", "to": NOW + 10 * DAY});
+        let valid = serde_json::json!({"id": "cert_2", "to": NOW + 60 * DAY});
+        let expired = serde_json::json!({"id": "cert_3", "to": NOW - DAY});
+        assert_eq!(
+            LintHelper::lint("Certificate", &expiring, &config, NOW).len(),
+            1
+        );
+        assert!(LintHelper::lint("Certificate", &valid, &config, NOW).is_empty());
+        assert!(
+            LintHelper::lint("Certificate", &expired, &config, NOW)[0]
+                .message
+                .starts_with("expired on")
+        );
+    }
+
+    #[test]
+    fn test_config() {
+        let config = LintConfig::parse(
+            "rules:\n  wildcard-domain:\n    enabled: false\n  certificate-expiring:\n    severity: error\n    days: 90\n  route-without-authentication:\n    plugins: [MyAuthPlugin]\n",
+        )
+        .unwrap();
+        let route = serde_json::json!({
+            "id": "route_1",
+            "frontend": {"domains": ["*.oto.tools"]},
+            "plugins": [{"plugin": "cp:my.company.MyAuthPlugin"}],
+        });
+        assert!(LintHelper::lint("Route", &route, &config, NOW).is_empty());
+        let certificate = serde_json::json!({"id": "cert_1", "to": NOW + 60 * DAY});
+        let findings = LintHelper::lint("Certificate", &certificate, &config, NOW);
+        assert_eq!(findings[0].severity, "error");
+
+        assert!(LintConfig::parse("rules:\n  unknown-rule: {}\n").is_err());
+        assert!(LintConfig::parse("rules:\n  wildcard-domain:\n    severity: fatal\n").is_err());
+    }
+
+    #[test]
+    fn test_sarif() {
+        let config = LintConfig::default();
+        let mut findings = LintHelper::lint(
+            "Route",
+            &serde_json::json!({"id": "route_1", "frontend": {"domains": ["*.oto.tools"]}}),
+            &config,
+            NOW,
+        );
+        findings[0].source = Some("./entities/route.yaml".to_string());
+        findings[0].line = Some(3);
+        let sarif: serde_json::Value =
+            serde_json::from_str(&LintHelper::sarif(&findings, &config)).unwrap();
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(result["ruleId"], "route-without-authentication");
+        assert_eq!(result["level"], "error");
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "entities/route.yaml"
+        );
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["region"]["startLine"],
+            3
+        );
+        assert_eq!(
+            sarif["runs"][0]["tool"]["driver"]["rules"]
+                .as_array()
+                .unwrap()
+                .len(),
+            RULES.len()
+        );
+    }
+}
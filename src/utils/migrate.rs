@@ -0,0 +1,565 @@
+use serde::{Deserialize, Serialize};
+
+/// Service descriptors otoroshi relies on, never migrated unless asked by id
+pub const INTERNAL_DESCRIPTORS: &[&str] = &["admin-api-service"];
+
+/// The legacy keys whose snake case is not the name used by the new engine
+const RENAMED_KEYS: &[(&str, &str)] = &[
+    ("key_pair_signed", "keypair_signed"),
+    ("include_request_attributes", "include_request_attrs"),
+    ("max_jwt_lifespan_secs", "max_jwt_lifespan_sec"),
+    ("white_list", "allowed_list"),
+    ("black_list", "blocked_list"),
+];
+
+/// What has been migrated from a service descriptor, and the settings without equivalent
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MigrationReport {
+    pub descriptor: String,
+    pub name: String,
+    pub route: String,
+    pub unmigrated: Vec<String>,
+}
+
+/// Offline conversion of legacy service descriptors into new engine routes, the descriptor
+/// settings being mapped to the corresponding plugins
+pub struct MigrateHelper {}
+
+impl MigrateHelper {
+    fn snake_case(key: &str) -> String {
+        let mut snake = String::new();
+        for c in key.chars() {
+            if c.is_ascii_uppercase() {
+                if !snake.is_empty() {
+                    snake.push('_');
+                }
+                snake.push(c.to_ascii_lowercase());
+            } else {
+                snake.push(c);
+            }
+        }
+        RENAMED_KEYS
+            .iter()
+            .find(|(from, _)| *from == snake)
+            .map(|(_, to)| to.to_string())
+            .unwrap_or(snake)
+    }
+
+    /// A legacy configuration with the keys of the new engine, without the `skipped` keys
+    fn snake_keys(value: &serde_json::Value, skipped: &[&str]) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(obj) => serde_json::Value::Object(
+                obj.iter()
+                    .filter(|(key, _)| !skipped.contains(&key.as_str()))
+                    .map(|(key, value)| (Self::snake_case(key), Self::snake_keys(value, &[])))
+                    .collect(),
+            ),
+            serde_json::Value::Array(arr) => {
+                serde_json::Value::Array(arr.iter().map(|v| Self::snake_keys(v, &[])).collect())
+            }
+            value => value.clone(),
+        }
+    }
+
+    fn flag(descriptor: &serde_json::Value, pointer: &str) -> bool {
+        descriptor
+            .pointer(pointer)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
+    fn strings(descriptor: &serde_json::Value, pointer: &str) -> Vec<String> {
+        descriptor
+            .pointer(pointer)
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn non_empty(descriptor: &serde_json::Value, pointer: &str) -> Option<serde_json::Value> {
+        descriptor
+            .pointer(pointer)
+            .filter(|v| match v {
+                serde_json::Value::Object(obj) => !obj.is_empty(),
+                serde_json::Value::Array(arr) => !arr.is_empty(),
+                serde_json::Value::String(s) => !s.is_empty(),
+                serde_json::Value::Null => false,
+                _ => true,
+            })
+            .cloned()
+    }
+
+    /// The hosts of a descriptor: the one made of its subdomain, env and domain, then its
+    /// additional hosts
+    pub fn hosts(descriptor: &serde_json::Value) -> Vec<String> {
+        let field = |name: &str| {
+            descriptor
+                .get(name)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        let (subdomain, env, domain) = (field("subdomain"), field("env"), field("domain"));
+        let mut hosts = Vec::new();
+        if !domain.is_empty() {
+            let parts: Vec<&str> = [subdomain.as_str(), env.as_str(), domain.as_str()]
+                .into_iter()
+                .enumerate()
+                .filter(|(idx, part)| !part.is_empty() && (*idx != 1 || *part != "prod"))
+                .map(|(_, part)| part)
+                .collect();
+            hosts.push(parts.join("."));
+        }
+        for host in Self::strings(descriptor, "/hosts") {
+            if !hosts.contains(&host) {
+                hosts.push(host);
+            }
+        }
+        hosts
+    }
+
+    fn target(target: &serde_json::Value) -> serde_json::Value {
+        let host = target.get("host").and_then(|h| h.as_str()).unwrap_or("");
+        let tls = target.get("scheme").and_then(|s| s.as_str()) == Some("https");
+        let (hostname, port) = match host.rsplit_once(':') {
+            Some((hostname, port)) if port.parse::<u16>().is_ok() => {
+                (hostname, port.parse::<u16>().unwrap())
+            }
+            _ => (host, if tls { 443 } else { 80 }),
+        };
+        let mut ng = serde_json::json!({
+            "id": format!("{}:{}", hostname, port),
+            "hostname": hostname,
+            "port": port,
+            "tls": tls,
+            "weight": target.get("weight").cloned().unwrap_or(serde_json::json!(1)),
+        });
+        for (legacy, field) in [
+            ("protocol", "protocol"),
+            ("predicate", "predicate"),
+            ("ipAddress", "ip_address"),
+        ] {
+            if let Some(value) = Self::non_empty(target, &format!("/{}", legacy)) {
+                ng[field] = value;
+            }
+        }
+        if let Some(mtls) = target.get("mtlsConfig").and_then(|m| m.as_object()) {
+            let value = |key: &str| mtls.get(key).cloned().unwrap_or_default();
+            ng["tls_config"] = serde_json::json!({
+                "certs": mtls.get("certs").cloned().unwrap_or(serde_json::json!([])),
+                "trusted_certs": mtls.get("trustedCerts").cloned().unwrap_or(serde_json::json!([])),
+                "enabled": value("mtls").as_bool().unwrap_or(false),
+                "loose": value("loose").as_bool().unwrap_or(false),
+                "trust_all": value("trustAll").as_bool().unwrap_or(false),
+            });
+        }
+        ng
+    }
+
+    fn plugin(
+        name: &str,
+        config: serde_json::Value,
+        include: Vec<String>,
+        exclude: Vec<String>,
+    ) -> serde_json::Value {
+        serde_json::json!({
+            "enabled": true,
+            "debug": false,
+            "plugin": format!("cp:otoroshi.next.plugins.{}", name),
+            "include": include,
+            "exclude": exclude,
+            "config": config,
+            "bound_listeners": [],
+            "plugin_index": {}
+        })
+    }
+
+    /// The plugin protecting the non public paths: the auth module of private apps, the
+    /// apikeys otherwise. Legacy patterns are regexes on the path, like plugin include/exclude
+    fn access_plugin(
+        descriptor: &serde_json::Value,
+        unmigrated: &mut Vec<String>,
+    ) -> Option<serde_json::Value> {
+        let public = Self::strings(descriptor, "/publicPatterns");
+        let private = Self::strings(descriptor, "/privatePatterns");
+        let all_public = public.iter().any(|p| p == "/.*" || p == ".*");
+        if all_public && private.is_empty() {
+            return None;
+        }
+        let (include, exclude) = if all_public {
+            (private, Vec::new())
+        } else {
+            let mut exclude = public;
+            exclude.extend(Self::strings(descriptor, "/securityExcludedPatterns"));
+            (Vec::new(), exclude)
+        };
+        if Self::flag(descriptor, "/privateApp") {
+            return match Self::non_empty(descriptor, "/authConfigRef") {
+                Some(auth_module) => Some(Self::plugin(
+                    "AuthModule",
+                    serde_json::json!({"auth_module": auth_module, "pass_with_apikey": false}),
+                    include,
+                    exclude,
+                )),
+                None => {
+                    unmigrated.push("privateApp without authConfigRef".to_string());
+                    None
+                }
+            };
+        }
+        let constraints = descriptor
+            .get("apiKeyConstraints")
+            .cloned()
+            .unwrap_or(serde_json::json!({}));
+        let extractor = |key: &str| {
+            Self::snake_keys(constraints.get(key).unwrap_or(&serde_json::json!({})), &[])
+        };
+        Some(Self::plugin(
+            "ApikeyCalls",
+            serde_json::json!({
+                "extractors": {
+                    "basic": extractor("basicAuth"),
+                    "custom_headers": extractor("customHeadersAuth"),
+                    "client_id": extractor("clientIdAuth"),
+                    "jwt": extractor("jwtAuth"),
+                },
+                "routing": extractor("routing"),
+                "validate": true,
+                "mandatory": true,
+                "pass_with_user": false,
+                "wipe_backend_request": true,
+                "update_quotas": true
+            }),
+            include,
+            exclude,
+        ))
+    }
+
+    /// The route equivalent to a service descriptor, and the settings of the descriptor without
+    /// equivalent
+    pub fn route(descriptor: &serde_json::Value) -> (serde_json::Value, Vec<String>) {
+        let mut unmigrated: Vec<String> = Vec::new();
+        let id = descriptor.get("id").and_then(|i| i.as_str()).unwrap_or("");
+        let root = descriptor
+            .get("matchingRoot")
+            .and_then(|r| r.as_str())
+            .unwrap_or("")
+            .trim_end_matches('/');
+        let domains: Vec<String> = Self::hosts(descriptor)
+            .iter()
+            .map(|host| format!("{}{}", host, root))
+            .collect();
+        let mut metadata = descriptor
+            .get("metadata")
+            .cloned()
+            .filter(|m| m.is_object())
+            .unwrap_or(serde_json::json!({}));
+        metadata["migrated_from"] = serde_json::json!(id);
+        let mut route = serde_json::json!({
+            "id": format!("route_{}", id.strip_prefix("service_").unwrap_or(id)),
+            "name": descriptor.get("name").cloned().unwrap_or(serde_json::json!(id)),
+            "description": descriptor.get("description").cloned().unwrap_or(serde_json::json!("")),
+            "tags": descriptor.get("tags").cloned().unwrap_or(serde_json::json!([])),
+            "metadata": metadata,
+            "enabled": descriptor.get("enabled").cloned().unwrap_or(serde_json::json!(true)),
+            "groups": descriptor.get("groups").cloned().unwrap_or(serde_json::json!([])),
+            "frontend": {
+                "domains": domains,
+                "strip_path": Self::flag(descriptor, "/stripPath"),
+                "exact": false,
+                "headers": descriptor.get("matchingHeaders").cloned().unwrap_or(serde_json::json!({})),
+                "query": {},
+                "methods": [],
+            },
+            "backend": {
+                "root": descriptor.get("root").cloned().unwrap_or(serde_json::json!("/")),
+                "rewrite": false,
+                "targets": descriptor
+                    .get("targets")
+                    .and_then(|t| t.as_array())
+                    .map(|targets| targets.iter().map(Self::target).collect::<Vec<_>>())
+                    .unwrap_or_default(),
+            },
+        });
+        if let Some(location) = descriptor.get("_loc") {
+            route["_loc"] = location.clone();
+        }
+        if let Some(load_balancing) = Self::non_empty(descriptor, "/targetsLoadBalancing") {
+            route["backend"]["load_balancing"] = load_balancing;
+        }
+        if let Some(client) = Self::non_empty(descriptor, "/clientConfig") {
+            if client.get("useCircuitBreaker").and_then(|c| c.as_bool()) == Some(false) {
+                unmigrated.push("clientConfig.useCircuitBreaker".to_string());
+            }
+            route["backend"]["client"] = Self::snake_keys(&client, &["useCircuitBreaker"]);
+        }
+        if let Some(health_check) = Self::non_empty(descriptor, "/healthCheck") {
+            route["backend"]["health_check"] = Self::snake_keys(&health_check, &[]);
+        }
+
+        let mut plugins: Vec<serde_json::Value> = Vec::new();
+        let mut add = |name: &str, config: serde_json::Value| {
+            plugins.push(Self::plugin(name, config, Vec::new(), Vec::new()))
+        };
+        for (flag, name) in [
+            ("/maintenanceMode", "MaintenanceMode"),
+            ("/buildMode", "BuildMode"),
+            ("/forceHttps", "ForceHttpsTraffic"),
+            ("/readOnly", "ReadOnlyCalls"),
+            ("/overrideHost", "OverrideHost"),
+            ("/xForwardedHeaders", "XForwardedHeaders"),
+            ("/sendOtoroshiHeadersBack", "SendOtoroshiHeadersBack"),
+        ] {
+            if Self::flag(descriptor, flag) {
+                add(name, serde_json::json!({}));
+            }
+        }
+        if Self::flag(descriptor, "/redirection/enabled") {
+            add(
+                "Redirection",
+                Self::snake_keys(&descriptor["redirection"], &["enabled"]),
+            );
+        }
+        for (field, name) in [
+            ("/whitelist", "IpAddressAllowedList"),
+            ("/blacklist", "IpAddressBlockList"),
+        ] {
+            if let Some(addresses) = Self::non_empty(descriptor, &format!("/ipFiltering{}", field))
+            {
+                add(name, serde_json::json!({ "addresses": addresses }));
+            }
+        }
+        if Self::flag(descriptor, "/cors/enabled") {
+            add("Cors", Self::snake_keys(&descriptor["cors"], &["enabled"]));
+        }
+        if Self::flag(descriptor, "/restrictions/enabled") {
+            add(
+                "RoutingRestrictions",
+                Self::snake_keys(&descriptor["restrictions"], &["enabled"]),
+            );
+        }
+        for (field, name) in [
+            ("/additionalHeaders", "AdditionalHeadersIn"),
+            ("/additionalHeadersOut", "AdditionalHeadersOut"),
+            ("/missingOnlyHeadersIn", "MissingHeadersIn"),
+            ("/missingOnlyHeadersOut", "MissingHeadersOut"),
+            ("/headersVerification", "HeadersValidation"),
+        ] {
+            if let Some(headers) = Self::non_empty(descriptor, field) {
+                add(name, serde_json::json!({ "headers": headers }));
+            }
+        }
+        for (field, name) in [
+            ("/removeHeadersIn", "RemoveHeadersIn"),
+            ("/removeHeadersOut", "RemoveHeadersOut"),
+        ] {
+            if let Some(names) = Self::non_empty(descriptor, field) {
+                add(name, serde_json::json!({ "names": names }));
+            }
+        }
+        if Self::flag(descriptor, "/jwtVerifier/enabled") {
+            match descriptor
+                .pointer("/jwtVerifier/type")
+                .and_then(|t| t.as_str())
+            {
+                Some("ref") => {
+                    let mut verifiers = Self::strings(descriptor, "/jwtVerifier/ids");
+                    if let Some(id) = descriptor
+                        .pointer("/jwtVerifier/id")
+                        .and_then(|i| i.as_str())
+                    {
+                        verifiers.push(id.to_string());
+                    }
+                    plugins.push(Self::plugin(
+                        "JwtVerification",
+                        serde_json::json!({ "verifiers": verifiers }),
+                        Vec::new(),
+                        Self::strings(descriptor, "/jwtVerifier/excludedPatterns"),
+                    ));
+                }
+                _ => unmigrated.push("jwtVerifier (inline verifier)".to_string()),
+            }
+        }
+        if let Some(plugin) = Self::access_plugin(descriptor, &mut unmigrated) {
+            plugins.push(plugin);
+        }
+        if Self::flag(descriptor, "/enforceSecureCommunication") {
+            if descriptor
+                .get("sendStateChallenge")
+                .and_then(|s| s.as_bool())
+                != Some(false)
+            {
+                plugins.push(Self::plugin(
+                    "OtoroshiChallenge",
+                    serde_json::json!({}),
+                    Vec::new(),
+                    Vec::new(),
+                ));
+            }
+            if descriptor.get("sendInfoToken").and_then(|s| s.as_bool()) != Some(false) {
+                plugins.push(Self::plugin(
+                    "OtoroshiInfos",
+                    serde_json::json!({}),
+                    Vec::new(),
+                    Vec::new(),
+                ));
+            }
+            unmigrated.push(
+                "secComSettings (OtoroshiChallenge and OtoroshiInfos use their default settings)"
+                    .to_string(),
+            );
+        }
+        if Self::flag(descriptor, "/gzip/enabled") {
+            plugins.push(Self::plugin(
+                "GzipResponseCompressor",
+                Self::snake_keys(&descriptor["gzip"], &["enabled"]),
+                Vec::new(),
+                Vec::new(),
+            ));
+        }
+        route["plugins"] = serde_json::Value::Array(plugins);
+
+        for (pointer, setting) in [
+            ("/paths", "paths"),
+            ("/canary/enabled", "canary"),
+            ("/chaosConfig/enabled", "chaosConfig"),
+            ("/plugins/refs", "plugins (legacy plugins)"),
+            ("/transformerRefs", "transformerRefs"),
+            ("/accessValidator/enabled", "accessValidator"),
+            ("/preRouting/enabled", "preRouting"),
+            ("/clientValidatorRef", "clientValidatorRef"),
+            ("/tcpUdpTunneling", "tcpUdpTunneling"),
+            ("/letsEncrypt", "letsEncrypt"),
+            ("/issueCert", "issueCert"),
+            ("/redirectToLocal", "redirectToLocal"),
+            ("/api/exposeApi", "api"),
+            ("/detectApiKeySooner", "detectApiKeySooner"),
+        ] {
+            if Self::non_empty(descriptor, pointer).is_some_and(|v| v != serde_json::json!(false)) {
+                unmigrated.push(setting.to_string());
+            }
+        }
+        (route, unmigrated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn descriptor() -> serde_json::Value {
+        serde_json::json!({
+            "id": "service_shop",
+            "name": "shop",
+            "env": "prod",
+            "domain": "oto.tools",
+            "subdomain": "shop",
+            "hosts": ["shop.example.com"],
+            "matchingRoot": "/api",
+            "stripPath": true,
+            "root": "/v1",
+            "enabled": true,
+            "targets": [
+                {"host": "shop-1.svc:8080", "scheme": "http", "weight": 2, "predicate": {"type": "AlwaysMatch"}},
+                {"host": "shop-2.svc", "scheme": "https", "weight": 1}
+            ],
+            "targetsLoadBalancing": {"type": "RoundRobin"},
+            "clientConfig": {"useCircuitBreaker": true, "retries": 1, "callTimeout": 30000},
+            "publicPatterns": ["/health"],
+            "privatePatterns": [],
+            "apiKeyConstraints": {
+                "basicAuth": {"enabled": true, "headerName": null, "queryName": null},
+                "jwtAuth": {"enabled": false, "keyPairSigned": true, "maxJwtLifespanSecs": null}
+            },
+            "additionalHeaders": {"x-env": "prod"},
+            "removeHeadersIn": [],
+            "cors": {"enabled": true, "allowOrigin": "*", "maxAge": null},
+            "enforceSecureCommunication": false,
+            "overrideHost": true,
+            "canary": {"enabled": true, "traffic": 0.2},
+            "paths": []
+        })
+    }
+
+    #[test]
+    fn test_hosts() {
+        assert_eq!(
+            MigrateHelper::hosts(&descriptor()),
+            vec!["shop.oto.tools", "shop.example.com"]
+        );
+        assert_eq!(
+            MigrateHelper::hosts(
+                &serde_json::json!({"env": "dev", "domain": "oto.tools", "subdomain": "shop"})
+            ),
+            vec!["shop.dev.oto.tools"]
+        );
+    }
+
+    #[test]
+    fn test_route() {
+        let (route, unmigrated) = MigrateHelper::route(&descriptor());
+        assert_eq!(route["id"], "route_shop");
+        assert_eq!(
+            route["frontend"]["domains"],
+            serde_json::json!(["shop.oto.tools/api", "shop.example.com/api"])
+        );
+        assert_eq!(route["frontend"]["strip_path"], true);
+        assert_eq!(route["backend"]["root"], "/v1");
+        assert_eq!(
+            route["backend"]["targets"],
+            serde_json::json!([
+                {"id": "shop-1.svc:8080", "hostname": "shop-1.svc", "port": 8080, "tls": false, "weight": 2, "predicate": {"type": "AlwaysMatch"}},
+                {"id": "shop-2.svc:443", "hostname": "shop-2.svc", "port": 443, "tls": true, "weight": 1}
+            ])
+        );
+        assert_eq!(
+            route["backend"]["client"],
+            serde_json::json!({"retries": 1, "call_timeout": 30000})
+        );
+        let plugins: Vec<&str> = route["plugins"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["plugin"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            plugins,
+            vec![
+                "cp:otoroshi.next.plugins.OverrideHost",
+                "cp:otoroshi.next.plugins.Cors",
+                "cp:otoroshi.next.plugins.AdditionalHeadersIn",
+                "cp:otoroshi.next.plugins.ApikeyCalls"
+            ]
+        );
+        let apikeys = &route["plugins"][3];
+        assert_eq!(apikeys["exclude"], serde_json::json!(["/health"]));
+        assert_eq!(
+            apikeys["config"]["extractors"]["jwt"],
+            serde_json::json!({"enabled": false, "keypair_signed": true, "max_jwt_lifespan_sec": null})
+        );
+        assert_eq!(
+            route["plugins"][1]["config"],
+            serde_json::json!({"allow_origin": "*", "max_age": null})
+        );
+        assert_eq!(unmigrated, vec!["canary"]);
+    }
+
+    #[test]
+    fn test_public_route_has_no_apikey() {
+        let mut descriptor = descriptor();
+        descriptor["publicPatterns"] = serde_json::json!(["/.*"]);
+        descriptor["privatePatterns"] = serde_json::json!(["/admin/.*"]);
+        let (route, _) = MigrateHelper::route(&descriptor);
+        assert_eq!(
+            route["plugins"][3]["include"],
+            serde_json::json!(["/admin/.*"])
+        );
+        descriptor["privatePatterns"] = serde_json::json!([]);
+        let (route, _) = MigrateHelper::route(&descriptor);
+        assert_eq!(route["plugins"].as_array().unwrap().len(), 3);
+    }
+}
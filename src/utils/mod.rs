@@ -1,10 +1,31 @@
 pub mod browser;
+pub mod convert;
+pub mod dependencies;
+pub mod describe;
+pub mod diff;
 pub mod entity;
 pub mod file;
+pub mod history;
 pub mod http;
 pub mod interactive;
+pub mod json_patch;
+pub mod json_path;
+pub mod k8s;
+pub mod lint;
+pub mod migrate;
+pub mod normalize;
+pub mod openapi;
 pub mod otoroshi;
+pub mod overlay;
+pub mod progress;
+pub mod render;
+pub mod report;
+pub mod schema;
+pub mod secret;
+pub mod selector;
 pub mod table;
+pub mod template;
+pub mod watch;
 
 #[macro_use]
 pub mod out;
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use crate::utils::entity::EntityHelper;
+
+/// Fields managed by otoroshi that change without anybody editing the entity
+const SERVER_FIELDS: &[&str] = &[
+    "created_at",
+    "createdAt",
+    "updated_at",
+    "updatedAt",
+    "last_used",
+    "lastUsed",
+    "stats",
+];
+
+/// Fields identifying an entity, kept even when they equal the template
+const IDENTITY_FIELDS: &[&str] = &[
+    "id",
+    "client_id",
+    "clientId",
+    "name",
+    "clientName",
+    "client_name",
+    "username",
+    "otoroshiId",
+    "serviceId",
+    "randomId",
+    "label",
+];
+
+/// Deterministic versions of the exported entities, so exporting an unchanged cluster twice gives
+/// the same files. The keys are sorted by `serde_json` itself
+pub struct NormalizeHelper {}
+
+impl NormalizeHelper {
+    fn default_location() -> serde_json::Value {
+        serde_json::json!({"tenant": "default", "teams": ["default"]})
+    }
+
+    /// Remove the timestamps and stats, in the entity and in its metadata, and the default `_loc`
+    pub fn strip_server_fields(entity: &mut serde_json::Value) {
+        let Some(obj) = entity.as_object_mut() else {
+            return;
+        };
+        for field in SERVER_FIELDS.iter() {
+            obj.remove(*field);
+        }
+        if obj.get("_loc") == Some(&Self::default_location()) {
+            obj.remove("_loc");
+        }
+        if let Some(metadata) = obj.get_mut("metadata").and_then(|m| m.as_object_mut()) {
+            for field in SERVER_FIELDS.iter() {
+                metadata.remove(*field);
+            }
+        }
+    }
+
+    /// Remove the fields whose value equals the one of the template, otoroshi fills them back
+    /// when the entity is applied. Arrays are compared as a whole
+    fn strip_defaults(entity: &mut serde_json::Value, template: &serde_json::Value, top: bool) {
+        let (Some(obj), Some(template)) = (entity.as_object_mut(), template.as_object()) else {
+            return;
+        };
+        obj.retain(|key, value| {
+            (top && IDENTITY_FIELDS.contains(&key.as_str())) || template.get(key) != Some(value)
+        });
+        for (key, value) in obj.iter_mut() {
+            if let Some(default) = template.get(key) {
+                Self::strip_defaults(value, default, false);
+            }
+        }
+    }
+
+    /// Normalize the entities of a kind, sorted by id
+    pub fn entities(
+        entities: Vec<serde_json::Value>,
+        template: Option<&serde_json::Value>,
+    ) -> Vec<serde_json::Value> {
+        let mut entities: Vec<serde_json::Value> = entities
+            .into_iter()
+            .map(|mut entity| {
+                Self::strip_server_fields(&mut entity);
+                if let Some(template) = template {
+                    let mut template = template.clone();
+                    Self::strip_server_fields(&mut template);
+                    Self::strip_defaults(&mut entity, &template, true);
+                }
+                entity
+            })
+            .collect();
+        entities.sort_by_key(EntityHelper::extract_json_entity_id);
+        entities
+    }
+
+    /// A lowercase file name made of the letters and digits of the name, separated by dashes
+    pub fn slug(name: &str) -> String {
+        let mut slug = String::new();
+        for c in name.to_lowercase().chars() {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c);
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        slug.trim_end_matches('-').to_string()
+    }
+
+    /// The file name of each entity, by position: the slug of its name, followed by the slug of
+    /// its id when several entities have the same name. The id alone without usable name
+    pub fn file_names(entities: &[serde_json::Value]) -> Vec<String> {
+        let slugs: Vec<(String, String)> = entities
+            .iter()
+            .map(|entity| {
+                let id =
+                    Self::slug(&EntityHelper::extract_json_entity_id(entity).unwrap_or_default());
+                let name = EntityHelper::extract_json_entity_name(entity)
+                    .map(|name| Self::slug(&name))
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or(id.clone());
+                (name, id)
+            })
+            .collect();
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for (name, _) in slugs.iter() {
+            *counts.entry(name).or_insert(0) += 1;
+        }
+        slugs
+            .iter()
+            .map(|(name, id)| match counts.get(name.as_str()) {
+                Some(count) if *count > 1 && name != id => format!("{}-{}", name, id),
+                _ => name.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_entities() {
+        let template = serde_json::json!({
+            "id": "route_template",
+            "name": "New route",
+            "enabled": true,
+            "capture": false,
+            "_loc": {"tenant": "default", "teams": ["default"]},
+            "frontend": {"domains": ["new-route.oto.tools"], "strip_path": true, "exact": false},
+        });
+        let entities = vec![
+            serde_json::json!({
+                "id": "route_2",
+                "name": "B",
+                "enabled": true,
+                "capture": true,
+                "_loc": {"tenant": "default", "teams": ["default"]},
+                "metadata": {"created_at": "2026-10-18", "team": "a"},
+                "frontend": {"domains": ["b.oto.tools"], "strip_path": true, "exact": false},
+            }),
+            serde_json::json!({
+                "id": "route_1",
+                "name": "New route",
+                "_loc": {"tenant": "prod", "teams": ["default"]},
+                "updated_at": 1760000000000u64,
+            }),
+        ];
+        let normalized = NormalizeHelper::entities(entities, Some(&template));
+        assert_eq!(
+            normalized,
+            vec![
+                serde_json::json!({
+                    "id": "route_1",
+                    "name": "New route",
+                    "_loc": {"tenant": "prod", "teams": ["default"]},
+                }),
+                serde_json::json!({
+                    "id": "route_2",
+                    "name": "B",
+                    "capture": true,
+                    "metadata": {"team": "a"},
+                    "frontend": {"domains": ["b.oto.tools"]},
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_names() {
+        assert_eq!(
+            NormalizeHelper::slug("My API (v2) -- prod"),
+            "my-api-v2-prod"
+        );
+        let entities = vec![
+            serde_json::json!({"id": "route_1", "name": "Api"}),
+            serde_json::json!({"id": "route_2", "name": "api"}),
+            serde_json::json!({"id": "route_3", "name": "Admin"}),
+            serde_json::json!({"id": "route_4", "name": "!!"}),
+        ];
+        assert_eq!(
+            NormalizeHelper::file_names(&entities),
+            vec!["api-route-1", "api-route-2", "admin", "route-4"]
+        );
+    }
+}
@@ -0,0 +1,657 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::utils::normalize::NormalizeHelper;
+
+/// The operations of an OpenAPI path item
+const HTTP_METHODS: &[&str] = &[
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+const APIKEY_PLUGIN: &str = "cp:otoroshi.next.plugins.ApikeyCalls";
+const JWT_PLUGIN: &str = "cp:otoroshi.next.plugins.JwtVerificationOnly";
+
+/// How the operations of an OpenAPI document are spread over the generated routes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OpenApiGroupBy {
+    /// one route for the whole document
+    Document,
+    /// one route per tag, the first tag of each operation
+    Tag,
+    /// one route per first segment of the paths
+    Path,
+}
+
+impl OpenApiGroupBy {
+    pub fn parse(group_by: Option<&str>) -> Result<OpenApiGroupBy, String> {
+        match group_by {
+            None => Ok(OpenApiGroupBy::Document),
+            Some("tag") => Ok(OpenApiGroupBy::Tag),
+            Some("path") => Ok(OpenApiGroupBy::Path),
+            Some(other) => Err(format!(
+                "unknown grouping '{}' (can be one of: tag, path)",
+                other
+            )),
+        }
+    }
+}
+
+/// The operations of a generated route
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OpenApiGroup {
+    /// the static prefixes of the paths, up to their first parameter
+    pub prefixes: BTreeSet<String>,
+    pub methods: BTreeSet<String>,
+    /// the authentication plugins required by every operation of the route, none when public
+    pub plugins: BTreeSet<String>,
+}
+
+/// The routes generated from a document, and what could not be translated exactly
+#[derive(Clone, Debug, Default)]
+pub struct OpenApiRoutes {
+    pub routes: Vec<serde_json::Value>,
+    pub issues: Vec<String>,
+}
+
+/// Where the generated routes send their requests
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpenApiBackend {
+    pub hostname: String,
+    pub port: u16,
+    pub tls: bool,
+    pub root: String,
+}
+
+impl OpenApiBackend {
+    pub fn parse(url: &str) -> Result<OpenApiBackend, String> {
+        let parsed =
+            url::Url::parse(url).map_err(|e| format!("invalid backend url '{}': {}", url, e))?;
+        let hostname = parsed
+            .host_str()
+            .ok_or_else(|| format!("invalid backend url '{}': missing host", url))?;
+        Ok(OpenApiBackend {
+            hostname: hostname.to_string(),
+            port: parsed.port_or_known_default().unwrap_or(80),
+            tls: parsed.scheme() == "https",
+            root: parsed.path().to_string(),
+        })
+    }
+}
+
+/// Generation of otoroshi routes from the paths of an OpenAPI 3 document
+pub struct OpenApiHelper {}
+
+impl OpenApiHelper {
+    /// Parse a json or yaml OpenAPI 3 document
+    pub fn parse(content: &str, source: &str) -> Result<serde_json::Value, String> {
+        let spec: serde_json::Value = serde_yaml::from_str(content)
+            .map_err(|e| format!("error while parsing {}: {}", source, e))?;
+        match spec.get("openapi").and_then(|v| v.as_str()) {
+            Some(version) if version.starts_with('3') => Ok(spec),
+            _ => Err(format!("{} is not an OpenAPI 3 document", source)),
+        }
+    }
+
+    /// The part of a path before its first parameter, `/pets/{id}/toys` giving `/pets`
+    fn prefix(path: &str) -> String {
+        let segments: Vec<&str> = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .take_while(|s| !s.contains('{'))
+            .collect();
+        format!("/{}", segments.join("/"))
+    }
+
+    /// The authentication plugin enforcing a security scheme of the document: apikeys for
+    /// `apiKey` and `http` schemes, jwt verification for jwt bearers, `oauth2` and `openIdConnect`
+    fn scheme_plugin(spec: &serde_json::Value, name: &str) -> Option<&'static str> {
+        let scheme = spec.pointer(&format!("/components/securitySchemes/{}", name))?;
+        let bearer_format = scheme
+            .get("bearerFormat")
+            .and_then(|f| f.as_str())
+            .unwrap_or("");
+        match scheme.get("type").and_then(|t| t.as_str()).unwrap_or("") {
+            "apiKey" => Some(APIKEY_PLUGIN),
+            "http" if bearer_format.eq_ignore_ascii_case("jwt") => Some(JWT_PLUGIN),
+            "http" => Some(APIKEY_PLUGIN),
+            "oauth2" | "openIdConnect" => Some(JWT_PLUGIN),
+            _ => None,
+        }
+    }
+
+    /// The plugins required by an operation, from its security requirements or the ones of the
+    /// document. Requirements are alternatives: an empty one makes the operation public,
+    /// otherwise the first one the plugins can enforce is required. Returns why the operation
+    /// is not translated exactly, when it is not
+    fn requirement(
+        spec: &serde_json::Value,
+        operation: &serde_json::Value,
+    ) -> (BTreeSet<String>, Option<String>) {
+        let alternatives: Vec<&serde_json::Map<String, serde_json::Value>> = operation
+            .get("security")
+            .or_else(|| spec.get("security"))
+            .and_then(|s| s.as_array())
+            .map(|requirements| requirements.iter().filter_map(|r| r.as_object()).collect())
+            .unwrap_or_default();
+        if alternatives.is_empty() || alternatives.iter().any(|a| a.is_empty()) {
+            return (BTreeSet::new(), None);
+        }
+        let enforced: Vec<BTreeSet<String>> = alternatives
+            .iter()
+            .map(|alternative| {
+                alternative
+                    .keys()
+                    .filter_map(|name| Self::scheme_plugin(spec, name))
+                    .map(String::from)
+                    .collect::<BTreeSet<String>>()
+            })
+            .filter(|plugins| !plugins.is_empty())
+            .collect();
+        match enforced.first() {
+            None => (
+                BTreeSet::new(),
+                Some(
+                    "no security scheme can be enforced by a plugin, the route is public"
+                        .to_string(),
+                ),
+            ),
+            Some(first) if enforced.iter().any(|plugins| plugins != first) => (
+                first.clone(),
+                Some(format!(
+                    "several security alternatives, the route only accepts {}",
+                    Self::label(first)
+                )),
+            ),
+            Some(first) => (first.clone(), None),
+        }
+    }
+
+    /// A short name for the plugins of a route: `apikey`, `jwt`, `apikey-jwt` or `public`
+    fn label(plugins: &BTreeSet<String>) -> String {
+        if plugins.is_empty() {
+            return "public".to_string();
+        }
+        plugins
+            .iter()
+            .map(|plugin| {
+                if plugin == APIKEY_PLUGIN {
+                    "apikey"
+                } else {
+                    "jwt"
+                }
+            })
+            .collect::<Vec<&str>>()
+            .join("-")
+    }
+
+    /// The operations of the document grouped by route, by group name. With `security`, the
+    /// operations of a group are split by required plugins, the group name being followed by
+    /// their label when there are several. Operations sharing a prefix and a method can't be
+    /// told apart by a route, so they all get the first authenticated requirement among them
+    pub fn groups(
+        spec: &serde_json::Value,
+        group_by: OpenApiGroupBy,
+        security: bool,
+        issues: &mut Vec<String>,
+    ) -> BTreeMap<String, OpenApiGroup> {
+        let mut groups: BTreeMap<String, OpenApiGroup> = BTreeMap::new();
+        let Some(paths) = spec.get("paths").and_then(|p| p.as_object()) else {
+            return groups;
+        };
+        // name, prefix, method, path and plugins of each operation
+        let mut operations: Vec<(String, String, String, String, BTreeSet<String>)> = Vec::new();
+        for (path, item) in paths.iter() {
+            let prefix = Self::prefix(path);
+            for method in HTTP_METHODS.iter() {
+                let Some(operation) = item.get(*method) else {
+                    continue;
+                };
+                let name = match group_by {
+                    OpenApiGroupBy::Document => String::new(),
+                    OpenApiGroupBy::Tag => operation
+                        .get("tags")
+                        .and_then(|t| t.get(0))
+                        .and_then(|t| t.as_str())
+                        .unwrap_or("default")
+                        .to_string(),
+                    OpenApiGroupBy::Path => {
+                        match prefix.trim_start_matches('/').split('/').next() {
+                            Some(segment) if !segment.is_empty() => segment.to_string(),
+                            _ => "root".to_string(),
+                        }
+                    }
+                };
+                let method = method.to_uppercase();
+                let plugins = if security {
+                    let (plugins, issue) = Self::requirement(spec, operation);
+                    if let Some(issue) = issue {
+                        issues.push(format!("{} {}: {}", method, path, issue));
+                    }
+                    plugins
+                } else {
+                    BTreeSet::new()
+                };
+                operations.push((name, prefix.clone(), method, path.clone(), plugins));
+            }
+        }
+        let mut shared: BTreeMap<(&str, &str, &str), &BTreeSet<String>> = BTreeMap::new();
+        for (name, prefix, method, path, plugins) in operations.iter() {
+            let key = (name.as_str(), prefix.as_str(), method.as_str());
+            match shared.get(&key) {
+                None => {
+                    shared.insert(key, plugins);
+                }
+                Some(existing) if *existing == plugins => (),
+                Some(existing) => {
+                    if existing.is_empty() {
+                        shared.insert(key, plugins);
+                    }
+                    issues.push(format!(
+                        "{} {}: shares the {} prefix with operations having other security requirements, the route only accepts {}",
+                        method,
+                        path,
+                        prefix,
+                        Self::label(shared[&key])
+                    ));
+                }
+            }
+        }
+        let mut labels: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+        for (key, plugins) in shared.iter() {
+            labels
+                .entry(key.0)
+                .or_default()
+                .insert(Self::label(plugins));
+        }
+        for (name, prefix, method, _, _) in operations.iter() {
+            let plugins = shared[&(name.as_str(), prefix.as_str(), method.as_str())];
+            let group_name = if labels[name.as_str()].len() > 1 {
+                format!("{} {}", name, Self::label(plugins))
+                    .trim()
+                    .to_string()
+            } else {
+                name.clone()
+            };
+            let group = groups.entry(group_name).or_default();
+            group.prefixes.insert(prefix.clone());
+            group.methods.insert(method.clone());
+            group.plugins = plugins.clone();
+        }
+        groups
+    }
+
+    fn plugin(id: &str, config: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "enabled": true,
+            "debug": false,
+            "plugin": id,
+            "include": [],
+            "exclude": [],
+            "config": config,
+            "bound_listeners": [],
+            "plugin_index": {}
+        })
+    }
+
+    /// The configured authentication plugins of a route. The jwt verification plugins use
+    /// `jwt_verifier`, they reject every call until a verifier is set
+    pub fn security_plugins(
+        plugins: &BTreeSet<String>,
+        jwt_verifier: Option<&str>,
+    ) -> Vec<serde_json::Value> {
+        plugins
+            .iter()
+            .map(|id| match id.as_str() {
+                APIKEY_PLUGIN => Self::plugin(
+                    id,
+                    serde_json::json!({
+                        "validate": true,
+                        "mandatory": true,
+                        "update_quotas": true,
+                        "wipe_backend_request": true
+                    }),
+                ),
+                _ => Self::plugin(
+                    id,
+                    serde_json::json!({"verifier": jwt_verifier, "fail_if_absent": true}),
+                ),
+            })
+            .collect()
+    }
+
+    /// Does the route verify jwt tokens without a verifier
+    pub fn missing_jwt_verifier(route: &serde_json::Value) -> bool {
+        route
+            .get("plugins")
+            .and_then(|p| p.as_array())
+            .is_some_and(|plugins| {
+                plugins.iter().any(|plugin| {
+                    plugin.get("plugin").and_then(|p| p.as_str()) == Some(JWT_PLUGIN)
+                        && plugin
+                            .pointer("/config/verifier")
+                            .is_none_or(|v| v.is_null())
+                })
+            })
+    }
+
+    /// A route, built on the route template, serving the operations of a group
+    fn route(
+        template: &serde_json::Value,
+        spec: &serde_json::Value,
+        name: &str,
+        group: &OpenApiGroup,
+        domain: &str,
+        backend: &OpenApiBackend,
+        security_plugins: Vec<serde_json::Value>,
+    ) -> serde_json::Value {
+        let title = spec
+            .pointer("/info/title")
+            .and_then(|t| t.as_str())
+            .unwrap_or("openapi");
+        let version = spec
+            .pointer("/info/version")
+            .and_then(|t| t.as_str())
+            .unwrap_or("");
+        let (route_name, slug) = if name.is_empty() {
+            (title.to_string(), NormalizeHelper::slug(title))
+        } else {
+            (
+                format!("{} - {}", title, name),
+                NormalizeHelper::slug(&format!("{} {}", title, name)),
+            )
+        };
+        let domain = domain.trim_end_matches('/');
+        let mut route = template.clone();
+        route["id"] = serde_json::json!(format!("route_{}", slug));
+        route["name"] = serde_json::json!(route_name);
+        route["description"] = serde_json::json!(
+            format!("Generated from the OpenAPI document {} {}", title, version).trim_end()
+        );
+        route["frontend"]["domains"] = group
+            .prefixes
+            .iter()
+            .map(|prefix| match prefix.as_str() {
+                "/" => domain.to_string(),
+                prefix => format!("{}{}", domain, prefix),
+            })
+            .collect();
+        route["frontend"]["methods"] = group.methods.iter().cloned().collect();
+        route["frontend"]["strip_path"] = serde_json::json!(false);
+        route["frontend"]["exact"] = serde_json::json!(false);
+        let mut target = route
+            .pointer("/backend/targets/0")
+            .cloned()
+            .unwrap_or(serde_json::json!({"weight": 1}));
+        target["id"] = serde_json::json!(format!("{}:{}", backend.hostname, backend.port));
+        target["hostname"] = serde_json::json!(backend.hostname);
+        target["port"] = serde_json::json!(backend.port);
+        target["tls"] = serde_json::json!(backend.tls);
+        route["backend"]["targets"] = serde_json::json!([target]);
+        route["backend"]["root"] = serde_json::json!(backend.root);
+        if !security_plugins.is_empty() {
+            let mut plugins = route
+                .get("plugins")
+                .and_then(|p| p.as_array())
+                .cloned()
+                .unwrap_or_default();
+            plugins.extend(security_plugins);
+            route["plugins"] = serde_json::Value::Array(plugins);
+        }
+        route
+    }
+
+    /// The routes serving the operations of the document, one per group. With `security`, the
+    /// routes get the authentication plugins required by their operations
+    pub fn routes(
+        spec: &serde_json::Value,
+        template: &serde_json::Value,
+        domain: &str,
+        backend: &OpenApiBackend,
+        group_by: OpenApiGroupBy,
+        security: bool,
+        jwt_verifier: Option<&str>,
+    ) -> OpenApiRoutes {
+        let mut issues = Vec::new();
+        let routes = Self::groups(spec, group_by, security, &mut issues)
+            .iter()
+            .map(|(name, group)| {
+                let plugins = Self::security_plugins(&group.plugins, jwt_verifier);
+                Self::route(template, spec, name, group, domain, backend, plugins)
+            })
+            .collect();
+        OpenApiRoutes { routes, issues }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = r#"
+openapi: 3.0.3
+info:
+  title: Pet Store
+  version: 1.0.0
+security:
+  - api_key: []
+paths:
+  /pets:
+    get:
+      tags: [pets]
+    post:
+      tags: [pets]
+      security:
+        - bearer: []
+  /pets/{petId}:
+    get:
+      tags: [pets]
+  /store/orders/{orderId}/items:
+    delete:
+      tags: [store]
+      security: []
+components:
+  securitySchemes:
+    api_key:
+      type: apiKey
+      in: header
+      name: X-Api-Key
+    bearer:
+      type: http
+      scheme: bearer
+      bearerFormat: JWT
+"#;
+
+    fn template() -> serde_json::Value {
+        serde_json::json!({
+            "id": "route_random",
+            "name": "New route",
+            "frontend": {"domains": ["new-route.oto.tools"], "strip_path": true, "exact": false},
+            "backend": {"root": "/", "targets": [{"id": "target_1", "hostname": "request.otoroshi.io", "port": 443, "tls": true, "weight": 1}]},
+            "plugins": [{"plugin": "cp:otoroshi.next.plugins.OverrideHost"}]
+        })
+    }
+
+    fn plugins(route: &serde_json::Value) -> Vec<&str> {
+        route["plugins"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["plugin"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_groups() {
+        let spec = OpenApiHelper::parse(SPEC, "spec.yaml").unwrap();
+        let mut issues = Vec::new();
+        let groups = OpenApiHelper::groups(&spec, OpenApiGroupBy::Tag, true, &mut issues);
+        assert!(issues.is_empty());
+        assert_eq!(
+            groups.keys().collect::<Vec<_>>(),
+            vec!["pets apikey", "pets jwt", "store"]
+        );
+        let pets = &groups["pets apikey"];
+        assert_eq!(pets.prefixes.iter().collect::<Vec<_>>(), vec!["/pets"]);
+        assert_eq!(pets.methods.iter().collect::<Vec<_>>(), vec!["GET"]);
+        assert_eq!(pets.plugins.iter().collect::<Vec<_>>(), vec![APIKEY_PLUGIN]);
+        let pets = &groups["pets jwt"];
+        assert_eq!(pets.methods.iter().collect::<Vec<_>>(), vec!["POST"]);
+        assert_eq!(pets.plugins.iter().collect::<Vec<_>>(), vec![JWT_PLUGIN]);
+        let store = &groups["store"];
+        assert_eq!(
+            store.prefixes.iter().collect::<Vec<_>>(),
+            vec!["/store/orders"]
+        );
+        assert!(store.plugins.is_empty());
+        let groups = OpenApiHelper::groups(&spec, OpenApiGroupBy::Tag, false, &mut issues);
+        assert_eq!(groups.keys().collect::<Vec<_>>(), vec!["pets", "store"]);
+        assert_eq!(
+            OpenApiHelper::groups(&spec, OpenApiGroupBy::Document, false, &mut issues).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_groups_security_alternatives() {
+        let spec = serde_json::json!({
+            "openapi": "3.0.3",
+            "paths": {
+                "/pets": {
+                    "get": {"security": [{"bearer": []}, {"api_key": []}]},
+                    "post": {"security": [{"api_key": []}, {}]}
+                },
+                "/pets/{petId}": {"get": {"security": [{"api_key": []}]}},
+                "/store": {"get": {"security": [{"basic": []}]}}
+            },
+            "components": {"securitySchemes": {
+                "api_key": {"type": "apiKey", "in": "header", "name": "X-Api-Key"},
+                "bearer": {"type": "http", "scheme": "bearer", "bearerFormat": "JWT"},
+                "basic": {"type": "mutualTLS"}
+            }}
+        });
+        let mut issues = Vec::new();
+        let groups = OpenApiHelper::groups(&spec, OpenApiGroupBy::Document, true, &mut issues);
+        assert_eq!(groups.keys().collect::<Vec<_>>(), vec!["jwt", "public"]);
+        assert_eq!(
+            groups["jwt"].methods.iter().collect::<Vec<_>>(),
+            vec!["GET"]
+        );
+        assert_eq!(
+            groups["jwt"].prefixes.iter().collect::<Vec<_>>(),
+            vec!["/pets"]
+        );
+        assert_eq!(
+            groups["public"].prefixes.iter().collect::<Vec<_>>(),
+            vec!["/pets", "/store"]
+        );
+        assert_eq!(
+            groups["public"].methods.iter().collect::<Vec<_>>(),
+            vec!["GET", "POST"]
+        );
+        assert_eq!(issues.len(), 3);
+        assert!(issues[0].starts_with("GET /pets: several security alternatives"));
+        assert!(issues[1].starts_with("GET /store: no security scheme"));
+        assert!(issues[2].starts_with("GET /pets/{petId}: shares the /pets prefix"));
+    }
+
+    #[test]
+    fn test_routes() {
+        let spec = OpenApiHelper::parse(SPEC, "spec.yaml").unwrap();
+        let backend = OpenApiBackend::parse("http://pets.svc:8080/api").unwrap();
+        let routes = OpenApiHelper::routes(
+            &spec,
+            &template(),
+            "api.example.com",
+            &backend,
+            OpenApiGroupBy::Document,
+            false,
+            None,
+        );
+        assert!(routes.issues.is_empty());
+        assert_eq!(routes.routes.len(), 1);
+        let route = &routes.routes[0];
+        assert_eq!(route["id"], "route_pet-store");
+        assert_eq!(
+            route["frontend"]["domains"],
+            serde_json::json!(["api.example.com/pets", "api.example.com/store/orders"])
+        );
+        assert_eq!(
+            route["frontend"]["methods"],
+            serde_json::json!(["DELETE", "GET", "POST"])
+        );
+        assert_eq!(route["frontend"]["strip_path"], false);
+        assert_eq!(
+            route["backend"],
+            serde_json::json!({"root": "/api", "targets": [{"id": "pets.svc:8080", "hostname": "pets.svc", "port": 8080, "tls": false, "weight": 1}]})
+        );
+        assert_eq!(
+            plugins(route),
+            vec!["cp:otoroshi.next.plugins.OverrideHost"]
+        );
+    }
+
+    #[test]
+    fn test_routes_security() {
+        let spec = OpenApiHelper::parse(SPEC, "spec.yaml").unwrap();
+        let backend = OpenApiBackend::parse("http://pets.svc:8080").unwrap();
+        let routes = OpenApiHelper::routes(
+            &spec,
+            &template(),
+            "api.example.com",
+            &backend,
+            OpenApiGroupBy::Document,
+            true,
+            None,
+        )
+        .routes;
+        let ids: Vec<&str> = routes.iter().map(|r| r["id"].as_str().unwrap()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "route_pet-store-apikey",
+                "route_pet-store-jwt",
+                "route_pet-store-public"
+            ]
+        );
+        assert_eq!(
+            plugins(&routes[0]),
+            vec!["cp:otoroshi.next.plugins.OverrideHost", APIKEY_PLUGIN]
+        );
+        assert_eq!(
+            routes[1]["frontend"]["methods"],
+            serde_json::json!(["POST"])
+        );
+        assert_eq!(
+            plugins(&routes[1]),
+            vec!["cp:otoroshi.next.plugins.OverrideHost", JWT_PLUGIN]
+        );
+        assert_eq!(
+            plugins(&routes[2]),
+            vec!["cp:otoroshi.next.plugins.OverrideHost"]
+        );
+        assert!(!OpenApiHelper::missing_jwt_verifier(&routes[0]));
+        assert!(OpenApiHelper::missing_jwt_verifier(&routes[1]));
+
+        let routes = OpenApiHelper::routes(
+            &spec,
+            &template(),
+            "api.example.com",
+            &backend,
+            OpenApiGroupBy::Document,
+            true,
+            Some("jwt_verifier_1"),
+        )
+        .routes;
+        assert_eq!(
+            routes[1]["plugins"][1]["config"]["verifier"],
+            "jwt_verifier_1"
+        );
+        assert!(!OpenApiHelper::missing_jwt_verifier(&routes[1]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(OpenApiHelper::parse("swagger: '2.0'", "spec.yaml").is_err());
+        assert!(OpenApiBackend::parse("pets.svc:8080").is_err());
+        assert!(OpenApiGroupBy::parse(Some("operation")).is_err());
+    }
+}
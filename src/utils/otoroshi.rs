@@ -28,6 +28,98 @@ pub struct OtoroshiApiMultiResult {
     pub body: Vec<serde_json::Value>,
 }
 
+/// Pages through the entities of a kind, one call of the admin api per page, so large
+/// collections never come in a single response. The last page is known from the `X-Pages` or
+/// `X-Count` headers when otoroshi sends them, or from a page shorter than the page size. A
+/// server ignoring the paging parameters is detected by a page longer than the page size, or
+/// by a page starting with the same entity as the previous one
+pub struct ResourcePager {
+    entity: OtoroshExposedResource,
+    filter: Vec<String>,
+    page_size: u32,
+    page: u32,
+    done: bool,
+    /// the id of the first entity of the previous page
+    first_id: Option<String>,
+    config: OtoroshiConnectionConfig,
+}
+
+impl ResourcePager {
+    pub const PAGE_SIZE: u32 = 500;
+
+    pub async fn new(
+        entity: OtoroshExposedResource,
+        filter: Vec<String>,
+        opts: CliOpts,
+    ) -> ResourcePager {
+        Self::with_config(entity, filter, Otoroshi::get_connection_config(opts).await)
+    }
+
+    pub fn with_config(
+        entity: OtoroshExposedResource,
+        filter: Vec<String>,
+        config: OtoroshiConnectionConfig,
+    ) -> ResourcePager {
+        ResourcePager {
+            entity,
+            filter,
+            page_size: Self::PAGE_SIZE,
+            page: 0,
+            done: false,
+            first_id: None,
+            config,
+        }
+    }
+
+    /// The number of pages from the `X-Pages` header, or from the `X-Count` one
+    fn page_count(headers: &HashMap<String, String>, page_size: u32) -> Option<u32> {
+        let header = |name: &str| headers.get(name).and_then(|v| v.trim().parse::<u32>().ok());
+        header("x-pages")
+            .or_else(|| header("x-count").map(|count| count.div_ceil(page_size.max(1))))
+    }
+
+    fn is_last(page: u32, page_size: u32, len: usize, pages: Option<u32>) -> bool {
+        (len as u32) != page_size || pages.is_some_and(|pages| page >= pages)
+    }
+
+    fn first_id(body: &[serde_json::Value]) -> Option<String> {
+        body.first()
+            .and_then(crate::utils::entity::EntityHelper::extract_json_entity_id)
+    }
+
+    /// Does the page start with the same entity as the previous one
+    fn is_repeated(previous_first_id: &Option<String>, body: &[serde_json::Value]) -> bool {
+        previous_first_id.is_some() && *previous_first_id == Self::first_id(body)
+    }
+
+    /// The next page of entities, `Some(None)` after the last one and `None` on error
+    pub async fn next_page(&mut self) -> Option<Option<Vec<serde_json::Value>>> {
+        if self.done {
+            return Some(None);
+        }
+        self.page += 1;
+        let (body, pages) = Otoroshi::get_resources_page(
+            &self.entity,
+            self.page,
+            self.page_size,
+            &self.filter,
+            self.config.clone(),
+        )
+        .await?;
+        if Self::is_repeated(&self.first_id, &body) {
+            self.done = true;
+            return Some(None);
+        }
+        self.first_id = Self::first_id(&body);
+        self.done = Self::is_last(self.page, self.page_size, body.len(), pages);
+        if body.is_empty() {
+            Some(None)
+        } else {
+            Some(Some(body))
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct OtoroshiConnectionConfig {
     /// the otoroshi api hostname
@@ -82,6 +174,33 @@ impl Otoroshi {
         content_type: Option<String>,
         opts: OtoroshiConnectionConfig,
     ) -> OtoroshiResponse {
+        let resp = Self::otoroshi_request(method, path, accept, body, content_type, opts).await;
+        let status = resp.status().as_u16();
+        let mut headers = HashMap::new();
+        for header in resp.headers().into_iter() {
+            headers.insert(
+                header.0.as_str().to_string(),
+                header.1.to_str().unwrap().to_string(),
+            );
+        }
+        let body_bytes = hyper::body::to_bytes(resp).await.unwrap();
+        // debug!("status: {}, body: {:?}", status, body_bytes);
+        OtoroshiResponse {
+            status,
+            headers,
+            body_bytes,
+        }
+    }
+
+    /// Call the otoroshi api without reading the response body, so it can be streamed
+    async fn otoroshi_request(
+        method: hyper::Method,
+        path: &str,
+        accept: Option<String>,
+        body: Option<hyper::Body>,
+        content_type: Option<String>,
+        opts: OtoroshiConnectionConfig,
+    ) -> hyper::Response<hyper::Body> {
         let client_id = opts.cid;
         let client_secret = opts.csec;
         let scheme = if opts.tls { "https" } else { "http" };
@@ -192,23 +311,7 @@ impl Otoroshi {
                 cli_stderr_printline!("error while calling otoroshi api: \n\n{}", err);
                 std::process::exit(-1)
             }
-            Ok(resp) => {
-                let status = resp.status().as_u16();
-                let mut headers = HashMap::new();
-                for header in resp.headers().into_iter() {
-                    headers.insert(
-                        header.0.as_str().to_string(),
-                        header.1.to_str().unwrap().to_string(),
-                    );
-                }
-                let body_bytes = hyper::body::to_bytes(resp).await.unwrap();
-                // debug!("status: {}, body: {:?}", status, body_bytes);
-                OtoroshiResponse {
-                    status,
-                    headers,
-                    body_bytes,
-                }
-            }
+            Ok(resp) => resp,
         }
     }
 
@@ -229,7 +332,7 @@ impl Otoroshi {
         if response.status == 200 || response.status == 201 {
             Some(response.body_bytes)
         } else {
-            println!(
+            debug!(
                 "status: {}, body: {:?}",
                 response.status, response.body_bytes
             );
@@ -292,6 +395,42 @@ impl Otoroshi {
         }
     }
 
+    /// An entity, `Some(None)` when it does not exist and `None` on error
+    pub async fn find_one_resource(
+        entity: OtoroshExposedResource,
+        id: String,
+        opts: CliOpts,
+    ) -> Option<Option<serde_json::Value>> {
+        let config = Self::get_connection_config(opts).await;
+        let response = Self::otoroshi_call(
+            Method::GET,
+            format!(
+                "/apis/{}/{}/{}/{}",
+                entity.group, entity.version.name, entity.plural_name, id
+            )
+            .as_str(),
+            None,
+            None,
+            Some("application/json".to_string()),
+            config,
+        )
+        .await;
+        match response.status {
+            200 => match serde_json::from_slice::<serde_json::Value>(&response.body_bytes) {
+                Ok(body) => Some(Some(body)),
+                Err(e) => {
+                    debug!("parse error: {}", e);
+                    None
+                }
+            },
+            404 => Some(None),
+            status => {
+                debug!("status: {}, body: {:?}", status, response.body_bytes);
+                None
+            }
+        }
+    }
+
     pub async fn get_global_config(opts: CliOpts) -> Option<OtoroshiApiSingleResult> {
         let config = Self::get_connection_config(opts).await;
         match Self::get_otoroshi_resource("/api/globalconfig", None, config).await {
@@ -330,9 +469,9 @@ impl Otoroshi {
         entity: OtoroshExposedResource,
         id: String,
         opts: CliOpts,
-    ) -> bool {
+    ) -> OtoroshiResponse {
         let config: OtoroshiConnectionConfig = Self::get_connection_config(opts).await;
-        match Self::otoroshi_call(
+        Self::otoroshi_call(
             Method::DELETE,
             format!(
                 "/apis/{}/{}/{}/{}",
@@ -345,10 +484,6 @@ impl Otoroshi {
             config,
         )
         .await
-        {
-            resp if resp.status == 200 => true,
-            _ => false,
-        }
     }
 
     pub async fn upsert_one_resource(
@@ -358,6 +493,15 @@ impl Otoroshi {
         opts: CliOpts,
     ) -> bool {
         let config: OtoroshiConnectionConfig = Self::get_connection_config(opts).await;
+        Self::upsert_one_resource_with_config(entity, id, body, config).await
+    }
+
+    pub async fn upsert_one_resource_with_config(
+        entity: OtoroshExposedResource,
+        id: String,
+        body: String,
+        config: OtoroshiConnectionConfig,
+    ) -> bool {
         match Self::otoroshi_call(
             Method::POST,
             format!(
@@ -471,11 +615,25 @@ impl Otoroshi {
         opts: CliOpts,
     ) -> Option<OtoroshiApiMultiResult> {
         let config = Self::get_connection_config(opts).await;
+        Self::get_resources_page(&entity, page, page_size, &filter, config)
+            .await
+            .map(|(body, _)| OtoroshiApiMultiResult { body })
+    }
+
+    /// One page of entities, with the number of pages when otoroshi sends the `X-Pages` or
+    /// `X-Count` headers
+    async fn get_resources_page(
+        entity: &OtoroshExposedResource,
+        page: u32,
+        page_size: u32,
+        filter: &[String],
+        config: OtoroshiConnectionConfig,
+    ) -> Option<(Vec<serde_json::Value>, Option<u32>)> {
         let filtering: String = if filter.is_empty() {
             "".to_string()
         } else {
             let terms = filter
-                .into_iter()
+                .iter()
                 .flat_map(|item| {
                     item.split(",")
                         .map(|i| i.to_string())
@@ -494,30 +652,60 @@ impl Otoroshi {
                 .join("&");
             format!("&{}", terms).to_string()
         };
-        match Self::get_otoroshi_resource(
+        let response = Self::otoroshi_call(
+            Method::GET,
             format!(
                 "/apis/{}/{}/{}?page={}&pageSize={}{}",
                 entity.group, entity.version.name, entity.plural_name, page, page_size, filtering
             )
             .as_str(),
             None,
+            None,
+            Some("application/json".to_string()),
             config,
         )
-        .await
-        {
-            None => None,
-            Some(body_bytes) => match serde_json::from_slice::<serde_json::Value>(&body_bytes) {
-                Ok(infos) => Some(OtoroshiApiMultiResult {
-                    body: infos.as_array().unwrap().to_vec(),
-                }),
-                Err(e) => {
-                    debug!("parse error: {}", e);
-                    None
-                }
-            },
+        .await;
+        if response.status != 200 {
+            debug!(
+                "status: {}, body: {:?}",
+                response.status, response.body_bytes
+            );
+            return None;
+        }
+        let pages = ResourcePager::page_count(&response.headers, page_size);
+        match serde_json::from_slice::<serde_json::Value>(&response.body_bytes) {
+            Ok(serde_json::Value::Array(body)) => Some((body, pages)),
+            Ok(_) => None,
+            Err(e) => {
+                debug!("parse error: {}", e);
+                None
+            }
         }
     }
 
+    /// All the entities of a kind, fetched page by page
+    pub async fn get_all_resources(
+        entity: OtoroshExposedResource,
+        filter: Vec<String>,
+        opts: CliOpts,
+    ) -> Option<OtoroshiApiMultiResult> {
+        let config = Self::get_connection_config(opts).await;
+        Self::get_all_resources_with_config(entity, filter, config).await
+    }
+
+    pub async fn get_all_resources_with_config(
+        entity: OtoroshExposedResource,
+        filter: Vec<String>,
+        config: OtoroshiConnectionConfig,
+    ) -> Option<OtoroshiApiMultiResult> {
+        let mut pager = ResourcePager::with_config(entity, filter, config);
+        let mut body: Vec<serde_json::Value> = Vec::new();
+        while let Some(page) = pager.next_page().await? {
+            body.extend(page);
+        }
+        Some(OtoroshiApiMultiResult { body })
+    }
+
     pub async fn get_health(opts: CliOpts) -> Option<OtoroshiHealth> {
         let config: OtoroshiConnectionConfig = Self::get_connection_config(opts).await;
         match Self::get_otoroshi_resource("/.well-known/otoroshi/monitoring/health", None, config)
@@ -578,16 +766,34 @@ impl Otoroshi {
         }
     }
 
-    pub async fn get_export_json(
-        accept: Option<String>,
-        opts: CliOpts,
-    ) -> Option<hyper::body::Bytes> {
+    /// The full export as a body read chunk by chunk
+    pub async fn get_export_body(accept: Option<String>, opts: CliOpts) -> Option<hyper::Body> {
         let config = Self::get_connection_config(opts).await;
-        Self::get_otoroshi_resource("/api/otoroshi.json", accept, config).await
+        let response = Self::otoroshi_request(
+            Method::GET,
+            "/api/otoroshi.json",
+            accept,
+            None,
+            Some("application/json".to_string()),
+            config,
+        )
+        .await;
+        if response.status().as_u16() == 200 {
+            Some(response.into_body())
+        } else {
+            debug!("status: {}", response.status());
+            None
+        }
     }
 
     pub async fn get_exposed_resources(opts: CliOpts) -> Option<OtoroshExposedResources> {
         let config = Self::get_connection_config(opts).await;
+        Self::get_exposed_resources_with_config(config).await
+    }
+
+    pub async fn get_exposed_resources_with_config(
+        config: OtoroshiConnectionConfig,
+    ) -> Option<OtoroshExposedResources> {
         match Self::get_otoroshi_resource("/apis/entities", None, config).await {
             None => None,
             Some(body_bytes) => {
@@ -867,9 +1073,14 @@ impl Otoroshi {
         }
     }
 
+    /// Get all the plugins of the cluster from /api/plugins/all
+    pub async fn get_plugins(opts: CliOpts) -> Option<serde_json::Value> {
+        Self::get_json_resource("/api/plugins/all", opts).await
+    }
+
     /// Check if a plugin is available in Otoroshi
     pub async fn is_plugin_available(plugin_id: &str, opts: CliOpts) -> bool {
-        match Self::get_json_resource("/api/plugins/all", opts).await {
+        match Self::get_plugins(opts).await {
             Some(plugins) => Self::plugin_exists_in_list(&plugins, plugin_id),
             None => false,
         }
@@ -900,6 +1111,64 @@ impl Otoroshi {
 mod tests {
     use super::*;
 
+    // -------------------------------------------------------------------------
+    // Tests for ResourcePager
+    // -------------------------------------------------------------------------
+
+    #[test]
+    fn test_resource_pager_page_count() {
+        let headers = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            ResourcePager::page_count(&headers(&[("x-pages", "3"), ("x-count", "99")]), 500),
+            Some(3)
+        );
+        assert_eq!(
+            ResourcePager::page_count(&headers(&[("x-count", "1001")]), 500),
+            Some(3)
+        );
+        assert_eq!(
+            ResourcePager::page_count(&headers(&[("x-count", "1000")]), 500),
+            Some(2)
+        );
+        assert_eq!(ResourcePager::page_count(&headers(&[]), 500), None);
+    }
+
+    #[test]
+    fn test_resource_pager_is_last() {
+        // without headers, a short page is the last one
+        assert!(!ResourcePager::is_last(1, 500, 500, None));
+        assert!(ResourcePager::is_last(2, 500, 12, None));
+        assert!(ResourcePager::is_last(3, 500, 0, None));
+        // a full last page is known from the headers
+        assert!(ResourcePager::is_last(2, 500, 500, Some(2)));
+        assert!(!ResourcePager::is_last(1, 500, 500, Some(2)));
+        // the paging parameters are ignored and the whole collection is sent
+        assert!(ResourcePager::is_last(1, 500, 800, None));
+    }
+
+    #[test]
+    fn test_resource_pager_is_repeated() {
+        let page = |ids: &[&str]| -> Vec<serde_json::Value> {
+            ids.iter()
+                .map(|id| serde_json::json!({ "id": id }))
+                .collect()
+        };
+        // a server ignoring the paging parameters sends the same full page again
+        let first_id = ResourcePager::first_id(&page(&["route_1", "route_2"]));
+        assert!(ResourcePager::is_repeated(
+            &first_id,
+            &page(&["route_1", "route_2"])
+        ));
+        assert!(!ResourcePager::is_repeated(&first_id, &page(&["route_3"])));
+        assert!(!ResourcePager::is_repeated(&first_id, &page(&[])));
+        assert!(!ResourcePager::is_repeated(&None, &page(&["route_1"])));
+    }
+
     // -------------------------------------------------------------------------
     // Tests for plugin_exists_in_list
     // -------------------------------------------------------------------------
@@ -11,6 +11,13 @@ macro_rules! cli_stdout_printline {
     }};
 }
 
+#[macro_export]
+macro_rules! cli_stdout_print {
+    ($($arg:tt)*) => {{
+        print!($($arg)*);
+    }};
+}
+
 #[macro_export]
 macro_rules! cli_stderr_printline {
     () => {
@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::entity::{EntityDocument, EntityHelper};
+use crate::utils::json_patch::JsonPatchHelper;
+use json_value_merge::Merge;
+
+/// The file describing an overlay directory
+pub const OVERLAY_FILE: &str = "overlay.yaml";
+
+/// Content of an `overlay.yaml` file. Paths are relative to the overlay directory
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct OverlaySpec {
+    /// Base directories, that can be overlays themselves
+    #[serde(default)]
+    pub bases: Vec<String>,
+    /// Additional entity files
+    #[serde(default)]
+    pub resources: Vec<String>,
+    #[serde(default)]
+    pub patches: Vec<OverlayPatch>,
+}
+
+/// A patch of an overlay. Either a `path` to entity documents merged into the base entities with
+/// the same kind and id (or name), or a `target` patched with an inline `merge` object, inline
+/// `json_patch` operations or the content of `path`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct OverlayPatch {
+    pub path: Option<String>,
+    pub target: Option<PatchTarget>,
+    pub merge: Option<serde_json::Value>,
+    pub json_patch: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PatchTarget {
+    pub kind: String,
+    pub id: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatchOperation {
+    Merge(serde_json::Value),
+    JsonPatch(serde_json::Value),
+}
+
+/// A patch ready to be applied, once its files have been read
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedPatch {
+    pub target: PatchTarget,
+    pub operation: PatchOperation,
+    /// Where the patch comes from, for error messages
+    pub source: String,
+}
+
+pub struct OverlayHelper {}
+
+impl OverlayHelper {
+    /// Kinds are compared without their group and case (`proxy.otoroshi.io/Route` matches `route`)
+    fn kind_matches(kind: &str, expected: &str) -> bool {
+        let short = |k: &str| k.rsplit('/').next().unwrap_or(k).to_lowercase();
+        short(kind) == short(expected)
+    }
+
+    fn matches(doc: &EntityDocument, target: &PatchTarget) -> bool {
+        let kind_ok = doc
+            .kind
+            .as_deref()
+            .map(|kind| Self::kind_matches(kind, &target.kind))
+            .unwrap_or(false);
+        let id_ok = target.id.is_none() || target.id == doc.id;
+        let name_ok = target.name.is_none() || target.name == doc.name;
+        kind_ok && id_ok && name_ok && (target.id.is_some() || target.name.is_some())
+    }
+
+    /// Turn patch documents into merge patches targeting the entity with the same kind and id,
+    /// or the same name when the patch has no id
+    pub fn patches_from_documents(
+        documents: Vec<EntityDocument>,
+    ) -> Result<Vec<ResolvedPatch>, String> {
+        documents
+            .into_iter()
+            .map(|doc| match doc.kind.clone() {
+                None => Err(format!("missing kind in patch from {}", doc.source)),
+                Some(kind) => Ok(ResolvedPatch {
+                    target: PatchTarget {
+                        kind,
+                        id: doc.id.clone(),
+                        name: if doc.id.is_none() {
+                            doc.name.clone()
+                        } else {
+                            None
+                        },
+                    },
+                    operation: PatchOperation::Merge(EntityHelper::without_kind(&doc.body)),
+                    source: doc.source,
+                }),
+            })
+            .collect()
+    }
+
+    /// Apply the patches in order. A patch that does not match any entity is an error
+    pub fn apply(
+        mut documents: Vec<EntityDocument>,
+        patches: &[ResolvedPatch],
+    ) -> Result<Vec<EntityDocument>, String> {
+        for patch in patches.iter() {
+            let mut matched = false;
+            for doc in documents.iter_mut() {
+                if !Self::matches(doc, &patch.target) {
+                    continue;
+                }
+                matched = true;
+                doc.body = match &patch.operation {
+                    PatchOperation::Merge(value) => {
+                        let mut body = doc.body.clone();
+                        body.merge(value);
+                        body
+                    }
+                    PatchOperation::JsonPatch(operations) => {
+                        JsonPatchHelper::apply(&doc.body, operations).map_err(|e| {
+                            format!(
+                                "error while patching {} with {}: {}",
+                                doc.display_name(),
+                                patch.source,
+                                e
+                            )
+                        })?
+                    }
+                };
+                doc.id = EntityHelper::extract_json_entity_id(&doc.body).or(doc.id.clone());
+                doc.name = EntityHelper::extract_json_entity_name(&doc.body).or(doc.name.clone());
+            }
+            if !matched {
+                return Err(format!(
+                    "the patch from {} does not match any entity ({} {})",
+                    patch.source,
+                    patch.target.kind,
+                    patch
+                        .target
+                        .id
+                        .clone()
+                        .or(patch.target.name.clone())
+                        .unwrap_or_default()
+                ));
+            }
+        }
+        Ok(documents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Vec<EntityDocument> {
+        let content = r#"[
+            {"kind": "proxy.otoroshi.io/Route", "id": "route_1", "name": "api", "enabled": true, "plugins": [{"plugin": "a"}]},
+            {"kind": "proxy.otoroshi.io/Route", "id": "route_2", "name": "admin", "enabled": true}
+        ]"#;
+        EntityHelper::parse_documents(content, true, "base.json").unwrap()
+    }
+
+    #[test]
+    fn test_patches_from_documents() {
+        let content = "kind: Route\nname: admin\nenabled: false\n";
+        let docs = EntityHelper::parse_documents(content, false, "patch.yaml").unwrap();
+        let patches = OverlayHelper::patches_from_documents(docs).unwrap();
+        assert_eq!(
+            patches[0].target,
+            PatchTarget {
+                kind: "Route".to_string(),
+                id: None,
+                name: Some("admin".to_string())
+            }
+        );
+        let res = OverlayHelper::apply(base(), &patches).unwrap();
+        assert_eq!(res[0].body["enabled"], true);
+        assert_eq!(res[1].body["enabled"], false);
+    }
+
+    #[test]
+    fn test_merge_and_json_patch() {
+        let patches = vec![
+            ResolvedPatch {
+                target: PatchTarget {
+                    kind: "route".to_string(),
+                    id: Some("route_1".to_string()),
+                    name: None,
+                },
+                operation: PatchOperation::Merge(serde_json::json!({"plugins": [{"plugin": "b"}]})),
+                source: "overlay.yaml".to_string(),
+            },
+            ResolvedPatch {
+                target: PatchTarget {
+                    kind: "Route".to_string(),
+                    id: Some("route_1".to_string()),
+                    name: None,
+                },
+                operation: PatchOperation::JsonPatch(
+                    serde_json::json!([{"op": "remove", "path": "/plugins/0"}]),
+                ),
+                source: "overlay.yaml".to_string(),
+            },
+        ];
+        let res = OverlayHelper::apply(base(), &patches).unwrap();
+        assert_eq!(res[0].body["plugins"], serde_json::json!([{"plugin": "b"}]));
+    }
+
+    #[test]
+    fn test_unmatched_patch() {
+        let patches = vec![ResolvedPatch {
+            target: PatchTarget {
+                kind: "Backend".to_string(),
+                id: Some("route_1".to_string()),
+                name: None,
+            },
+            operation: PatchOperation::Merge(serde_json::json!({})),
+            source: "overlay.yaml".to_string(),
+        }];
+        let err = OverlayHelper::apply(base(), &patches).unwrap_err();
+        assert!(err.contains("does not match any entity"));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::cli_stdout_printline;
+use std::io::{self, IsTerminal, Write};
+
+/// A progress bar drawn on stderr when it is a terminal. Lines printed through the bar are
+/// written to stdout above it, so the output of a command stays the same without a terminal
+pub struct ProgressBar {
+    label: String,
+    total: usize,
+    done: usize,
+    enabled: bool,
+}
+
+impl ProgressBar {
+    const WIDTH: usize = 30;
+
+    pub fn new(label: &str, total: usize) -> ProgressBar {
+        let bar = ProgressBar {
+            label: label.to_string(),
+            total,
+            done: 0,
+            enabled: total > 0 && io::stderr().is_terminal(),
+        };
+        bar.draw();
+        bar
+    }
+
+    pub fn render(label: &str, done: usize, total: usize) -> String {
+        let filled = (done * Self::WIDTH)
+            .checked_div(total)
+            .unwrap_or(Self::WIDTH);
+        format!(
+            "{} [{}{}] {}/{}",
+            label,
+            "=".repeat(filled),
+            " ".repeat(Self::WIDTH - filled),
+            done,
+            total
+        )
+    }
+
+    fn clear(&self) {
+        if self.enabled {
+            eprint!("\r\x1b[2K");
+        }
+    }
+
+    fn draw(&self) {
+        if self.enabled {
+            eprint!("\r{}", Self::render(&self.label, self.done, self.total));
+            let _ = io::stderr().flush();
+        }
+    }
+
+    /// Print a line on stdout without breaking the bar
+    pub fn println(&self, line: &str) {
+        self.clear();
+        cli_stdout_printline!("{}", line);
+        self.draw();
+    }
+
+    pub fn inc(&mut self) {
+        self.done = (self.done + 1).min(self.total);
+        self.draw();
+    }
+
+    pub fn finish(&self) {
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            ProgressBar::render("apply", 1, 3),
+            format!("apply [{}{}] 1/3", "=".repeat(10), " ".repeat(20))
+        );
+        assert_eq!(
+            ProgressBar::render("export", 0, 0),
+            format!("export [{}] 0/0", "=".repeat(30))
+        );
+    }
+}
@@ -0,0 +1,545 @@
+use serde::Serialize;
+
+use crate::cli::cliopts::CliOpts;
+use crate::utils::json_path::JsonPathHelper;
+use crate::{cli_stderr_printline, cli_stdout_printline};
+
+/// The rendering of `-o` shared by every command. The structured formats (json, json_pretty,
+/// yaml, jsonpath=) render the whole value, the tabular ones (csv, tsv, markdown, template=)
+/// render one row per item of an array (or the value itself) with the columns of the command.
+/// Anything else is left to the default display of the command, usually a table
+pub struct RenderHelper {}
+
+impl RenderHelper {
+    /// The rows of a value, the items of an array or the value itself
+    pub fn rows(value: &serde_json::Value) -> Vec<serde_json::Value> {
+        match value {
+            serde_json::Value::Array(items) => items.clone(),
+            serde_json::Value::Null => Vec::new(),
+            other => vec![other.clone()],
+        }
+    }
+
+    /// The keys of the rows, in order of appearance, when the command has no columns
+    fn keys(rows: &[serde_json::Value]) -> Vec<String> {
+        let mut keys: Vec<String> = Vec::new();
+        for row in rows.iter() {
+            if let Some(obj) = row.as_object() {
+                for key in obj.keys() {
+                    if !keys.contains(key) {
+                        keys.push(key.to_string());
+                    }
+                }
+            }
+        }
+        keys
+    }
+
+    /// The raw text of a column, arrays and objects being displayed as json and the values of a
+    /// projection separated by commas
+    pub fn cell_text(column: &str, row: &serde_json::Value) -> String {
+        JsonPathHelper::select(column, row)
+            .unwrap_or_default()
+            .iter()
+            .map(|value| match value {
+                serde_json::Value::Null => "".to_string(),
+                other => JsonPathHelper::text(other),
+            })
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    fn csv_field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+
+    fn tsv_field(value: &str) -> String {
+        value.replace(['\t', '\n', '\r'], " ")
+    }
+
+    fn markdown_field(value: &str) -> String {
+        value
+            .replace('|', "\\|")
+            .replace("\r\n", "<br>")
+            .replace('\n', "<br>")
+    }
+
+    pub fn csv(rows: &[serde_json::Value], columns: &[String]) -> String {
+        let mut lines = vec![
+            columns
+                .iter()
+                .map(|c| Self::csv_field(c))
+                .collect::<Vec<String>>()
+                .join(","),
+        ];
+        for row in rows.iter() {
+            lines.push(
+                columns
+                    .iter()
+                    .map(|c| Self::csv_field(&Self::cell_text(c, row)))
+                    .collect::<Vec<String>>()
+                    .join(","),
+            );
+        }
+        lines.join("\n")
+    }
+
+    pub fn tsv(rows: &[serde_json::Value], columns: &[String]) -> String {
+        let mut lines = vec![
+            columns
+                .iter()
+                .map(|c| Self::tsv_field(c))
+                .collect::<Vec<String>>()
+                .join("\t"),
+        ];
+        for row in rows.iter() {
+            lines.push(
+                columns
+                    .iter()
+                    .map(|c| Self::tsv_field(&Self::cell_text(c, row)))
+                    .collect::<Vec<String>>()
+                    .join("\t"),
+            );
+        }
+        lines.join("\n")
+    }
+
+    pub fn markdown(rows: &[serde_json::Value], columns: &[String]) -> String {
+        let line = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+        let mut lines = vec![
+            line(columns.iter().map(|c| Self::markdown_field(c)).collect()),
+            line(columns.iter().map(|_| "---".to_string()).collect()),
+        ];
+        for row in rows.iter() {
+            lines.push(line(
+                columns
+                    .iter()
+                    .map(|c| Self::markdown_field(&Self::cell_text(c, row)))
+                    .collect(),
+            ));
+        }
+        lines.join("\n")
+    }
+
+    fn template_expression(expression: &str, row: &serde_json::Value) -> Result<String, String> {
+        let mut parts = expression.split('|').map(|p| p.trim());
+        let path = parts.next().unwrap_or_default();
+        let values = JsonPathHelper::select(path, row)?;
+        let mut text = Self::cell_text(path, row);
+        for filter in parts {
+            text = match filter.split_once(':') {
+                Some(("default", default)) if text.is_empty() => default.trim().to_string(),
+                Some(("default", _)) => text,
+                _ => match filter {
+                    "upper" => text.to_uppercase(),
+                    "lower" => text.to_lowercase(),
+                    "json" => match values.as_slice() {
+                        [single] => single.to_string(),
+                        _ => serde_json::Value::Array(values.clone()).to_string(),
+                    },
+                    other => return Err(format!("unknown template filter '{}'", other)),
+                },
+            };
+        }
+        Ok(text)
+    }
+
+    /// Render the template once per row. `{{ path }}` is replaced by the column at this JSONPath
+    /// (relative to the row, like `{{ .name }}` or `{{ frontend.domains[0] }}`) and can be piped
+    /// in the filters `upper`, `lower`, `json` and `default:VALUE`
+    pub fn template(template: &str, rows: &[serde_json::Value]) -> Result<String, String> {
+        let mut output = String::new();
+        for row in rows.iter() {
+            output.push_str(&Self::template_row(template, row)?);
+        }
+        Ok(output.trim_end().to_string())
+    }
+
+    fn template_row(template: &str, row: &serde_json::Value) -> Result<String, String> {
+        let mut output = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            let line = template[..template.len() - rest.len() + start]
+                .matches('\n')
+                .count()
+                + 1;
+            output.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find("}}")
+                .ok_or_else(|| format!("line {}: missing }}}} in template", line))?;
+            let expression = &rest[start + 2..start + end];
+            output.push_str(
+                &Self::template_expression(expression, row)
+                    .map_err(|e| format!("line {}: {}", line, e))?,
+            );
+            rest = &rest[start + end + 2..];
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    /// Whether the format is rendered here rather than by the default display of the command
+    pub fn is_rendered(format: Option<&str>) -> bool {
+        match format {
+            Some("json" | "json_pretty" | "yaml" | "csv" | "tsv" | "markdown") => true,
+            Some(format) => format.starts_with("jsonpath=") || format.starts_with("template="),
+            None => false,
+        }
+    }
+
+    /// Render the value in the format of `-o`, or `None` for the default display of the command.
+    /// When the command has no columns, the keys of the rows are used
+    pub fn render<T: Serialize>(
+        format: Option<&str>,
+        value: &T,
+        columns: &[String],
+    ) -> Result<Option<String>, String> {
+        let tabular = |value: &T| {
+            let rows = Self::rows(&serde_json::to_value(value).unwrap());
+            let columns = if columns.is_empty() {
+                Self::keys(&rows)
+            } else {
+                columns.to_vec()
+            };
+            (rows, columns)
+        };
+        match format {
+            Some("json") => Ok(Some(serde_json::to_string(value).unwrap())),
+            Some("json_pretty") => Ok(Some(serde_json::to_string_pretty(value).unwrap())),
+            Some("yaml") => Ok(Some(serde_yaml::to_string(value).unwrap())),
+            Some("csv") => {
+                let (rows, columns) = tabular(value);
+                Ok(Some(Self::csv(&rows, &columns)))
+            }
+            Some("tsv") => {
+                let (rows, columns) = tabular(value);
+                Ok(Some(Self::tsv(&rows, &columns)))
+            }
+            Some("markdown") => {
+                let (rows, columns) = tabular(value);
+                Ok(Some(Self::markdown(&rows, &columns)))
+            }
+            Some(format) if format.starts_with("jsonpath=") => {
+                let expression = format.trim_start_matches("jsonpath=");
+                let values =
+                    JsonPathHelper::select(expression, &serde_json::to_value(value).unwrap())
+                        .map_err(|e| format!("invalid jsonpath {}: {}", expression, e))?;
+                Ok(Some(
+                    values
+                        .iter()
+                        .map(JsonPathHelper::text)
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                ))
+            }
+            Some(format) if format.starts_with("template=") => {
+                let path = format.trim_start_matches("template=");
+                let template = std::fs::read_to_string(path)
+                    .map_err(|e| format!("error while reading template {}: {}", path, e))?;
+                let (rows, _) = tabular(value);
+                Self::template(&template, &rows)
+                    .map(Some)
+                    .map_err(|e| format!("error while rendering template {}: {}", path, e))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Print the value in the format of `-o`. Returns false when the command should use its
+    /// default display
+    pub fn display<T: Serialize>(cli_opts: &CliOpts, value: &T, columns: &[String]) -> bool {
+        match Self::render(cli_opts.ouput.as_deref(), value, columns) {
+            Ok(Some(rendered)) => {
+                cli_stdout_printline!("{}", rendered);
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                cli_stderr_printline!("{}", e);
+                std::process::exit(-1)
+            }
+        }
+    }
+}
+
+/// The rendering of a list that arrives page by page, for `-o` formats that can be written
+/// without the whole list: json, json_pretty, yaml, csv, tsv, markdown and template=. The
+/// concatenation of the chunks is the same text as `RenderHelper::display` of the whole list.
+/// When there are no columns, the keys of the first page are used
+pub struct RenderStream {
+    format: String,
+    columns: Vec<String>,
+    template: Option<(String, String)>,
+    started: bool,
+    // trailing whitespace of the template output, only written if more rows follow
+    pending: String,
+}
+
+impl RenderStream {
+    /// `None` when the format needs the whole list, like jsonpath= or the default tables
+    pub fn new(format: Option<&str>, columns: &[String]) -> Result<Option<RenderStream>, String> {
+        let template = match format {
+            Some("json" | "json_pretty" | "yaml" | "csv" | "tsv" | "markdown") => None,
+            Some(format) if format.starts_with("template=") => {
+                let path = format.trim_start_matches("template=");
+                Some((
+                    path.to_string(),
+                    std::fs::read_to_string(path)
+                        .map_err(|e| format!("error while reading template {}: {}", path, e))?,
+                ))
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(RenderStream {
+            format: format.unwrap_or_default().to_string(),
+            columns: columns.to_vec(),
+            template,
+            started: false,
+            pending: String::new(),
+        }))
+    }
+
+    fn header(&mut self, rows: &[serde_json::Value]) -> String {
+        if self.columns.is_empty() {
+            self.columns = RenderHelper::keys(rows);
+        }
+        match self.format.as_str() {
+            "csv" => RenderHelper::csv(&[], &self.columns),
+            "tsv" => RenderHelper::tsv(&[], &self.columns),
+            "markdown" => RenderHelper::markdown(&[], &self.columns),
+            _ => String::new(),
+        }
+    }
+
+    /// The text of the next page of items
+    pub fn push<T: Serialize>(&mut self, items: &[T]) -> Result<String, String> {
+        if items.is_empty() {
+            return Ok(String::new());
+        }
+        let rows: Vec<serde_json::Value> = items
+            .iter()
+            .map(|item| serde_json::to_value(item).unwrap())
+            .collect();
+        let mut chunk = String::new();
+        let first = !self.started;
+        if first {
+            chunk.push_str(&self.header(&rows));
+            self.started = true;
+        }
+        // the rows of a tabular format, without the header written with the first page
+        let body = |rendered: String, header: String| rendered[header.len()..].to_string();
+        match self.format.as_str() {
+            "json" => {
+                for (idx, item) in items.iter().enumerate() {
+                    chunk.push_str(if first && idx == 0 { "[" } else { "," });
+                    chunk.push_str(&serde_json::to_string(item).unwrap());
+                }
+            }
+            "json_pretty" => {
+                for (idx, item) in items.iter().enumerate() {
+                    let rendered = serde_json::to_string_pretty(&[item]).unwrap();
+                    chunk.push_str(if first && idx == 0 { "[\n" } else { ",\n" });
+                    chunk.push_str(&rendered[2..rendered.len() - 2]);
+                }
+            }
+            "yaml" => chunk.push_str(&serde_yaml::to_string(items).unwrap()),
+            "csv" => chunk.push_str(&body(
+                RenderHelper::csv(&rows, &self.columns),
+                RenderHelper::csv(&[], &self.columns),
+            )),
+            "tsv" => chunk.push_str(&body(
+                RenderHelper::tsv(&rows, &self.columns),
+                RenderHelper::tsv(&[], &self.columns),
+            )),
+            "markdown" => chunk.push_str(&body(
+                RenderHelper::markdown(&rows, &self.columns),
+                RenderHelper::markdown(&[], &self.columns),
+            )),
+            _ => {
+                let (path, template) = self.template.clone().unwrap_or_default();
+                let mut rendered = String::new();
+                for row in rows.iter() {
+                    rendered.push_str(
+                        &RenderHelper::template_row(&template, row).map_err(|e| {
+                            format!("error while rendering template {}: {}", path, e)
+                        })?,
+                    );
+                }
+                let trimmed = rendered.trim_end().len();
+                if trimmed > 0 {
+                    chunk.push_str(&std::mem::take(&mut self.pending));
+                }
+                chunk.push_str(&rendered[..trimmed]);
+                self.pending.push_str(&rendered[trimmed..]);
+            }
+        }
+        Ok(chunk)
+    }
+
+    /// The text after the last page
+    pub fn finish(mut self) -> String {
+        let end = match self.format.as_str() {
+            "json" if self.started => "]",
+            "json_pretty" if self.started => "\n]",
+            "json" | "json_pretty" => "[]",
+            "yaml" if !self.started => "[]\n",
+            _ => "",
+        };
+        let header = if self.started {
+            String::new()
+        } else {
+            self.header(&[])
+        };
+        format!("{}{}\n", header, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn routes() -> serde_json::Value {
+        serde_json::json!([
+            {"id": "route_1", "name": "api, v1", "enabled": true, "frontend": {"domains": ["a.oto.tools", "b.oto.tools"]}},
+            {"id": "route_2", "name": "admin \"panel\"", "enabled": false, "frontend": {"domains": []}}
+        ])
+    }
+
+    fn columns() -> Vec<String> {
+        vec![
+            "id".to_string(),
+            "name".to_string(),
+            "frontend.domains[*]".to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_tabular_formats() {
+        let routes = routes();
+        let render = |format: &str| {
+            RenderHelper::render(Some(format), &routes, &columns())
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(
+            render("csv"),
+            "id,name,frontend.domains[*]\nroute_1,\"api, v1\",\"a.oto.tools,b.oto.tools\"\nroute_2,\"admin \"\"panel\"\"\","
+        );
+        assert_eq!(
+            render("tsv").lines().nth(1),
+            Some("route_1\tapi, v1\ta.oto.tools,b.oto.tools")
+        );
+        assert_eq!(
+            render("markdown"),
+            "| id | name | frontend.domains[*] |\n| --- | --- | --- |\n| route_1 | api, v1 | a.oto.tools,b.oto.tools |\n| route_2 | admin \"panel\" |  |"
+        );
+        assert_eq!(render("jsonpath=$[*].id"), "route_1\nroute_2");
+        assert!(
+            RenderHelper::render(None, &routes, &columns())
+                .unwrap()
+                .is_none()
+        );
+        assert!(RenderHelper::is_rendered(Some("template=routes.tpl")));
+        assert!(!RenderHelper::is_rendered(Some("raw")));
+
+        let single = serde_json::json!({"status": "healthy", "cluster": null});
+        assert_eq!(
+            RenderHelper::render(Some("csv"), &single, &[]).unwrap(),
+            Some("cluster,status\n,healthy".to_string())
+        );
+    }
+
+    #[test]
+    fn test_cell_text() {
+        let route = serde_json::json!({
+            "id": "route_1",
+            "enabled": false,
+            "frontend": {"domains": ["a.oto.tools", "b.oto.tools"]},
+            "backend": {"targets": []}
+        });
+        assert_eq!(RenderHelper::cell_text("id", &route), "route_1");
+        assert_eq!(RenderHelper::cell_text("enabled", &route), "false");
+        assert_eq!(
+            RenderHelper::cell_text("frontend.domains", &route),
+            r#"["a.oto.tools","b.oto.tools"]"#
+        );
+        assert_eq!(
+            RenderHelper::cell_text("backend.targets.0.hostname", &route),
+            ""
+        );
+        assert_eq!(
+            RenderHelper::cell_text("frontend.domains[*]", &route),
+            "a.oto.tools,b.oto.tools"
+        );
+        assert_eq!(
+            RenderHelper::cell_text("frontend.domains[1]", &route),
+            "b.oto.tools"
+        );
+    }
+
+    #[test]
+    fn test_stream_is_the_whole_rendering() {
+        let path = std::env::temp_dir().join("otoroshictl-render-stream.tpl");
+        std::fs::write(&path, "{{ id }}: {{ name }}\n\n").unwrap();
+        let template = format!("template={}", path.display());
+        let rows = RenderHelper::rows(&routes());
+        let formats = [
+            "json",
+            "json_pretty",
+            "yaml",
+            "csv",
+            "tsv",
+            "markdown",
+            template.as_str(),
+        ];
+        for format in formats {
+            for items in [&rows[..], &rows[..1], &[]] {
+                let mut stream = RenderStream::new(Some(format), &columns())
+                    .unwrap()
+                    .unwrap();
+                let mut streamed = String::new();
+                for page in items.chunks(1) {
+                    streamed.push_str(&stream.push(page).unwrap());
+                    streamed.push_str(&stream.push::<serde_json::Value>(&[]).unwrap());
+                }
+                streamed.push_str(&stream.finish());
+                let whole = RenderHelper::render(Some(format), &items, &columns())
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(streamed, format!("{}\n", whole), "{}", format);
+            }
+        }
+        assert!(
+            RenderStream::new(Some("jsonpath=$[*].id"), &[])
+                .unwrap()
+                .is_none()
+        );
+        assert!(RenderStream::new(None, &[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_template() {
+        let rows = RenderHelper::rows(&routes());
+        assert_eq!(
+            RenderHelper::template(
+                "{{ .id }} -> {{ frontend.domains[0] | upper | default:none }}\n",
+                &rows
+            )
+            .unwrap(),
+            "route_1 -> A.OTO.TOOLS\nroute_2 -> none"
+        );
+        assert_eq!(
+            RenderHelper::template("{{ frontend.domains | json }};", &rows[..1]).unwrap(),
+            "[\"a.oto.tools\",\"b.oto.tools\"];"
+        );
+        assert_eq!(
+            RenderHelper::template("ok\n{{ id", &rows).unwrap_err(),
+            "line 2: missing }} in template"
+        );
+        assert!(RenderHelper::template("{{ id | reverse }}", &rows).is_err());
+    }
+}
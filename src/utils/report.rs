@@ -0,0 +1,149 @@
+use crate::cli::commands::resources::PlannedAction;
+
+/// Machine readable reports of the result of each entity of a command
+pub struct ReportHelper {}
+
+impl ReportHelper {
+    pub fn is_failure(result: &PlannedAction) -> bool {
+        result.action == "error"
+    }
+
+    fn escape_xml(value: &str) -> String {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&apos;")
+    }
+
+    pub fn json(command: &str, results: &[PlannedAction]) -> String {
+        let report = serde_json::json!({
+            "command": command,
+            "total": results.len(),
+            "failed": results.iter().filter(|r| Self::is_failure(r)).count(),
+            "results": results,
+        });
+        serde_json::to_string_pretty(&report).unwrap()
+    }
+
+    /// One test suite per kind, one test case per entity
+    pub fn junit(command: &str, results: &[PlannedAction]) -> String {
+        let mut kinds: Vec<&str> = Vec::new();
+        for result in results.iter() {
+            if !kinds.contains(&result.kind.as_str()) {
+                kinds.push(&result.kind);
+            }
+        }
+        let failed = results.iter().filter(|r| Self::is_failure(r)).count();
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"otoroshictl {}\" tests=\"{}\" failures=\"{}\">\n",
+            Self::escape_xml(command),
+            results.len(),
+            failed
+        );
+        for kind in kinds.into_iter() {
+            let suite: Vec<&PlannedAction> = results.iter().filter(|r| r.kind == kind).collect();
+            xml.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+                Self::escape_xml(kind),
+                suite.len(),
+                suite.iter().filter(|r| Self::is_failure(r)).count()
+            ));
+            for result in suite.into_iter() {
+                xml.push_str(&format!(
+                    "    <testcase classname=\"{}\" name=\"{} ({})\">\n",
+                    Self::escape_xml(kind),
+                    Self::escape_xml(&result.name),
+                    Self::escape_xml(&result.id)
+                ));
+                if Self::is_failure(result) {
+                    let message = match result.status {
+                        Some(status) => format!("http status {}", status),
+                        None => "error".to_string(),
+                    };
+                    xml.push_str(&format!(
+                        "      <failure message=\"{}\">{}</failure>\n",
+                        message,
+                        Self::escape_xml(result.error.as_deref().unwrap_or_default())
+                    ));
+                } else {
+                    xml.push_str(&format!(
+                        "      <system-out>{}</system-out>\n",
+                        Self::escape_xml(&result.action)
+                    ));
+                }
+                xml.push_str("    </testcase>\n");
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+
+    /// Render the report in the given format (can be one of: json, junit)
+    pub fn render(
+        format: &str,
+        command: &str,
+        results: &[PlannedAction],
+    ) -> Result<String, String> {
+        match format {
+            "json" => Ok(Self::json(command, results)),
+            "junit" => Ok(Self::junit(command, results)),
+            other => Err(format!(
+                "unknown report format '{}' (can be one of: json, junit)",
+                other
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results() -> Vec<PlannedAction> {
+        vec![
+            PlannedAction {
+                kind: "proxy.otoroshi.io/Route".to_string(),
+                id: "route_1".to_string(),
+                name: "api".to_string(),
+                action: "created".to_string(),
+                status: Some(201),
+                error: None,
+            },
+            PlannedAction {
+                kind: "proxy.otoroshi.io/Route".to_string(),
+                id: "route_2".to_string(),
+                name: "admin <v2>".to_string(),
+                action: "error".to_string(),
+                status: Some(400),
+                error: Some("{\"error\":\"bad request\"}".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_json_report() {
+        let report: serde_json::Value =
+            serde_json::from_str(&ReportHelper::json("apply", &results())).unwrap();
+        assert_eq!(report["total"], 2);
+        assert_eq!(report["failed"], 1);
+        assert_eq!(report["results"][1]["status"], 400);
+    }
+
+    #[test]
+    fn test_junit_report() {
+        let report = ReportHelper::junit("apply", &results());
+        assert!(
+            report.contains("<testsuites name=\"otoroshictl apply\" tests=\"2\" failures=\"1\">")
+        );
+        assert!(report.contains(
+            "<testcase classname=\"proxy.otoroshi.io/Route\" name=\"admin &lt;v2&gt; (route_2)\">"
+        ));
+        assert!(report.contains(
+            "<failure message=\"http status 400\">{&quot;error&quot;:&quot;bad request&quot;}</failure>"
+        ));
+        assert!(ReportHelper::render("xml", "apply", &results()).is_err());
+    }
+}
@@ -0,0 +1,407 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::cli::commands::entities::{OtoroshExposedResource, OtoroshExposedResources};
+use crate::cli::config::OtoroshiCtlConfig;
+
+/// The objects of these keys are free-form maps, any key is accepted
+const OPEN_OBJECTS: [&str; 3] = ["metadata", "config", "headers"];
+
+/// A problem found in an entity document, at the line of its source where it has been found
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SchemaViolation {
+    pub source: String,
+    pub line: usize,
+    /// the field, using the `--data PATH=VALUE` notation, empty for the whole document
+    pub path: String,
+    pub message: String,
+}
+
+/// Validation of entity documents against a schema, a subset of JSON schema with `type`,
+/// `properties`, `additionalProperties` and `items`. The schema of a kind is derived from the
+/// template of the kind exposed by otoroshi, or embedded for the most common kinds when there is
+/// no template at hand. Derived schemas are cached per kind and version, so validation works
+/// offline once they have been fetched
+pub struct SchemaHelper {}
+
+impl SchemaHelper {
+    fn type_name(value: &serde_json::Value) -> &'static str {
+        match value {
+            serde_json::Value::Null => "null",
+            serde_json::Value::Bool(_) => "boolean",
+            serde_json::Value::Number(_) => "number",
+            serde_json::Value::String(_) => "string",
+            serde_json::Value::Array(_) => "array",
+            serde_json::Value::Object(_) => "object",
+        }
+    }
+
+    fn derive(key: &str, value: &serde_json::Value) -> serde_json::Value {
+        match value {
+            // nothing to learn from a null, any value is accepted
+            serde_json::Value::Null => serde_json::json!({}),
+            serde_json::Value::Array(items) => {
+                // the items of a template array can differ, their fields are merged
+                let mut schema = serde_json::json!({"type": "array"});
+                let mut items_schema: Option<serde_json::Value> = None;
+                for item in items.iter() {
+                    let item_schema = Self::derive("", item);
+                    items_schema = Some(match items_schema {
+                        None => item_schema,
+                        Some(current) => Self::merge(current, item_schema),
+                    });
+                }
+                if let Some(items_schema) = items_schema {
+                    schema["items"] = items_schema;
+                }
+                schema
+            }
+            serde_json::Value::Object(obj) => {
+                let open = obj.is_empty()
+                    || OPEN_OBJECTS.contains(&key)
+                    || obj.values().all(|v| v.is_string());
+                if open {
+                    return serde_json::json!({"type": "object"});
+                }
+                let properties: serde_json::Map<String, serde_json::Value> = obj
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), Self::derive(k, v)))
+                    .collect();
+                serde_json::json!({
+                    "type": "object",
+                    "properties": properties,
+                    "additionalProperties": false,
+                })
+            }
+            other => serde_json::json!({"type": Self::type_name(other)}),
+        }
+    }
+
+    /// Merge the schemas of two items of the same array. Fields are merged and a type conflict
+    /// accepts any value
+    fn merge(a: serde_json::Value, b: serde_json::Value) -> serde_json::Value {
+        if a.get("type") != b.get("type") {
+            return serde_json::json!({});
+        }
+        match (a.get("properties"), b.get("properties")) {
+            (Some(pa), Some(pb)) => {
+                let mut properties = pa.as_object().cloned().unwrap_or_default();
+                for (k, v) in pb.as_object().cloned().unwrap_or_default() {
+                    let merged = match properties.remove(&k) {
+                        Some(current) => Self::merge(current, v),
+                        None => v,
+                    };
+                    properties.insert(k, merged);
+                }
+                let mut merged = a.clone();
+                merged["properties"] = serde_json::Value::Object(properties);
+                merged
+            }
+            // one of them is an open object
+            (Some(_), None) => b,
+            _ => a,
+        }
+    }
+
+    /// The schema of a kind, derived from its template
+    pub fn from_template(template: &serde_json::Value) -> serde_json::Value {
+        Self::derive("", template)
+    }
+
+    /// The schema of the most common kinds, used when no template is available. Only the main
+    /// fields are known, so unknown fields are not reported. Kinds are compared without case, as
+    /// otoroshi names them `Apikey` where the documents often say `ApiKey`
+    pub fn embedded(kind: &str) -> Option<serde_json::Value> {
+        let fields: &[(&str, &str)] = match kind.to_lowercase().as_str() {
+            "route" => &[
+                ("id", "string"),
+                ("name", "string"),
+                ("description", "string"),
+                ("enabled", "boolean"),
+                ("frontend", "object"),
+                ("backend", "object"),
+                ("backend_ref", "string"),
+                ("plugins", "array"),
+                ("groups", "array"),
+            ],
+            "backend" => &[
+                ("id", "string"),
+                ("name", "string"),
+                ("description", "string"),
+                ("backend", "object"),
+            ],
+            "apikey" => &[
+                ("clientId", "string"),
+                ("clientSecret", "string"),
+                ("clientName", "string"),
+                ("enabled", "boolean"),
+                ("authorizedEntities", "array"),
+            ],
+            "certificate" => &[
+                ("id", "string"),
+                ("name", "string"),
+                ("chain", "string"),
+                ("privateKey", "string"),
+            ],
+            "servicegroup" => &[
+                ("id", "string"),
+                ("name", "string"),
+                ("description", "string"),
+            ],
+            _ => return None,
+        };
+        let mut properties: serde_json::Map<String, serde_json::Value> = fields
+            .iter()
+            .map(|(k, t)| (k.to_string(), serde_json::json!({"type": t})))
+            .collect();
+        properties.insert("tags".to_string(), serde_json::json!({"type": "array"}));
+        properties.insert(
+            "metadata".to_string(),
+            serde_json::json!({"type": "object"}),
+        );
+        Some(serde_json::json!({"type": "object", "properties": properties}))
+    }
+
+    fn walk(
+        schema: &serde_json::Value,
+        value: &serde_json::Value,
+        path: &str,
+        found: &mut Vec<(String, String)>,
+    ) {
+        // null is accepted for every field, otoroshi uses the default value
+        if value.is_null() {
+            return;
+        }
+        let child = |key: &str| {
+            if path.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", path, key)
+            }
+        };
+        if let Some(expected) = schema.get("type").and_then(|t| t.as_str())
+            && expected != Self::type_name(value)
+        {
+            found.push((
+                path.to_string(),
+                format!(
+                    "{} should be a {}, found a {}",
+                    path,
+                    expected,
+                    Self::type_name(value)
+                ),
+            ));
+            return;
+        }
+        match value {
+            serde_json::Value::Object(obj) => {
+                let properties = schema.get("properties").and_then(|p| p.as_object());
+                let closed =
+                    schema.get("additionalProperties") == Some(&serde_json::Value::Bool(false));
+                for (key, value) in obj.iter() {
+                    match properties.and_then(|p| p.get(key)) {
+                        Some(field_schema) => Self::walk(field_schema, value, &child(key), found),
+                        None if closed => {
+                            found.push((child(key), format!("unknown field {}", child(key))))
+                        }
+                        None => (),
+                    }
+                }
+            }
+            serde_json::Value::Array(items) => {
+                if let Some(items_schema) = schema.get("items") {
+                    for (idx, item) in items.iter().enumerate() {
+                        Self::walk(items_schema, item, &child(&idx.to_string()), found);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// The unknown fields and type mismatches of the entity, as paths and messages. The `kind`
+    /// field added by exports and `resources get` is accepted
+    pub fn validate(
+        schema: &serde_json::Value,
+        entity: &serde_json::Value,
+    ) -> Vec<(String, String)> {
+        let mut found: Vec<(String, String)> = Vec::new();
+        let mut entity = entity.clone();
+        if let Some(obj) = entity.as_object_mut() {
+            obj.remove("kind");
+        }
+        Self::walk(schema, &entity, "", &mut found);
+        found
+    }
+
+    fn is_key_of(line: &str, key: &str) -> bool {
+        let followed_by_colon = |rest: &str| rest.trim_start().starts_with(':');
+        let trimmed = line.trim_start().trim_start_matches("- ");
+        if trimmed.strip_prefix(key).is_some_and(followed_by_colon) {
+            return true;
+        }
+        [format!("\"{}\"", key), format!("'{}'", key)]
+            .iter()
+            .any(|quoted| {
+                line.match_indices(quoted.as_str())
+                    .any(|(idx, _)| followed_by_colon(&line[idx + quoted.len()..]))
+            })
+    }
+
+    /// The line of a field in the content of a source, looking for each key of the path in turn
+    /// from the line where the document starts. Indexes of arrays are skipped, so the line is
+    /// the one of the first item having the field. Falls back on the last key found
+    pub fn line_of(content: &str, start: usize, path: &[&str]) -> usize {
+        let lines: Vec<&str> = content.lines().collect();
+        let mut current = start.max(1);
+        for key in path.iter().filter(|k| k.parse::<usize>().is_err()) {
+            match (current - 1..lines.len()).find(|idx| Self::is_key_of(lines[*idx], key)) {
+                Some(idx) => current = idx + 1,
+                None => break,
+            }
+        }
+        current
+    }
+
+    /// Where the schemas are cached: `OTOROSHICTL_SCHEMA_CACHE`, or the `schemas` directory next
+    /// to the config file
+    pub fn cache_dir(config_file: Option<&str>) -> PathBuf {
+        if let Ok(dir) = std::env::var("OTOROSHICTL_SCHEMA_CACHE")
+            && !dir.is_empty()
+        {
+            return PathBuf::from(dir);
+        }
+        OtoroshiCtlConfig::config_dir(config_file).join("schemas")
+    }
+
+    fn schema_file(resource: &OtoroshExposedResource) -> String {
+        format!(
+            "{}_{}_{}.json",
+            resource.group, resource.kind, resource.version.name
+        )
+    }
+
+    fn read_json<T: serde::de::DeserializeOwned>(path: PathBuf) -> Option<T> {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+    }
+
+    fn write_json<T: Serialize>(cache: &Path, file: &str, value: &T) -> Result<(), String> {
+        let path = cache.join(file);
+        std::fs::create_dir_all(cache)
+            .and_then(|_| std::fs::write(&path, serde_json::to_string_pretty(value).unwrap()))
+            .map_err(|e| format!("error while writing {:?}: {}", path, e))
+    }
+
+    /// The kinds exposed by the cluster when they were last fetched
+    pub fn cached_resources(cache: &Path) -> Option<OtoroshExposedResources> {
+        Self::read_json(cache.join("entities.json"))
+    }
+
+    pub fn store_resources(
+        cache: &Path,
+        resources: &OtoroshExposedResources,
+    ) -> Result<(), String> {
+        Self::write_json(cache, "entities.json", resources)
+    }
+
+    pub fn cached(cache: &Path, resource: &OtoroshExposedResource) -> Option<serde_json::Value> {
+        Self::read_json(cache.join(Self::schema_file(resource)))
+    }
+
+    pub fn store(
+        cache: &Path,
+        resource: &OtoroshExposedResource,
+        schema: &serde_json::Value,
+    ) -> Result<(), String> {
+        Self::write_json(cache, &Self::schema_file(resource), schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template() -> serde_json::Value {
+        serde_json::json!({
+            "id": "route_xxx",
+            "name": "New route",
+            "enabled": true,
+            "metadata": {},
+            "tags": [],
+            "frontend": {"domains": ["new-route.oto.tools"], "strip_path": true, "headers": {}},
+            "backend": {"targets": [{"hostname": "mirror.otoroshi.io", "port": 443, "tls": true}]},
+            "backend_ref": null,
+            "plugins": [
+                {"plugin": "cp:otoroshi.next.plugins.OverrideHost", "enabled": true, "config": {}},
+                {"plugin": "cp:otoroshi.next.plugins.ApikeyCalls", "enabled": true, "include": [], "config": {"validate": true}}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_validate_against_template() {
+        let schema = SchemaHelper::from_template(&template());
+        let route = serde_json::json!({
+            "kind": "Route",
+            "id": "route_1",
+            "enabled": "yes",
+            "metadata": {"team": "a"},
+            "frontend": {"domainz": ["api.oto.tools"], "headers": {"x-foo": "bar"}},
+            "backend": {"targets": [{"hostname": "a.svc", "port": "80"}]},
+            "backend_ref": "backend_1",
+            "plugins": [{"plugin": "cp:foo", "include": ["/"], "config": {"anything": 1}}]
+        });
+        assert_eq!(
+            SchemaHelper::validate(&schema, &route),
+            vec![
+                (
+                    "backend.targets.0.port".to_string(),
+                    "backend.targets.0.port should be a number, found a string".to_string()
+                ),
+                (
+                    "enabled".to_string(),
+                    "enabled should be a boolean, found a string".to_string()
+                ),
+                (
+                    "frontend.domainz".to_string(),
+                    "unknown field frontend.domainz".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_embedded() {
+        let schema = SchemaHelper::embedded("Route").unwrap();
+        let route = serde_json::json!({"id": "route_1", "enabled": 1, "anything": true});
+        assert_eq!(
+            SchemaHelper::validate(&schema, &route),
+            vec![(
+                "enabled".to_string(),
+                "enabled should be a boolean, found a number".to_string()
+            )]
+        );
+        assert!(SchemaHelper::embedded("Unknown").is_none());
+        let apikey = serde_json::json!({"clientId": "key_1", "enabled": "yes"});
+        for kind in ["Apikey", "ApiKey"] {
+            assert_eq!(
+                SchemaHelper::validate(&SchemaHelper::embedded(kind).unwrap(), &apikey).len(),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn test_line_of() {
+        let yaml = "kind: Route\nid: route_1\nfrontend:\n  domains: []\n---\nkind: Route\nid: route_2\nfrontend:\n  domainz: []\n";
+        assert_eq!(SchemaHelper::line_of(yaml, 6, &["frontend", "domainz"]), 9);
+        assert_eq!(SchemaHelper::line_of(yaml, 1, &["frontend", "nope"]), 3);
+        let json = "{\n  \"id\": \"route_1\",\n  \"backend\": {\"targets\": [\n    {\"hostname\": \"a\", \"port\": \"80\"}\n  ]}\n}";
+        assert_eq!(
+            SchemaHelper::line_of(json, 1, &["backend", "targets", "0", "port"]),
+            4
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use base64::{Engine as _, engine::general_purpose};
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+
+/// Fields holding secrets in otoroshi entities (apikeys, certificates, auth modules, jwt verifiers, ...)
+pub const DEFAULT_SECRET_FIELDS: [&str; 7] = [
+    "clientSecret",
+    "client_secret",
+    "privateKey",
+    "secret",
+    "password",
+    "adminPassword",
+    "bindPassword",
+];
+
+const PREFIX: &str = "ENC[AES256_GCM,";
+const PBKDF2_ITERATIONS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+
+/// A passphrase based key. Each value is encrypted with AES-256-GCM using a key derived from the
+/// passphrase with PBKDF2-HMAC-SHA256. The salt is generated once per key, and derived keys are
+/// cached by salt, so encrypting or decrypting a whole directory only derives a few keys
+pub struct SecretKey {
+    passphrase: Vec<u8>,
+    salt: [u8; SALT_LEN],
+    keys: HashMap<Vec<u8>, [u8; 32]>,
+    rng: SystemRandom,
+}
+
+impl SecretKey {
+    pub fn from_passphrase(passphrase: &str) -> Result<SecretKey, String> {
+        let passphrase = passphrase.trim();
+        if passphrase.is_empty() {
+            return Err("the key file is empty".to_string());
+        }
+        let rng = SystemRandom::new();
+        let mut salt = [0u8; SALT_LEN];
+        rng.fill(&mut salt)
+            .map_err(|_| "unable to generate a salt".to_string())?;
+        Ok(SecretKey {
+            passphrase: passphrase.as_bytes().to_vec(),
+            salt,
+            keys: HashMap::new(),
+            rng,
+        })
+    }
+
+    /// Read the passphrase from a key file
+    pub fn from_file(path: &str) -> Result<SecretKey, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("error while reading key file {}: {}", path, e))?;
+        Self::from_passphrase(&content)
+    }
+
+    fn cipher(&mut self, salt: &[u8]) -> Result<LessSafeKey, String> {
+        let passphrase = &self.passphrase;
+        let key = self.keys.entry(salt.to_vec()).or_insert_with(|| {
+            let mut key = [0u8; 32];
+            ring::pbkdf2::derive(
+                ring::pbkdf2::PBKDF2_HMAC_SHA256,
+                NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+                salt,
+                passphrase,
+                &mut key,
+            );
+            key
+        });
+        UnboundKey::new(&AES_256_GCM, key)
+            .map(LessSafeKey::new)
+            .map_err(|_| "invalid key".to_string())
+    }
+
+    /// Encrypt a json value as an `ENC[AES256_GCM,salt:...,iv:...,data:...]` string
+    pub fn encrypt(&mut self, value: &serde_json::Value) -> Result<String, String> {
+        let salt = self.salt;
+        let cipher = self.cipher(&salt)?;
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| "unable to generate a nonce".to_string())?;
+        let mut data = serde_json::to_vec(value).map_err(|e| e.to_string())?;
+        cipher
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| "encryption failed".to_string())?;
+        Ok(format!(
+            "{}salt:{},iv:{},data:{}]",
+            PREFIX,
+            general_purpose::STANDARD.encode(salt),
+            general_purpose::STANDARD.encode(nonce),
+            general_purpose::STANDARD.encode(data)
+        ))
+    }
+
+    /// Decrypt a value produced by `encrypt`
+    pub fn decrypt(&mut self, encrypted: &str) -> Result<serde_json::Value, String> {
+        let invalid = || "invalid encrypted value".to_string();
+        let fields: HashMap<&str, Vec<u8>> = encrypted
+            .strip_prefix(PREFIX)
+            .and_then(|e| e.strip_suffix(']'))
+            .ok_or_else(invalid)?
+            .split(',')
+            .map(|part| {
+                let (name, value) = part.split_once(':').ok_or_else(invalid)?;
+                let value = general_purpose::STANDARD
+                    .decode(value)
+                    .map_err(|_| invalid())?;
+                Ok((name, value))
+            })
+            .collect::<Result<_, String>>()?;
+        let (salt, iv, data) = match (fields.get("salt"), fields.get("iv"), fields.get("data")) {
+            (Some(salt), Some(iv), Some(data)) => (salt, iv, data),
+            _ => return Err(invalid()),
+        };
+        let nonce = Nonce::try_assume_unique_for_key(iv).map_err(|_| invalid())?;
+        let cipher = self.cipher(salt)?;
+        let mut data = data.clone();
+        let plain = cipher
+            .open_in_place(nonce, Aad::empty(), &mut data)
+            .map_err(|_| "unable to decrypt a value, wrong key ?".to_string())?;
+        serde_json::from_slice(plain).map_err(|_| invalid())
+    }
+}
+
+pub struct SecretHelper {}
+
+impl SecretHelper {
+    pub fn is_encrypted(value: &serde_json::Value) -> bool {
+        value
+            .as_str()
+            .map(|v| v.starts_with(PREFIX) && v.ends_with(']'))
+            .unwrap_or(false)
+    }
+
+    /// Does the value contain any encrypted field
+    pub fn contains_encrypted(value: &serde_json::Value) -> bool {
+        match value {
+            serde_json::Value::Object(obj) => obj.values().any(Self::contains_encrypted),
+            serde_json::Value::Array(arr) => arr.iter().any(Self::contains_encrypted),
+            other => Self::is_encrypted(other),
+        }
+    }
+
+    /// The encrypted value of `previous` when it holds the same plain value
+    fn unchanged(
+        previous: Option<&serde_json::Value>,
+        value: &serde_json::Value,
+        key: &mut SecretKey,
+    ) -> Option<serde_json::Value> {
+        let encrypted = previous.filter(|p| Self::is_encrypted(p))?;
+        match key.decrypt(encrypted.as_str().unwrap()) {
+            Ok(plain) if plain == *value => Some(encrypted.clone()),
+            _ => None,
+        }
+    }
+
+    fn walk(
+        path: &str,
+        value: &mut serde_json::Value,
+        previous: Option<&serde_json::Value>,
+        fields: &[String],
+        key: &mut SecretKey,
+        count: &mut usize,
+    ) -> Result<(), String> {
+        match value {
+            serde_json::Value::Object(obj) => {
+                for (name, child) in obj.iter_mut() {
+                    let child_path = if path.is_empty() {
+                        name.to_string()
+                    } else {
+                        format!("{}.{}", path, name)
+                    };
+                    let child_previous = previous.and_then(|p| p.get(name));
+                    let selected = fields.iter().any(|f| *f == *name || *f == child_path);
+                    if selected && !child.is_null() && !Self::is_encrypted(child) {
+                        *child = match Self::unchanged(child_previous, child, key) {
+                            Some(encrypted) => encrypted,
+                            None => serde_json::Value::String(key.encrypt(child)?),
+                        };
+                        *count += 1;
+                    } else {
+                        Self::walk(&child_path, child, child_previous, fields, key, count)?;
+                    }
+                }
+            }
+            serde_json::Value::Array(arr) => {
+                for (idx, child) in arr.iter_mut().enumerate() {
+                    let child_path = if path.is_empty() {
+                        idx.to_string()
+                    } else {
+                        format!("{}.{}", path, idx)
+                    };
+                    let child_previous = previous.and_then(|p| p.get(idx));
+                    Self::walk(&child_path, child, child_previous, fields, key, count)?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Encrypt the fields whose name or dotted path is listed, returns the number of encrypted fields
+    pub fn encrypt_fields(
+        value: &mut serde_json::Value,
+        fields: &[String],
+        key: &mut SecretKey,
+    ) -> Result<usize, String> {
+        let mut count = 0;
+        Self::walk("", value, None, fields, key, &mut count)?;
+        Ok(count)
+    }
+
+    /// Encrypt the fields like `encrypt_fields`, keeping the encrypted values of a previous
+    /// version of the entity when their plain value did not change. Encryption uses a random
+    /// nonce, so this is what makes encrypting an unchanged entity twice give the same content
+    pub fn encrypt_fields_since(
+        value: &mut serde_json::Value,
+        previous: &serde_json::Value,
+        fields: &[String],
+        key: &mut SecretKey,
+    ) -> Result<usize, String> {
+        let mut count = 0;
+        Self::walk("", value, Some(previous), fields, key, &mut count)?;
+        Ok(count)
+    }
+
+    /// Decrypt every encrypted field, returns the number of decrypted fields
+    pub fn decrypt_all(
+        value: &mut serde_json::Value,
+        key: &mut SecretKey,
+    ) -> Result<usize, String> {
+        match value {
+            serde_json::Value::Object(obj) => obj
+                .values_mut()
+                .map(|child| Self::decrypt_all(child, key))
+                .sum(),
+            serde_json::Value::Array(arr) => arr
+                .iter_mut()
+                .map(|child| Self::decrypt_all(child, key))
+                .sum(),
+            other if Self::is_encrypted(other) => {
+                *other = key.decrypt(other.as_str().unwrap())?;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+
+    pub fn default_fields() -> Vec<String> {
+        DEFAULT_SECRET_FIELDS
+            .iter()
+            .map(|f| f.to_string())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let mut key = SecretKey::from_passphrase("my passphrase\n").unwrap();
+        let original = serde_json::json!({
+            "clientId": "key_1",
+            "clientSecret": "very-secret",
+            "metadata": {"password": 42},
+            "plugins": [{"config": {"secret": {"a": true}}}]
+        });
+        let mut value = original.clone();
+        let count =
+            SecretHelper::encrypt_fields(&mut value, &SecretHelper::default_fields(), &mut key)
+                .unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(value["clientId"], "key_1");
+        assert!(SecretHelper::is_encrypted(&value["clientSecret"]));
+        assert!(SecretHelper::is_encrypted(
+            &value["plugins"][0]["config"]["secret"]
+        ));
+        assert!(SecretHelper::contains_encrypted(&value));
+
+        // a new key with the same passphrase has another salt but can decrypt
+        let mut other_key = SecretKey::from_passphrase("my passphrase").unwrap();
+        assert_eq!(
+            SecretHelper::decrypt_all(&mut value, &mut other_key).unwrap(),
+            3
+        );
+        assert_eq!(value, original);
+    }
+
+    #[test]
+    fn test_encrypt_by_path_is_idempotent() {
+        let mut key = SecretKey::from_passphrase("pass").unwrap();
+        let mut value = serde_json::json!({"config": {"token": "t", "other": {"token": "u"}}});
+        let fields = vec!["config.token".to_string()];
+        assert_eq!(
+            SecretHelper::encrypt_fields(&mut value, &fields, &mut key).unwrap(),
+            1
+        );
+        assert_eq!(
+            SecretHelper::encrypt_fields(&mut value, &fields, &mut key).unwrap(),
+            0
+        );
+        assert_eq!(value["config"]["other"]["token"], "u");
+    }
+
+    #[test]
+    fn test_encrypt_since_keeps_unchanged_values() {
+        let mut key = SecretKey::from_passphrase("pass").unwrap();
+        let fields = SecretHelper::default_fields();
+        let mut previous = serde_json::json!({"clientSecret": "a", "password": "b"});
+        SecretHelper::encrypt_fields(&mut previous, &fields, &mut key).unwrap();
+
+        let mut other_key = SecretKey::from_passphrase("pass").unwrap();
+        let mut value = serde_json::json!({"clientSecret": "a", "password": "c"});
+        SecretHelper::encrypt_fields_since(&mut value, &previous, &fields, &mut other_key).unwrap();
+        assert_eq!(value["clientSecret"], previous["clientSecret"]);
+        assert_ne!(value["password"], previous["password"]);
+        assert_eq!(
+            other_key
+                .decrypt(value["password"].as_str().unwrap())
+                .unwrap(),
+            "c"
+        );
+    }
+
+    #[test]
+    fn test_decrypt_with_wrong_key() {
+        let mut key = SecretKey::from_passphrase("pass").unwrap();
+        let encrypted = key.encrypt(&serde_json::json!("secret")).unwrap();
+        let mut wrong = SecretKey::from_passphrase("other").unwrap();
+        assert!(wrong.decrypt(&encrypted).is_err());
+        assert!(key.decrypt("ENC[AES256_GCM,salt:???]").is_err());
+        assert!(SecretKey::from_passphrase("  \n").is_err());
+    }
+}
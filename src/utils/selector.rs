@@ -0,0 +1,187 @@
+/// A requirement on one key of the `metadata` of an entity
+#[derive(Clone, Debug, PartialEq)]
+enum Requirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    In(String, Vec<String>),
+    NotIn(String, Vec<String>),
+    Exists(String),
+    NotExists(String),
+}
+
+/// The `-l` and `--tag` selectors, evaluated client-side against the `metadata` and `tags` of
+/// the entities. Like kubernetes label selectors, the requirements are separated by commas and
+/// can be `key=value`, `key==value`, `key!=value`, `key in (a,b)`, `key notin (a,b)`, `key` or
+/// `!key`. An entity matches when it matches every requirement and has every tag
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Selector {
+    requirements: Vec<Requirement>,
+    tags: Vec<String>,
+}
+
+impl Selector {
+    /// Split on the commas that are not inside the parentheses of a set
+    fn split(raw: &str) -> Vec<String> {
+        let mut parts: Vec<String> = Vec::new();
+        let mut current = String::new();
+        let mut depth = 0;
+        for c in raw.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => (),
+            }
+            if c == ',' && depth == 0 {
+                parts.push(std::mem::take(&mut current));
+            } else {
+                current.push(c);
+            }
+        }
+        parts.push(current);
+        parts
+            .into_iter()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect()
+    }
+
+    fn parse_set(raw: &str, requirement: &str) -> Result<Vec<String>, String> {
+        raw.trim()
+            .strip_prefix('(')
+            .and_then(|r| r.strip_suffix(')'))
+            .map(|r| {
+                r.split(',')
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+                    .collect()
+            })
+            .ok_or_else(|| format!("invalid set in selector '{}'", requirement))
+    }
+
+    fn parse_requirement(raw: &str) -> Result<Requirement, String> {
+        let key = |key: &str| -> Result<String, String> {
+            let key = key.trim();
+            if key.is_empty() || key.contains(char::is_whitespace) {
+                Err(format!("invalid key in selector '{}'", raw))
+            } else {
+                Ok(key.to_string())
+            }
+        };
+        if let Some((k, set)) = raw.split_once(" notin ") {
+            Ok(Requirement::NotIn(key(k)?, Self::parse_set(set, raw)?))
+        } else if let Some((k, set)) = raw.split_once(" in ") {
+            Ok(Requirement::In(key(k)?, Self::parse_set(set, raw)?))
+        } else if let Some((k, v)) = raw.split_once("!=") {
+            Ok(Requirement::NotEquals(key(k)?, v.trim().to_string()))
+        } else if let Some((k, v)) = raw.split_once("==").or_else(|| raw.split_once('=')) {
+            Ok(Requirement::Equals(key(k)?, v.trim().to_string()))
+        } else if let Some(k) = raw.strip_prefix('!') {
+            Ok(Requirement::NotExists(key(k)?))
+        } else {
+            Ok(Requirement::Exists(key(raw)?))
+        }
+    }
+
+    /// Parse the `-l` and `--tag` flags, `None` when there is nothing to select on
+    pub fn parse(selectors: &[String], tags: &[String]) -> Result<Option<Selector>, String> {
+        let requirements = selectors
+            .iter()
+            .flat_map(|s| Self::split(s))
+            .map(|r| Self::parse_requirement(&r))
+            .collect::<Result<Vec<Requirement>, String>>()?;
+        if requirements.is_empty() && tags.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Selector {
+            requirements,
+            tags: tags.to_vec(),
+        }))
+    }
+
+    fn metadata(entity: &serde_json::Value, key: &str) -> Option<String> {
+        entity
+            .get("metadata")
+            .and_then(|m| m.get(key))
+            .map(|v| match v {
+                serde_json::Value::String(str) => str.to_string(),
+                other => other.to_string(),
+            })
+    }
+
+    pub fn matches(&self, entity: &serde_json::Value) -> bool {
+        let tags: Vec<&str> = entity
+            .get("tags")
+            .and_then(|t| t.as_array())
+            .map(|t| t.iter().filter_map(|t| t.as_str()).collect())
+            .unwrap_or_default();
+        self.tags.iter().all(|tag| tags.contains(&tag.as_str()))
+            && self
+                .requirements
+                .iter()
+                .all(|requirement| match requirement {
+                    Requirement::Equals(k, v) => Self::metadata(entity, k).as_ref() == Some(v),
+                    Requirement::NotEquals(k, v) => Self::metadata(entity, k).as_ref() != Some(v),
+                    Requirement::In(k, set) => {
+                        Self::metadata(entity, k).is_some_and(|v| set.contains(&v))
+                    }
+                    Requirement::NotIn(k, set) => {
+                        !Self::metadata(entity, k).is_some_and(|v| set.contains(&v))
+                    }
+                    Requirement::Exists(k) => Self::metadata(entity, k).is_some(),
+                    Requirement::NotExists(k) => Self::metadata(entity, k).is_none(),
+                })
+    }
+
+    /// Keep the matching entities
+    pub fn select(&self, entities: Vec<serde_json::Value>) -> Vec<serde_json::Value> {
+        entities.into_iter().filter(|e| self.matches(e)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn routes() -> Vec<serde_json::Value> {
+        vec![
+            serde_json::json!({"id": "route_1", "metadata": {"env": "prod", "team": "a"}, "tags": ["public"]}),
+            serde_json::json!({"id": "route_2", "metadata": {"env": "dev", "team": "b"}, "tags": ["ephemeral", "public"]}),
+            serde_json::json!({"id": "route_3", "metadata": {"env": "dev"}, "tags": []}),
+        ]
+    }
+
+    fn select(selectors: &[&str], tags: &[&str]) -> Vec<String> {
+        let selector = Selector::parse(
+            &selectors.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+            &tags.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+        )
+        .unwrap()
+        .unwrap();
+        selector
+            .select(routes())
+            .iter()
+            .map(|r| r["id"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_requirements() {
+        assert_eq!(select(&["env=dev"], &[]), vec!["route_2", "route_3"]);
+        assert_eq!(select(&["env==dev,team!=b"], &[]), vec!["route_3"]);
+        assert_eq!(select(&["team in (a, b)"], &[]), vec!["route_1", "route_2"]);
+        assert_eq!(
+            select(&["team notin (a),env"], &[]),
+            vec!["route_2", "route_3"]
+        );
+        assert_eq!(select(&["!team"], &[]), vec!["route_3"]);
+        assert_eq!(select(&["env=dev"], &["ephemeral"]), vec!["route_2"]);
+        assert_eq!(select(&[], &["public"]), vec!["route_1", "route_2"]);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Selector::parse(&[], &[]).unwrap(), None);
+        assert!(Selector::parse(&["team in a,b".to_string()], &[]).is_err());
+        assert!(Selector::parse(&["=prod".to_string()], &[]).is_err());
+    }
+}
@@ -1,5 +1,7 @@
 use cli_table::{Cell, CellStruct, Color, Style, Table, print_stdout};
 
+use crate::utils::json_path::JsonPathHelper;
+use crate::utils::render::RenderHelper;
 use std::vec::Vec;
 
 pub struct TableResource {
@@ -9,47 +11,36 @@ pub struct TableResource {
 pub struct TableHelper {}
 
 impl TableHelper {
-    fn walk_through_object(
-        path: Vec<String>,
-        value: &serde_json::Map<String, serde_json::Value>,
-    ) -> serde_json::Map<String, serde_json::Value> {
-        if path.is_empty() {
-            value.to_owned()
-        } else {
-            value
-                .get(path.first().unwrap())
-                .unwrap()
-                .as_object()
-                .unwrap()
-                .to_owned()
-        }
-    }
-
-    fn column_as_cell(
-        name: &String,
-        value: &serde_json::Map<String, serde_json::Value>,
-    ) -> CellStruct {
-        match value.get(name) {
-            Some(serde_json::Value::Array(arr)) => {
+    fn value_as_cell(value: &serde_json::Value) -> CellStruct {
+        match value {
+            serde_json::Value::Array(arr) => {
                 arr.len().cell().justify(cli_table::format::Justify::Center)
             }
-            Some(serde_json::Value::Object(obj)) => {
+            serde_json::Value::Object(obj) => {
                 obj.len().cell().justify(cli_table::format::Justify::Center)
             }
-            Some(serde_json::Value::Bool(true)) => {
+            serde_json::Value::Bool(true) => {
                 "yes".cell().justify(cli_table::format::Justify::Center)
             }
-            Some(serde_json::Value::Bool(false)) => {
+            serde_json::Value::Bool(false) => {
                 "no".cell().justify(cli_table::format::Justify::Center)
             }
-            Some(serde_json::Value::Number(v)) => {
-                v.cell().justify(cli_table::format::Justify::Center)
-            }
-            Some(serde_json::Value::String(str)) => str.cell(),
+            serde_json::Value::Number(v) => v.cell().justify(cli_table::format::Justify::Center),
+            serde_json::Value::String(str) => str.cell(),
             _ => "".cell(),
         }
     }
 
+    /// A column is a path like `frontend.domains[0]`. A projection like
+    /// `backend.targets[*].hostname` displays the comma separated values
+    fn column_as_cell(column: &str, value: &serde_json::Value) -> CellStruct {
+        let values = JsonPathHelper::select(column, value).unwrap_or_default();
+        match values.as_slice() {
+            [single] => Self::value_as_cell(single),
+            _ => RenderHelper::cell_text(column, value).cell(),
+        }
+    }
+
     pub fn display_table_of_resources_with_custom_columns(
         vec: Vec<TableResource>,
         columns: Vec<String>,
@@ -57,28 +48,10 @@ impl TableHelper {
         let table = vec
             .into_iter()
             .map(|item| {
-                let value = item.raw.as_object().unwrap();
-                let values: Vec<CellStruct> = columns
+                columns
                     .iter()
-                    .cloned()
-                    .map(|name| {
-                        if name.contains(".") {
-                            let path: Vec<String> =
-                                name.split(".").map(|i| i.to_string()).collect();
-                            let last_name: String = path.last().unwrap().to_string();
-                            Self::column_as_cell(
-                                &last_name,
-                                &Self::walk_through_object(
-                                    path.get(0..path.len() - 1).unwrap().to_vec(),
-                                    value,
-                                ),
-                            )
-                        } else {
-                            Self::column_as_cell(&name, value)
-                        }
-                    })
-                    .collect();
-                values
+                    .map(|name| Self::column_as_cell(name, &item.raw))
+                    .collect::<Vec<CellStruct>>()
             })
             .table()
             .title(columns.into_iter().map(|name| {
@@ -99,17 +72,61 @@ impl TableHelper {
         let _ = print_stdout(table);
     }
 
-    pub fn display_table_of_resources_default(vec: Vec<TableResource>) {
-        Self::display_table_of_resources_with_custom_columns(
-            vec,
-            vec![
-                "id".to_string(),
-                "name".to_string(),
-                "description".to_string(),
-                "enabled".to_string(),
-                "tags".to_string(),
-                "metadata".to_string(),
-            ],
-        )
+    /// Display rows of counters, the first column being a label
+    pub fn display_summary(columns: Vec<&str>, rows: Vec<(String, Vec<usize>)>) {
+        let table = rows
+            .into_iter()
+            .map(|(label, counts)| {
+                let mut cells: Vec<CellStruct> = vec![label.cell()];
+                cells.extend(
+                    counts
+                        .into_iter()
+                        .map(|count| count.cell().justify(cli_table::format::Justify::Center)),
+                );
+                cells
+            })
+            .table()
+            .title(columns.into_iter().map(|name| {
+                name.cell()
+                    .bold(true)
+                    .background_color(Some(Color::Rgb(0, 0, 0)))
+                    .foreground_color(Some(Color::White))
+            }));
+        let _ = print_stdout(table);
+    }
+
+    pub fn default_columns() -> Vec<String> {
+        vec![
+            "id".to_string(),
+            "name".to_string(),
+            "description".to_string(),
+            "enabled".to_string(),
+            "tags".to_string(),
+            "metadata".to_string(),
+        ]
+    }
+
+    /// A line of space aligned cells, for output streamed row by row
+    pub fn format_row(cells: &[String], width: usize) -> String {
+        cells
+            .iter()
+            .map(|cell| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_row() {
+        assert_eq!(
+            TableHelper::format_row(&["ADDED".to_string(), "route_1".to_string()], 8),
+            "ADDED     route_1"
+        );
     }
 }
@@ -0,0 +1,128 @@
+/// Substitution of the `${env.NAME}` and `${vars.path.to.key}` placeholders of entity files.
+/// A default value can be provided with `${env.NAME:-default}`, and `$${env.NAME}` is kept as
+/// the literal `${env.NAME}`. Any other `${...}` expression is left untouched as it belongs to
+/// the otoroshi expression language
+pub struct TemplateHelper {}
+
+impl TemplateHelper {
+    const PREFIXES: [&'static str; 2] = ["${env.", "${vars."];
+
+    fn lookup_var(vars: &serde_json::Value, path: &str) -> Option<String> {
+        let mut current = vars;
+        for segment in path.split('.') {
+            current = match current {
+                serde_json::Value::Object(obj) => obj.get(segment)?,
+                serde_json::Value::Array(arr) => arr.get(segment.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        match current {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(str) => Some(str.to_string()),
+            other => Some(other.to_string()),
+        }
+    }
+
+    fn resolve(expression: &str, vars: &serde_json::Value) -> Option<String> {
+        let (name, default) = match expression.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expression, None),
+        };
+        let value = if let Some(name) = name.strip_prefix("env.") {
+            std::env::var(name).ok()
+        } else if let Some(path) = name.strip_prefix("vars.") {
+            Self::lookup_var(vars, path)
+        } else {
+            None
+        };
+        value.or(default.map(|d| d.to_string()))
+    }
+
+    /// Replace every placeholder of the content, or list all the unresolved ones with their line
+    pub fn render(content: &str, vars: &serde_json::Value) -> Result<String, String> {
+        let mut output = String::with_capacity(content.len());
+        let mut unresolved: Vec<String> = Vec::new();
+        let mut rest = content;
+        while let Some(pos) = rest.find("${") {
+            let (before, from) = rest.split_at(pos);
+            if !Self::PREFIXES.iter().any(|p| from.starts_with(p)) {
+                output.push_str(before);
+                output.push_str("${");
+                rest = &from[2..];
+                continue;
+            }
+            let Some(end) = from.find('}') else {
+                output.push_str(rest);
+                rest = "";
+                break;
+            };
+            if let Some(before) = before.strip_suffix('$') {
+                output.push_str(before);
+                output.push_str(&from[..=end]);
+            } else {
+                output.push_str(before);
+                match Self::resolve(&from[2..end], vars) {
+                    Some(value) => output.push_str(&value),
+                    None => {
+                        let line = content.len() - rest.len() + pos;
+                        unresolved.push(format!(
+                            "line {}: unresolved placeholder {}",
+                            content[..line].matches('\n').count() + 1,
+                            &from[..=end]
+                        ));
+                    }
+                }
+            }
+            rest = &from[end + 1..];
+        }
+        output.push_str(rest);
+        if unresolved.is_empty() {
+            Ok(output)
+        } else {
+            Err(unresolved.join("\n"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_vars_and_defaults() {
+        let vars = serde_json::json!({"domain": "oto.tools", "backend": {"targets": ["a", "b"], "port": 8080}});
+        let content = "domains: [\"api.${vars.domain}\"]\nhost: ${vars.backend.targets.1}:${vars.backend.port}\nenv: ${env.OTOROSHICTL_TEST_UNKNOWN_VAR:-dev}";
+        assert_eq!(
+            TemplateHelper::render(content, &vars).unwrap(),
+            "domains: [\"api.oto.tools\"]\nhost: b:8080\nenv: dev"
+        );
+    }
+
+    #[test]
+    fn test_render_env() {
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(
+            TemplateHelper::render("${env.PATH}", &serde_json::Value::Null).unwrap(),
+            path
+        );
+    }
+
+    #[test]
+    fn test_render_keeps_expression_language_and_escapes() {
+        let content = "header: ${req.headers.host} $${vars.domain}";
+        assert_eq!(
+            TemplateHelper::render(content, &serde_json::json!({})).unwrap(),
+            "header: ${req.headers.host} ${vars.domain}"
+        );
+    }
+
+    #[test]
+    fn test_render_unresolved_placeholders() {
+        let content = "id: route_1\nname: ${vars.name}\nhost: ${env.OTOROSHICTL_TEST_UNKNOWN_VAR}";
+        let err = TemplateHelper::render(content, &serde_json::json!({})).unwrap_err();
+        assert_eq!(
+            err,
+            "line 2: unresolved placeholder ${vars.name}\nline 3: unresolved placeholder ${env.OTOROSHICTL_TEST_UNKNOWN_VAR}"
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::utils::entity::EntityHelper;
+
+/// A change of an entity between two polls, like the events of `kubectl get -w`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WatchEvent {
+    /// one of ADDED, MODIFIED or DELETED
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub kind: String,
+    pub entity: serde_json::Value,
+}
+
+pub struct WatchHelper {}
+
+impl WatchHelper {
+    fn event(event_type: &str, kind: &str, entity: serde_json::Value) -> WatchEvent {
+        WatchEvent {
+            event_type: event_type.to_string(),
+            kind: kind.to_string(),
+            entity,
+        }
+    }
+
+    /// Compare the entities of a poll with the previous ones by id. Returns the events, added and
+    /// modified entities first in the order of the poll, then the deleted ones, and the new state.
+    /// Entities without id are ignored
+    pub fn events(
+        kind: &str,
+        previous: &BTreeMap<String, serde_json::Value>,
+        current: Vec<serde_json::Value>,
+    ) -> (Vec<WatchEvent>, BTreeMap<String, serde_json::Value>) {
+        let mut events: Vec<WatchEvent> = Vec::new();
+        let mut state: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        for entity in current.into_iter() {
+            let Some(id) = EntityHelper::extract_json_entity_id(&entity) else {
+                continue;
+            };
+            match previous.get(&id) {
+                None => events.push(Self::event("ADDED", kind, entity.clone())),
+                Some(old) if *old != entity => {
+                    events.push(Self::event("MODIFIED", kind, entity.clone()))
+                }
+                Some(_) => (),
+            }
+            seen.insert(id.clone());
+            state.insert(id, entity);
+        }
+        for (id, entity) in previous.iter() {
+            if !seen.contains(id) {
+                events.push(Self::event("DELETED", kind, entity.clone()));
+            }
+        }
+        (events, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_by_id() {
+        let kind = "proxy.otoroshi.io/Route";
+        let (events, state) = WatchHelper::events(
+            kind,
+            &BTreeMap::new(),
+            vec![
+                serde_json::json!({"id": "route_1", "enabled": true}),
+                serde_json::json!({"id": "route_2", "enabled": true}),
+                serde_json::json!({"name": "no id"}),
+            ],
+        );
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| e.event_type == "ADDED"));
+
+        let (events, state) = WatchHelper::events(
+            kind,
+            &state,
+            vec![
+                serde_json::json!({"id": "route_3", "enabled": true}),
+                serde_json::json!({"id": "route_1", "enabled": false}),
+            ],
+        );
+        let types: Vec<&str> = events.iter().map(|e| e.event_type.as_str()).collect();
+        assert_eq!(types, vec!["ADDED", "MODIFIED", "DELETED"]);
+        assert_eq!(events[2].entity["id"], "route_2");
+        assert_eq!(serde_json::to_value(&events[2]).unwrap()["type"], "DELETED");
+        assert_eq!(state.len(), 2);
+
+        let (events, _) = WatchHelper::events(kind, &state, state.values().cloned().collect());
+        assert!(events.is_empty());
+    }
+}
@@ -45,6 +45,21 @@ fn test_config_use_nonexistent_context() {
         .stdout(predicate::str::contains("does not exist"));
 }
 
+#[test]
+fn test_config_list_contexts_tabular_formats() {
+    let cli = OtoroshiCtl::new();
+    cli.run(&["config", "list-contexts", "-o", "csv"])
+        .success()
+        .stdout(predicate::str::starts_with(
+            "name,cluster,user,cloud_apim\n",
+        ))
+        .stdout(predicate::str::contains("default,"));
+    cli.run(&["config", "list", "-o", "markdown"])
+        .success()
+        .stdout(predicate::str::contains("| name | current | cloud_apim |"))
+        .stdout(predicate::str::contains("| default | yes |"));
+}
+
 // -----------------------------------------------------------------------------
 // Cluster management
 // -----------------------------------------------------------------------------
@@ -103,6 +103,88 @@ fn test_resources_get_nonexistent_route() {
         .stdout(predicate::str::contains("not found"));
 }
 
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_describe_route() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+
+    cli.run(&["resources", "apply", "-f", test_data.route_path()])
+        .success();
+
+    cli.run(&["resources", "describe", "route", "test-route-1"])
+        .success()
+        .stdout(predicate::str::contains("Id:       test-route-1"))
+        .stdout(predicate::str::contains("Plugins:"))
+        .stdout(predicate::str::contains("Authorized apikeys:"));
+
+    cli.run(&[
+        "resources",
+        "describe",
+        "route",
+        "test-route-1",
+        "-o",
+        "json",
+    ])
+    .success()
+    .stdout(predicate::str::contains("\"certificates\":"));
+
+    // Cleanup
+    cli.run(&["resources", "delete", "routes", "test-route-1"])
+        .success();
+}
+
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_history_and_rollback() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+
+    cli.run(&["resources", "apply", "-f", test_data.route_path()])
+        .success();
+    cli.run(&[
+        "resources",
+        "patch",
+        "routes",
+        "test-route-1",
+        r#"{"enabled":false}"#,
+    ])
+    .success();
+
+    cli.run(&["resources", "history", "routes", "test-route-1"])
+        .success()
+        .stdout(predicate::str::contains("rev 1"))
+        .stdout(predicate::str::contains("replaced by patch"))
+        .stdout(predicate::str::contains("enabled: true -> false"));
+
+    cli.run(&[
+        "resources",
+        "rollback",
+        "routes",
+        "test-route-1",
+        "--to",
+        "1",
+    ])
+    .success()
+    .stdout(predicate::str::contains("restored to rev 1"));
+
+    let output = cli.run_success(&["resources", "get", "routes", "test-route-1", "-o", "json"]);
+    let parsed: serde_json::Value = serde_json::from_str(&output).expect("Invalid JSON output");
+    assert_eq!(parsed["enabled"], true);
+
+    // the rollback itself is recorded
+    cli.run(&["resources", "history", "routes", "test-route-1"])
+        .success()
+        .stdout(predicate::str::contains("rev 2"))
+        .stdout(predicate::str::contains("replaced by rollback"));
+
+    // Cleanup
+    cli.run(&["resources", "delete", "routes", "test-route-1"])
+        .success();
+}
+
 // -----------------------------------------------------------------------------
 // TEMPLATE command
 // -----------------------------------------------------------------------------
@@ -233,10 +315,688 @@ fn test_resources_delete_from_file() {
         .stdout(predicate::str::contains("not found"));
 }
 
+// -----------------------------------------------------------------------------
+// DIFF
+// -----------------------------------------------------------------------------
+
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_diff_drift_exit_code() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+
+    // Not applied yet: the route is reported as added and the command fails
+    cli.run(&[
+        "resources",
+        "diff",
+        "-f",
+        test_data.route_path(),
+        "--ignore-removed",
+    ])
+    .code(1)
+    .stdout(predicate::str::contains("test-route-1"));
+
+    cli.run(&["resources", "apply", "-f", test_data.route_path()])
+        .success();
+
+    // Applied: no drift
+    cli.run(&[
+        "resources",
+        "diff",
+        "-f",
+        test_data.route_path(),
+        "--ignore-removed",
+    ])
+    .success()
+    .stdout(predicate::str::contains("0 added, 0 changed"));
+
+    // Cleanup
+    cli.run(&["resources", "delete", "routes", "test-route-1"])
+        .success();
+}
+
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_diff_json_output() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+
+    let output = cli
+        .run(&[
+            "resources",
+            "diff",
+            "-f",
+            test_data.route_path(),
+            "--ignore-removed",
+            "-o",
+            "json",
+        ])
+        .code(1);
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout).to_string();
+    let parsed: serde_json::Value = serde_json::from_str(&stdout).expect("Invalid JSON output");
+    assert_eq!(parsed[0]["status"], "added");
+}
+
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_apply_dry_run_does_not_write() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+
+    let output = cli
+        .run(&[
+            "resources",
+            "apply",
+            "-f",
+            test_data.route_path(),
+            "--dry-run",
+            "-o",
+            "json",
+        ])
+        .success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout).to_string();
+    let parsed: serde_json::Value = serde_json::from_str(&stdout).expect("Invalid JSON output");
+    assert_eq!(parsed[0]["action"], "create");
+
+    // Nothing has been created
+    cli.run(&["resources", "get", "routes", "test-route-1"])
+        .success()
+        .stdout(predicate::str::contains("not found"));
+}
+
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_edit_data_dry_run_plan() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+
+    cli.run(&["resources", "apply", "-f", test_data.route_path()])
+        .success();
+
+    let plan = |data: &str| -> serde_json::Value {
+        let stdout = cli.run_success(&[
+            "resources",
+            "edit",
+            "route",
+            "test-route-1",
+            "--data",
+            data,
+            "--dry-run",
+            "-o",
+            "json",
+        ]);
+        serde_json::from_str(&stdout).expect("Invalid JSON output")
+    };
+    let unchanged = plan("name=Test Route 1");
+    assert_eq!(unchanged[0]["kind"], "proxy.otoroshi.io/Route");
+    assert_eq!(unchanged[0]["action"], "unchanged");
+    assert_eq!(plan("enabled=false")[0]["action"], "update");
+
+    // Cleanup
+    cli.run(&["resources", "delete", "routes", "test-route-1"])
+        .success();
+}
+
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_apply_prune_removes_undeclared_entities() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let entities = test_data.dir.path().join("entities");
+    std::fs::create_dir(&entities).unwrap();
+    let apply = |extra: &[&str]| {
+        let mut args = vec![
+            "resources",
+            "apply",
+            "-d",
+            entities.to_str().unwrap(),
+            "--prune",
+            "--managed-by",
+            "prune-test",
+        ];
+        args.extend_from_slice(extra);
+        cli.run(&args)
+    };
+
+    std::fs::copy(test_data.multi_route_path(), entities.join("routes.yaml")).unwrap();
+    apply(&[]).success();
+
+    // test-route-2 is not declared anymore
+    std::fs::copy(test_data.route_path(), entities.join("routes.yaml")).unwrap();
+    apply(&["--dry-run"])
+        .success()
+        .stdout(predicate::str::contains("test-route-2"))
+        .stdout(predicate::str::contains("delete"));
+
+    apply(&[])
+        .success()
+        .stdout(predicate::str::contains("pruned"));
+
+    cli.run(&["resources", "get", "routes", "test-route-2"])
+        .success()
+        .stdout(predicate::str::contains("not found"));
+
+    // Cleanup
+    cli.run(&["resources", "delete", "routes", "test-route-1"])
+        .success();
+}
+
+#[test]
+fn test_resources_apply_prune_needs_directory_and_managed_by() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+
+    cli.run(&["resources", "apply", "-d", "entities", "--prune"])
+        .failure()
+        .stderr(predicate::str::contains("--managed-by"));
+    cli.run(&[
+        "resources",
+        "apply",
+        "-f",
+        test_data.route_path(),
+        "--prune",
+        "--managed-by",
+        "team",
+    ])
+    .failure()
+    .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_patch_json_patch() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+
+    cli.run(&["resources", "apply", "-f", test_data.route_path()])
+        .success();
+
+    // A failing test operation must not write anything
+    cli.run(&[
+        "resources",
+        "patch",
+        "routes",
+        "test-route-1",
+        r#"[{"op":"replace","path":"/enabled","value":false},{"op":"test","path":"/id","value":"nope"}]"#,
+    ])
+    .failure();
+
+    cli.run(&[
+        "resources",
+        "patch",
+        "routes",
+        "test-route-1",
+        r#"[{"op":"test","path":"/id","value":"test-route-1"},{"op":"replace","path":"/enabled","value":false}]"#,
+    ])
+    .success();
+
+    let output = cli
+        .run(&["resources", "get", "routes", "test-route-1", "-o", "json"])
+        .success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout).to_string();
+    let parsed: serde_json::Value = serde_json::from_str(&stdout).expect("Invalid JSON output");
+    assert_eq!(parsed["enabled"], false);
+
+    // Cleanup
+    cli.run(&["resources", "delete", "routes", "test-route-1"])
+        .success();
+}
+
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_apply_unresolved_reference() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let route_file = test_data.dir.path().join("route_with_ref.json");
+    std::fs::write(
+        &route_file,
+        r#"{"kind": "Route", "id": "test-route-ref", "name": "test-route-ref", "backend_ref": "test-missing-backend"}"#,
+    )
+    .unwrap();
+
+    cli.run(&["resources", "apply", "-f", route_file.to_str().unwrap()])
+        .failure()
+        .stderr(predicate::str::contains("test-missing-backend"));
+
+    // Nothing has been written
+    cli.run(&["resources", "get", "routes", "test-route-ref"])
+        .success()
+        .stdout(predicate::str::contains("not found"));
+}
+
 // -----------------------------------------------------------------------------
 // EXPORT / IMPORT
 // -----------------------------------------------------------------------------
 
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_export_normalize_is_deterministic() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let first = test_data.dir.path().join("first");
+    let second = test_data.dir.path().join("second");
+
+    cli.run(&["resources", "apply", "-f", test_data.route_path()])
+        .success();
+
+    for dir in [&first, &second] {
+        cli.run(&[
+            "resources",
+            "export",
+            "-d",
+            dir.to_str().unwrap(),
+            "--split-files",
+            "--normalize",
+            "-o",
+            "yaml",
+        ])
+        .success();
+    }
+
+    let files = |dir: &std::path::Path| -> Vec<(String, Vec<u8>)> {
+        let mut files: Vec<(String, Vec<u8>)> = walkdir::WalkDir::new(dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| {
+                (
+                    e.path().strip_prefix(dir).unwrap().display().to_string(),
+                    std::fs::read(e.path()).unwrap(),
+                )
+            })
+            .collect();
+        files.sort();
+        files
+    };
+    let exported = files(&first);
+    assert!(!exported.is_empty());
+    assert!(exported.iter().any(|(path, _)| path.starts_with("routes/")));
+    assert_eq!(exported, files(&second));
+
+    // Cleanup
+    cli.run(&["resources", "delete", "routes", "test-route-1"])
+        .success();
+}
+
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_export_normalize_keeps_unchanged_ciphertexts() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let key_file = test_data.dir.path().join("key.txt");
+    std::fs::write(&key_file, "my passphrase").unwrap();
+    let apikey = test_data.dir.path().join("apikey.yaml");
+    std::fs::write(
+        &apikey,
+        "kind: apim.otoroshi.io/Apikey\nclientId: test-normalize-key\nclientSecret: very-secret\nclientName: Test Normalize Key\nauthorizedEntities: []\n",
+    )
+    .unwrap();
+    let exported = test_data.dir.path().join("exported");
+    let export = || {
+        cli.run(&[
+            "resources",
+            "export",
+            "-d",
+            exported.to_str().unwrap(),
+            "--split-files",
+            "--normalize",
+            "--encrypt",
+            "--key-file",
+            key_file.to_str().unwrap(),
+            "-o",
+            "yaml",
+        ])
+        .success();
+        std::fs::read_to_string(exported.join("apikeys/test-normalize-key.yaml")).unwrap()
+    };
+
+    cli.run(&["resources", "apply", "-f", apikey.to_str().unwrap()])
+        .success();
+    let first = export();
+    assert!(first.contains("ENC[AES256_GCM,"));
+    assert_eq!(first, export());
+
+    // Cleanup
+    cli.run(&["resources", "delete", "apikeys", "test-normalize-key"])
+        .success();
+}
+
+#[test]
+fn test_resources_export_normalize_requires_directory() {
+    let cli = OtoroshiCtl::new();
+
+    cli.run(&["resources", "export", "-f", "export.json", "--normalize"])
+        .failure()
+        .stderr(predicate::str::contains("--directory"));
+}
+
+const OPENAPI_SPEC: &str = r#"
+openapi: 3.0.3
+info:
+  title: Test Openapi
+  version: 1.0.0
+paths:
+  /pets:
+    get:
+      tags: [pets]
+  /pets/{petId}:
+    delete:
+      tags: [pets]
+  /orders:
+    post:
+      tags: [store]
+"#;
+
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_import_openapi() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let spec = test_data.dir.path().join("openapi.yaml");
+    std::fs::write(&spec, OPENAPI_SPEC).unwrap();
+    let out = test_data.dir.path().join("routes");
+
+    cli.run(&[
+        "resources",
+        "import-openapi",
+        spec.to_str().unwrap(),
+        "--domain",
+        "openapi.oto.tools",
+        "--backend",
+        "http://pets.svc:8080",
+        "--group-by",
+        "tag",
+        "--out-dir",
+        out.to_str().unwrap(),
+    ])
+    .success();
+    let pets =
+        std::fs::read_to_string(out.join("routes").join("route_test-openapi-pets.yaml")).unwrap();
+    assert!(pets.contains("openapi.oto.tools/pets"));
+    assert!(pets.contains("hostname: pets.svc"));
+    assert!(
+        out.join("routes")
+            .join("route_test-openapi-store.yaml")
+            .exists()
+    );
+
+    cli.run(&[
+        "resources",
+        "import-openapi",
+        spec.to_str().unwrap(),
+        "--domain",
+        "openapi.oto.tools",
+        "--backend",
+        "http://pets.svc:8080",
+        "--apply",
+    ])
+    .success();
+    cli.run(&["resources", "get", "route", "route_test-openapi"])
+        .success()
+        .stdout(predicate::str::contains("Test Openapi"));
+
+    // Cleanup
+    cli.run(&["resources", "delete", "routes", "route_test-openapi"])
+        .success();
+}
+
+#[test]
+fn test_resources_import_openapi_invalid_grouping() {
+    let cli = OtoroshiCtl::new();
+
+    cli.run(&[
+        "resources",
+        "import-openapi",
+        "openapi.yaml",
+        "--domain",
+        "api.oto.tools",
+        "--backend",
+        "http://pets.svc:8080",
+        "--group-by",
+        "operation",
+    ])
+    .failure()
+    .stderr(predicate::str::contains("can be one of: tag, path"));
+}
+
+#[test]
+fn test_resources_import_k8s_offline() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let manifests = test_data.dir.path().join("manifests");
+    std::fs::create_dir_all(&manifests).unwrap();
+    std::fs::write(
+        manifests.join("ingress.yaml"),
+        "apiVersion: networking.k8s.io/v1\nkind: Ingress\nmetadata:\n  name: web\n  namespace: shop\nspec:\n  defaultBackend:\n    service:\n      name: front\n      port:\n        number: 80\n  rules:\n    - host: shop.oto.tools\n      http:\n        paths:\n          - path: /api\n            pathType: Prefix\n            backend:\n              service:\n                name: api\n                port:\n                  number: 8080\n",
+    )
+    .unwrap();
+    std::fs::write(
+        manifests.join("httproute.yaml"),
+        "apiVersion: gateway.networking.k8s.io/v1\nkind: HTTPRoute\nmetadata:\n  name: store\nspec:\n  hostnames: [store.oto.tools]\n  rules:\n    - backendRefs:\n        - name: store\n          port: 80\n",
+    )
+    .unwrap();
+    let out = test_data.dir.path().join("out");
+
+    cli.cmd()
+        .args([
+            "resources",
+            "import-k8s",
+            "-d",
+            manifests.to_str().unwrap(),
+            "--out-dir",
+            out.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Ingress shop/web: the default backend is not translated",
+        ))
+        .stderr(predicate::str::contains("2 routes translated, 1 issues"));
+    let ingress =
+        std::fs::read_to_string(out.join("routes").join("route_ingress-shop-web-1.yaml")).unwrap();
+    assert!(ingress.contains("shop.oto.tools/api"));
+    assert!(ingress.contains("hostname: api.shop.svc.cluster.local"));
+    assert!(
+        out.join("routes")
+            .join("route_httproute-default-store-1.yaml")
+            .exists()
+    );
+}
+
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_migrate_service_descriptors() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let descriptor = test_data.dir.path().join("descriptor.yaml");
+    std::fs::write(
+        &descriptor,
+        "kind: ServiceDescriptor\nid: test-service-1\nname: Test Service 1\nenv: prod\ndomain: oto.tools\nsubdomain: test-migrate\nenabled: true\ntargets:\n  - host: request.otoroshi.io\n    scheme: https\npublicPatterns:\n  - /.*\n",
+    )
+    .unwrap();
+    let report = test_data.dir.path().join("report.json");
+
+    cli.run(&["resources", "apply", "-f", descriptor.to_str().unwrap()])
+        .success();
+    cli.run(&[
+        "resources",
+        "migrate",
+        "service-descriptors",
+        "test-service-1",
+        "--apply",
+        "--report-file",
+        report.to_str().unwrap(),
+    ])
+    .success()
+    .stdout(predicate::str::contains(
+        "service descriptor test-service-1 disabled",
+    ));
+
+    let route = cli.run_success(&[
+        "resources",
+        "get",
+        "route",
+        "route_test-service-1",
+        "-o",
+        "json",
+    ]);
+    assert!(route.contains("test-migrate.oto.tools"));
+    let descriptor = cli.run_success(&[
+        "resources",
+        "get",
+        "service-descriptor",
+        "test-service-1",
+        "-o",
+        "json",
+    ]);
+    let descriptor: serde_json::Value = serde_json::from_str(&descriptor).unwrap();
+    assert_eq!(descriptor["enabled"], false);
+    let report: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    assert_eq!(report[0]["route"], "route_test-service-1");
+
+    // Cleanup
+    cli.run(&["resources", "delete", "routes", "route_test-service-1"])
+        .success();
+    cli.run(&[
+        "resources",
+        "delete",
+        "service-descriptors",
+        "test-service-1",
+    ])
+    .success();
+}
+
+#[test]
+fn test_resources_migrate_dry_run_requires_apply() {
+    let cli = OtoroshiCtl::new();
+
+    cli.run(&["resources", "migrate", "service-descriptors", "--dry-run"])
+        .failure()
+        .stderr(predicate::str::contains("--apply"));
+}
+
+#[test]
+#[ignore]
+#[serial]
+fn test_resources_copy_between_contexts() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+
+    cli.run(&["resources", "apply", "-f", test_data.route_path()])
+        .success();
+    cli.run(&[
+        "resources",
+        "copy",
+        "route",
+        "test-route-1",
+        "--to-context",
+        "default",
+        "--map-id",
+        "test-route-1=test-route-copy",
+        "--set",
+        "name=Test Route Copy",
+        "--dry-run",
+    ])
+    .success()
+    .stdout(predicate::str::contains(
+        "+ test-route-copy (Test Route Copy)",
+    ));
+    cli.run(&["resources", "get", "route", "test-route-copy"])
+        .success()
+        .stdout(predicate::str::contains("not found"));
+
+    cli.run(&[
+        "resources",
+        "copy",
+        "route",
+        "test-route-1",
+        "--from-context",
+        "default",
+        "--to-context",
+        "default",
+        "--map-id",
+        "test-route-1=test-route-copy",
+        "--set",
+        "name=Test Route Copy",
+        "--set",
+        "enabled=false",
+    ])
+    .success()
+    .stdout(predicate::str::contains(
+        "test-route-copy copied to default",
+    ));
+    let copy = cli.run_success(&["resources", "get", "route", "test-route-copy", "-o", "json"]);
+    let copy: serde_json::Value = serde_json::from_str(&copy).unwrap();
+    assert_eq!(copy["name"], "Test Route Copy");
+    assert_eq!(copy["enabled"], false);
+
+    cli.run(&["resources", "delete", "route", "test-route-copy"])
+        .success();
+}
+
+#[test]
+fn test_resources_copy_unknown_context() {
+    let cli = OtoroshiCtl::new();
+
+    cli.run(&[
+        "resources",
+        "copy",
+        "route",
+        "test-route-1",
+        "--to-context",
+        "nope",
+    ])
+    .failure()
+    .stderr(predicate::str::contains("context nope not found"));
+    cli.run(&["resources", "copy", "route", "--to-context", "default"])
+        .failure()
+        .stderr(predicate::str::contains("ids or a selector"));
+}
+
+#[test]
+fn test_resources_invalid_data_inputs() {
+    let cli = OtoroshiCtl::new();
+
+    cli.run(&[
+        "resources",
+        "copy",
+        "route",
+        "test-route-1",
+        "--to-context",
+        "default",
+        "--set",
+        "name",
+    ])
+    .failure()
+    .stderr(predicate::str::contains(
+        "invalid input 'name', expected PATH=VALUE",
+    ));
+    cli.run(&[
+        "resources",
+        "patch",
+        "routes",
+        "test-route-1",
+        "--data",
+        "=Test Route",
+    ])
+    .failure()
+    .stderr(predicate::str::contains(
+        "invalid input '=Test Route', expected PATH=VALUE",
+    ));
+}
+
 #[test]
 #[ignore]
 #[serial]
@@ -383,9 +1143,401 @@ fn test_resources_help() {
         .stdout(predicate::str::contains("template"));
 }
 
+#[test]
+fn test_resources_diff_help() {
+    let cli = OtoroshiCtl::new();
+
+    cli.run(&["resources", "diff", "--help"])
+        .success()
+        .stdout(predicate::str::contains("--ignore-removed"));
+}
+
+#[test]
+fn test_resources_rollback_help() {
+    let cli = OtoroshiCtl::new();
+
+    cli.run(&["resources", "rollback", "--help"])
+        .success()
+        .stdout(predicate::str::contains("--to <REV>"))
+        .stdout(predicate::str::contains("--dry-run"));
+}
+
+#[test]
+fn test_resources_apply_help() {
+    let cli = OtoroshiCtl::new();
+
+    cli.run(&["resources", "apply", "--help"])
+        .success()
+        .stdout(predicate::str::contains("--dry-run"))
+        .stdout(predicate::str::contains("--prune"))
+        .stdout(predicate::str::contains("--concurrency"))
+        .stdout(predicate::str::contains("--skip-validation"));
+}
+
+#[test]
+fn test_resources_lint_files() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let route_file = test_data.dir.path().join("public_route.yaml");
+    std::fs::write(
+        &route_file,
+        "kind: Route\nid: test-route-public\nname: public\nfrontend:\n  domains:\n    - \"*.oto.tools\"\nplugins: []\n",
+    )
+    .unwrap();
+    let route = route_file.to_str().unwrap();
+
+    cli.cmd()
+        .current_dir(test_data.dir.path())
+        .args(["resources", "lint", "-f", route])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "public_route.yaml:7: error [route-without-authentication]",
+        ))
+        .stdout(predicate::str::contains(
+            "public_route.yaml:5: note [wildcard-domain]",
+        ));
+
+    let output = cli
+        .cmd()
+        .current_dir(test_data.dir.path())
+        .args(["resources", "lint", "-f", route, "-o", "sarif"])
+        .assert()
+        .code(1);
+    let sarif: serde_json::Value =
+        serde_json::from_slice(&output.get_output().stdout).expect("Invalid SARIF output");
+    assert_eq!(sarif["version"], "2.1.0");
+    assert_eq!(
+        sarif["runs"][0]["results"][0]["ruleId"],
+        "route-without-authentication"
+    );
+
+    std::fs::write(
+        test_data.dir.path().join(".otoroshictl-lint.yaml"),
+        "rules:\n  route-without-authentication:\n    severity: warning\n",
+    )
+    .unwrap();
+    cli.cmd()
+        .current_dir(test_data.dir.path())
+        .args(["resources", "lint", "-f", route])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("0 errors, 1 warnings, 1 notes"));
+}
+
+#[test]
+fn test_resources_validate_offline() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let cache = test_data.dir.path().join("schemas");
+    std::fs::create_dir_all(&cache).unwrap();
+    std::fs::write(
+        cache.join("entities.json"),
+        r#"{"version":"v1","resources":[{"kind":"Route","plural_name":"routes","singular_name":"route","group":"proxy.otoroshi.io","version":{"name":"v1","served":true,"deprecated":false,"storage":true}}]}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        cache.join("proxy.otoroshi.io_Route_v1.json"),
+        r#"{"type":"object","additionalProperties":false,"properties":{"id":{"type":"string"},"name":{"type":"string"},"frontend":{"type":"object","additionalProperties":false,"properties":{"domains":{"type":"array","items":{"type":"string"}}}}}}"#,
+    )
+    .unwrap();
+    let route_file = test_data.dir.path().join("typo_route.yaml");
+    std::fs::write(
+        &route_file,
+        "kind: Route\nid: test-route-typo\nname: typo\nfrontend:\n  domainz:\n    - typo.oto.tools\n",
+    )
+    .unwrap();
+
+    cli.cmd()
+        .env("OTOROSHICTL_SCHEMA_CACHE", &cache)
+        .args([
+            "resources",
+            "validate",
+            "-f",
+            route_file.to_str().unwrap(),
+            "--offline",
+        ])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "typo_route.yaml:5: unknown field frontend.domainz",
+        ));
+}
+
+#[test]
+fn test_resources_convert_offline() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let ndjson = test_data.dir.path().join("export.ndjson");
+    std::fs::write(
+        &ndjson,
+        "{\"type\":\"route\",\"value\":{\"id\":\"route_1\",\"name\":\"api\"}}\n{\"kind\":\"proxy.otoroshi.io/Route\",\"id\":\"route_2\",\"name\":\"admin\"}\n",
+    )
+    .unwrap();
+    let cache = test_data.dir.path().join("schemas");
+    std::fs::create_dir_all(&cache).unwrap();
+    std::fs::write(
+        cache.join("entities.json"),
+        r#"{"version":"v1","resources":[{"kind":"Route","plural_name":"routes","singular_name":"route","group":"proxy.otoroshi.io","version":{"name":"v1","served":true,"deprecated":false,"storage":true}}]}"#,
+    )
+    .unwrap();
+    let manifests = test_data.dir.path().join("manifests");
+
+    cli.cmd()
+        .env("OTOROSHICTL_SCHEMA_CACHE", &cache)
+        .args([
+            "resources",
+            "convert",
+            "-f",
+            ndjson.to_str().unwrap(),
+            "--to",
+            "kube",
+            "--out-dir",
+            manifests.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    let kube = std::fs::read_to_string(manifests.join("routes").join("route_1.yaml")).unwrap();
+    assert!(kube.contains("kind: Route"));
+    assert!(kube.contains("metadata:\n  name: api"));
+    assert!(manifests.join("routes").join("route_2.yaml").exists());
+
+    cli.cmd()
+        .env("OTOROSHICTL_SCHEMA_CACHE", &cache)
+        .args([
+            "resources",
+            "convert",
+            "-d",
+            manifests.to_str().unwrap(),
+            "--recursive",
+            "--to",
+            "ndjson",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            "{\"id\":\"route_1\",\"kind\":\"proxy.otoroshi.io/Route\",\"name\":\"api\"}\n{\"id\":\"route_2\",\"kind\":\"proxy.otoroshi.io/Route\",\"name\":\"admin\"}\n",
+        );
+
+    cli.cmd()
+        .args([
+            "resources",
+            "convert",
+            "-f",
+            ndjson.to_str().unwrap(),
+            "--to",
+            "xml",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown format 'xml'"));
+}
+
+#[test]
+fn test_resources_render_placeholders() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let route_file = test_data.dir.path().join("templated_route.yaml");
+    let values_file = test_data.dir.path().join("values.yaml");
+    std::fs::write(
+        &route_file,
+        "kind: Route\nid: test-route-tpl\nname: ${vars.route.name}\ndescription: ${env.OTOROSHICTL_TEST_UNSET_VAR:-none}\n",
+    )
+    .unwrap();
+    std::fs::write(&values_file, "route:\n  name: templated\n").unwrap();
+
+    let output = cli.run_success(&[
+        "resources",
+        "render",
+        "-f",
+        route_file.to_str().unwrap(),
+        "--values",
+        values_file.to_str().unwrap(),
+        "-o",
+        "json",
+    ]);
+    let parsed: serde_json::Value = serde_json::from_str(&output).expect("Invalid JSON output");
+    assert_eq!(parsed[0]["name"], "templated");
+    assert_eq!(parsed[0]["description"], "none");
+
+    // Unresolved placeholders are reported with their line
+    cli.run(&["resources", "render", "-f", route_file.to_str().unwrap()])
+        .failure()
+        .stderr(predicate::str::contains("line 3: unresolved placeholder"));
+}
+
+#[test]
+fn test_resources_build_overlay() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let base = test_data.dir.path().join("base");
+    let overlay = test_data.dir.path().join("overlays").join("prod");
+    std::fs::create_dir_all(&base).unwrap();
+    std::fs::create_dir_all(&overlay).unwrap();
+    std::fs::copy(test_data.multi_route_path(), base.join("routes.yaml")).unwrap();
+    std::fs::write(
+        overlay.join("overlay.yaml"),
+        r#"bases:
+  - ../../base
+patches:
+  - target: { kind: Route, id: test-route-2 }
+    merge: { enabled: false }
+"#,
+    )
+    .unwrap();
+
+    let output = cli.run_success(&[
+        "resources",
+        "build",
+        overlay.to_str().unwrap(),
+        "-o",
+        "json",
+    ]);
+    let parsed: serde_json::Value = serde_json::from_str(&output).expect("Invalid JSON output");
+    assert_eq!(parsed.as_array().unwrap().len(), 2);
+    assert_eq!(parsed[1]["id"], "test-route-2");
+    assert_eq!(parsed[1]["enabled"], false);
+
+    // A patch matching nothing is an error
+    std::fs::write(
+        overlay.join("overlay.yaml"),
+        "bases: [../../base]\npatches:\n  - target: { kind: Route, id: unknown }\n    merge: {}\n",
+    )
+    .unwrap();
+    cli.run(&["resources", "build", overlay.to_str().unwrap()])
+        .failure()
+        .stderr(predicate::str::contains("does not match any entity"));
+}
+
+#[test]
+fn test_resources_report_options() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let report_file = test_data.dir.path().join("report.xml");
+
+    // Options are checked before anything is sent to the cluster
+    cli.run(&[
+        "resources",
+        "apply",
+        "-f",
+        test_data.route_path(),
+        "--report",
+        "xml",
+        "--report-file",
+        report_file.to_str().unwrap(),
+    ])
+    .failure()
+    .stderr(predicate::str::contains("unknown report format 'xml'"));
+
+    cli.run(&[
+        "resources",
+        "delete",
+        "-f",
+        test_data.route_path(),
+        "--report",
+        "junit",
+    ])
+    .failure()
+    .stderr(predicate::str::contains(
+        "--report and --report-file must be used together",
+    ));
+    assert!(!report_file.exists());
+}
+
+#[test]
+fn test_resources_encrypt_decrypt_roundtrip() {
+    let cli = OtoroshiCtl::new();
+    let test_data = TestData::new();
+    let key_file = test_data.dir.path().join("key.txt");
+    let apikey_file = test_data.dir.path().join("apikey.yaml");
+    std::fs::write(&key_file, "a very long passphrase\n").unwrap();
+    std::fs::write(
+        &apikey_file,
+        "kind: ApiKey\nclientId: test-key\nclientName: test\nclientSecret: very-secret\n",
+    )
+    .unwrap();
+
+    cli.run_success(&[
+        "resources",
+        "encrypt",
+        "-f",
+        apikey_file.to_str().unwrap(),
+        "--in-place",
+        "--key-file",
+        key_file.to_str().unwrap(),
+    ]);
+    let encrypted = std::fs::read_to_string(&apikey_file).unwrap();
+    assert!(encrypted.contains("clientSecret: ENC[AES256_GCM,"));
+    assert!(!encrypted.contains("very-secret"));
+    assert!(encrypted.contains("clientId: test-key"));
+
+    let output = cli.run_success(&[
+        "resources",
+        "decrypt",
+        "-f",
+        apikey_file.to_str().unwrap(),
+        "--key-file",
+        key_file.to_str().unwrap(),
+    ]);
+    assert!(output.contains("clientSecret: very-secret"));
+
+    // Encrypted values can not be used without the key
+    cli.run(&["resources", "decrypt", "-f", apikey_file.to_str().unwrap()])
+        .failure()
+        .stderr(predicate::str::contains("--key-file"));
+}
+
 #[test]
 fn test_resources_get_help() {
     let cli = OtoroshiCtl::new();
 
-    cli.run(&["resources", "get", "--help"]).success();
+    cli.run(&["resources", "get", "--help"])
+        .success()
+        .stdout(predicate::str::contains("--watch"))
+        .stdout(predicate::str::contains("--interval"))
+        .stdout(predicate::str::contains("--query"));
+}
+
+#[test]
+fn test_resources_selector_options() {
+    let cli = OtoroshiCtl::new();
+
+    cli.run(&["resources", "get", "routes", "-l", "team in a,b"])
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid set in selector 'team in a'",
+        ));
+    cli.run(&[
+        "resources",
+        "delete",
+        "routes",
+        "route_1",
+        "--tag",
+        "ephemeral",
+    ])
+    .failure()
+    .stderr(predicate::str::contains(
+        "ids and selectors can't be used together",
+    ));
+    cli.run(&["resources", "patch", "--help"])
+        .success()
+        .stdout(predicate::str::contains("--selector"))
+        .stdout(predicate::str::contains("--tag"));
+}
+
+#[test]
+fn test_resources_get_invalid_column() {
+    let cli = OtoroshiCtl::new();
+
+    cli.run(&[
+        "resources",
+        "get",
+        "routes",
+        "--columns",
+        "id,frontend.domains[0",
+    ])
+    .failure()
+    .stderr(predicate::str::contains(
+        "invalid column frontend.domains[0",
+    ));
 }